@@ -1,15 +1,16 @@
 use crate::checkpoint::CheckPoint;
 use crate::errors::{BeaconChainError as Error, BlockProductionError};
 use crate::eth1_chain::{Eth1Chain, Eth1ChainBackend};
-use crate::events::{EventHandler, EventKind};
+use crate::events::{BalanceChange, EventHandler, EventKind, EventTopic, PoolOperation};
 use crate::fork_choice::{Error as ForkChoiceError, ForkChoice};
 use crate::iter::{ReverseBlockRootIterator, ReverseStateRootIterator};
 use crate::metrics;
+use crate::naive_aggregation_pool::{NaiveAggregationPool, SLOTS_RETAINED};
 use crate::persisted_beacon_chain::{PersistedBeaconChain, BEACON_CHAIN_DB_KEY};
 use lmd_ghost::LmdGhost;
 use operation_pool::DepositInsertStatus;
 use operation_pool::{OperationPool, PersistedOperationPool};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use slog::{debug, error, info, trace, warn, Logger};
 use slot_clock::SlotClock;
 use ssz::Encode;
@@ -21,7 +22,8 @@ use state_processing::per_block_processing::{
     verify_attestation_for_state, VerifySignatures,
 };
 use state_processing::{
-    per_block_processing, per_slot_processing, BlockProcessingError, BlockSignatureStrategy,
+    common::get_attesting_indices, per_block_processing, per_slot_processing, BlockProcessingError,
+    BlockSignatureStrategy,
 };
 use std::fs;
 use std::io::prelude::*;
@@ -46,6 +48,10 @@ const WRITE_BLOCK_PROCESSING_SSZ: bool = cfg!(feature = "write_ssz_files");
 
 const BLOCK_SKIPPING_LOGGING_THRESHOLD: u64 = 3;
 
+/// The maximum number of slots prior to the previous head that are searched for the common
+/// ancestor of a re-org.
+const MAX_REORG_SEARCH_DEPTH: u64 = 1_024;
+
 #[derive(Debug, PartialEq)]
 pub enum BlockProcessingOutcome {
     /// Block was valid and imported into the block graph.
@@ -89,9 +95,36 @@ pub enum AttestationProcessingOutcome {
         attestation: Epoch,
         finalized: Epoch,
     },
+    /// The aggregate of an `AggregateAndProof` is not from the current or previous epoch of the
+    /// head, so the committee of the aggregator is unknown.
+    AggregateNotRecent {
+        aggregate: Epoch,
+        head: Epoch,
+    },
+    /// The aggregator of an `AggregateAndProof` is not a member of the committee of the aggregate.
+    AggregatorNotInCommittee {
+        aggregator_index: u64,
+    },
+    /// The `selection_proof` of an `AggregateAndProof` does not select the aggregator.
+    AggregatorNotSelected {
+        aggregator_index: u64,
+    },
+    /// The `selection_proof` of an `AggregateAndProof` is not the aggregator's signature of the
+    /// attestation data.
+    InvalidSelectionProof {
+        aggregator_index: u64,
+    },
     Invalid(AttestationValidationError),
 }
 
+/// The duty of a local validator to aggregate the attestations of its committee at `slot`.
+struct AggregationDuty<E: EthSpec> {
+    slot: Slot,
+    aggregate_and_proof: AggregateAndProof<E>,
+    /// Set once the aggregate has been produced, so that it is never produced twice.
+    produced: bool,
+}
+
 pub trait BeaconChainTypes: Send + Sync + 'static {
     type Store: store::Store;
     type SlotClock: slot_clock::SlotClock;
@@ -112,6 +145,10 @@ pub struct BeaconChain<T: BeaconChainTypes> {
     /// Stores all operations (e.g., `Attestation`, `Deposit`, etc) that are candidates for
     /// inclusion in a block.
     pub op_pool: OperationPool<T::EthSpec>,
+    /// Aggregates unaggregated attestations, for publishing by aggregators.
+    pub naive_aggregation_pool: NaiveAggregationPool<T::EthSpec>,
+    /// The attestations of local validators which have been selected to aggregate.
+    aggregation_duties: Mutex<Vec<AggregationDuty<T::EthSpec>>>,
     /// Provides information from the Ethereum 1 (PoW) chain.
     pub eth1_chain: Eth1Chain<T>,
     /// Stores a "snapshot" of the chain at the time the head-of-the-chain block was received.
@@ -178,6 +215,8 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             spec,
             slot_clock,
             op_pool: OperationPool::new(),
+            naive_aggregation_pool: NaiveAggregationPool::default(),
+            aggregation_duties: Mutex::new(vec![]),
             eth1_chain: Eth1Chain::new(eth1_backend),
             canonical_head,
             genesis_block_root,
@@ -228,6 +267,8 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             slot_clock,
             fork_choice: ForkChoice::new(store.clone(), last_finalized_block, last_finalized_root),
             op_pool,
+            naive_aggregation_pool: NaiveAggregationPool::default(),
+            aggregation_duties: Mutex::new(vec![]),
             event_handler,
             eth1_chain: Eth1Chain::new(eth1_backend),
             canonical_head: RwLock::new(p.canonical_head),
@@ -659,11 +700,6 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
                         "target_epoch" => attestation.data.target.epoch,
                         "shard" => attestation.data.crosslink.shard,
                     );
-                    let _ = self
-                        .event_handler
-                        .register(EventKind::BeaconAttestationImported {
-                            attestation: Box::new(attestation),
-                        });
                 }
                 other => {
                     warn!(
@@ -900,6 +936,33 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
                 return Err(e.into());
             }
 
+            let attesting_indices =
+                get_attesting_indices(state, &attestation.data, &attestation.aggregation_bits)?;
+            let _ = self
+                .event_handler
+                .register(EventKind::BeaconAttestationImported {
+                    attesting_indices: attesting_indices
+                        .into_iter()
+                        .map(|index| index as u64)
+                        .collect(),
+                    attestation: Box::new(attestation.clone()),
+                });
+
+            // Unaggregated attestations are aggregated for any local aggregators.
+            if attestation.aggregation_bits.num_set_bits() == 1 {
+                let attestation_slot = state.get_attestation_data_slot(&attestation.data)?;
+                if let Err(e) = self
+                    .naive_aggregation_pool
+                    .insert(&attestation, attestation_slot)
+                {
+                    debug!(
+                        self.log,
+                        "Attestation not aggregated";
+                        "error" => format!("{:?}", e),
+                    );
+                }
+            }
+
             // Provide the valid attestation to op pool, which may choose to retain the
             // attestation for inclusion in a future block.
             self.op_pool
@@ -912,19 +975,225 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         }
     }
 
+    /// Accept a new, potentially invalid `AggregateAndProof` from the network.
+    ///
+    /// The aggregator must be a member of the committee of the aggregate that is selected by its
+    /// `selection_proof`. If so, the aggregate is processed as per `Self::process_attestation`.
+    pub fn process_aggregate_and_proof(
+        &self,
+        aggregate_and_proof: AggregateAndProof<T::EthSpec>,
+    ) -> Result<AttestationProcessingOutcome, Error> {
+        if let Some(outcome) = self.verify_aggregator(&aggregate_and_proof)? {
+            warn!(
+                self.log,
+                "Aggregate rejected";
+                "reason" => format!("{:?}", outcome),
+            );
+            return Ok(outcome);
+        }
+
+        self.process_attestation(aggregate_and_proof.aggregate)
+    }
+
+    /// Verifies that the aggregator of `aggregate_and_proof` is selected to aggregate the
+    /// attestations of its committee, using the committees of the head state.
+    ///
+    /// Returns `None` if the aggregator is valid, otherwise the reason it is invalid.
+    fn verify_aggregator(
+        &self,
+        aggregate_and_proof: &AggregateAndProof<T::EthSpec>,
+    ) -> Result<Option<AttestationProcessingOutcome>, Error> {
+        let head = self.head();
+        let state = &head.beacon_state;
+        let data = &aggregate_and_proof.aggregate.data;
+        let aggregator_index = aggregate_and_proof.aggregator_index;
+
+        let relative_epoch =
+            match RelativeEpoch::from_epoch(state.current_epoch(), data.target.epoch) {
+                Ok(relative_epoch) if relative_epoch != RelativeEpoch::Next => relative_epoch,
+                _ => {
+                    return Ok(Some(AttestationProcessingOutcome::AggregateNotRecent {
+                        aggregate: data.target.epoch,
+                        head: state.current_epoch(),
+                    }))
+                }
+            };
+
+        let committee =
+            state.get_crosslink_committee_for_shard(data.crosslink.shard, relative_epoch)?;
+
+        if !committee
+            .committee
+            .iter()
+            .any(|index| *index as u64 == aggregator_index)
+        {
+            return Ok(Some(
+                AttestationProcessingOutcome::AggregatorNotInCommittee { aggregator_index },
+            ));
+        }
+
+        if !AggregateAndProof::<T::EthSpec>::is_aggregator(
+            committee.committee.len(),
+            &aggregate_and_proof.selection_proof,
+            &self.spec,
+        ) {
+            return Ok(Some(AttestationProcessingOutcome::AggregatorNotSelected {
+                aggregator_index,
+            }));
+        }
+
+        if !AggregateAndProof::<T::EthSpec>::is_valid_selection_proof(
+            &aggregate_and_proof.selection_proof,
+            committee.slot,
+            &state.validators[aggregator_index as usize].pubkey,
+            &state.fork,
+            &self.spec,
+        ) {
+            return Ok(Some(AttestationProcessingOutcome::InvalidSelectionProof {
+                aggregator_index,
+            }));
+        }
+
+        Ok(None)
+    }
+
+    /// Registers the unaggregated `attestation` of a local validator, along with its
+    /// `selection_proof` for the slot of the attestation. If the `selection_proof` selects the
+    /// validator to aggregate the attestations of its committee, an aggregate will be produced by
+    /// `Self::produce_aggregates`.
+    ///
+    /// Registering the same duty more than once has no effect.
+    ///
+    /// Returns `true` if the validator is an aggregator.
+    pub fn register_aggregation_duty(
+        &self,
+        attestation: &Attestation<T::EthSpec>,
+        selection_proof: Signature,
+    ) -> Result<bool, Error> {
+        if attestation.aggregation_bits.num_set_bits() != 1 {
+            return Ok(false);
+        }
+
+        let head = self.head();
+        let state = &head.beacon_state;
+        let data = &attestation.data;
+
+        let relative_epoch = RelativeEpoch::from_epoch(state.current_epoch(), data.target.epoch)
+            .map_err(BeaconStateError::from)?;
+        let committee =
+            state.get_crosslink_committee_for_shard(data.crosslink.shard, relative_epoch)?;
+
+        let aggregator_index = match attestation
+            .aggregation_bits
+            .iter()
+            .position(|bit| bit)
+            .and_then(|position| committee.committee.get(position))
+        {
+            Some(index) => *index as u64,
+            None => return Ok(false),
+        };
+
+        if !AggregateAndProof::<T::EthSpec>::is_valid_selection_proof(
+            &selection_proof,
+            committee.slot,
+            &state.validators[aggregator_index as usize].pubkey,
+            &state.fork,
+            &self.spec,
+        ) {
+            return Err(Error::InvalidSelectionProof { aggregator_index });
+        }
+
+        if !AggregateAndProof::<T::EthSpec>::is_aggregator(
+            committee.committee.len(),
+            &selection_proof,
+            &self.spec,
+        ) {
+            return Ok(false);
+        }
+
+        debug!(
+            self.log,
+            "Registered aggregation duty";
+            "aggregator_index" => aggregator_index,
+            "shard" => data.crosslink.shard,
+            "slot" => committee.slot,
+        );
+
+        let mut duties = self.aggregation_duties.lock();
+        if !duties.iter().any(|duty| {
+            duty.slot == committee.slot
+                && duty.aggregate_and_proof.aggregator_index == aggregator_index
+                && duty.aggregate_and_proof.aggregate.data == *data
+        }) {
+            duties.push(AggregationDuty {
+                slot: committee.slot,
+                aggregate_and_proof: AggregateAndProof {
+                    aggregator_index,
+                    aggregate: attestation.clone(),
+                    selection_proof,
+                },
+                produced: false,
+            });
+        }
+
+        Ok(true)
+    }
+
+    /// Produces an `AggregateAndProof` for each aggregation duty with a slot equal to or prior to
+    /// `slot` that has not yet been produced. The aggregate is the aggregate of all attestations
+    /// for the aggregator's `AttestationData` seen so far.
+    ///
+    /// Each duty is produced at most once, regardless of how many times this function is called.
+    /// Duties from slots that are no longer retained by `self.naive_aggregation_pool` are dropped.
+    pub fn produce_aggregates(&self, slot: Slot) -> Vec<AggregateAndProof<T::EthSpec>> {
+        let mut duties = self.aggregation_duties.lock();
+        duties.retain(|duty| duty.slot + SLOTS_RETAINED >= slot);
+
+        duties
+            .iter_mut()
+            .filter(|duty| duty.slot <= slot && !duty.produced)
+            .map(|duty| {
+                duty.produced = true;
+
+                let mut aggregate_and_proof = duty.aggregate_and_proof.clone();
+                if let Some(aggregate) = self
+                    .naive_aggregation_pool
+                    .get(&aggregate_and_proof.aggregate.data, duty.slot)
+                {
+                    aggregate_and_proof.aggregate = aggregate;
+                }
+                aggregate_and_proof
+            })
+            .collect()
+    }
+
     /// Accept some deposit and queue it for inclusion in an appropriate block.
     pub fn process_deposit(
         &self,
         index: u64,
         deposit: Deposit,
     ) -> Result<DepositInsertStatus, DepositValidationError> {
-        self.op_pool.insert_deposit(index, deposit)
+        let status = self.op_pool.insert_deposit(index, deposit.clone())?;
+
+        if status != DepositInsertStatus::Duplicate {
+            self.register_pool_insertion(PoolOperation::Deposit {
+                index,
+                deposit: Box::new(deposit),
+            });
+        }
+
+        Ok(status)
     }
 
     /// Accept some exit and queue it for inclusion in an appropriate block.
     pub fn process_voluntary_exit(&self, exit: VoluntaryExit) -> Result<(), ExitValidationError> {
         match self.wall_clock_state() {
-            Ok(state) => self.op_pool.insert_voluntary_exit(exit, &state, &self.spec),
+            Ok(state) => {
+                self.op_pool
+                    .insert_voluntary_exit(exit.clone(), &state, &self.spec)?;
+                self.register_pool_insertion(PoolOperation::VoluntaryExit(exit));
+                Ok(())
+            }
             Err(e) => {
                 error!(
                     &self.log,
@@ -940,7 +1209,12 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
     /// Accept some transfer and queue it for inclusion in an appropriate block.
     pub fn process_transfer(&self, transfer: Transfer) -> Result<(), TransferValidationError> {
         match self.wall_clock_state() {
-            Ok(state) => self.op_pool.insert_transfer(transfer, &state, &self.spec),
+            Ok(state) => {
+                self.op_pool
+                    .insert_transfer(transfer.clone(), &state, &self.spec)?;
+                self.register_pool_insertion(PoolOperation::Transfer(transfer));
+                Ok(())
+            }
             Err(e) => {
                 error!(
                     &self.log,
@@ -960,8 +1234,15 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
     ) -> Result<(), ProposerSlashingValidationError> {
         match self.wall_clock_state() {
             Ok(state) => {
-                self.op_pool
-                    .insert_proposer_slashing(proposer_slashing, &state, &self.spec)
+                self.op_pool.insert_proposer_slashing(
+                    proposer_slashing.clone(),
+                    &state,
+                    &self.spec,
+                )?;
+                self.register_pool_insertion(PoolOperation::ProposerSlashing(Box::new(
+                    proposer_slashing,
+                )));
+                Ok(())
             }
             Err(e) => {
                 error!(
@@ -982,8 +1263,15 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
     ) -> Result<(), AttesterSlashingValidationError> {
         match self.wall_clock_state() {
             Ok(state) => {
-                self.op_pool
-                    .insert_attester_slashing(attester_slashing, &state, &self.spec)
+                self.op_pool.insert_attester_slashing(
+                    attester_slashing.clone(),
+                    &state,
+                    &self.spec,
+                )?;
+                self.register_pool_insertion(PoolOperation::AttesterSlashing(Box::new(
+                    attester_slashing,
+                )));
+                Ok(())
             }
             Err(e) => {
                 error!(
@@ -997,6 +1285,13 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         }
     }
 
+    /// Informs the event handler that `operation` has been added to the operation pool.
+    fn register_pool_insertion(&self, operation: PoolOperation<T::EthSpec>) {
+        let _ = self
+            .event_handler
+            .register(EventKind::OperationPoolInsertion { operation });
+    }
+
     /// Accept some block and attempt to add it to block DAG.
     ///
     /// Will accept blocks from prior slots, however it will reject any block from a future slot.
@@ -1015,10 +1310,6 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
                         "block_root" => format!("{:?}", block_root),
                         "block_slot" => format!("{:?}", block_root),
                     );
-                    let _ = self.event_handler.register(EventKind::BeaconBlockImported {
-                        block_root: *block_root,
-                        block: Box::new(block),
-                    });
                 }
                 other => {
                     warn!(
@@ -1158,6 +1449,9 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
         let core_timer = metrics::start_timer(&metrics::BLOCK_PROCESSING_CORE);
 
+        let proposer_index =
+            state.get_beacon_proposer_index(block.slot, RelativeEpoch::Current, &self.spec)?;
+
         // Apply the received block to its parent state (which has been transitioned into this
         // slot).
         match per_block_processing(
@@ -1235,6 +1529,17 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
         metrics::stop_timer(fork_choice_register_timer);
 
+        // Announce the block before running fork choice, so that subscribers learn of the block
+        // before any head change that it causes. The block is only cloned if there is a
+        // subscriber to receive it.
+        if self.event_handler.is_subscribed(EventTopic::Block) {
+            let _ = self.event_handler.register(EventKind::BeaconBlockImported {
+                block_root,
+                proposer_index: proposer_index as u64,
+                block: Box::new(block.clone()),
+            });
+        }
+
         let find_head_timer =
             metrics::start_timer(&metrics::BLOCK_PROCESSING_FORK_CHOICE_FIND_HEAD);
 
@@ -1316,6 +1621,13 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         let (proposer_slashings, attester_slashings) =
             self.op_pool.get_slashings(&state, &self.spec);
 
+        let eth1_data = self
+            .eth1_chain
+            .eth1_data_for_block_production(&state, &self.spec)?;
+        let deposits = self
+            .eth1_chain
+            .deposits_for_block_inclusion(&state, &eth1_data)?;
+
         let mut block = BeaconBlock {
             slot: state.slot,
             parent_root,
@@ -1323,13 +1635,12 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             signature: Signature::empty_signature(), // To be completed by a validator.
             body: BeaconBlockBody {
                 randao_reveal,
-                // TODO: replace with real data.
-                eth1_data: self.eth1_chain.eth1_data_for_block_production(&state)?,
+                eth1_data,
                 graffiti,
                 proposer_slashings: proposer_slashings.into(),
                 attester_slashings: attester_slashings.into(),
                 attestations: self.op_pool.get_attestations(&state, &self.spec).into(),
-                deposits: self.eth1_chain.deposits_for_block_inclusion(&state)?.into(),
+                deposits: deposits.into(),
                 voluntary_exits: self.op_pool.get_voluntary_exits(&state, &self.spec).into(),
                 transfers: self.op_pool.get_transfers(&state, &self.spec).into(),
             },
@@ -1443,6 +1754,9 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
                 new_head.beacon_state.build_all_caches(&self.spec)?;
 
+                let events =
+                    self.head_change_events(&self.canonical_head.read(), &new_head, is_reorg)?;
+
                 let timer = metrics::start_timer(&metrics::UPDATE_HEAD_TIMES);
 
                 // Update the checkpoint that stores the head of the chain at the time it received the
@@ -1460,6 +1774,10 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
                     current_head_beacon_block_root,
                 });
 
+                for event in events {
+                    let _ = self.event_handler.register(event);
+                }
+
                 if new_finalized_epoch != old_finalized_epoch {
                     self.after_finalization(old_finalized_epoch, finalized_root)?;
                 }
@@ -1480,6 +1798,158 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         result
     }
 
+    /// Returns the events, besides `EventKind::BeaconHeadChanged`, caused by the head changing from
+    /// `previous_head` to `new_head`.
+    ///
+    /// The events describe any re-org, a newly justified checkpoint and, when the head moves into a
+    /// new epoch, the changes in validator balances.
+    fn head_change_events(
+        &self,
+        previous_head: &CheckPoint<T::EthSpec>,
+        new_head: &CheckPoint<T::EthSpec>,
+        is_reorg: bool,
+    ) -> Result<Vec<EventKind<T::EthSpec>>, Error> {
+        let mut events = vec![];
+        let previous_state = &previous_head.beacon_state;
+        let new_state = &new_head.beacon_state;
+
+        if is_reorg {
+            if let Some((common_ancestor_root, common_ancestor_slot)) =
+                self.find_common_ancestor(previous_head, new_head)?
+            {
+                events.push(EventKind::BeaconChainReorg {
+                    depth: previous_head
+                        .beacon_block
+                        .slot
+                        .as_u64()
+                        .saturating_sub(common_ancestor_slot.as_u64()),
+                    previous_head_beacon_block_root: previous_head.beacon_block_root,
+                    current_head_beacon_block_root: new_head.beacon_block_root,
+                    common_ancestor_beacon_block_root: common_ancestor_root,
+                    common_ancestor_slot,
+                });
+            }
+        }
+
+        if new_state.current_justified_checkpoint.epoch
+            > previous_state.current_justified_checkpoint.epoch
+        {
+            events.push(EventKind::BeaconJustification {
+                epoch: new_state.current_justified_checkpoint.epoch,
+                root: new_state.current_justified_checkpoint.root,
+            });
+        }
+
+        if new_state.current_epoch() > previous_state.current_epoch() {
+            let changes: Vec<BalanceChange> = new_state
+                .balances
+                .iter()
+                .enumerate()
+                .filter_map(|(validator_index, &balance)| {
+                    let previous_balance = previous_state
+                        .balances
+                        .get(validator_index)
+                        .copied()
+                        .unwrap_or(0);
+
+                    if balance != previous_balance {
+                        Some(BalanceChange {
+                            validator_index: validator_index as u64,
+                            previous_balance,
+                            balance,
+                        })
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            if !changes.is_empty() {
+                events.push(EventKind::ValidatorBalancesChanged {
+                    epoch: new_state.current_epoch(),
+                    changes,
+                });
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Returns the root and slot of the latest block that is an ancestor of both `previous_head`
+    /// and `new_head`.
+    ///
+    /// Both chains are walked backwards in step, searching no further back than the finalized
+    /// checkpoint of `previous_head` or `MAX_REORG_SEARCH_DEPTH` slots prior to it. `None` is
+    /// returned if no common ancestor is found within those slots.
+    fn find_common_ancestor(
+        &self,
+        previous_head: &CheckPoint<T::EthSpec>,
+        new_head: &CheckPoint<T::EthSpec>,
+    ) -> Result<Option<(Hash256, Slot)>, Error> {
+        let finalized_slot = previous_head
+            .beacon_state
+            .finalized_checkpoint
+            .epoch
+            .start_slot(T::EthSpec::slots_per_epoch());
+
+        let earliest_slot = std::cmp::max(
+            finalized_slot,
+            previous_head
+                .beacon_block
+                .slot
+                .saturating_sub(MAX_REORG_SEARCH_DEPTH),
+        );
+
+        let mut previous_chain = ReverseBlockRootIterator::new(
+            (
+                previous_head.beacon_block_root,
+                previous_head.beacon_block.slot,
+            ),
+            BlockRootsIterator::new(self.store.clone(), &previous_head.beacon_state),
+        )
+        .take_while(|(_, slot)| *slot >= earliest_slot)
+        .peekable();
+
+        let mut new_chain = ReverseBlockRootIterator::new(
+            (new_head.beacon_block_root, new_head.beacon_block.slot),
+            BlockRootsIterator::new(self.store.clone(), &new_head.beacon_state),
+        )
+        .take_while(|(_, slot)| *slot >= earliest_slot)
+        .peekable();
+
+        // Step back whichever chain is ahead until both are at the same slot, then compare roots.
+        let common_ancestor_root = loop {
+            match (previous_chain.peek(), new_chain.peek()) {
+                (Some(&(previous_root, previous_slot)), Some(&(new_root, new_slot))) => {
+                    if previous_slot > new_slot {
+                        previous_chain.next();
+                    } else if new_slot > previous_slot {
+                        new_chain.next();
+                    } else if previous_root == new_root {
+                        break Some(new_root);
+                    } else {
+                        previous_chain.next();
+                        new_chain.next();
+                    }
+                }
+                _ => break None,
+            }
+        };
+
+        match common_ancestor_root {
+            Some(root) => {
+                // The iterators repeat the root of the prior block across skipped slots, so the
+                // slot of the ancestor is read from the block itself.
+                let block = self
+                    .store
+                    .get::<BeaconBlock<T::EthSpec>>(&root)?
+                    .ok_or_else(|| Error::MissingBeaconBlock(root))?;
+                Ok(Some((root, block.slot)))
+            }
+            None => Ok(None),
+        }
+    }
+
     /// Called after `self` has had a new block finalized.
     ///
     /// Performs pruning and finality-based optimizations.
@@ -104,7 +104,9 @@ impl<T: BeaconChainTypes> BeaconChainBuilder<T> {
         })
     }
 
-    fn from_genesis_state(
+    /// Starts a new chain from the given `genesis_state`, e.g., one that was built from the
+    /// deposit contract.
+    pub fn from_genesis_state(
         genesis_state: BeaconState<T::EthSpec>,
         spec: ChainSpec,
         log: Logger,
@@ -39,6 +39,9 @@ pub enum BeaconChainError {
         beacon_block_root: Hash256,
     },
     AttestationValidationError(AttestationValidationError),
+    InvalidSelectionProof {
+        aggregator_index: u64,
+    },
     /// Returned when an internal check fails, indicating corrupt data.
     InvariantViolated(String),
 }
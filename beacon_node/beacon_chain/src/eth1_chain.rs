@@ -1,7 +1,11 @@
 use crate::BeaconChainTypes;
+use eth1::{Config as Eth1Config, Eth1Block, Service as Eth1Service};
 use eth2_hashing::hash;
+use futures::Future;
+use slog::Logger;
+use std::collections::HashMap;
 use std::marker::PhantomData;
-use types::{BeaconState, Deposit, Eth1Data, EthSpec, Hash256};
+use types::{BeaconState, ChainSpec, Deposit, Eth1Data, EthSpec, Hash256, Unsigned};
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -20,21 +24,27 @@ impl<T: BeaconChainTypes> Eth1Chain<T> {
     pub fn eth1_data_for_block_production(
         &self,
         state: &BeaconState<T::EthSpec>,
+        spec: &ChainSpec,
     ) -> Result<Eth1Data> {
-        self.backend.eth1_data(state)
+        self.backend.eth1_data(state, spec)
     }
 
-    /// Returns a list of `Deposits` that may be included in a block.
+    /// Returns a list of `Deposits` that may be included in a block which votes for
+    /// `eth1_data_vote`.
     ///
     /// Including all of the returned `Deposits` in a block should _not_ cause it to become
     /// invalid.
     pub fn deposits_for_block_inclusion(
         &self,
         state: &BeaconState<T::EthSpec>,
+        eth1_data_vote: &Eth1Data,
     ) -> Result<Vec<Deposit>> {
-        let deposits = self.backend.queued_deposits(state)?;
+        // The block's vote is processed before its deposits, so it may change the deposit root
+        // that the deposits are verified against.
+        let eth1_data = eth1_data_after_vote(state, eth1_data_vote);
 
-        // TODO: truncate deposits if required.
+        let mut deposits = self.backend.queued_deposits(state, &eth1_data)?;
+        deposits.truncate(<T::EthSpec as EthSpec>::MaxDeposits::to_usize());
 
         Ok(deposits)
     }
@@ -49,20 +59,33 @@ pub enum Error {
 }
 
 pub trait Eth1ChainBackend<T: EthSpec>: Sized + Send + Sync {
-    fn new(server: String) -> Result<Self>;
+    fn new(config: &Eth1Config, log: &Logger) -> Result<Self>;
+
+    /// Returns a future which keeps the backend up-to-date (e.g., by polling an eth1 node), if
+    /// the backend requires one. The caller should run it until shutdown.
+    fn update_task(&self) -> Option<Box<dyn Future<Item = (), Error = ()> + Send>> {
+        None
+    }
 
     /// Returns the `Eth1Data` that should be included in a block being produced for the given
     /// `state`.
-    fn eth1_data(&self, beacon_state: &BeaconState<T>) -> Result<Eth1Data>;
+    fn eth1_data(&self, beacon_state: &BeaconState<T>, spec: &ChainSpec) -> Result<Eth1Data>;
 
-    /// Returns all `Deposits` between `state.eth1_deposit_index` and
-    /// `state.eth1_data.deposit_count`.
+    /// Returns all `Deposits` between `state.eth1_deposit_index` and `eth1_data.deposit_count`,
+    /// with proofs against `eth1_data.deposit_root`.
+    ///
+    /// `eth1_data` is the `state.eth1_data` once the eth1 vote of the block being produced has
+    /// been processed.
     ///
     /// # Note:
     ///
     /// It is possible that not all returned `Deposits` can be included in a block. E.g., there may
     /// be more than `MAX_DEPOSIT_COUNT` or the churn may be too high.
-    fn queued_deposits(&self, beacon_state: &BeaconState<T>) -> Result<Vec<Deposit>>;
+    fn queued_deposits(
+        &self,
+        beacon_state: &BeaconState<T>,
+        eth1_data: &Eth1Data,
+    ) -> Result<Vec<Deposit>>;
 }
 
 pub struct InteropEth1ChainBackend<T: EthSpec> {
@@ -70,11 +93,11 @@ pub struct InteropEth1ChainBackend<T: EthSpec> {
 }
 
 impl<T: EthSpec> Eth1ChainBackend<T> for InteropEth1ChainBackend<T> {
-    fn new(_server: String) -> Result<Self> {
+    fn new(_config: &Eth1Config, _log: &Logger) -> Result<Self> {
         Ok(Self::default())
     }
 
-    fn eth1_data(&self, state: &BeaconState<T>) -> Result<Eth1Data> {
+    fn eth1_data(&self, state: &BeaconState<T>, _spec: &ChainSpec) -> Result<Eth1Data> {
         let current_epoch = state.current_epoch();
         let slots_per_voting_period = T::slots_per_eth1_voting_period() as u64;
         let current_voting_period: u64 = current_epoch.as_u64() / slots_per_voting_period;
@@ -89,7 +112,7 @@ impl<T: EthSpec> Eth1ChainBackend<T> for InteropEth1ChainBackend<T> {
         })
     }
 
-    fn queued_deposits(&self, _: &BeaconState<T>) -> Result<Vec<Deposit>> {
+    fn queued_deposits(&self, _: &BeaconState<T>, _: &Eth1Data) -> Result<Vec<Deposit>> {
         Ok(vec![])
     }
 }
@@ -102,9 +125,296 @@ impl<T: EthSpec> Default for InteropEth1ChainBackend<T> {
     }
 }
 
+/// Follows the deposit contract of an eth1 node via web3 JSON-RPC, serving deposits (with proofs)
+/// from its cache.
+pub struct CachingEth1Backend<T: EthSpec> {
+    core: Eth1Service,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: EthSpec> CachingEth1Backend<T> {
+    /// Returns the service which follows the eth1 node.
+    pub fn core(&self) -> &Eth1Service {
+        &self.core
+    }
+}
+
+impl<T: EthSpec> Eth1ChainBackend<T> for CachingEth1Backend<T> {
+    fn new(config: &Eth1Config, log: &Logger) -> Result<Self> {
+        Ok(Self {
+            core: Eth1Service::new(config.clone(), log.clone()),
+            _phantom: PhantomData,
+        })
+    }
+
+    fn update_task(&self) -> Option<Box<dyn Future<Item = (), Error = ()> + Send>> {
+        Some(Box::new(self.core.auto_update()))
+    }
+
+    fn eth1_data(&self, state: &BeaconState<T>, spec: &ChainSpec) -> Result<Eth1Data> {
+        Ok(eth1_vote(state, self.core.blocks().read().iter(), spec))
+    }
+
+    fn queued_deposits(
+        &self,
+        state: &BeaconState<T>,
+        eth1_data: &Eth1Data,
+    ) -> Result<Vec<Deposit>> {
+        let deposit_index = state.eth1_deposit_index;
+        let deposit_count = eth1_data.deposit_count;
+
+        if deposit_index >= deposit_count {
+            return Ok(vec![]);
+        }
+
+        let (deposit_root, deposits) = self
+            .core
+            .deposits()
+            .read()
+            .cache
+            .get_deposits(deposit_index..deposit_count, deposit_count)
+            .map_err(|e| Error::BackendError(format!("Failed to get deposits: {:?}", e)))?;
+
+        // The deposits would be invalid if the eth1 node disagrees with the state.
+        if deposit_root != eth1_data.deposit_root {
+            return Err(Error::BackendError(format!(
+                "Deposit root {:?} after {} deposits does not match the state ({:?})",
+                deposit_root, deposit_count, eth1_data.deposit_root
+            )));
+        }
+
+        Ok(deposits)
+    }
+}
+
+/// Returns the eth1 vote for a block produced upon `state`, as per the honest validator guide.
+///
+/// The candidates are the `blocks` with timestamps between `2 * ETH1_FOLLOW_DISTANCE` and
+/// `ETH1_FOLLOW_DISTANCE` eth1 blocks (of `SECONDS_PER_ETH1_BLOCK`) prior to the start of the
+/// current voting period, excluding any which would reduce the deposit count. The candidate with
+/// the most votes in `state.eth1_data_votes` is chosen, with ties going to the earliest vote.
+///
+/// If no candidate has any votes the latest candidate is chosen, falling back to the current
+/// `state.eth1_data` if there are no candidates.
+fn eth1_vote<'a, T: EthSpec>(
+    state: &BeaconState<T>,
+    blocks: impl Iterator<Item = &'a Eth1Block>,
+    spec: &ChainSpec,
+) -> Eth1Data {
+    let period_start = voting_period_start_seconds(state, spec);
+    let follow_distance_seconds = spec.eth1_follow_distance * spec.seconds_per_eth1_block;
+
+    let mut latest_candidate = None;
+    let candidates: HashMap<Hash256, Eth1Data> = blocks
+        .filter(|block| {
+            block.timestamp + follow_distance_seconds <= period_start
+                && block.timestamp + 2 * follow_distance_seconds >= period_start
+        })
+        .map(Eth1Block::eth1_data)
+        .filter(|eth1_data| eth1_data.deposit_count >= state.eth1_data.deposit_count)
+        .map(|eth1_data| {
+            latest_candidate = Some(eth1_data.clone());
+            (eth1_data.block_hash, eth1_data)
+        })
+        .collect();
+
+    let valid_votes: Vec<&Eth1Data> = state
+        .eth1_data_votes
+        .iter()
+        .filter(|vote| candidates.get(&vote.block_hash) == Some(*vote))
+        .collect();
+
+    let mut vote_counts: HashMap<Hash256, usize> = HashMap::new();
+    for vote in &valid_votes {
+        *vote_counts.entry(vote.block_hash).or_insert(0) += 1;
+    }
+
+    // Only replace the best vote if a vote has strictly more votes, so ties go to the earliest.
+    let best_vote = valid_votes
+        .iter()
+        .fold(None, |best: Option<&Eth1Data>, vote| match best {
+            Some(best) if vote_counts[&best.block_hash] >= vote_counts[&vote.block_hash] => {
+                Some(best)
+            }
+            _ => Some(*vote),
+        });
+
+    best_vote
+        .cloned()
+        .or(latest_candidate)
+        .unwrap_or_else(|| state.eth1_data.clone())
+}
+
+/// Returns the timestamp of the first slot of the eth1 voting period of `state`.
+fn voting_period_start_seconds<T: EthSpec>(state: &BeaconState<T>, spec: &ChainSpec) -> u64 {
+    let slot = state.slot.as_u64();
+    let period_start_slot = slot - slot % T::slots_per_eth1_voting_period() as u64;
+
+    state.genesis_time + period_start_slot * spec.milliseconds_per_slot / 1000
+}
+
+/// Returns the `state.eth1_data` that `state` will have once a block voting for `eth1_data_vote`
+/// has been processed.
+fn eth1_data_after_vote<T: EthSpec>(state: &BeaconState<T>, eth1_data_vote: &Eth1Data) -> Eth1Data {
+    let num_votes = state
+        .eth1_data_votes
+        .iter()
+        .filter(|vote| *vote == eth1_data_vote)
+        .count()
+        + 1;
+
+    if num_votes * 2 > T::SlotsPerEth1VotingPeriod::to_usize() {
+        eth1_data_vote.clone()
+    } else {
+        state.eth1_data.clone()
+    }
+}
+
 /// Returns `int` as little-endian bytes with a length of 32.
 fn int_to_bytes32(int: u64) -> Vec<u8> {
     let mut vec = int.to_le_bytes().to_vec();
     vec.resize(32, 0);
     vec
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{MinimalEthSpec, Slot};
+
+    type E = MinimalEthSpec;
+
+    const GENESIS_TIME: u64 = 1_000_000;
+
+    /// Returns a state at the start of its third voting period, with the given votes.
+    fn state(votes: &[Eth1Data], spec: &ChainSpec) -> BeaconState<E> {
+        let mut state = BeaconState::new(GENESIS_TIME, Eth1Data::default(), spec);
+        state.slot = Slot::new(2 * E::slots_per_eth1_voting_period() as u64);
+        for vote in votes {
+            state
+                .eth1_data_votes
+                .push(vote.clone())
+                .expect("should add vote");
+        }
+        state
+    }
+
+    /// Returns blocks every `SECONDS_PER_ETH1_BLOCK`, where block `i` is `3 *
+    /// ETH1_FOLLOW_DISTANCE - i` blocks prior to the start of the voting period of `state`.
+    ///
+    /// Hence, blocks `ETH1_FOLLOW_DISTANCE..=2 * ETH1_FOLLOW_DISTANCE` are the candidates.
+    fn blocks(state: &BeaconState<E>, spec: &ChainSpec) -> Vec<Eth1Block> {
+        let period_start = voting_period_start_seconds(state, spec);
+        let first_timestamp =
+            period_start - 3 * spec.eth1_follow_distance * spec.seconds_per_eth1_block;
+
+        (0..=3 * spec.eth1_follow_distance)
+            .map(|i| Eth1Block {
+                hash: Hash256::from_low_u64_be(i + 1),
+                timestamp: first_timestamp + i * spec.seconds_per_eth1_block,
+                number: i,
+                deposit_root: Hash256::from_low_u64_be(i / 2),
+                deposit_count: i / 2,
+            })
+            .collect()
+    }
+
+    fn vote(blocks: &[Eth1Block], i: u64) -> Eth1Data {
+        blocks[i as usize].eth1_data()
+    }
+
+    #[test]
+    fn voting_period_start() {
+        let spec = E::default_spec();
+        let state = state(&[], &spec);
+
+        assert_eq!(
+            voting_period_start_seconds(&state, &spec),
+            GENESIS_TIME
+                + 2 * E::slots_per_eth1_voting_period() as u64 * spec.milliseconds_per_slot / 1000
+        );
+    }
+
+    #[test]
+    fn vote_defaults_to_latest_candidate() {
+        let spec = E::default_spec();
+        let state = state(&[], &spec);
+        let blocks = blocks(&state, &spec);
+        let follow_distance = spec.eth1_follow_distance;
+
+        assert_eq!(
+            eth1_vote(&state, blocks.iter(), &spec),
+            vote(&blocks, 2 * follow_distance)
+        );
+        assert_eq!(eth1_vote(&state, [].iter(), &spec), state.eth1_data);
+
+        // Blocks that are too recent are not candidates.
+        let recent = &blocks[2 * follow_distance as usize + 1..];
+        assert_eq!(eth1_vote(&state, recent.iter(), &spec), state.eth1_data);
+    }
+
+    #[test]
+    fn vote_prefers_most_voted_candidate() {
+        let spec = E::default_spec();
+        let blocks = blocks(&state(&[], &spec), &spec);
+        let follow_distance = spec.eth1_follow_distance;
+        let (a, b, recent, old) = (
+            vote(&blocks, follow_distance + 2),
+            vote(&blocks, follow_distance + 4),
+            vote(&blocks, 2 * follow_distance + 1),
+            vote(&blocks, follow_distance - 1),
+        );
+
+        // Votes for blocks outside the window are ignored, however many there are.
+        let votes = vec![
+            a.clone(),
+            recent.clone(),
+            b.clone(),
+            recent.clone(),
+            old.clone(),
+            b.clone(),
+            recent.clone(),
+            old.clone(),
+            a.clone(),
+            b.clone(),
+            recent.clone(),
+        ];
+        assert_eq!(eth1_vote(&state(&votes, &spec), blocks.iter(), &spec), b);
+
+        // Ties are broken by the earliest vote.
+        let votes = vec![b.clone(), a.clone(), a.clone(), b.clone()];
+        assert_eq!(eth1_vote(&state(&votes, &spec), blocks.iter(), &spec), b);
+    }
+
+    #[test]
+    fn vote_never_reduces_deposit_count() {
+        let spec = E::default_spec();
+        let follow_distance = spec.eth1_follow_distance;
+        let blocks = blocks(&state(&[], &spec), &spec);
+        let latest = vote(&blocks, 2 * follow_distance);
+        let fewer_deposits = vote(&blocks, follow_distance);
+
+        let mut state = state(&[fewer_deposits.clone(), fewer_deposits], &spec);
+        state.eth1_data.deposit_count = latest.deposit_count;
+
+        assert_eq!(eth1_vote(&state, blocks.iter(), &spec), latest);
+    }
+
+    #[test]
+    fn eth1_data_changes_on_majority() {
+        let spec = E::default_spec();
+        let blocks = blocks(&state(&[], &spec), &spec);
+        let new_eth1_data = vote(&blocks, 1);
+        let half = E::slots_per_eth1_voting_period() / 2;
+
+        // With the vote of the block, exactly half of the period is not a majority.
+        let state = state(&vec![new_eth1_data.clone(); half - 1], &spec);
+        assert_eq!(
+            eth1_data_after_vote(&state, &new_eth1_data),
+            state.eth1_data
+        );
+
+        let state = self::state(&vec![new_eth1_data.clone(); half], &spec);
+        assert_eq!(eth1_data_after_vote(&state, &new_eth1_data), new_eth1_data);
+    }
+}
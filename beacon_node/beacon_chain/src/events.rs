@@ -1,9 +1,27 @@
+use futures::sync::mpsc;
+use parking_lot::Mutex;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::marker::PhantomData;
-use types::{Attestation, BeaconBlock, Epoch, EthSpec, Hash256};
+use std::str::FromStr;
+use std::sync::Arc;
+use types::{
+    Attestation, AttesterSlashing, BeaconBlock, Deposit, Epoch, EthSpec, Hash256,
+    IndexedAttestation, ProposerSlashing, Slot, Transfer, VoluntaryExit,
+};
+
+/// The default number of events that may be buffered for a single subscriber of an
+/// `EventBroadcaster` before further events are dropped for that subscriber.
+pub const DEFAULT_SUBSCRIBER_CAPACITY: usize = 256;
 
 pub trait EventHandler<T: EthSpec>: Sized + Send + Sync {
     fn register(&self, kind: EventKind<T>) -> Result<(), String>;
+
+    /// Returns `true` if an event on `topic` may be delivered to anyone. Used to avoid building
+    /// expensive events that would be discarded.
+    fn is_subscribed(&self, _topic: EventTopic) -> bool {
+        true
+    }
 }
 
 pub struct NullEventHandler<T: EthSpec>(PhantomData<T>);
@@ -12,6 +30,10 @@ impl<T: EthSpec> EventHandler<T> for NullEventHandler<T> {
     fn register(&self, _kind: EventKind<T>) -> Result<(), String> {
         Ok(())
     }
+
+    fn is_subscribed(&self, _topic: EventTopic) -> bool {
+        false
+    }
 }
 
 impl<T: EthSpec> Default for NullEventHandler<T> {
@@ -20,7 +42,175 @@ impl<T: EthSpec> Default for NullEventHandler<T> {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The receiving end of a subscription to an `EventBroadcaster`.
+pub type EventReceiver<T> = mpsc::Receiver<Arc<EventKind<T>>>;
+
+struct Subscriber<T: EthSpec> {
+    /// The topics this subscriber is interested in. `None` means all topics.
+    topics: Option<HashSet<EventTopic>>,
+    sender: mpsc::Sender<Arc<EventKind<T>>>,
+}
+
+impl<T: EthSpec> Subscriber<T> {
+    /// Returns `true` if the subscriber is interested in events on `topic`.
+    fn wants(&self, topic: EventTopic) -> bool {
+        self.topics
+            .as_ref()
+            .map_or(true, |topics| topics.contains(&topic))
+    }
+}
+
+/// An `EventHandler` which fans each event out to any number of subscribers.
+///
+/// Each subscriber has its own bounded buffer. If a subscriber does not keep up, events are
+/// dropped for that subscriber only; the beacon chain is never blocked and other subscribers are
+/// unaffected. Subscribers are removed once their receiver is dropped.
+pub struct EventBroadcaster<T: EthSpec> {
+    subscribers: Arc<Mutex<Vec<Subscriber<T>>>>,
+}
+
+impl<T: EthSpec> EventBroadcaster<T> {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Subscribes to all events whose topic is in `topics`, or all events if `topics` is `None`.
+    ///
+    /// At most `capacity` events are buffered for the subscriber.
+    pub fn subscribe(
+        &self,
+        topics: Option<HashSet<EventTopic>>,
+        capacity: usize,
+    ) -> EventReceiver<T> {
+        let (sender, receiver) = mpsc::channel(capacity);
+        self.subscribers.lock().push(Subscriber { topics, sender });
+        receiver
+    }
+
+    /// Returns the number of subscribers that have not yet been removed.
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().len()
+    }
+}
+
+impl<T: EthSpec> Default for EventBroadcaster<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: EthSpec> Clone for EventBroadcaster<T> {
+    fn clone(&self) -> Self {
+        Self {
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+impl<T: EthSpec> EventHandler<T> for EventBroadcaster<T> {
+    fn register(&self, kind: EventKind<T>) -> Result<(), String> {
+        let topic = kind.topic();
+        let event = Arc::new(kind);
+
+        let mut subscribers = self.subscribers.lock();
+        *subscribers = std::mem::replace(&mut *subscribers, vec![])
+            .into_iter()
+            .filter_map(|mut subscriber| {
+                if !subscriber.wants(topic) {
+                    return Some(subscriber);
+                }
+
+                match subscriber.sender.try_send(event.clone()) {
+                    Ok(()) => Some(subscriber),
+                    // The subscriber is too slow. Drop this event for it, but keep the subscription.
+                    Err(ref e) if e.is_full() => Some(subscriber),
+                    // The receiver has been dropped.
+                    Err(_) => None,
+                }
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    fn is_subscribed(&self, topic: EventTopic) -> bool {
+        self.subscribers
+            .lock()
+            .iter()
+            .any(|subscriber| subscriber.wants(topic))
+    }
+}
+
+/// The topics which subscribers may filter events by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventTopic {
+    Head,
+    ChainReorg,
+    Block,
+    BlockRejected,
+    JustifiedCheckpoint,
+    FinalizedCheckpoint,
+    Attestation,
+    AttestationRejected,
+    Slot,
+    Epoch,
+    OperationPool,
+    BalanceChanges,
+}
+
+impl EventTopic {
+    /// All topics, in the order they are documented.
+    pub fn all() -> &'static [EventTopic] {
+        &[
+            EventTopic::Head,
+            EventTopic::ChainReorg,
+            EventTopic::Block,
+            EventTopic::BlockRejected,
+            EventTopic::JustifiedCheckpoint,
+            EventTopic::FinalizedCheckpoint,
+            EventTopic::Attestation,
+            EventTopic::AttestationRejected,
+            EventTopic::Slot,
+            EventTopic::Epoch,
+            EventTopic::OperationPool,
+            EventTopic::BalanceChanges,
+        ]
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventTopic::Head => "head",
+            EventTopic::ChainReorg => "chain_reorg",
+            EventTopic::Block => "block",
+            EventTopic::BlockRejected => "block_rejected",
+            EventTopic::JustifiedCheckpoint => "justified_checkpoint",
+            EventTopic::FinalizedCheckpoint => "finalized_checkpoint",
+            EventTopic::Attestation => "attestation",
+            EventTopic::AttestationRejected => "attestation_rejected",
+            EventTopic::Slot => "slot",
+            EventTopic::Epoch => "epoch",
+            EventTopic::OperationPool => "operation_pool",
+            EventTopic::BalanceChanges => "balance_changes",
+        }
+    }
+}
+
+impl FromStr for EventTopic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        EventTopic::all()
+            .iter()
+            .find(|topic| topic.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown event topic: {}", s))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(
     bound = "T: EthSpec",
     rename_all = "snake_case",
@@ -33,12 +223,25 @@ pub enum EventKind<T: EthSpec> {
         current_head_beacon_block_root: Hash256,
         previous_head_beacon_block_root: Hash256,
     },
+    BeaconChainReorg {
+        /// The number of slots between the previous head and the common ancestor.
+        depth: u64,
+        previous_head_beacon_block_root: Hash256,
+        current_head_beacon_block_root: Hash256,
+        common_ancestor_beacon_block_root: Hash256,
+        common_ancestor_slot: Slot,
+    },
+    BeaconJustification {
+        epoch: Epoch,
+        root: Hash256,
+    },
     BeaconFinalization {
         epoch: Epoch,
         root: Hash256,
     },
     BeaconBlockImported {
         block_root: Hash256,
+        proposer_index: u64,
         block: Box<BeaconBlock<T>>,
     },
     BeaconBlockRejected {
@@ -46,10 +249,189 @@ pub enum EventKind<T: EthSpec> {
         block: Box<BeaconBlock<T>>,
     },
     BeaconAttestationImported {
+        attesting_indices: Vec<u64>,
         attestation: Box<Attestation<T>>,
     },
     BeaconAttestationRejected {
         reason: String,
         attestation: Box<Attestation<T>>,
     },
+    BeaconSlotStart {
+        slot: Slot,
+    },
+    BeaconEpochStart {
+        epoch: Epoch,
+    },
+    OperationPoolInsertion {
+        operation: PoolOperation<T>,
+    },
+    ValidatorBalancesChanged {
+        epoch: Epoch,
+        changes: Vec<BalanceChange>,
+    },
+}
+
+impl<T: EthSpec> EventKind<T> {
+    /// Returns the topic that this event is published on.
+    pub fn topic(&self) -> EventTopic {
+        match self {
+            EventKind::BeaconHeadChanged { .. } => EventTopic::Head,
+            EventKind::BeaconChainReorg { .. } => EventTopic::ChainReorg,
+            EventKind::BeaconJustification { .. } => EventTopic::JustifiedCheckpoint,
+            EventKind::BeaconFinalization { .. } => EventTopic::FinalizedCheckpoint,
+            EventKind::BeaconBlockImported { .. } => EventTopic::Block,
+            EventKind::BeaconBlockRejected { .. } => EventTopic::BlockRejected,
+            EventKind::BeaconAttestationImported { .. } => EventTopic::Attestation,
+            EventKind::BeaconAttestationRejected { .. } => EventTopic::AttestationRejected,
+            EventKind::BeaconSlotStart { .. } => EventTopic::Slot,
+            EventKind::BeaconEpochStart { .. } => EventTopic::Epoch,
+            EventKind::OperationPoolInsertion { .. } => EventTopic::OperationPool,
+            EventKind::ValidatorBalancesChanged { .. } => EventTopic::BalanceChanges,
+        }
+    }
+}
+
+/// An operation that has been added to the operation pool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    bound = "T: EthSpec",
+    rename_all = "snake_case",
+    tag = "kind",
+    content = "operation"
+)]
+pub enum PoolOperation<T: EthSpec> {
+    Deposit { index: u64, deposit: Box<Deposit> },
+    VoluntaryExit(VoluntaryExit),
+    Transfer(Transfer),
+    ProposerSlashing(Box<ProposerSlashing>),
+    AttesterSlashing(Box<AttesterSlashing<T>>),
+}
+
+impl<T: EthSpec> PoolOperation<T> {
+    /// Returns the indices of the validators that the operation concerns.
+    ///
+    /// Deposits do not reference validators by index, so an empty list is returned for them.
+    pub fn validator_indices(&self) -> Vec<u64> {
+        match self {
+            PoolOperation::Deposit { .. } => vec![],
+            PoolOperation::VoluntaryExit(exit) => vec![exit.validator_index],
+            PoolOperation::Transfer(transfer) => vec![transfer.sender, transfer.recipient],
+            PoolOperation::ProposerSlashing(slashing) => vec![slashing.proposer_index],
+            PoolOperation::AttesterSlashing(slashing) => {
+                let attesting_indices = |attestation: &IndexedAttestation<T>| {
+                    attestation
+                        .custody_bit_0_indices
+                        .iter()
+                        .chain(attestation.custody_bit_1_indices.iter())
+                        .copied()
+                        .collect::<HashSet<u64>>()
+                };
+
+                let mut indices: Vec<u64> = attesting_indices(&slashing.attestation_1)
+                    .intersection(&attesting_indices(&slashing.attestation_2))
+                    .copied()
+                    .collect();
+                indices.sort();
+                indices
+            }
+        }
+    }
+}
+
+/// The change in the balance of a single validator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceChange {
+    pub validator_index: u64,
+    pub previous_balance: u64,
+    pub balance: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{Async, Future, Stream};
+    use types::MinimalEthSpec;
+
+    type E = MinimalEthSpec;
+
+    fn finalization(epoch: u64) -> EventKind<E> {
+        EventKind::BeaconFinalization {
+            epoch: Epoch::new(epoch),
+            root: Hash256::zero(),
+        }
+    }
+
+    fn head() -> EventKind<E> {
+        EventKind::BeaconHeadChanged {
+            reorg: false,
+            current_head_beacon_block_root: Hash256::zero(),
+            previous_head_beacon_block_root: Hash256::zero(),
+        }
+    }
+
+    /// Drains all events that are ready on `receiver`.
+    fn drain(receiver: &mut EventReceiver<E>) -> Vec<EventTopic> {
+        let mut topics = vec![];
+        futures::future::lazy(|| {
+            while let Ok(Async::Ready(Some(event))) = receiver.poll() {
+                topics.push(event.topic());
+            }
+            Ok::<_, ()>(())
+        })
+        .wait()
+        .unwrap();
+        topics
+    }
+
+    #[test]
+    fn filters_by_topic() {
+        let broadcaster = EventBroadcaster::<E>::new();
+        let mut all = broadcaster.subscribe(None, 8);
+        let mut heads =
+            broadcaster.subscribe(Some(vec![EventTopic::Head].into_iter().collect()), 8);
+
+        broadcaster.register(head()).unwrap();
+        broadcaster.register(finalization(1)).unwrap();
+
+        assert_eq!(
+            drain(&mut all),
+            vec![EventTopic::Head, EventTopic::FinalizedCheckpoint]
+        );
+        assert_eq!(drain(&mut heads), vec![EventTopic::Head]);
+    }
+
+    #[test]
+    fn is_subscribed_by_topic() {
+        let broadcaster = EventBroadcaster::<E>::new();
+        assert!(!broadcaster.is_subscribed(EventTopic::Block));
+
+        let _heads = broadcaster.subscribe(Some(vec![EventTopic::Head].into_iter().collect()), 8);
+        assert!(broadcaster.is_subscribed(EventTopic::Head));
+        assert!(!broadcaster.is_subscribed(EventTopic::Block));
+
+        let _all = broadcaster.subscribe(None, 8);
+        assert!(broadcaster.is_subscribed(EventTopic::Block));
+    }
+
+    #[test]
+    fn slow_and_dropped_subscribers() {
+        let broadcaster = EventBroadcaster::<E>::new();
+        let mut slow = broadcaster.subscribe(None, 1);
+        let dropped = broadcaster.subscribe(None, 1);
+        drop(dropped);
+
+        for epoch in 0..8 {
+            broadcaster.register(finalization(epoch)).unwrap();
+        }
+
+        // The dropped subscriber is removed whilst the slow subscriber is kept.
+        assert_eq!(broadcaster.subscriber_count(), 1);
+
+        let received = drain(&mut slow);
+        assert!(!received.is_empty() && received.len() < 8);
+
+        // The slow subscriber continues to receive events once it catches up.
+        broadcaster.register(head()).unwrap();
+        assert_eq!(drain(&mut slow), vec![EventTopic::Head]);
+    }
 }
@@ -1,5 +1,5 @@
 use crate::{metrics, BeaconChain, BeaconChainTypes};
-use lmd_ghost::LmdGhost;
+use lmd_ghost::{ForkChoiceDump, LmdGhost};
 use state_processing::common::get_attesting_indices;
 use std::sync::Arc;
 use store::{Error as StoreError, Store};
@@ -58,6 +58,13 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
         let (start_state, start_block_root, start_block_slot) = {
             let state = &chain.head().beacon_state;
 
+            // Only blocks which agree with the head about justification and finalization may
+            // become the head.
+            self.backend.update_checkpoint_epochs(
+                state.current_justified_checkpoint.epoch,
+                state.finalized_checkpoint.epoch,
+            )?;
+
             let (block_root, block_slot) =
                 if state.current_epoch() + 1 > state.current_justified_checkpoint.epoch {
                     (
@@ -142,7 +149,12 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
         //
         // A case where a block without any votes can be the head is where it is the only child of
         // a block that has the majority of votes applied to it.
-        self.backend.process_block(block, block_root)?;
+        self.backend.process_block(
+            block,
+            block_root,
+            state.current_justified_checkpoint.epoch,
+            state.finalized_checkpoint.epoch,
+        )?;
 
         metrics::stop_timer(timer);
 
@@ -207,6 +219,11 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
         self.backend.verify_integrity()
     }
 
+    /// Returns a snapshot of the nodes and latest messages held by the fork choice backend.
+    pub fn dump(&self) -> Result<ForkChoiceDump> {
+        self.backend.dump().map_err(Error::BackendError)
+    }
+
     /// Inform the fork choice that the given block (and corresponding root) have been finalized so
     /// it may prune it's storage.
     ///
@@ -11,6 +11,7 @@ pub mod events;
 mod fork_choice;
 mod iter;
 mod metrics;
+pub mod naive_aggregation_pool;
 mod persisted_beacon_chain;
 pub mod test_utils;
 
@@ -20,7 +21,8 @@ pub use self::beacon_chain::{
 pub use self::checkpoint::CheckPoint;
 pub use self::errors::{BeaconChainError, BlockProductionError};
 pub use beacon_chain_builder::BeaconChainBuilder;
-pub use eth1_chain::{Eth1ChainBackend, InteropEth1ChainBackend};
+pub use eth1;
+pub use eth1_chain::{CachingEth1Backend, Eth1ChainBackend, InteropEth1ChainBackend};
 pub use lmd_ghost;
 pub use metrics::scrape_for_metrics;
 pub use parking_lot;
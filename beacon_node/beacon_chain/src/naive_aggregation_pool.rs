@@ -0,0 +1,239 @@
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use types::{Attestation, AttestationData, EthSpec, Slot};
+
+/// The number of slots prior to the highest known slot for which aggregates are retained.
+///
+/// Aggregators publish during the slot of the attestation, so only recent slots are required.
+pub const SLOTS_RETAINED: u64 = 3;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The attestation does not have exactly one aggregation bit set.
+    NotUnaggregated { set_bits: usize },
+    /// The attestation is from a slot which has already been pruned.
+    SlotTooLow {
+        slot: Slot,
+        lowest_permissible: Slot,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InsertOutcome {
+    /// No other attestation had been seen for the `AttestationData`, a new aggregate was started.
+    NewAggregate,
+    /// The attestation was aggregated into the existing aggregate for its `AttestationData`.
+    SignatureAggregated,
+    /// The attester is already included in the aggregate for the `AttestationData`.
+    AlreadyKnown,
+}
+
+/// Aggregates unaggregated attestations (i.e., those with a single signer) with the same
+/// `AttestationData`, as they are received.
+///
+/// Each new attestation is aggregated into the single aggregate for its data, so the aggregate is
+/// always the union of all attestations seen. Only attestations from the last `SLOTS_RETAINED`
+/// slots are kept.
+pub struct NaiveAggregationPool<E: EthSpec> {
+    aggregates: RwLock<HashMap<Slot, HashMap<AttestationData, Attestation<E>>>>,
+}
+
+impl<E: EthSpec> Default for NaiveAggregationPool<E> {
+    fn default() -> Self {
+        Self {
+            aggregates: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl<E: EthSpec> NaiveAggregationPool<E> {
+    /// Aggregates the unaggregated `attestation`, which was created for `slot`.
+    ///
+    /// Assumes the `attestation` is valid.
+    pub fn insert(&self, attestation: &Attestation<E>, slot: Slot) -> Result<InsertOutcome, Error> {
+        let set_bits = attestation.aggregation_bits.num_set_bits();
+        if set_bits != 1 {
+            return Err(Error::NotUnaggregated { set_bits });
+        }
+
+        let mut aggregates = self.aggregates.write();
+
+        let lowest_permissible = aggregates
+            .keys()
+            .max()
+            .map_or(slot, |highest| std::cmp::max(slot, *highest))
+            .saturating_sub(SLOTS_RETAINED);
+        if slot < lowest_permissible {
+            return Err(Error::SlotTooLow {
+                slot,
+                lowest_permissible,
+            });
+        }
+
+        if !aggregates.contains_key(&slot) {
+            aggregates.retain(|existing_slot, _| *existing_slot >= lowest_permissible);
+        }
+
+        let outcome = match aggregates
+            .entry(slot)
+            .or_insert_with(HashMap::new)
+            .get_mut(&attestation.data)
+        {
+            Some(aggregate) if !aggregate.signers_disjoint_from(attestation) => {
+                InsertOutcome::AlreadyKnown
+            }
+            Some(aggregate) => {
+                aggregate.aggregate(attestation);
+                InsertOutcome::SignatureAggregated
+            }
+            None => InsertOutcome::NewAggregate,
+        };
+
+        if outcome == InsertOutcome::NewAggregate {
+            aggregates
+                .get_mut(&slot)
+                .expect("slot was inserted above")
+                .insert(attestation.data.clone(), attestation.clone());
+        }
+
+        Ok(outcome)
+    }
+
+    /// Returns the aggregate of all attestations seen for `data`, which was created for `slot`.
+    pub fn get(&self, data: &AttestationData, slot: Slot) -> Option<Attestation<E>> {
+        self.aggregates
+            .read()
+            .get(&slot)
+            .and_then(|aggregates| aggregates.get(data))
+            .cloned()
+    }
+
+    /// Returns the number of aggregates in the pool.
+    pub fn num_aggregates(&self) -> usize {
+        self.aggregates.read().values().map(HashMap::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{
+        test_utils::{SeedableRng, TestRandom, XorShiftRng},
+        AggregateSignature, BitList, Hash256, MinimalEthSpec,
+    };
+
+    type E = MinimalEthSpec;
+
+    fn unaggregated(data: &AttestationData, committee_len: usize, bit: usize) -> Attestation<E> {
+        let mut aggregation_bits = BitList::with_capacity(committee_len).expect("valid length");
+        aggregation_bits.set(bit, true).expect("valid bit");
+
+        Attestation {
+            aggregation_bits,
+            data: data.clone(),
+            custody_bits: BitList::with_capacity(committee_len).expect("valid length"),
+            signature: AggregateSignature::new(),
+        }
+    }
+
+    fn data(seed: u64) -> AttestationData {
+        let mut rng = XorShiftRng::from_seed([seed as u8; 16]);
+        AttestationData::random_for_test(&mut rng)
+    }
+
+    #[test]
+    fn aggregates_each_attester_once() {
+        let pool = NaiveAggregationPool::<E>::default();
+        let data = data(42);
+        let slot = Slot::new(1);
+
+        assert_eq!(
+            pool.insert(&unaggregated(&data, 4, 0), slot),
+            Ok(InsertOutcome::NewAggregate)
+        );
+        assert_eq!(
+            pool.insert(&unaggregated(&data, 4, 2), slot),
+            Ok(InsertOutcome::SignatureAggregated)
+        );
+        assert_eq!(
+            pool.insert(&unaggregated(&data, 4, 0), slot),
+            Ok(InsertOutcome::AlreadyKnown)
+        );
+
+        let aggregate = pool.get(&data, slot).expect("aggregate should exist");
+        assert_eq!(aggregate.aggregation_bits.num_set_bits(), 2);
+        assert!(aggregate.aggregation_bits.get(0).unwrap());
+        assert!(aggregate.aggregation_bits.get(2).unwrap());
+
+        assert_eq!(pool.get(&data, slot + 1), None);
+        assert_eq!(pool.num_aggregates(), 1);
+    }
+
+    #[test]
+    fn separates_attestation_data() {
+        let pool = NaiveAggregationPool::<E>::default();
+        let slot = Slot::new(1);
+        let data_a = data(1);
+        let mut data_b = data_a.clone();
+        data_b.beacon_block_root = Hash256::from_low_u64_be(1);
+
+        pool.insert(&unaggregated(&data_a, 4, 0), slot).unwrap();
+        pool.insert(&unaggregated(&data_b, 4, 1), slot).unwrap();
+
+        assert_eq!(pool.num_aggregates(), 2);
+        assert_eq!(
+            pool.get(&data_b, slot)
+                .expect("aggregate should exist")
+                .aggregation_bits
+                .num_set_bits(),
+            1
+        );
+    }
+
+    #[test]
+    fn rejects_aggregated_attestations() {
+        let pool = NaiveAggregationPool::<E>::default();
+        let data = data(7);
+
+        let mut attestation = unaggregated(&data, 4, 0);
+        attestation.aggregation_bits.set(1, true).unwrap();
+
+        assert_eq!(
+            pool.insert(&attestation, Slot::new(0)),
+            Err(Error::NotUnaggregated { set_bits: 2 })
+        );
+
+        let empty = Attestation {
+            aggregation_bits: BitList::with_capacity(4).unwrap(),
+            ..attestation
+        };
+        assert_eq!(
+            pool.insert(&empty, Slot::new(0)),
+            Err(Error::NotUnaggregated { set_bits: 0 })
+        );
+    }
+
+    #[test]
+    fn prunes_old_slots() {
+        let pool = NaiveAggregationPool::<E>::default();
+        let data = data(3);
+
+        for slot in 0..=SLOTS_RETAINED * 2 {
+            pool.insert(&unaggregated(&data, 4, 0), Slot::new(slot))
+                .unwrap();
+        }
+
+        let highest = Slot::new(SLOTS_RETAINED * 2);
+        assert_eq!(pool.num_aggregates(), SLOTS_RETAINED as usize + 1);
+        assert!(pool.get(&data, highest - SLOTS_RETAINED).is_some());
+        assert!(pool.get(&data, highest - SLOTS_RETAINED - 1).is_none());
+
+        assert_eq!(
+            pool.insert(&unaggregated(&data, 4, 1), highest - SLOTS_RETAINED - 1),
+            Err(Error::SlotTooLow {
+                slot: highest - SLOTS_RETAINED - 1,
+                lowest_permissible: highest - SLOTS_RETAINED,
+            })
+        );
+    }
+}
@@ -15,7 +15,9 @@ use lmd_ghost::ThreadSafeReducedTree;
 use rand::Rng;
 use store::{MemoryStore, Store};
 use types::test_utils::{SeedableRng, TestRandom, XorShiftRng};
-use types::{Deposit, EthSpec, Hash256, Keypair, MinimalEthSpec, RelativeEpoch, Slot};
+use types::{
+    AggregateAndProof, Deposit, EthSpec, Hash256, Keypair, MinimalEthSpec, RelativeEpoch, Slot,
+};
 
 // Should ideally be divisible by 3.
 pub const VALIDATOR_COUNT: usize = 24;
@@ -414,6 +416,93 @@ fn attestations_with_increasing_slots() {
     }
 }
 
+#[test]
+fn aggregates_are_produced_exactly_once() {
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    harness.extend_chain(
+        1,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::SomeValidators(vec![]),
+    );
+
+    let head = harness.chain.head();
+    let state = &head.beacon_state;
+    let attestations = harness.get_free_attestations(
+        &AttestationStrategy::AllValidators,
+        state,
+        head.beacon_block_root,
+        head.beacon_block.slot,
+    );
+
+    for attestation in &attestations {
+        assert_eq!(
+            harness.chain.process_attestation(attestation.clone()),
+            Ok(AttestationProcessingOutcome::Processed)
+        );
+    }
+
+    let attestation = &attestations[0];
+    let committee = state
+        .get_crosslink_committee_for_shard(attestation.data.crosslink.shard, RelativeEpoch::Current)
+        .expect("should get committee");
+    let position = attestation
+        .aggregation_bits
+        .iter()
+        .position(|bit| bit)
+        .expect("should have an attester");
+    let validator_index = committee.committee[position];
+    let selection_proof = AggregateAndProof::<MinimalEthSpec>::produce_selection_proof(
+        committee.slot,
+        &KEYPAIRS[validator_index].sk,
+        &state.fork,
+        &harness.spec,
+    );
+
+    // With small committees, every member is selected to aggregate.
+    for _ in 0..2 {
+        assert_eq!(
+            harness
+                .chain
+                .register_aggregation_duty(attestation, selection_proof.clone()),
+            Ok(true),
+            "should register the duty"
+        );
+    }
+
+    let aggregates = harness.chain.produce_aggregates(committee.slot);
+    assert_eq!(aggregates.len(), 1, "should produce a single aggregate");
+    assert_eq!(aggregates[0].aggregator_index, validator_index as u64);
+    assert_eq!(
+        aggregates[0].aggregate.aggregation_bits.num_set_bits(),
+        committee.committee.len(),
+        "should aggregate the whole committee"
+    );
+
+    assert!(
+        harness.chain.produce_aggregates(committee.slot).is_empty(),
+        "should not produce the aggregate again in the same slot"
+    );
+    assert!(
+        harness
+            .chain
+            .produce_aggregates(committee.slot + 1)
+            .is_empty(),
+        "should not produce the aggregate again in a later slot"
+    );
+
+    assert_eq!(
+        harness
+            .chain
+            .register_aggregation_duty(attestation, selection_proof),
+        Ok(true)
+    );
+    assert!(
+        harness.chain.produce_aggregates(committee.slot).is_empty(),
+        "should not produce the aggregate of a re-registered duty"
+    );
+}
+
 #[test]
 fn free_attestations_added_to_fork_choice_all_updated() {
     let num_blocks_produced = MinimalEthSpec::slots_per_epoch() * 2 - 1;
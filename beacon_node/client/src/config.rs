@@ -1,3 +1,4 @@
+use beacon_chain::eth1::Config as Eth1Config;
 use clap::ArgMatches;
 use network::NetworkConfig;
 use serde_derive::{Deserialize, Serialize};
@@ -24,6 +25,8 @@ pub struct Config {
     #[serde(skip)]
     pub beacon_chain_start_method: BeaconChainStartMethod,
     pub eth1_backend_method: Eth1BackendMethod,
+    /// Configures how the eth1 node is followed by the `Web3` backend.
+    pub eth1: Eth1Config,
     pub network: network::NetworkConfig,
     pub rpc: rpc::RPCConfig,
     pub rest_api: rest_api::ApiConfig,
@@ -63,6 +66,9 @@ pub enum BeaconChainStartMethod {
     /// Create a new beacon chain by using a HTTP server (running our REST-API) to load genesis and
     /// finalized states and blocks.
     HttpBootstrap { server: String, port: Option<u16> },
+    /// Create a new beacon chain from the deposit contract of the eth1 node configured by
+    /// `Config::eth1`, waiting until the deposits and eth1 blocks produce a valid genesis state.
+    DepositContract,
 }
 
 impl Default for BeaconChainStartMethod {
@@ -77,8 +83,8 @@ impl Default for BeaconChainStartMethod {
 pub enum Eth1BackendMethod {
     /// Use the mocked eth1 backend used in interop testing
     Interop,
-    /// Use a web3 connection to a running Eth1 node.
-    Web3 { server: String },
+    /// Use a web3 connection to a running Eth1 node, as configured by `Config::eth1`.
+    Web3,
 }
 
 impl Default for Eth1BackendMethod {
@@ -101,6 +107,7 @@ impl Default for Config {
             spec_constants: TESTNET_SPEC_CONSTANTS.into(),
             beacon_chain_start_method: <_>::default(),
             eth1_backend_method: <_>::default(),
+            eth1: <_>::default(),
         }
     }
 }
@@ -175,6 +182,20 @@ impl Config {
         self.rest_api.apply_cli_args(args)?;
         self.websocket_server.apply_cli_args(args)?;
 
+        if let Some(server) = args.value_of("eth1-server") {
+            self.eth1.endpoint = server.to_string();
+        }
+
+        if let Some(address) = args.value_of("eth1-deposit-contract") {
+            self.eth1.deposit_contract_address = address.to_string();
+        }
+
+        if let Some(block) = args.value_of("eth1-deposit-contract-deploy-block") {
+            self.eth1.deposit_contract_deploy_block = block
+                .parse()
+                .map_err(|_| "eth1-deposit-contract-deploy-block is not a valid u64.")?;
+        }
+
         if let Some(log_file) = args.value_of("logfile") {
             self.log_file = PathBuf::from(log_file);
             self.update_logger(log)?;
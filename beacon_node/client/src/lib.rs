@@ -6,75 +6,118 @@ pub mod error;
 pub mod notifier;
 
 use beacon_chain::{
-    lmd_ghost::ThreadSafeReducedTree, slot_clock::SystemTimeSlotClock, store::Store,
-    test_utils::generate_deterministic_keypairs, BeaconChain, BeaconChainBuilder,
+    eth1::Config as Eth1Config,
+    events::{EventBroadcaster, EventHandler, EventKind},
+    slot_clock::SystemTimeSlotClock,
+    store::Store,
+    test_utils::generate_deterministic_keypairs,
+    BeaconChain, BeaconChainBuilder,
+};
+use eth2_libp2p::{
+    PubsubMessage, Topic, BEACON_AGGREGATE_AND_PROOF_TOPIC, TOPIC_ENCODING_POSTFIX, TOPIC_PREFIX,
 };
 use exit_future::Signal;
 use futures::{future::Future, Stream};
-use network::Service as NetworkService;
+use genesis::Eth1GenesisService;
+use network::{NetworkMessage, Service as NetworkService};
 use rest_api::NetworkInfo;
 use slog::{crit, debug, error, info, o};
 use slot_clock::SlotClock;
+use ssz::Encode;
 use std::marker::PhantomData;
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use tokio::runtime::TaskExecutor;
+use tokio::runtime::{Runtime, TaskExecutor};
+use tokio::sync::mpsc;
 use tokio::timer::Interval;
 use types::EthSpec;
-use websocket_server::WebSocketSender;
 
-pub use beacon_chain::{BeaconChainTypes, Eth1ChainBackend, InteropEth1ChainBackend};
+pub use beacon_chain::{
+    BeaconChainTypes, CachingEth1Backend, Eth1ChainBackend, InteropEth1ChainBackend,
+};
 pub use config::{BeaconChainStartMethod, Config as ClientConfig, Eth1BackendMethod};
 pub use eth2_config::Eth2Config;
 
+/// The fork choice implementation, selected at build time by the `proto_array_fork_choice`
+/// feature.
+#[cfg(not(feature = "proto_array_fork_choice"))]
+type DefaultLmdGhost<S, E> = beacon_chain::lmd_ghost::ThreadSafeReducedTree<S, E>;
+#[cfg(feature = "proto_array_fork_choice")]
+type DefaultLmdGhost<S, E> = beacon_chain::lmd_ghost::ThreadSafeProtoArray<S, E>;
+
+/// The `BeaconChainTypes` of a running client.
+///
+/// The slot clock defaults to the system time. Other clocks (e.g., `TestingSlotClock`) may be used
+/// to run a client whose slots are driven externally.
+///
+/// The eth1 backend defaults to the interop backend, which requires no eth1 node.
 #[derive(Clone)]
-pub struct RuntimeBeaconChainTypes<S: Store, E: EthSpec> {
+pub struct RuntimeBeaconChainTypes<
+    S: Store,
+    E: EthSpec,
+    C = SystemTimeSlotClock,
+    B = InteropEth1ChainBackend<E>,
+> {
     _phantom_s: PhantomData<S>,
     _phantom_e: PhantomData<E>,
+    _phantom_c: PhantomData<C>,
+    _phantom_b: PhantomData<B>,
 }
 
-impl<S, E> BeaconChainTypes for RuntimeBeaconChainTypes<S, E>
+impl<S, E, C, B> BeaconChainTypes for RuntimeBeaconChainTypes<S, E, C, B>
 where
     S: Store + 'static,
     E: EthSpec,
+    C: SlotClock + 'static,
+    B: Eth1ChainBackend<E> + 'static,
 {
     type Store = S;
-    type SlotClock = SystemTimeSlotClock;
-    type LmdGhost = ThreadSafeReducedTree<S, E>;
-    type Eth1Chain = InteropEth1ChainBackend<E>;
+    type SlotClock = C;
+    type LmdGhost = DefaultLmdGhost<S, E>;
+    type Eth1Chain = B;
     type EthSpec = E;
-    type EventHandler = WebSocketSender<E>;
+    type EventHandler = EventBroadcaster<E>;
 }
 
 /// Main beacon node client service. This provides the connection and initialisation of the clients
 /// sub-services in multiple threads.
-pub struct Client<S, E>
+pub struct Client<S, E, C = SystemTimeSlotClock, B = InteropEth1ChainBackend<E>>
 where
     S: Store + Clone + 'static,
     E: EthSpec,
+    C: SlotClock + 'static,
+    B: Eth1ChainBackend<E> + 'static,
 {
     /// Configuration for the lighthouse client.
     _client_config: ClientConfig,
     /// The beacon chain for the running client.
-    beacon_chain: Arc<BeaconChain<RuntimeBeaconChainTypes<S, E>>>,
+    beacon_chain: Arc<BeaconChain<RuntimeBeaconChainTypes<S, E, C, B>>>,
     /// Reference to the network service.
-    pub network: Arc<NetworkService<RuntimeBeaconChainTypes<S, E>>>,
+    pub network: Arc<NetworkService<RuntimeBeaconChainTypes<S, E, C, B>>>,
+    /// Channel for sending messages (e.g., gossip publications) to the network service.
+    pub network_send: mpsc::UnboundedSender<NetworkMessage>,
     /// Signal to terminate the RPC server.
     pub rpc_exit_signal: Option<Signal>,
     /// Signal to terminate the slot timer.
     pub slot_timer_exit_signal: Option<Signal>,
+    /// Signal to terminate the aggregation timer.
+    pub aggregation_timer_exit_signal: Option<Signal>,
     /// Signal to terminate the API
     pub api_exit_signal: Option<Signal>,
     /// Signal to terminate the websocket server
     pub websocket_exit_signal: Option<Signal>,
+    /// Signal to stop updating the eth1 backend.
+    pub eth1_exit_signal: Option<Signal>,
     /// The clients logger.
     log: slog::Logger,
 }
 
-impl<S, E> Client<S, E>
+impl<S, E, C, B> Client<S, E, C, B>
 where
     S: Store + Clone + 'static,
     E: EthSpec,
+    C: SlotClock + 'static,
+    B: Eth1ChainBackend<E> + 'static,
 {
     /// Generate an instance of the client. Spawn and link all internal sub-processes.
     pub fn new(
@@ -89,6 +132,13 @@ where
 
         let spec = &eth2_config.spec.clone();
 
+        // Eth1 data votes (and genesis) are only valid for blocks at the spec's follow distance, so
+        // the eth1 node must not be followed any closer than that.
+        let eth1_config = Eth1Config {
+            follow_distance: spec.eth1_follow_distance,
+            ..client_config.eth1.clone()
+        };
+
         let beacon_chain_builder = match &client_config.beacon_chain_start_method {
             BeaconChainStartMethod::Resume => {
                 info!(
@@ -175,27 +225,56 @@ where
                 );
                 BeaconChainBuilder::http_bootstrap(server, spec.clone(), log.clone())?
             }
+            BeaconChainStartMethod::DepositContract => {
+                info!(
+                    log,
+                    "Waiting for eth1 genesis";
+                    "deposit_contract" => &eth1_config.deposit_contract_address,
+                    "eth1_node" => &eth1_config.endpoint,
+                    "method" => "deposit_contract"
+                );
+                let genesis_service = Eth1GenesisService::new(
+                    eth1_config.clone(),
+                    log.new(o!("Service" => "Eth1Genesis")),
+                );
+                let genesis_state = Runtime::new()
+                    .map_err(|e| format!("Unable to start eth1 genesis runtime: {:?}", e))?
+                    .block_on(genesis_service.wait_for_genesis_state::<E>(
+                        Duration::from_millis(eth1_config.auto_update_interval_millis),
+                        spec.clone(),
+                    ))?;
+                BeaconChainBuilder::from_genesis_state(genesis_state, spec.clone(), log.clone())
+            }
         };
 
-        let eth1_backend =
-            InteropEth1ChainBackend::new(String::new()).map_err(|e| format!("{:?}", e))?;
+        let eth1_backend = B::new(&eth1_config, &log.new(o!("Service" => "Eth1")))
+            .map_err(|e| format!("Unable to start eth1 backend: {:?}", e))?;
+
+        // Keep the eth1 backend up-to-date, if it follows an eth1 node.
+        let eth1_exit_signal = eth1_backend.update_task().map(|update_task| {
+            let (eth1_exit_signal, exit) = exit_future::signal();
+            executor.spawn(exit.until(update_task).map(|_| ()));
+            eth1_exit_signal
+        });
+
+        // Chain events are broadcast to the websocket server and the `rest_api` event stream.
+        let event_broadcaster = EventBroadcaster::new();
 
         // Start the websocket server.
-        let (websocket_sender, websocket_exit_signal): (WebSocketSender<E>, Option<_>) =
-            if client_config.websocket_server.enabled {
-                let (sender, exit) = websocket_server::start_server(
-                    &client_config.websocket_server,
-                    executor,
-                    &log,
-                )?;
-                (sender, Some(exit))
-            } else {
-                (WebSocketSender::dummy(), None)
-            };
+        let websocket_exit_signal = if client_config.websocket_server.enabled {
+            Some(websocket_server::start_server(
+                &client_config.websocket_server,
+                executor,
+                &event_broadcaster,
+                &log,
+            )?)
+        } else {
+            None
+        };
 
-        let beacon_chain: Arc<BeaconChain<RuntimeBeaconChainTypes<S, E>>> = Arc::new(
+        let beacon_chain: Arc<BeaconChain<RuntimeBeaconChainTypes<S, E, C, B>>> = Arc::new(
             beacon_chain_builder
-                .build(store, eth1_backend, websocket_sender)
+                .build(store, eth1_backend, event_broadcaster.clone())
                 .map_err(error::Error::from)?,
         );
 
@@ -241,6 +320,7 @@ where
                 executor,
                 beacon_chain.clone(),
                 network_info,
+                event_broadcaster,
                 client_config.db_path().expect("unable to read datadir"),
                 eth2_config.clone(),
                 &log,
@@ -272,6 +352,36 @@ where
                     interval
                         .for_each(move |_| {
                             log_new_slot(&chain, &log);
+                            register_slot_events(&chain);
+
+                            Ok(())
+                        })
+                        .map_err(|_| ()),
+                )
+                .map(|_| ()),
+            );
+        }
+
+        let (aggregation_timer_exit_signal, exit) = exit_future::signal();
+        if let Some(duration_to_next_slot) = beacon_chain.slot_clock.duration_to_next_slot() {
+            // Publish aggregates two-thirds of the way through each slot, allowing time for the
+            // unaggregated attestations of the slot to be received.
+            let interval = {
+                let slot_duration = Duration::from_millis(milliseconds_per_slot);
+                Interval::new(
+                    Instant::now() + duration_to_next_slot + slot_duration * 2 / 3,
+                    slot_duration,
+                )
+            };
+
+            let chain = beacon_chain.clone();
+            let mut network_send = network_send.clone();
+            let log = log.new(o!("Service" => "AggregationTimer"));
+            executor.spawn(
+                exit.until(
+                    interval
+                        .for_each(move |_| {
+                            publish_aggregates(&chain, &mut network_send, &log);
 
                             Ok(())
                         })
@@ -286,15 +396,25 @@ where
             beacon_chain,
             rpc_exit_signal,
             slot_timer_exit_signal: Some(slot_timer_exit_signal),
+            aggregation_timer_exit_signal: Some(aggregation_timer_exit_signal),
             api_exit_signal,
             websocket_exit_signal,
+            eth1_exit_signal,
             log,
             network,
+            network_send,
         })
     }
+
+    /// Returns the beacon chain of the running client.
+    pub fn beacon_chain(&self) -> Arc<BeaconChain<RuntimeBeaconChainTypes<S, E, C, B>>> {
+        self.beacon_chain.clone()
+    }
 }
 
-impl<S: Store + Clone, E: EthSpec> Drop for Client<S, E> {
+impl<S: Store + Clone, E: EthSpec, C: SlotClock, B: Eth1ChainBackend<E>> Drop
+    for Client<S, E, C, B>
+{
     fn drop(&mut self) {
         // Save the beacon chain to it's store before dropping.
         let _result = self.beacon_chain.persist();
@@ -326,3 +446,57 @@ fn log_new_slot<T: BeaconChainTypes>(chain: &Arc<BeaconChain<T>>, log: &slog::Lo
         );
     };
 }
+
+/// Informs the event handler of `chain` that a new slot, and possibly a new epoch, has started.
+fn register_slot_events<T: BeaconChainTypes>(chain: &BeaconChain<T>) {
+    if let Ok(slot) = chain.slot() {
+        let _ = chain
+            .event_handler
+            .register(EventKind::BeaconSlotStart { slot });
+
+        let slots_per_epoch = T::EthSpec::slots_per_epoch();
+        if slot.as_u64() % slots_per_epoch == 0 {
+            let _ = chain.event_handler.register(EventKind::BeaconEpochStart {
+                epoch: slot.epoch(slots_per_epoch),
+            });
+        }
+    }
+}
+
+/// Publishes the aggregates of all local validators which have been selected to aggregate
+/// attestations for the current, or an earlier, slot.
+fn publish_aggregates<T: BeaconChainTypes>(
+    chain: &BeaconChain<T>,
+    network_send: &mut mpsc::UnboundedSender<NetworkMessage>,
+    log: &slog::Logger,
+) {
+    let aggregates = match chain.slot() {
+        Ok(slot) => chain.produce_aggregates(slot),
+        Err(e) => {
+            error!(log, "Unable to read slot for aggregates"; "error" => format!("{:?}", e));
+            return;
+        }
+    };
+
+    let topic = Topic::new(format!(
+        "/{}/{}/{}",
+        TOPIC_PREFIX, BEACON_AGGREGATE_AND_PROOF_TOPIC, TOPIC_ENCODING_POSTFIX
+    ));
+
+    for aggregate_and_proof in aggregates {
+        debug!(
+            log,
+            "Publishing aggregate";
+            "aggregator_index" => aggregate_and_proof.aggregator_index,
+            "attesters" => aggregate_and_proof.aggregate.aggregation_bits.num_set_bits(),
+            "target_epoch" => aggregate_and_proof.aggregate.data.target.epoch,
+        );
+
+        if let Err(e) = network_send.try_send(NetworkMessage::Publish {
+            topics: vec![topic.clone()],
+            message: PubsubMessage::AggregateAndProof(aggregate_and_proof.as_ssz_bytes()),
+        }) {
+            error!(log, "Unable to send aggregate to network"; "error" => format!("{:?}", e));
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use crate::Client;
+use crate::{Client, Eth1ChainBackend};
 use exit_future::Exit;
 use futures::{Future, Stream};
 use slog::{debug, o, warn};
+use slot_clock::SlotClock;
 use std::time::{Duration, Instant};
 use store::Store;
 use tokio::runtime::TaskExecutor;
@@ -18,10 +19,12 @@ pub const WARN_PEER_COUNT: usize = 1;
 /// durations.
 ///
 /// Presently unused, but remains for future use.
-pub fn run<S, E>(client: &Client<S, E>, executor: TaskExecutor, exit: Exit)
+pub fn run<S, E, C, B>(client: &Client<S, E, C, B>, executor: TaskExecutor, exit: Exit)
 where
     S: Store + Clone + 'static,
     E: EthSpec,
+    C: SlotClock + 'static,
+    B: Eth1ChainBackend<E> + 'static,
 {
     // notification heartbeat
     let interval = Interval::new(
@@ -0,0 +1,184 @@
+use types::{Eth1Data, Hash256};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Error {
+    /// The timestamp of each block must be equal to or later than the block prior to it.
+    InconsistentTimestamp { parent: u64, child: u64 },
+    /// Some `Eth1Block` was provided with the same block number but different data. The source
+    /// of eth1 data is inconsistent.
+    Conflicting(u64),
+    /// The given block was not one block number higher than the highest known block number.
+    NonConsecutive { given: u64, expected: u64 },
+}
+
+/// A block of the eth1 chain, along with the state of the deposit contract at the end of it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Eth1Block {
+    pub hash: Hash256,
+    pub timestamp: u64,
+    pub number: u64,
+    pub deposit_root: Hash256,
+    pub deposit_count: u64,
+}
+
+impl Eth1Block {
+    /// Returns the `Eth1Data` that a validator would vote for if this block were chosen.
+    pub fn eth1_data(&self) -> Eth1Data {
+        Eth1Data {
+            deposit_root: self.deposit_root,
+            deposit_count: self.deposit_count,
+            block_hash: self.hash,
+        }
+    }
+}
+
+/// Stores a contiguous, ordered range of eth1 blocks.
+#[derive(Debug, Default)]
+pub struct BlockCache {
+    blocks: Vec<Eth1Block>,
+}
+
+impl BlockCache {
+    /// Returns the number of blocks in the cache.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns `true` if there are no blocks in the cache.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Returns the highest block number stored.
+    pub fn highest_block_number(&self) -> Option<u64> {
+        self.blocks.last().map(|block| block.number)
+    }
+
+    /// Returns the lowest block number stored.
+    pub fn lowest_block_number(&self) -> Option<u64> {
+        self.blocks.first().map(|block| block.number)
+    }
+
+    /// Returns an iterator over all blocks, ordered from lowest to highest block number.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Eth1Block> {
+        self.blocks.iter()
+    }
+
+    /// Returns the block with the given `number`, if it is stored.
+    pub fn block_by_number(&self, number: u64) -> Option<&Eth1Block> {
+        let lowest = self.lowest_block_number()?;
+        number
+            .checked_sub(lowest)
+            .and_then(|i| self.blocks.get(i as usize))
+    }
+
+    /// Shortens the cache, keeping the latest (by block number) `len` blocks and dropping the
+    /// rest.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.blocks.len() {
+            self.blocks = self.blocks.split_off(self.blocks.len() - len);
+        }
+    }
+
+    /// Removes all blocks from the cache.
+    pub fn clear(&mut self) {
+        self.blocks.clear()
+    }
+
+    /// Inserts a block that is either the first block in the cache or the child of the highest
+    /// block.
+    ///
+    /// Inserting a block that is already stored is a no-op.
+    pub fn insert_root_or_child(&mut self, block: Eth1Block) -> Result<(), Error> {
+        let expected = match self.blocks.last() {
+            Some(highest) if block.number <= highest.number => {
+                return if self.block_by_number(block.number) == Some(&block) {
+                    Ok(())
+                } else {
+                    Err(Error::Conflicting(block.number))
+                };
+            }
+            Some(highest) if block.timestamp < highest.timestamp => {
+                return Err(Error::InconsistentTimestamp {
+                    parent: highest.timestamp,
+                    child: block.timestamp,
+                });
+            }
+            Some(highest) => highest.number + 1,
+            None => block.number,
+        };
+
+        if block.number != expected {
+            return Err(Error::NonConsecutive {
+                given: block.number,
+                expected,
+            });
+        }
+
+        self.blocks.push(block);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u64) -> Eth1Block {
+        Eth1Block {
+            hash: Hash256::from_low_u64_be(number),
+            timestamp: number * 15,
+            number,
+            deposit_root: Hash256::zero(),
+            deposit_count: 0,
+        }
+    }
+
+    #[test]
+    fn insert_and_truncate() {
+        let mut cache = BlockCache::default();
+
+        for number in 10..20 {
+            assert_eq!(cache.insert_root_or_child(block(number)), Ok(()));
+        }
+        assert_eq!(cache.len(), 10);
+        assert_eq!(cache.lowest_block_number(), Some(10));
+        assert_eq!(cache.highest_block_number(), Some(19));
+        assert_eq!(cache.block_by_number(12), Some(&block(12)));
+        assert_eq!(cache.block_by_number(9), None);
+        assert_eq!(cache.block_by_number(20), None);
+
+        assert_eq!(cache.insert_root_or_child(block(15)), Ok(()));
+        let mut conflicting = block(15);
+        conflicting.deposit_count = 1;
+        assert_eq!(
+            cache.insert_root_or_child(conflicting),
+            Err(Error::Conflicting(15))
+        );
+        assert_eq!(
+            cache.insert_root_or_child(block(21)),
+            Err(Error::NonConsecutive {
+                given: 21,
+                expected: 20
+            })
+        );
+        let mut early = block(20);
+        early.timestamp = 0;
+        assert_eq!(
+            cache.insert_root_or_child(early),
+            Err(Error::InconsistentTimestamp {
+                parent: 19 * 15,
+                child: 0
+            })
+        );
+
+        cache.truncate(4);
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.lowest_block_number(), Some(16));
+        assert_eq!(cache.highest_block_number(), Some(19));
+
+        cache.truncate(8);
+        assert_eq!(cache.len(), 4);
+    }
+}
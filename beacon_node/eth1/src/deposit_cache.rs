@@ -0,0 +1,290 @@
+use crate::DepositLog;
+use eth2_hashing::hash_concat;
+use int_to_bytes::int_to_bytes32;
+use merkle_proof::{MerkleTree, MerkleTreeError};
+use std::ops::Range;
+use tree_hash::TreeHash;
+use types::{Deposit, Hash256};
+
+/// The depth of the deposit contract Merkle tree, excluding the length mix-in.
+pub const DEPOSIT_CONTRACT_TREE_DEPTH: usize = 32;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// A deposit log was added when a prior deposit was not already in the cache.
+    ///
+    /// Logs have to be added with monotonically-increasing `deposit.index` values, therefore we
+    /// should not encounter this.
+    NonConsecutive { log_index: u64, expected: usize },
+    /// A log with the same index was previously imported, but its contents differed.
+    DuplicateDistinctLog(u64),
+    /// The deposit tree could not accept another leaf.
+    FailedToPushLeaf(MerkleTreeError),
+    /// Deposits were requested for a range that was invalid or not fully known.
+    InvalidRange {
+        range: Range<u64>,
+        deposit_count: u64,
+        known_deposits: usize,
+    },
+}
+
+/// Stores all deposit logs from the deposit contract, incrementally building the deposit Merkle
+/// tree so that proofs for any prefix of the deposits can be generated.
+#[derive(Debug)]
+pub struct DepositCache {
+    logs: Vec<DepositLog>,
+    leaves: Vec<Hash256>,
+    /// `roots[i]` is the deposit root (including the length mix-in) after `i` deposits.
+    roots: Vec<Hash256>,
+    tree: MerkleTree,
+}
+
+impl Default for DepositCache {
+    fn default() -> Self {
+        let tree = MerkleTree::create(&[], DEPOSIT_CONTRACT_TREE_DEPTH);
+        let roots = vec![mix_in_length(tree.hash(), 0)];
+
+        Self {
+            logs: vec![],
+            leaves: vec![],
+            roots,
+            tree,
+        }
+    }
+}
+
+impl DepositCache {
+    /// Returns the number of deposits in the cache.
+    pub fn len(&self) -> usize {
+        self.logs.len()
+    }
+
+    /// Returns `true` if there are no deposits in the cache.
+    pub fn is_empty(&self) -> bool {
+        self.logs.is_empty()
+    }
+
+    /// Returns all deposit logs, ordered by index.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &DepositLog> {
+        self.logs.iter()
+    }
+
+    /// Adds `log` to the cache and the deposit tree.
+    ///
+    /// Re-importing a log that is already in the cache is a no-op, so a range of logs may be
+    /// safely re-imported after a failure part-way through it.
+    pub fn insert_log(&mut self, log: DepositLog) -> Result<(), Error> {
+        let index = log.index as usize;
+
+        if index == self.logs.len() {
+            let leaf = Hash256::from_slice(&log.deposit_data.tree_hash_root());
+            self.tree
+                .push_leaf(leaf, DEPOSIT_CONTRACT_TREE_DEPTH)
+                .map_err(Error::FailedToPushLeaf)?;
+
+            self.leaves.push(leaf);
+            self.logs.push(log);
+            self.roots
+                .push(mix_in_length(self.tree.hash(), self.leaves.len() as u64));
+
+            Ok(())
+        } else if index < self.logs.len() {
+            if self.logs[index] == log {
+                Ok(())
+            } else {
+                Err(Error::DuplicateDistinctLog(log.index))
+            }
+        } else {
+            Err(Error::NonConsecutive {
+                log_index: log.index,
+                expected: self.logs.len(),
+            })
+        }
+    }
+
+    /// Returns the number of deposits that had been made at the end of the eth1 block with the
+    /// given `block_number`.
+    ///
+    /// Only accurate if all logs up to and including `block_number` have been imported.
+    pub fn deposit_count_at_block(&self, block_number: u64) -> u64 {
+        // Blocks are typically recent, so count backwards from the latest deposit.
+        let later_deposits = self
+            .logs
+            .iter()
+            .rev()
+            .take_while(|log| log.block_number > block_number)
+            .count();
+
+        (self.logs.len() - later_deposits) as u64
+    }
+
+    /// Returns the deposit root (including the length mix-in) after `deposit_count` deposits, if
+    /// that many deposits are known.
+    pub fn deposit_root(&self, deposit_count: u64) -> Option<Hash256> {
+        self.roots.get(deposit_count as usize).copied()
+    }
+
+    /// Returns the deposits in `range`, with proofs against the deposit root of the first
+    /// `deposit_count` deposits. Also returns that deposit root.
+    ///
+    /// The proofs are suitable for inclusion in a block whose state has an `eth1_data` with the
+    /// given `deposit_count`.
+    pub fn get_deposits(
+        &self,
+        range: Range<u64>,
+        deposit_count: u64,
+    ) -> Result<(Hash256, Vec<Deposit>), Error> {
+        let invalid_range = || Error::InvalidRange {
+            range: range.clone(),
+            deposit_count,
+            known_deposits: self.logs.len(),
+        };
+
+        if range.start > range.end
+            || range.end > deposit_count
+            || deposit_count as usize > self.logs.len()
+        {
+            return Err(invalid_range());
+        }
+
+        // The proofs must be against the tree of the first `deposit_count` leaves, which is only
+        // the cached tree if there have been no later deposits.
+        let historical_tree;
+        let tree = if deposit_count as usize == self.leaves.len() {
+            &self.tree
+        } else {
+            historical_tree = MerkleTree::create(
+                &self.leaves[0..deposit_count as usize],
+                DEPOSIT_CONTRACT_TREE_DEPTH,
+            );
+            &historical_tree
+        };
+
+        let deposits = self.logs[range.start as usize..range.end as usize]
+            .iter()
+            .map(|log| {
+                let (_leaf, mut proof) =
+                    tree.generate_proof(log.index as usize, DEPOSIT_CONTRACT_TREE_DEPTH);
+                proof.push(Hash256::from_slice(&int_to_bytes32(deposit_count)));
+
+                Deposit {
+                    proof: proof.into(),
+                    data: log.deposit_data.clone(),
+                }
+            })
+            .collect();
+
+        let root = self.deposit_root(deposit_count).ok_or_else(invalid_range)?;
+
+        Ok((root, deposits))
+    }
+}
+
+/// Returns the deposit root given the root of the deposit tree and the number of deposits.
+fn mix_in_length(tree_root: Hash256, deposit_count: u64) -> Hash256 {
+    Hash256::from_slice(&hash_concat(
+        tree_root.as_bytes(),
+        &int_to_bytes32(deposit_count),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::test_utils::{SeedableRng, TestRandom, XorShiftRng};
+    use types::DepositData;
+
+    fn logs(n: u64) -> Vec<DepositLog> {
+        let mut rng = XorShiftRng::from_seed([42; 16]);
+        (0..n)
+            .map(|index| DepositLog {
+                deposit_data: DepositData::random_for_test(&mut rng),
+                block_number: index / 2,
+                index,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn insert_logs() {
+        let mut cache = DepositCache::default();
+        let logs = logs(4);
+
+        assert_eq!(
+            cache.insert_log(logs[1].clone()),
+            Err(Error::NonConsecutive {
+                log_index: 1,
+                expected: 0
+            })
+        );
+
+        for log in &logs {
+            assert_eq!(cache.insert_log(log.clone()), Ok(()));
+        }
+        assert_eq!(cache.len(), 4);
+
+        // Re-importing an identical log is permitted, a different log is not.
+        assert_eq!(cache.insert_log(logs[2].clone()), Ok(()));
+        let mut distinct = logs[2].clone();
+        distinct.deposit_data.amount += 1;
+        assert_eq!(
+            cache.insert_log(distinct),
+            Err(Error::DuplicateDistinctLog(2))
+        );
+        assert_eq!(cache.len(), 4);
+    }
+
+    #[test]
+    fn deposit_count_at_block() {
+        let mut cache = DepositCache::default();
+        for log in logs(5) {
+            cache.insert_log(log).expect("should insert log");
+        }
+
+        // Logs are at blocks 0, 0, 1, 1 and 2.
+        assert_eq!(cache.deposit_count_at_block(0), 2);
+        assert_eq!(cache.deposit_count_at_block(1), 4);
+        assert_eq!(cache.deposit_count_at_block(2), 5);
+        assert_eq!(cache.deposit_count_at_block(9), 5);
+    }
+
+    #[test]
+    fn roots_match_a_fresh_tree() {
+        let mut cache = DepositCache::default();
+        let logs = logs(9);
+        for log in &logs {
+            cache.insert_log(log.clone()).expect("should insert log");
+        }
+
+        for count in 0..=logs.len() {
+            let leaves = logs[0..count]
+                .iter()
+                .map(|log| Hash256::from_slice(&log.deposit_data.tree_hash_root()))
+                .collect::<Vec<_>>();
+            let tree = MerkleTree::create(&leaves, DEPOSIT_CONTRACT_TREE_DEPTH);
+
+            assert_eq!(
+                cache.deposit_root(count as u64),
+                Some(mix_in_length(tree.hash(), count as u64)),
+                "root should match for {} deposits",
+                count
+            );
+        }
+        assert_eq!(cache.deposit_root(10), None);
+    }
+
+    #[test]
+    fn get_deposits_checks_range() {
+        let mut cache = DepositCache::default();
+        for log in logs(4) {
+            cache.insert_log(log).expect("should insert log");
+        }
+
+        assert!(cache.get_deposits(0..4, 4).is_ok());
+        assert!(cache.get_deposits(1..3, 3).is_ok());
+        assert_eq!(cache.get_deposits(2..2, 2).map(|(_, d)| d.len()), Ok(0));
+        assert!(cache.get_deposits(0..4, 3).is_err());
+        assert!(cache.get_deposits(0..5, 5).is_err());
+        assert!(cache.get_deposits(3..2, 4).is_err());
+    }
+}
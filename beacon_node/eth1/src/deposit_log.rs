@@ -0,0 +1,144 @@
+use crate::http::Log;
+use types::{DepositData, Hash256, PublicKeyBytes, SignatureBytes};
+
+/// The number of fields in a `DepositEvent`.
+pub(crate) const FIELD_COUNT: usize = 5;
+/// The length of an ABI word.
+pub(crate) const WORD_LEN: usize = 32;
+
+const PUBKEY_LEN: usize = 48;
+const CREDS_LEN: usize = 32;
+const AMOUNT_LEN: usize = 8;
+const SIG_LEN: usize = 96;
+const INDEX_LEN: usize = 8;
+
+/// A fully parsed eth1 deposit contract log.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DepositLog {
+    pub deposit_data: DepositData,
+    /// The block number of the log that included this `DepositData`.
+    pub block_number: u64,
+    /// The index included with the deposit log.
+    pub index: u64,
+}
+
+impl DepositLog {
+    /// Attempts to parse a raw `Log` from the deposit contract into a `DepositLog`.
+    ///
+    /// The log data is the ABI encoding of `DepositEvent(bytes,bytes,bytes,bytes,bytes)`, where
+    /// the fields are the pubkey, withdrawal credentials, amount, signature and index (integers
+    /// are little-endian).
+    pub fn from_log(log: &Log) -> Result<Self, String> {
+        let pubkey = decode_field(&log.data, 0, PUBKEY_LEN)?;
+        let withdrawal_credentials = decode_field(&log.data, 1, CREDS_LEN)?;
+        let amount = decode_field(&log.data, 2, AMOUNT_LEN)?;
+        let signature = decode_field(&log.data, 3, SIG_LEN)?;
+        let index = decode_field(&log.data, 4, INDEX_LEN)?;
+
+        let deposit_data = DepositData {
+            pubkey: PublicKeyBytes::from_bytes(pubkey)
+                .map_err(|e| format!("Invalid pubkey ssz: {:?}", e))?,
+            withdrawal_credentials: Hash256::from_slice(withdrawal_credentials),
+            amount: u64_from_le_slice(amount),
+            signature: SignatureBytes::from_bytes(signature)
+                .map_err(|e| format!("Invalid signature ssz: {:?}", e))?,
+        };
+
+        Ok(DepositLog {
+            deposit_data,
+            block_number: log.block_number,
+            index: u64_from_le_slice(index),
+        })
+    }
+}
+
+/// Returns the contents of the `i`'th dynamic `bytes` field of ABI-encoded `data`, checking that
+/// it is `expected_len` bytes long.
+fn decode_field(data: &[u8], i: usize, expected_len: usize) -> Result<&[u8], String> {
+    if data.len() < FIELD_COUNT * WORD_LEN {
+        return Err(format!("Deposit log data is too short: {}", data.len()));
+    }
+
+    let offset = read_word_as_usize(data, i * WORD_LEN)?;
+    let len = read_word_as_usize(data, offset)?;
+    if len != expected_len {
+        return Err(format!(
+            "Deposit log field {} has length {}, expected {}",
+            i, len, expected_len
+        ));
+    }
+
+    let start = offset + WORD_LEN;
+    data.get(start..start + len)
+        .ok_or_else(|| format!("Deposit log field {} is out of bounds", i))
+}
+
+/// Reads the big-endian ABI word at `position` as a `usize`.
+fn read_word_as_usize(data: &[u8], position: usize) -> Result<usize, String> {
+    let word = data
+        .get(position..position + WORD_LEN)
+        .ok_or_else(|| format!("Deposit log word at {} is out of bounds", position))?;
+
+    // Reject values that would not fit in a `u32`, they cannot be valid offsets or lengths.
+    if word[..WORD_LEN - 4].iter().any(|byte| *byte != 0) {
+        return Err(format!("Deposit log word at {} is too large", position));
+    }
+
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&word[WORD_LEN - 4..]);
+    Ok(u32::from_be_bytes(bytes) as usize)
+}
+
+fn u64_from_le_slice(slice: &[u8]) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(slice);
+    u64::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::deposit_log_data;
+    use types::test_utils::{SeedableRng, TestRandom, XorShiftRng};
+
+    #[test]
+    fn round_trip_deposit_log() {
+        let mut rng = XorShiftRng::from_seed([42; 16]);
+        let deposit_data = DepositData::random_for_test(&mut rng);
+
+        let log = Log {
+            block_number: 7,
+            data: deposit_log_data(&deposit_data, 3),
+        };
+
+        assert_eq!(
+            DepositLog::from_log(&log),
+            Ok(DepositLog {
+                deposit_data,
+                block_number: 7,
+                index: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_deposit_log() {
+        let mut rng = XorShiftRng::from_seed([42; 16]);
+        let data = deposit_log_data(&DepositData::random_for_test(&mut rng), 0);
+
+        let truncated = Log {
+            block_number: 0,
+            data: data[..data.len() - WORD_LEN].to_vec(),
+        };
+        assert!(DepositLog::from_log(&truncated).is_err());
+
+        let mut bad_length = data.clone();
+        // Corrupt the length of the pubkey field.
+        bad_length[FIELD_COUNT * WORD_LEN + WORD_LEN - 1] = 47;
+        assert!(DepositLog::from_log(&Log {
+            block_number: 0,
+            data: bad_length
+        })
+        .is_err());
+    }
+}
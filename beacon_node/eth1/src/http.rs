@@ -0,0 +1,191 @@
+//! Provides a minimal, asynchronous web3 JSON-RPC client for the handful of methods required to
+//! follow the deposit contract.
+//!
+//! All errors are returned as `String`, since they are only ever logged.
+use futures::{Future, IntoFuture};
+use reqwest::r#async::ClientBuilder;
+use serde_json::{json, Value};
+use std::ops::Range;
+use std::time::Duration;
+use types::Hash256;
+
+/// `keccak("DepositEvent(bytes,bytes,bytes,bytes,bytes)")`
+pub const DEPOSIT_EVENT_TOPIC: &str =
+    "0x649bbc62d0e31342afea4e5cd82d4049e7e1ee912fc0889aa790803be39038c5";
+
+/// The subset of an eth1 block that is required to vote for `Eth1Data`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Block {
+    pub hash: Hash256,
+    pub timestamp: u64,
+    pub number: u64,
+}
+
+/// A log emitted by the deposit contract, with its data left ABI-encoded.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Log {
+    pub block_number: u64,
+    pub data: Vec<u8>,
+}
+
+/// Returns the number of the latest block known to the eth1 node at `endpoint`.
+pub fn get_block_number(
+    endpoint: &str,
+    timeout: Duration,
+) -> impl Future<Item = u64, Error = String> {
+    send_rpc_request(endpoint, "eth_blockNumber", json!([]), timeout)
+        .and_then(|response| {
+            let result = response_result(&response)?;
+            hex_to_u64_be(
+                result
+                    .as_str()
+                    .ok_or_else(|| "Block number was not a string".to_string())?,
+            )
+        })
+        .map_err(|e| format!("Failed to get block number: {}", e))
+}
+
+/// Returns the block with the given `number` from the eth1 node at `endpoint`.
+pub fn get_block(
+    endpoint: &str,
+    number: u64,
+    timeout: Duration,
+) -> impl Future<Item = Block, Error = String> {
+    let params = json!([format!("0x{:x}", number), false]);
+
+    send_rpc_request(endpoint, "eth_getBlockByNumber", params, timeout)
+        .and_then(|response| {
+            let result = response_result(&response)?;
+            if result.is_null() {
+                return Err("Block not found".to_string());
+            }
+
+            let field = |name: &str| {
+                result
+                    .get(name)
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format!("Block has no {} string", name))
+            };
+
+            let hash = hex_to_bytes(field("hash")?)?;
+            if hash.len() != 32 {
+                return Err(format!("Block hash had invalid length: {}", hash.len()));
+            }
+
+            Ok(Block {
+                hash: Hash256::from_slice(&hash),
+                timestamp: hex_to_u64_be(field("timestamp")?)?,
+                number: hex_to_u64_be(field("number")?)?,
+            })
+        })
+        .map_err(move |e| format!("Failed to get block {}: {}", number, e))
+}
+
+/// Returns all `DepositEvent` logs emitted by the contract at `address` in the blocks of
+/// `block_range` (the end is exclusive).
+pub fn get_deposit_logs_in_range(
+    endpoint: &str,
+    address: &str,
+    block_range: Range<u64>,
+    timeout: Duration,
+) -> impl Future<Item = Vec<Log>, Error = String> {
+    let params = json!([{
+        "address": address,
+        "topics": [DEPOSIT_EVENT_TOPIC],
+        "fromBlock": format!("0x{:x}", block_range.start),
+        "toBlock": format!("0x{:x}", block_range.end.saturating_sub(1)),
+    }]);
+
+    send_rpc_request(endpoint, "eth_getLogs", params, timeout)
+        .and_then(|response| {
+            response_result(&response)?
+                .as_array()
+                .ok_or_else(|| "Logs were not an array".to_string())?
+                .iter()
+                .map(|log| {
+                    let field = |name: &str| {
+                        log.get(name)
+                            .and_then(Value::as_str)
+                            .ok_or_else(|| format!("Log has no {} string", name))
+                    };
+
+                    Ok(Log {
+                        block_number: hex_to_u64_be(field("blockNumber")?)?,
+                        data: hex_to_bytes(field("data")?)?,
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()
+        })
+        .map_err(move |e| format!("Failed to get logs in {:?}: {}", block_range, e))
+}
+
+/// Sends a JSON-RPC request to `endpoint`, returning the entire response body.
+pub fn send_rpc_request(
+    endpoint: &str,
+    method: &str,
+    params: Value,
+    timeout: Duration,
+) -> impl Future<Item = Value, Error = String> {
+    let endpoint = endpoint.to_string();
+    let body = json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": 1
+    });
+
+    ClientBuilder::new()
+        .timeout(timeout)
+        .build()
+        .into_future()
+        .and_then(move |client| client.post(endpoint.as_str()).json(&body).send())
+        .and_then(|response| response.error_for_status())
+        .and_then(|mut response| response.json::<Value>())
+        .map_err(|e| format!("Request failed: {:?}", e))
+}
+
+/// Returns the `result` of a JSON-RPC response, or its `error`.
+fn response_result(response: &Value) -> Result<&Value, String> {
+    if let Some(error) = response.get("error") {
+        Err(format!("RPC error: {}", error))
+    } else {
+        response
+            .get("result")
+            .ok_or_else(|| "Response has no result".to_string())
+    }
+}
+
+/// Parses a `0x` prefixed, big-endian hex quantity.
+fn hex_to_u64_be(hex: &str) -> Result<u64, String> {
+    u64::from_str_radix(strip_prefix(hex)?, 16)
+        .map_err(|e| format!("Failed to parse {} as u64: {:?}", hex, e))
+}
+
+/// Parses a `0x` prefixed hex string of bytes.
+fn hex_to_bytes(hex: &str) -> Result<Vec<u8>, String> {
+    hex::decode(strip_prefix(hex)?)
+        .map_err(|e| format!("Failed to parse {} as bytes: {:?}", hex, e))
+}
+
+fn strip_prefix(hex: &str) -> Result<&str, String> {
+    if hex.starts_with("0x") {
+        Ok(&hex[2..])
+    } else {
+        Err(format!("{} is not 0x prefixed", hex))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_parsing() {
+        assert_eq!(hex_to_u64_be("0x0"), Ok(0));
+        assert_eq!(hex_to_u64_be("0x1f"), Ok(31));
+        assert!(hex_to_u64_be("1f").is_err());
+        assert_eq!(hex_to_bytes("0x"), Ok(vec![]));
+        assert_eq!(hex_to_bytes("0x00ff"), Ok(vec![0, 255]));
+        assert!(hex_to_bytes("0xf").is_err());
+    }
+}
@@ -0,0 +1,14 @@
+//! Follows the eth1 deposit contract via the web3 JSON-RPC API of an eth1 node, caching all
+//! deposits (with the deposit Merkle tree) and the recent blocks of the eth1 chain.
+mod block_cache;
+mod deposit_cache;
+mod deposit_log;
+pub mod http;
+mod service;
+#[cfg(any(test, feature = "test_utils"))]
+pub mod test_utils;
+
+pub use block_cache::{BlockCache, Error as BlockCacheError, Eth1Block};
+pub use deposit_cache::{DepositCache, Error as DepositCacheError, DEPOSIT_CONTRACT_TREE_DEPTH};
+pub use deposit_log::DepositLog;
+pub use service::{Config, DepositUpdater, Error, Service};
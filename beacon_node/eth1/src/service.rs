@@ -0,0 +1,333 @@
+use crate::block_cache::{BlockCache, Error as BlockCacheError, Eth1Block};
+use crate::deposit_cache::{DepositCache, Error as DepositCacheError};
+use crate::deposit_log::DepositLog;
+use crate::http::{get_block, get_block_number, get_deposit_logs_in_range, Block, Log};
+use futures::{future, stream, Future, Stream};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use slog::{debug, error, trace, Logger};
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::timer::Interval;
+
+/// Timeout when making any request to the eth1 node.
+const STANDARD_TIMEOUT_MILLIS: u64 = 15_000;
+/// The number of blocks that are requested from the eth1 node at once.
+const BLOCK_DOWNLOAD_CONCURRENCY: usize = 8;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The eth1 node did not return its latest block number.
+    GetBlockNumberFailed(String),
+    /// The eth1 node did not return the deposit logs in some block range.
+    GetDepositLogsFailed(String),
+    /// The eth1 node did not return some block.
+    GetBlockFailed(String),
+    /// A log from the deposit contract could not be parsed.
+    FailedToParseDepositLog(String),
+    /// A deposit log could not be added to the deposit cache.
+    FailedToInsertDeposit(DepositCacheError),
+    /// A block could not be added to the block cache.
+    FailedToInsertEth1Block(BlockCacheError),
+    /// A block was downloaded before all deposit logs up to and including it were imported.
+    DepositCacheBehind {
+        block_number: u64,
+        last_processed_block: Option<u64>,
+    },
+}
+
+/// Configures how the eth1 node is followed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// An eth1 node (e.g., Geth) web3 JSON-RPC HTTP endpoint.
+    pub endpoint: String,
+    /// The address of the deposit contract.
+    pub deposit_contract_address: String,
+    /// The block in which the deposit contract was deployed, logs are not requested for earlier
+    /// blocks.
+    pub deposit_contract_deploy_block: u64,
+    /// Blocks are only imported once they are this many blocks behind the head of the eth1
+    /// chain, so they are unlikely to be re-organised.
+    pub follow_distance: u64,
+    /// Only the latest `block_cache_truncation` blocks are kept in the block cache.
+    pub block_cache_truncation: Option<usize>,
+    /// The interval between updates when using `Service::auto_update`.
+    pub auto_update_interval_millis: u64,
+    /// The number of blocks whose logs are requested in a single `eth_getLogs` call.
+    pub blocks_per_log_query: usize,
+    /// If more blocks than this are required to bring the block cache up to date, the earlier
+    /// blocks are skipped.
+    pub max_blocks_per_update: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://localhost:8545".into(),
+            deposit_contract_address: "0x0000000000000000000000000000000000000000".into(),
+            deposit_contract_deploy_block: 0,
+            follow_distance: 1_024,
+            block_cache_truncation: Some(4_096),
+            auto_update_interval_millis: 7_000,
+            blocks_per_log_query: 1_000,
+            max_blocks_per_update: Some(8_192),
+        }
+    }
+}
+
+/// The deposit cache, along with the highest block for which all deposit logs have been
+/// imported.
+#[derive(Debug, Default)]
+pub struct DepositUpdater {
+    pub cache: DepositCache,
+    pub last_processed_block: Option<u64>,
+}
+
+struct Inner {
+    config: Config,
+    deposits: RwLock<DepositUpdater>,
+    blocks: RwLock<BlockCache>,
+}
+
+/// Follows the deposit contract of an eth1 node, caching all deposits and the recent blocks of
+/// the eth1 chain.
+///
+/// Cheap to clone, all clones share the same caches.
+#[derive(Clone)]
+pub struct Service {
+    inner: Arc<Inner>,
+    log: Logger,
+}
+
+impl Service {
+    /// Creates a new service with empty caches. No requests are made until `update` or
+    /// `auto_update` is called.
+    pub fn new(config: Config, log: Logger) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                deposits: RwLock::new(DepositUpdater::default()),
+                blocks: RwLock::new(BlockCache::default()),
+            }),
+            log,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.inner.config
+    }
+
+    /// Returns the cache of all deposits.
+    pub fn deposits(&self) -> &RwLock<DepositUpdater> {
+        &self.inner.deposits
+    }
+
+    /// Returns the cache of recent eth1 blocks.
+    pub fn blocks(&self) -> &RwLock<BlockCache> {
+        &self.inner.blocks
+    }
+
+    /// Returns a future which updates the caches every `config.auto_update_interval_millis`,
+    /// logging any errors. The future never resolves.
+    pub fn auto_update(&self) -> impl Future<Item = (), Error = ()> {
+        let service = self.clone();
+        let log = self.log.clone();
+        let interval = Duration::from_millis(self.config().auto_update_interval_millis);
+
+        Interval::new(Instant::now(), interval)
+            .map_err(move |e| {
+                error!(
+                    log,
+                    "Failed to trigger eth1 cache update";
+                    "error" => format!("{:?}", e)
+                )
+            })
+            .for_each(move |_| {
+                let log = service.log.clone();
+
+                service.update().then(move |result| {
+                    match result {
+                        Ok((logs_imported, blocks_imported)) => trace!(
+                            log,
+                            "Updated eth1 cache";
+                            "logs_imported" => logs_imported,
+                            "blocks_imported" => blocks_imported,
+                        ),
+                        Err(e) => error!(
+                            log,
+                            "Failed to update eth1 cache";
+                            "error" => format!("{:?}", e)
+                        ),
+                    };
+
+                    Ok(())
+                })
+            })
+    }
+
+    /// Imports all deposit logs and blocks up to the block which is `config.follow_distance`
+    /// behind the head of the eth1 chain.
+    ///
+    /// Returns the number of deposit logs and blocks imported.
+    pub fn update(&self) -> impl Future<Item = (usize, usize), Error = Error> {
+        let service = self.clone();
+        let follow_distance = self.config().follow_distance;
+
+        get_block_number(&self.config().endpoint, timeout())
+            .map_err(Error::GetBlockNumberFailed)
+            .and_then(move |head| match head.checked_sub(follow_distance) {
+                Some(target) => future::Either::A(service.update_deposit_cache(target).and_then(
+                    move |logs_imported| {
+                        service
+                            .update_block_cache(target)
+                            .map(move |blocks_imported| (logs_imported, blocks_imported))
+                    },
+                )),
+                None => future::Either::B(future::ok((0, 0))),
+            })
+    }
+
+    /// Imports all deposit logs up to and including the block `target`.
+    ///
+    /// Logs are requested `config.blocks_per_log_query` blocks at a time. Each batch is imported
+    /// before the next is requested, so progress is kept if a later request fails.
+    fn update_deposit_cache(&self, target: u64) -> impl Future<Item = usize, Error = Error> {
+        let service = self.clone();
+        let config = self.config();
+        let endpoint = config.endpoint.clone();
+        let address = config.deposit_contract_address.clone();
+
+        let start = self
+            .deposits()
+            .read()
+            .last_processed_block
+            .map(|n| n + 1)
+            .unwrap_or(config.deposit_contract_deploy_block);
+        let step = std::cmp::max(config.blocks_per_log_query, 1);
+
+        let ranges = if start > target {
+            vec![]
+        } else {
+            (start..=target)
+                .step_by(step)
+                .map(|from| from..std::cmp::min(from + step as u64, target + 1))
+                .collect()
+        };
+
+        stream::iter_ok::<_, Error>(ranges)
+            .and_then(move |range: Range<u64>| {
+                get_deposit_logs_in_range(&endpoint, &address, range.clone(), timeout())
+                    .map_err(Error::GetDepositLogsFailed)
+                    .map(move |logs| (range, logs))
+            })
+            .fold(
+                0,
+                move |logs_imported,
+                      (range, logs): (Range<u64>, Vec<Log>)|
+                      -> Result<usize, Error> {
+                    let mut deposits = service.deposits().write();
+
+                    for raw_log in &logs {
+                        let log = DepositLog::from_log(raw_log)
+                            .map_err(Error::FailedToParseDepositLog)?;
+                        deposits
+                            .cache
+                            .insert_log(log)
+                            .map_err(Error::FailedToInsertDeposit)?;
+                    }
+                    deposits.last_processed_block = Some(range.end - 1);
+
+                    Ok(logs_imported + logs.len())
+                },
+            )
+    }
+
+    /// Imports all blocks from the highest block in the cache up to and including `target`.
+    ///
+    /// All deposit logs up to `target` must have already been imported, they are used to
+    /// determine the deposit root and count of each block.
+    fn update_block_cache(&self, target: u64) -> impl Future<Item = usize, Error = Error> {
+        let service = self.clone();
+        let config = self.config();
+        let endpoint = config.endpoint.clone();
+        let truncation = config.block_cache_truncation;
+
+        let range = {
+            let mut blocks = self.blocks().write();
+            let start = blocks
+                .highest_block_number()
+                .map(|n| n + 1)
+                .unwrap_or(config.deposit_contract_deploy_block);
+
+            // There is no point downloading blocks that would immediately be truncated.
+            let limit = [config.max_blocks_per_update, truncation]
+                .iter()
+                .filter_map(|limit| *limit)
+                .min();
+
+            match limit {
+                Some(limit) if target + 1 > start + limit as u64 => {
+                    let skip_to = target + 1 - limit as u64;
+                    debug!(
+                        self.log,
+                        "Skipping eth1 blocks";
+                        "from" => start,
+                        "to" => skip_to,
+                    );
+                    blocks.clear();
+                    skip_to..target + 1
+                }
+                _ => start..target + 1,
+            }
+        };
+
+        stream::iter_ok::<_, Error>(range)
+            .map(move |number| {
+                get_block(&endpoint, number, timeout()).map_err(Error::GetBlockFailed)
+            })
+            .buffered(BLOCK_DOWNLOAD_CONCURRENCY)
+            .fold(
+                0,
+                move |blocks_imported, block: Block| -> Result<usize, Error> {
+                    let eth1_block = {
+                        let deposits = service.deposits().read();
+
+                        let deposit_count = deposits.cache.deposit_count_at_block(block.number);
+                        let deposit_root = match deposits.last_processed_block {
+                            Some(last) if last >= block.number => {
+                                deposits.cache.deposit_root(deposit_count)
+                            }
+                            _ => None,
+                        }
+                        .ok_or_else(|| Error::DepositCacheBehind {
+                            block_number: block.number,
+                            last_processed_block: deposits.last_processed_block,
+                        })?;
+
+                        Eth1Block {
+                            hash: block.hash,
+                            timestamp: block.timestamp,
+                            number: block.number,
+                            deposit_root,
+                            deposit_count,
+                        }
+                    };
+
+                    let mut blocks = service.blocks().write();
+                    blocks
+                        .insert_root_or_child(eth1_block)
+                        .map_err(Error::FailedToInsertEth1Block)?;
+                    if let Some(truncation) = truncation {
+                        blocks.truncate(truncation);
+                    }
+
+                    Ok(blocks_imported + 1)
+                },
+            )
+    }
+}
+
+fn timeout() -> Duration {
+    Duration::from_millis(STANDARD_TIMEOUT_MILLIS)
+}
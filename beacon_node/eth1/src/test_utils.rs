@@ -0,0 +1,232 @@
+//! Provides a mock eth1 node which serves the subset of the web3 JSON-RPC API used by this crate.
+//!
+//! Intended for testing only.
+use crate::deposit_log::{FIELD_COUNT, WORD_LEN};
+use crate::http::DEPOSIT_EVENT_TOPIC;
+use eth2_hashing::hash;
+use futures::{Future, Stream};
+use hyper::service::service_fn;
+use hyper::{Body, Request, Response, Server};
+use int_to_bytes::int_to_bytes32;
+use parking_lot::RwLock;
+use serde_json::{json, Value};
+use slog::crit;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use types::{DepositData, Hash256};
+
+/// The address of the deposit contract on the mock eth1 node.
+pub const DEPOSIT_CONTRACT_ADDRESS: &str = "0x1234567890123456789012345678901234567890";
+
+#[derive(Default)]
+struct MockChain {
+    /// The timestamp of each block, indexed by block number.
+    timestamps: Vec<u64>,
+    /// The block number and ABI-encoded data of each deposit log.
+    logs: Vec<(u64, Vec<u8>)>,
+}
+
+/// An eth1 node, listening on a random localhost port, with a chain and deposit contract that are
+/// built by the test.
+///
+/// The chain starts with a single block (number `0`), blocks are added with `add_block` and
+/// deposits are added to the latest block with `add_deposit`. The server is shut down when this
+/// struct is dropped.
+pub struct MockEth1Node {
+    chain: Arc<RwLock<MockChain>>,
+    endpoint: String,
+    _runtime: Runtime,
+}
+
+impl MockEth1Node {
+    /// Starts a new mock node with a chain consisting of one block with the given `timestamp`.
+    pub fn new(timestamp: u64, log: slog::Logger) -> Self {
+        let chain = Arc::new(RwLock::new(MockChain {
+            timestamps: vec![timestamp],
+            logs: vec![],
+        }));
+
+        let server_chain = chain.clone();
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(move || {
+            let chain = server_chain.clone();
+            service_fn(move |req: Request<Body>| {
+                let chain = chain.clone();
+                req.into_body()
+                    .concat2()
+                    .map(move |body| handle_request(&chain.read(), &body))
+            })
+        });
+        let endpoint = format!("http://{}", server.local_addr());
+
+        let mut runtime = Runtime::new().expect("should create runtime");
+        runtime.spawn(server.map_err(move |e| {
+            crit!(log, "Mock eth1 node failed"; "error" => format!("{:?}", e));
+        }));
+
+        Self {
+            chain,
+            endpoint,
+            _runtime: runtime,
+        }
+    }
+
+    /// Returns the HTTP endpoint of the node.
+    pub fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    /// Returns the number of the latest block.
+    pub fn block_number(&self) -> u64 {
+        self.chain.read().timestamps.len() as u64 - 1
+    }
+
+    /// Returns the hash of the block with the given `number`.
+    pub fn block_hash(&self, number: u64) -> Hash256 {
+        block_hash(number)
+    }
+
+    /// Adds a block with the given `timestamp` to the chain, returning its number.
+    pub fn add_block(&self, timestamp: u64) -> u64 {
+        let mut chain = self.chain.write();
+        chain.timestamps.push(timestamp);
+        chain.timestamps.len() as u64 - 1
+    }
+
+    /// Adds `n` blocks to the chain, each `seconds_per_block` after its parent.
+    pub fn add_blocks(&self, n: usize, seconds_per_block: u64) {
+        for _ in 0..n {
+            let timestamp = *self
+                .chain
+                .read()
+                .timestamps
+                .last()
+                .expect("chain is not empty");
+            self.add_block(timestamp + seconds_per_block);
+        }
+    }
+
+    /// Makes a deposit in the latest block of the chain.
+    pub fn add_deposit(&self, deposit_data: &DepositData) {
+        let mut chain = self.chain.write();
+        let index = chain.logs.len() as u64;
+        let block_number = chain.timestamps.len() as u64 - 1;
+        chain
+            .logs
+            .push((block_number, deposit_log_data(deposit_data, index)));
+    }
+}
+
+/// Returns the data of the `DepositEvent` that the deposit contract emits for the
+/// `index`'th deposit of `deposit_data`.
+pub fn deposit_log_data(deposit_data: &DepositData, index: u64) -> Vec<u8> {
+    let fields: [Vec<u8>; FIELD_COUNT] = [
+        deposit_data.pubkey.as_bytes(),
+        deposit_data.withdrawal_credentials.as_bytes().to_vec(),
+        deposit_data.amount.to_le_bytes().to_vec(),
+        deposit_data.signature.as_bytes(),
+        index.to_le_bytes().to_vec(),
+    ];
+
+    let mut head = vec![];
+    let mut tail = vec![];
+
+    for field in fields.iter() {
+        // Each field is referenced by its offset from the start of the data.
+        head.extend_from_slice(&abi_word(FIELD_COUNT * WORD_LEN + tail.len()));
+        tail.extend_from_slice(&abi_word(field.len()));
+        tail.extend_from_slice(field);
+        tail.resize(
+            tail.len() + (WORD_LEN - field.len() % WORD_LEN) % WORD_LEN,
+            0,
+        );
+    }
+
+    head.append(&mut tail);
+    head
+}
+
+/// Returns `int` as a big-endian ABI word.
+fn abi_word(int: usize) -> [u8; WORD_LEN] {
+    let mut word = [0; WORD_LEN];
+    word[WORD_LEN - 8..].copy_from_slice(&(int as u64).to_be_bytes());
+    word
+}
+
+fn block_hash(number: u64) -> Hash256 {
+    Hash256::from_slice(&hash(&int_to_bytes32(number)))
+}
+
+fn handle_request(chain: &MockChain, body: &[u8]) -> Response<Body> {
+    let request: Value = serde_json::from_slice(body).unwrap_or(Value::Null);
+    let params = &request["params"];
+
+    let result = match request["method"].as_str() {
+        Some("eth_blockNumber") => Ok(json!(format!("0x{:x}", chain.timestamps.len() - 1))),
+        Some("eth_getBlockByNumber") => parse_quantity(&params[0]).map(|number| {
+            chain
+                .timestamps
+                .get(number as usize)
+                .map(|timestamp| {
+                    json!({
+                        "hash": format!("{:?}", block_hash(number)),
+                        "number": format!("0x{:x}", number),
+                        "timestamp": format!("0x{:x}", timestamp),
+                    })
+                })
+                .unwrap_or(Value::Null)
+        }),
+        Some("eth_getLogs") => get_logs(chain, &params[0]),
+        other => Err(format!("Unsupported method: {:?}", other)),
+    };
+
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+        Err(message) => json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "error": { "code": -32602, "message": message }
+        }),
+    };
+
+    Response::builder()
+        .header("content-type", "application/json")
+        .body(Body::from(response.to_string()))
+        .expect("should build response")
+}
+
+fn get_logs(chain: &MockChain, filter: &Value) -> Result<Value, String> {
+    let from = parse_quantity(&filter["fromBlock"])?;
+    let to = parse_quantity(&filter["toBlock"])?;
+
+    let address_matches = filter["address"].as_str().map_or(false, |address| {
+        address.eq_ignore_ascii_case(DEPOSIT_CONTRACT_ADDRESS)
+    });
+    let topic_matches = filter["topics"][0].as_str() == Some(DEPOSIT_EVENT_TOPIC);
+
+    if !address_matches || !topic_matches {
+        return Ok(json!([]));
+    }
+
+    Ok(chain
+        .logs
+        .iter()
+        .filter(|(block_number, _)| *block_number >= from && *block_number <= to)
+        .map(|(block_number, data)| {
+            json!({
+                "address": DEPOSIT_CONTRACT_ADDRESS,
+                "topics": [DEPOSIT_EVENT_TOPIC],
+                "blockNumber": format!("0x{:x}", block_number),
+                "blockHash": format!("{:?}", block_hash(*block_number)),
+                "data": format!("0x{}", hex::encode(data)),
+            })
+        })
+        .collect())
+}
+
+fn parse_quantity(value: &Value) -> Result<u64, String> {
+    value
+        .as_str()
+        .filter(|s| s.starts_with("0x"))
+        .and_then(|s| u64::from_str_radix(&s[2..], 16).ok())
+        .ok_or_else(|| format!("Invalid quantity: {}", value))
+}
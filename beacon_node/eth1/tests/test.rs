@@ -0,0 +1,174 @@
+#![cfg(test)]
+use eth1::test_utils::{MockEth1Node, DEPOSIT_CONTRACT_ADDRESS};
+use eth1::{Config, Service};
+use slog::Logger;
+use state_processing::per_block_processing::verify_deposit_merkle_proof;
+use tokio::runtime::Runtime;
+use types::test_utils::{SeedableRng, TestRandom, XorShiftRng};
+use types::{BeaconState, DepositData, EthSpec, MinimalEthSpec};
+
+type E = MinimalEthSpec;
+
+const FOLLOW_DISTANCE: u64 = 4;
+
+fn null_logger() -> Logger {
+    Logger::root(slog::Discard, slog::o!())
+}
+
+fn config(endpoint: String) -> Config {
+    Config {
+        endpoint,
+        deposit_contract_address: DEPOSIT_CONTRACT_ADDRESS.to_string(),
+        follow_distance: FOLLOW_DISTANCE,
+        // Use a small query size to ensure logs are requested across several batches.
+        blocks_per_log_query: 3,
+        ..Config::default()
+    }
+}
+
+fn random_deposits(n: usize) -> Vec<DepositData> {
+    let mut rng = XorShiftRng::from_seed([42; 16]);
+    (0..n)
+        .map(|_| DepositData::random_for_test(&mut rng))
+        .collect()
+}
+
+/// Adds one deposit to each of `deposits.len()` new blocks.
+fn add_deposits(node: &MockEth1Node, deposits: &[DepositData]) {
+    for deposit in deposits {
+        node.add_blocks(1, 15);
+        node.add_deposit(deposit);
+    }
+}
+
+#[test]
+fn follows_deposits_and_blocks() {
+    let mut runtime = Runtime::new().expect("should create runtime");
+    let node = MockEth1Node::new(0, null_logger());
+    let service = Service::new(config(node.endpoint()), null_logger());
+
+    // The chain is too short to be followed.
+    node.add_blocks(FOLLOW_DISTANCE as usize - 1, 15);
+    assert_eq!(runtime.block_on(service.update()), Ok((0, 0)));
+
+    let deposits = random_deposits(8);
+    add_deposits(&node, &deposits);
+    node.add_blocks(FOLLOW_DISTANCE as usize, 15);
+
+    let target = node.block_number() - FOLLOW_DISTANCE;
+    assert_eq!(
+        runtime.block_on(service.update()),
+        Ok((deposits.len(), target as usize + 1))
+    );
+
+    {
+        let blocks = service.blocks().read();
+        assert_eq!(blocks.lowest_block_number(), Some(0));
+        assert_eq!(blocks.highest_block_number(), Some(target));
+
+        for block in blocks.iter() {
+            assert_eq!(block.hash, node.block_hash(block.number));
+            assert_eq!(block.timestamp, block.number * 15);
+        }
+
+        // The deposits were made in blocks `FOLLOW_DISTANCE..FOLLOW_DISTANCE + 8`.
+        let block = blocks
+            .block_by_number(FOLLOW_DISTANCE + 2)
+            .expect("should have block");
+        assert_eq!(block.deposit_count, 3);
+        assert_eq!(
+            Some(block.deposit_root),
+            service.deposits().read().cache.deposit_root(3)
+        );
+    }
+
+    assert_eq!(service.deposits().read().last_processed_block, Some(target));
+
+    // A further update with no new blocks imports nothing.
+    assert_eq!(runtime.block_on(service.update()), Ok((0, 0)));
+
+    node.add_blocks(2, 15);
+    assert_eq!(runtime.block_on(service.update()), Ok((0, 2)));
+    assert_eq!(
+        service.blocks().read().highest_block_number(),
+        Some(target + 2)
+    );
+}
+
+#[test]
+fn truncates_block_cache() {
+    let mut runtime = Runtime::new().expect("should create runtime");
+    let node = MockEth1Node::new(0, null_logger());
+    let service = Service::new(
+        Config {
+            block_cache_truncation: Some(5),
+            ..config(node.endpoint())
+        },
+        null_logger(),
+    );
+
+    node.add_blocks(20, 15);
+    runtime
+        .block_on(service.update())
+        .expect("should update service");
+
+    let blocks = service.blocks().read();
+    let target = node.block_number() - FOLLOW_DISTANCE;
+    assert_eq!(blocks.len(), 5);
+    assert_eq!(blocks.lowest_block_number(), Some(target - 4));
+    assert_eq!(blocks.highest_block_number(), Some(target));
+}
+
+#[test]
+fn deposit_proofs_are_valid() {
+    let mut runtime = Runtime::new().expect("should create runtime");
+    let spec = E::default_spec();
+    let node = MockEth1Node::new(0, null_logger());
+    let service = Service::new(config(node.endpoint()), null_logger());
+
+    let deposits = random_deposits(12);
+    add_deposits(&node, &deposits);
+    node.add_blocks(FOLLOW_DISTANCE as usize, 15);
+    runtime
+        .block_on(service.update())
+        .expect("should update service");
+
+    let blocks = service.blocks().read();
+    let deposits_guard = service.deposits().read();
+    let deposit_cache = &deposits_guard.cache;
+
+    // Proofs must be valid against the deposit root of every block, not just the latest.
+    for block in blocks.iter() {
+        let state: BeaconState<E> = BeaconState::new(0, block.eth1_data(), &spec);
+
+        for start in 0..block.deposit_count {
+            let (root, proven) = deposit_cache
+                .get_deposits(start..block.deposit_count, block.deposit_count)
+                .expect("should get deposits");
+            assert_eq!(root, block.deposit_root);
+            assert_eq!(proven.len() as u64, block.deposit_count - start);
+
+            for (i, deposit) in proven.iter().enumerate() {
+                let index = start + i as u64;
+                assert_eq!(deposit.data, deposits[index as usize]);
+                assert_eq!(
+                    verify_deposit_merkle_proof(&state, deposit, index, &spec),
+                    Ok(()),
+                    "deposit {} should be valid at block {}",
+                    index,
+                    block.number
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn unreachable_endpoint() {
+    let mut runtime = Runtime::new().expect("should create runtime");
+    let service = Service::new(config("http://127.0.0.1:1".to_string()), null_logger());
+
+    assert!(runtime.block_on(service.update()).is_err());
+    assert!(service.blocks().read().is_empty());
+    assert!(service.deposits().read().cache.is_empty());
+}
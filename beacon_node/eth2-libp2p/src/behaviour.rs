@@ -1,7 +1,7 @@
 use crate::config::*;
 use crate::discovery::Discovery;
 use crate::rpc::{RPCEvent, RPCMessage, RPC};
-use crate::{error, NetworkConfig};
+use crate::{error, NetworkConfig, SubnetId};
 use crate::{Topic, TopicHash};
 use crate::{BEACON_ATTESTATION_TOPIC, BEACON_BLOCK_TOPIC};
 use futures::prelude::*;
@@ -17,7 +17,7 @@ use libp2p::{
 };
 use slog::{debug, o};
 use std::num::NonZeroU32;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const MAX_IDENTIFY_ADDRESSES: usize = 20;
 
@@ -51,6 +51,7 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
     pub fn new(
         local_key: &Keypair,
         net_conf: &NetworkConfig,
+        long_lived_subnets: &[SubnetId],
         log: &slog::Logger,
     ) -> error::Result<Self> {
         let local_peer_id = local_key.public().clone().into_peer_id();
@@ -69,9 +70,9 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
         );
 
         Ok(Behaviour {
-            eth2_rpc: RPC::new(log),
+            eth2_rpc: RPC::new(net_conf.max_request_blocks, log),
             gossipsub: Gossipsub::new(local_peer_id.clone(), net_conf.gs_config.clone()),
-            discovery: Discovery::new(local_key, net_conf, log)?,
+            discovery: Discovery::new(local_key, net_conf, long_lived_subnets, log)?,
             ping: Ping::new(ping_config),
             identify,
             events: Vec::new(),
@@ -82,6 +83,10 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
     pub fn discovery(&self) -> &Discovery<TSubstream> {
         &self.discovery
     }
+
+    pub fn discovery_mut(&mut self) -> &mut Discovery<TSubstream> {
+        &mut self.discovery
+    }
 }
 
 // Implement the NetworkBehaviourEventProcess trait so that we can derive NetworkBehaviour for Behaviour
@@ -196,6 +201,11 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
         self.gossipsub.subscribe(topic)
     }
 
+    /// Unsubscribes from a gossipsub topic.
+    pub fn unsubscribe(&mut self, topic: Topic) -> bool {
+        self.gossipsub.unsubscribe(topic)
+    }
+
     /// Publishes a message on the pubsub (gossipsub) behaviour.
     pub fn publish(&mut self, topics: &[Topic], message: PubsubMessage) {
         let message_data = message.to_data();
@@ -222,6 +232,11 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
     pub fn connected_peers(&self) -> usize {
         self.discovery.connected_peers()
     }
+
+    /// Searches for and connects to peers on the given attestation subnet until `expiry`.
+    pub fn discover_subnet_peers(&mut self, subnet_id: SubnetId, expiry: Instant) {
+        self.discovery.discover_subnet_peers(subnet_id, expiry);
+    }
 }
 
 /// The types of events than can be obtained from polling the behaviour.
@@ -253,6 +268,8 @@ pub enum PubsubMessage {
     Block(Vec<u8>),
     /// Gossipsub message providing notification of a new attestation.
     Attestation(Vec<u8>),
+    /// Gossipsub message providing notification of a new aggregate from a selected aggregator.
+    AggregateAndProof(Vec<u8>),
     /// Gossipsub message providing notification of a voluntary exit.
     VoluntaryExit(Vec<u8>),
     /// Gossipsub message providing notification of a new proposer slashing.
@@ -281,10 +298,17 @@ impl PubsubMessage {
                 match topic_parts[2] {
                     BEACON_BLOCK_TOPIC => return PubsubMessage::Block(data),
                     BEACON_ATTESTATION_TOPIC => return PubsubMessage::Attestation(data),
+                    BEACON_AGGREGATE_AND_PROOF_TOPIC => {
+                        return PubsubMessage::AggregateAndProof(data)
+                    }
                     VOLUNTARY_EXIT_TOPIC => return PubsubMessage::VoluntaryExit(data),
                     PROPOSER_SLASHING_TOPIC => return PubsubMessage::ProposerSlashing(data),
                     ATTESTER_SLASHING_TOPIC => return PubsubMessage::AttesterSlashing(data),
-                    _ => {}
+                    topic_name => {
+                        if SubnetId::from_topic_name(topic_name).is_some() {
+                            return PubsubMessage::Attestation(data);
+                        }
+                    }
                 }
             }
         }
@@ -295,6 +319,7 @@ impl PubsubMessage {
         match self {
             PubsubMessage::Block(data)
             | PubsubMessage::Attestation(data)
+            | PubsubMessage::AggregateAndProof(data)
             | PubsubMessage::VoluntaryExit(data)
             | PubsubMessage::ProposerSlashing(data)
             | PubsubMessage::AttesterSlashing(data)
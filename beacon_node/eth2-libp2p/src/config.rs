@@ -1,9 +1,12 @@
+use crate::peer_lists::{parse_peer_id, parse_trusted_peer, IpRange};
+use crate::rpc::DEFAULT_MAX_REQUEST_BLOCKS;
 use clap::ArgMatches;
 use enr::Enr;
 use libp2p::gossipsub::{GossipsubConfig, GossipsubConfigBuilder};
 use libp2p::Multiaddr;
 use serde_derive::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::time::Duration;
 
 /// The gossipsub topic names.
@@ -13,11 +16,37 @@ pub const TOPIC_PREFIX: &str = "eth2";
 pub const TOPIC_ENCODING_POSTFIX: &str = "ssz";
 pub const BEACON_BLOCK_TOPIC: &str = "beacon_block";
 pub const BEACON_ATTESTATION_TOPIC: &str = "beacon_attestation";
+pub const BEACON_AGGREGATE_AND_PROOF_TOPIC: &str = "beacon_aggregate_and_proof";
 pub const VOLUNTARY_EXIT_TOPIC: &str = "voluntary_exit";
 pub const PROPOSER_SLASHING_TOPIC: &str = "proposer_slashing";
 pub const ATTESTER_SLASHING_TOPIC: &str = "attester_slashing";
 pub const SHARD_TOPIC_PREFIX: &str = "shard";
 
+/// The secure channel protocols that are offered when negotiating a connection.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecureChannel {
+    /// Offer Noise (XX handshake), falling back to secio if the remote does not support Noise.
+    NoiseWithSecioFallback,
+    /// Only offer Noise.
+    Noise,
+    /// Only offer secio.
+    Secio,
+}
+
+impl FromStr for SecureChannel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "noise-with-secio-fallback" => Ok(SecureChannel::NoiseWithSecioFallback),
+            "noise" => Ok(SecureChannel::Noise),
+            "secio" => Ok(SecureChannel::Secio),
+            _ => Err(format!("Invalid secure channel: {}", s)),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 /// Network configuration for lighthouse.
@@ -61,6 +90,30 @@ pub struct Config {
 
     /// List of extra topics to initially subscribe to as strings.
     pub topics: Vec<String>,
+
+    /// The number of randomly selected attestation subnets this node subscribes to for its
+    /// lifetime and advertises in its ENR.
+    pub long_lived_subnet_count: usize,
+
+    /// Subscribe to every attestation subnet, rather than a random selection.
+    pub subscribe_all_subnets: bool,
+
+    /// The maximum number of blocks a peer may request in a single `BeaconBlocks` request. This
+    /// is also the number of blocks a peer may request per rate-limiting period.
+    pub max_request_blocks: u64,
+
+    /// The secure channel protocols offered to peers.
+    pub secure_channel: SecureChannel,
+
+    /// Peers that are always connected to and exempt from `max_peers`. Each multiaddr must
+    /// contain a `/p2p/` component and may contain an address to dial.
+    pub trusted_peers: Vec<Multiaddr>,
+
+    /// Base58 encoded peer ids that are banned.
+    pub denied_peers: Vec<String>,
+
+    /// IP ranges, in CIDR notation, from which connections are rejected.
+    pub denied_ip_ranges: Vec<String>,
 }
 
 impl Default for Config {
@@ -88,6 +141,13 @@ impl Default for Config {
             libp2p_nodes: vec![],
             client_version: version::version(),
             topics: Vec::new(),
+            long_lived_subnet_count: 1,
+            subscribe_all_subnets: false,
+            max_request_blocks: DEFAULT_MAX_REQUEST_BLOCKS,
+            secure_channel: SecureChannel::NoiseWithSecioFallback,
+            trusted_peers: vec![],
+            denied_peers: vec![],
+            denied_ip_ranges: vec![],
         }
     }
 }
@@ -165,6 +225,45 @@ impl Config {
                 .map_err(|_| format!("Invalid discovery port: {}", disc_port_str))?;
         }
 
+        if let Some(subnets_str) = args.value_of("subnets") {
+            self.long_lived_subnet_count = subnets_str
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid number of subnets: {}", subnets_str))?;
+        }
+
+        if args.is_present("subscribe-all-subnets") {
+            self.subscribe_all_subnets = true;
+        }
+
+        if let Some(max_request_blocks_str) = args.value_of("max-request-blocks") {
+            self.max_request_blocks = parse_max_request_blocks(max_request_blocks_str)?;
+        }
+
+        if let Some(secure_channel_str) = args.value_of("secure-channel") {
+            self.secure_channel = secure_channel_str.parse()?;
+        }
+
+        if let Some(trusted_peers_str) = args.value_of("trusted-peers") {
+            self.trusted_peers = trusted_peers_str
+                .split(',')
+                .map(parse_trusted_peer)
+                .collect::<Result<Vec<Multiaddr>, _>>()?;
+        }
+
+        if let Some(denied_peers_str) = args.value_of("denied-peers") {
+            self.denied_peers = denied_peers_str
+                .split(',')
+                .map(|peer_id| parse_peer_id(peer_id).map(|_| peer_id.to_string()))
+                .collect::<Result<Vec<String>, _>>()?;
+        }
+
+        if let Some(denied_ip_ranges_str) = args.value_of("denied-ip-ranges") {
+            self.denied_ip_ranges = denied_ip_ranges_str
+                .split(',')
+                .map(|range| range.parse::<IpRange>().map(|_| range.to_string()))
+                .collect::<Result<Vec<String>, _>>()?;
+        }
+
         if let Some(p2p_priv_key) = args.value_of("p2p-priv-key") {
             self.secret_key_hex = Some(p2p_priv_key.to_string());
         }
@@ -172,3 +271,27 @@ impl Config {
         Ok(())
     }
 }
+
+/// Parses the maximum number of blocks served per `BeaconBlocksRequest`.
+///
+/// A value of zero is rejected since it would rate-limit every `BeaconBlocksRequest`.
+fn parse_max_request_blocks(s: &str) -> Result<u64, String> {
+    match s.parse::<u64>() {
+        Ok(0) => Err("Invalid max request blocks: must be greater than zero".into()),
+        Ok(max_request_blocks) => Ok(max_request_blocks),
+        Err(_) => Err(format!("Invalid max request blocks: {}", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_request_blocks() {
+        assert_eq!(parse_max_request_blocks("64"), Ok(64));
+        assert!(parse_max_request_blocks("0").is_err());
+        assert!(parse_max_request_blocks("-1").is_err());
+        assert!(parse_max_request_blocks("many").is_err());
+    }
+}
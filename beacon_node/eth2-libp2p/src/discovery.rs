@@ -1,5 +1,8 @@
 use crate::metrics;
-use crate::{error, NetworkConfig};
+use crate::peer_db::PeerDB;
+use crate::peer_lists::{IpRange, PeerLists};
+use crate::subnets::{enr_has_subnet, subnets_to_enr_bytes, ATTESTATION_SUBNETS_ENR_KEY};
+use crate::{error, NetworkConfig, SubnetId};
 /// This manages the discovery and management of peers.
 ///
 /// Currently using discv5 for peer discovery.
@@ -11,7 +14,7 @@ use libp2p::enr::{Enr, EnrBuilder, NodeId};
 use libp2p::multiaddr::Protocol;
 use libp2p::swarm::{NetworkBehaviour, NetworkBehaviourAction, PollParameters, ProtocolsHandler};
 use slog::{debug, info, warn};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
@@ -26,6 +29,15 @@ const MAX_TIME_BETWEEN_PEER_SEARCHES: u64 = 60;
 const INITIAL_SEARCH_DELAY: u64 = 5;
 /// Local ENR storage filename.
 const ENR_FILENAME: &str = "enr.dat";
+/// The number of peers above `max_peers` that may be connected in order to find peers on a
+/// required attestation subnet.
+const SUBNET_PEER_EXCESS: usize = 5;
+/// The maximum number of peers returned by a query for peers on an attestation subnet.
+const SUBNET_QUERY_PEERS: usize = SUBNET_PEER_EXCESS;
+/// Seconds between writes of the peer database to disk.
+const PEER_DB_SAVE_INTERVAL: u64 = 300;
+/// Seconds between attempts to reconnect to disconnected trusted peers.
+const TRUSTED_PEER_REDIAL_INTERVAL: u64 = 30;
 
 /// Lighthouse discovery behaviour. This provides peer management and discovery using the Discv5
 /// libp2p protocol.
@@ -54,6 +66,45 @@ pub struct Discovery<TSubstream> {
     /// The discovery behaviour used to discover new peers.
     discovery: Discv5<TSubstream>,
 
+    /// Attestation subnets we are searching for peers on, along with the time at which the
+    /// search is no longer required.
+    subnet_queries: HashMap<SubnetId, Instant>,
+
+    /// The subnet of each subnet query in flight, keyed by the target of the query. At most one
+    /// query is in flight for each subnet.
+    active_subnet_queries: HashMap<NodeId, SubnetId>,
+
+    /// Peers discovered on a required subnet which are waiting to be dialed.
+    subnet_dial_queue: VecDeque<PeerId>,
+
+    /// Known peers, loaded from the peer database on startup, which are waiting to be dialed.
+    known_peer_dial_queue: VecDeque<PeerId>,
+
+    /// The database of known peers, persisted in the network directory.
+    peer_db: PeerDB,
+
+    /// The delay until the peer database is next written to disk.
+    peer_db_save_delay: Delay,
+
+    /// The trusted and denied peers.
+    peer_lists: PeerLists,
+
+    /// Trusted peers which are waiting to be dialed.
+    trusted_peer_dial_queue: VecDeque<PeerId>,
+
+    /// The delay until disconnected trusted peers are next redialed.
+    trusted_peer_redial_delay: Delay,
+
+    /// The endpoints of the connected peers, used to disconnect peers from a newly denied IP
+    /// range.
+    connected_endpoints: HashMap<PeerId, ConnectedPoint>,
+
+    /// Peers whose connection has been rejected and which have not yet disconnected.
+    rejected_peers: HashSet<PeerId>,
+
+    /// Peers which are waiting to be disconnected by the swarm.
+    disconnect_queue: Vec<PeerId>,
+
     /// Logger for the discovery behaviour.
     log: slog::Logger,
 }
@@ -62,12 +113,13 @@ impl<TSubstream> Discovery<TSubstream> {
     pub fn new(
         local_key: &Keypair,
         config: &NetworkConfig,
+        long_lived_subnets: &[SubnetId],
         log: &slog::Logger,
     ) -> error::Result<Self> {
         let log = log.clone();
 
         // checks if current ENR matches that found on disk
-        let local_enr = load_enr(local_key, config, &log)?;
+        let local_enr = load_enr(local_key, config, long_lived_subnets, &log)?;
 
         let enr_dir = match config.network_dir.to_str() {
             Some(path) => String::from(path),
@@ -91,6 +143,19 @@ impl<TSubstream> Discovery<TSubstream> {
             discovery.add_enr(bootnode_enr);
         }
 
+        // Add the best known peers from previous runs to the routing table and queue them to be
+        // dialed, so that we reconnect quickly without waiting on a discovery query.
+        let peer_db = PeerDB::load(&config.network_dir, &log);
+        let mut known_peer_dial_queue = VecDeque::new();
+        for enr in peer_db.best_enrs(config.max_peers) {
+            known_peer_dial_queue.push_back(enr.peer_id());
+            discovery.add_enr(enr);
+        }
+        debug!(log, "Known peers loaded"; "stored" => peer_db.len(), "dialing" => known_peer_dial_queue.len());
+
+        let peer_lists = PeerLists::from_config(config)?;
+        let trusted_peer_dial_queue = peer_lists.trusted_peers().keys().cloned().collect();
+
         Ok(Self {
             connected_peers: HashSet::new(),
             max_peers: config.max_peers,
@@ -98,6 +163,22 @@ impl<TSubstream> Discovery<TSubstream> {
             past_discovery_delay: INITIAL_SEARCH_DELAY,
             tcp_port: config.libp2p_port,
             discovery,
+            subnet_queries: HashMap::new(),
+            active_subnet_queries: HashMap::new(),
+            subnet_dial_queue: VecDeque::new(),
+            known_peer_dial_queue,
+            peer_db,
+            peer_db_save_delay: Delay::new(
+                Instant::now() + Duration::from_secs(PEER_DB_SAVE_INTERVAL),
+            ),
+            peer_lists,
+            trusted_peer_dial_queue,
+            trusted_peer_redial_delay: Delay::new(
+                Instant::now() + Duration::from_secs(TRUSTED_PEER_REDIAL_INTERVAL),
+            ),
+            connected_endpoints: HashMap::new(),
+            rejected_peers: HashSet::new(),
+            disconnect_queue: Vec::new(),
             log,
             enr_dir,
         })
@@ -129,6 +210,102 @@ impl<TSubstream> Discovery<TSubstream> {
         &self.connected_peers
     }
 
+    /// The trusted and denied peers.
+    pub fn peer_lists(&self) -> &PeerLists {
+        &self.peer_lists
+    }
+
+    /// Mutable access to the trusted and denied peers.
+    pub fn peer_lists_mut(&mut self) -> &mut PeerLists {
+        &mut self.peer_lists
+    }
+
+    /// Adds a trusted peer and dials it. Returns the `PeerId` of the trusted peer.
+    pub fn add_trusted_peer(&mut self, multiaddr: Multiaddr) -> Result<PeerId, String> {
+        let peer_id = self.peer_lists.add_trusted_peer(multiaddr)?;
+        self.trusted_peer_dial_queue.push_back(peer_id.clone());
+        Ok(peer_id)
+    }
+
+    /// Adds an IP range to the deny list and queues any connected peers within the range to be
+    /// disconnected.
+    pub fn deny_ip_range(&mut self, ip_range: IpRange) {
+        self.peer_lists.deny_ip_range(ip_range);
+
+        let peer_lists = &self.peer_lists;
+        let denied_peers = self
+            .connected_endpoints
+            .iter()
+            .filter(|(peer_id, endpoint)| peer_lists.is_denied_connection(peer_id, endpoint))
+            .map(|(peer_id, _)| peer_id.clone());
+        self.disconnect_queue.extend(denied_peers);
+    }
+
+    /// Returns `true` if the connection of the peer has been rejected and the peer has not yet
+    /// disconnected. Events from rejected peers must be ignored.
+    pub fn is_rejected(&self, peer_id: &PeerId) -> bool {
+        self.rejected_peers.contains(peer_id)
+    }
+
+    /// Returns the peers that have been queued for disconnection since the last call. These peers
+    /// must be disconnected by the swarm.
+    pub fn take_rejected_peers(&mut self) -> Vec<PeerId> {
+        std::mem::replace(&mut self.disconnect_queue, Vec::new())
+    }
+
+    /// Returns `true` if fewer than `limit` peers are connected. Trusted peers do not count
+    /// towards the limit.
+    fn has_peer_capacity(&self, limit: usize) -> bool {
+        self.connected_peers
+            .iter()
+            .filter(|peer_id| !self.peer_lists.is_trusted(peer_id))
+            .count()
+            < limit
+    }
+
+    /// Searches for peers that advertise a long-lived subscription to `subnet_id` in their ENR.
+    /// Any such peers that are discovered before `expiry` are dialed.
+    ///
+    /// Only one query is run for a subnet at a time. Requesting a search for a subnet that is
+    /// already being searched only extends the expiry of the search.
+    pub fn discover_subnet_peers(&mut self, subnet_id: SubnetId, expiry: Instant) {
+        let current_expiry = self.subnet_queries.entry(subnet_id).or_insert(expiry);
+        if *current_expiry < expiry {
+            *current_expiry = expiry;
+        }
+
+        if self
+            .active_subnet_queries
+            .values()
+            .any(|active| *active == subnet_id)
+        {
+            debug!(self.log, "Subnet query already in progress"; "subnet" => format!("{}", subnet_id));
+            return;
+        }
+
+        debug!(self.log, "Searching for subnet peers"; "subnet" => format!("{}", subnet_id));
+        let target = NodeId::random();
+        self.discovery.find_enr_predicate(
+            target,
+            move |enr: &Enr| enr_has_subnet(enr, subnet_id),
+            SUBNET_QUERY_PEERS,
+        );
+        self.active_subnet_queries.insert(target, subnet_id);
+    }
+
+    /// Returns the next queued subnet peer that should be dialed, if any.
+    fn next_subnet_peer(&mut self) -> Option<PeerId> {
+        while let Some(peer_id) = self.subnet_dial_queue.pop_front() {
+            if self.has_peer_capacity(self.max_peers + SUBNET_PEER_EXCESS)
+                && !self.connected_peers.contains(&peer_id)
+                && self.peer_lists.is_dialable(&peer_id)
+            {
+                return Some(peer_id);
+            }
+        }
+        None
+    }
+
     /// Search for new peers using the underlying discovery mechanism.
     fn find_peers(&mut self) {
         // pick a random NodeId
@@ -163,11 +340,29 @@ where
     }
 
     fn addresses_of_peer(&mut self, peer_id: &PeerId) -> Vec<Multiaddr> {
-        // Let discovery track possible known peers.
-        self.discovery.addresses_of_peer(peer_id)
+        // Trusted peers may be configured with an address, otherwise let discovery track possible
+        // known peers.
+        let mut addresses: Vec<Multiaddr> = self
+            .peer_lists
+            .trusted_peers()
+            .get(peer_id)
+            .and_then(|address| address.clone())
+            .into_iter()
+            .collect();
+        addresses.extend(self.discovery.addresses_of_peer(peer_id));
+        addresses
     }
 
-    fn inject_connected(&mut self, peer_id: PeerId, _endpoint: ConnectedPoint) {
+    fn inject_connected(&mut self, peer_id: PeerId, endpoint: ConnectedPoint) {
+        if self.peer_lists.is_denied_connection(&peer_id, &endpoint) {
+            debug!(self.log, "Rejecting denied peer"; "peer_id" => format!("{:?}", peer_id));
+            self.rejected_peers.insert(peer_id.clone());
+            self.disconnect_queue.push(peer_id);
+            return;
+        }
+
+        self.peer_db.on_connected(&peer_id);
+        self.connected_endpoints.insert(peer_id.clone(), endpoint);
         self.connected_peers.insert(peer_id);
         // TODO: Drop peers if over max_peer limit
 
@@ -176,19 +371,33 @@ where
     }
 
     fn inject_disconnected(&mut self, peer_id: &PeerId, _endpoint: ConnectedPoint) {
+        // rejected peers were never counted as connected
+        if self.rejected_peers.remove(peer_id) {
+            return;
+        }
+
+        self.peer_db.on_disconnected(peer_id);
+        self.connected_endpoints.remove(peer_id);
         self.connected_peers.remove(peer_id);
 
         metrics::inc_counter(&metrics::PEER_DISCONNECT_EVENT_COUNT);
         metrics::set_gauge(&metrics::PEERS_CONNECTED, self.connected_peers() as i64);
     }
 
+    fn inject_dial_failure(&mut self, peer_id: &PeerId) {
+        self.peer_db.on_dial_failure(peer_id);
+    }
+
     fn inject_replaced(
         &mut self,
-        _peer_id: PeerId,
+        peer_id: PeerId,
         _closed: ConnectedPoint,
-        _opened: ConnectedPoint,
+        opened: ConnectedPoint,
     ) {
-        // discv5 doesn't implement
+        // discv5 doesn't implement, track the new endpoint of the peer
+        if self.connected_peers.contains(&peer_id) {
+            self.connected_endpoints.insert(peer_id, opened);
+        }
     }
 
     fn inject_node_event(
@@ -212,7 +421,7 @@ where
         loop {
             match self.peer_discovery_delay.poll() {
                 Ok(Async::Ready(_)) => {
-                    if self.connected_peers.len() < self.max_peers {
+                    if self.has_peer_capacity(self.max_peers) {
                         self.find_peers();
                     }
                 }
@@ -223,14 +432,93 @@ where
             }
         }
 
+        // periodically persist the peer database
+        loop {
+            match self.peer_db_save_delay.poll() {
+                Ok(Async::Ready(_)) => {
+                    self.peer_db.save();
+                    self.peer_db_save_delay
+                        .reset(Instant::now() + Duration::from_secs(PEER_DB_SAVE_INTERVAL));
+                }
+                Ok(Async::NotReady) => break,
+                Err(e) => {
+                    warn!(self.log, "Peer database save timer failed"; "Error" => format!("{:?}", e));
+                    break;
+                }
+            }
+        }
+
+        // periodically queue any disconnected trusted peers to be redialed
+        loop {
+            match self.trusted_peer_redial_delay.poll() {
+                Ok(Async::Ready(_)) => {
+                    for peer_id in self.peer_lists.trusted_peers().keys() {
+                        if !self.connected_peers.contains(peer_id)
+                            && !self.trusted_peer_dial_queue.contains(peer_id)
+                        {
+                            self.trusted_peer_dial_queue.push_back(peer_id.clone());
+                        }
+                    }
+                    self.trusted_peer_redial_delay
+                        .reset(Instant::now() + Duration::from_secs(TRUSTED_PEER_REDIAL_INTERVAL));
+                }
+                Ok(Async::NotReady) => break,
+                Err(e) => {
+                    warn!(self.log, "Trusted peer redial timer failed"; "Error" => format!("{:?}", e));
+                    break;
+                }
+            }
+        }
+
+        // dial trusted peers, regardless of the peer limit
+        while let Some(peer_id) = self.trusted_peer_dial_queue.pop_front() {
+            if self.connected_peers.contains(&peer_id) {
+                continue;
+            }
+            match self.peer_lists.trusted_peers().get(&peer_id) {
+                Some(Some(address)) => {
+                    debug!(self.log, "Dialing trusted peer"; "peer_id" => format!("{:?}", peer_id), "address" => format!("{}", address));
+                    return Async::Ready(NetworkBehaviourAction::DialAddress {
+                        address: address.clone(),
+                    });
+                }
+                Some(None) => {
+                    debug!(self.log, "Dialing trusted peer"; "peer_id" => format!("{:?}", peer_id));
+                    return Async::Ready(NetworkBehaviourAction::DialPeer { peer_id });
+                }
+                // the peer is no longer trusted
+                None => {}
+            }
+        }
+
+        // reconnect to peers known from previous runs
+        while let Some(peer_id) = self.known_peer_dial_queue.pop_front() {
+            if self.has_peer_capacity(self.max_peers)
+                && !self.connected_peers.contains(&peer_id)
+                && self.peer_lists.is_dialable(&peer_id)
+            {
+                debug!(self.log, "Dialing known peer"; "peer_id"=> format!("{:?}", peer_id));
+                return Async::Ready(NetworkBehaviourAction::DialPeer { peer_id });
+            }
+        }
+
+        // remove any subnet searches that are no longer required
+        let now = Instant::now();
+        self.subnet_queries.retain(|_, expiry| *expiry > now);
+
+        // dial any peers that were discovered on a required subnet
+        if let Some(peer_id) = self.next_subnet_peer() {
+            debug!(self.log, "Subnet peer discovered"; "peer_id"=> format!("{:?}", peer_id));
+            return Async::Ready(NetworkBehaviourAction::DialPeer { peer_id });
+        }
+
         // Poll discovery
         loop {
             match self.discovery.poll(params) {
                 Async::Ready(NetworkBehaviourAction::GenerateEvent(event)) => {
                     match event {
-                        Discv5Event::Discovered(_enr) => {
-                            // not concerned about FINDNODE results, rather the result of an entire
-                            // query.
+                        Discv5Event::Discovered(enr) => {
+                            self.peer_db.insert_enr(enr);
                         }
                         Discv5Event::SocketUpdated(socket) => {
                             info!(self.log, "Address updated"; "IP" => format!("{}",socket.ip()));
@@ -244,15 +532,31 @@ where
                                 address,
                             });
                         }
-                        Discv5Event::FindNodeResult { closer_peers, .. } => {
+                        Discv5Event::FindNodeResult { key, closer_peers } => {
+                            // the peers found by a subnet query all advertise the subnet
+                            if let Some(subnet_id) = self.active_subnet_queries.remove(&key) {
+                                debug!(self.log, "Subnet query completed"; "subnet" => format!("{}", subnet_id), "peers_found" => closer_peers.len());
+                                if self.subnet_queries.contains_key(&subnet_id) {
+                                    self.subnet_dial_queue.extend(closer_peers);
+                                }
+                                if let Some(peer_id) = self.next_subnet_peer() {
+                                    debug!(self.log, "Subnet peer discovered"; "peer_id"=> format!("{:?}", peer_id));
+                                    return Async::Ready(NetworkBehaviourAction::DialPeer {
+                                        peer_id,
+                                    });
+                                }
+                                continue;
+                            }
+
                             debug!(self.log, "Discovery query completed"; "peers_found" => closer_peers.len());
                             if closer_peers.is_empty() {
                                 debug!(self.log, "Discovery random query found no peers");
                             }
                             for peer_id in closer_peers {
                                 // if we need more peers, attempt a connection
-                                if self.connected_peers.len() < self.max_peers
+                                if self.has_peer_capacity(self.max_peers)
                                     && self.connected_peers.get(&peer_id).is_none()
+                                    && self.peer_lists.is_dialable(&peer_id)
                                 {
                                     debug!(self.log, "Peer discovered"; "peer_id"=> format!("{:?}", peer_id));
                                     return Async::Ready(NetworkBehaviourAction::DialPeer {
@@ -273,6 +577,13 @@ where
     }
 }
 
+impl<TSubstream> Drop for Discovery<TSubstream> {
+    fn drop(&mut self) {
+        // persist the known peers on shutdown
+        self.peer_db.save();
+    }
+}
+
 /// Loads an ENR from file if it exists and matches the current NodeId and sequence number. If none
 /// exists, generates a new one.
 ///
@@ -281,8 +592,11 @@ where
 fn load_enr(
     local_key: &Keypair,
     config: &NetworkConfig,
+    long_lived_subnets: &[SubnetId],
     log: &slog::Logger,
 ) -> Result<Enr, String> {
+    let subnet_bytes = subnets_to_enr_bytes(long_lived_subnets);
+
     // Build the local ENR.
     // Note: Discovery should update the ENR record's IP to the external IP as seen by the
     // majority of our peers.
@@ -290,6 +604,7 @@ fn load_enr(
         .ip(config.discovery_address)
         .tcp(config.libp2p_port)
         .udp(config.discovery_port)
+        .add_value(ATTESTATION_SUBNETS_ENR_KEY, subnet_bytes.clone())
         .build(&local_key)
         .map_err(|e| format!("Could not build Local ENR: {:?}", e))?;
 
@@ -305,6 +620,7 @@ fn load_enr(
                             if enr.ip().map(Into::into) == Some(config.discovery_address)
                                 && enr.tcp() == Some(config.libp2p_port)
                                 && enr.udp() == Some(config.discovery_port)
+                                && enr.get(ATTESTATION_SUBNETS_ENR_KEY) == Some(&subnet_bytes)
                             {
                                 debug!(log, "ENR loaded from file"; "file" => format!("{:?}", enr_f));
                                 // the stored ENR has the same configuration, use it
@@ -10,13 +10,18 @@ mod config;
 mod discovery;
 pub mod error;
 mod metrics;
+pub mod peer_db;
+pub mod peer_lists;
 pub mod rpc;
 mod service;
+pub mod subnets;
 
 pub use behaviour::PubsubMessage;
 pub use config::{
-    Config as NetworkConfig, BEACON_ATTESTATION_TOPIC, BEACON_BLOCK_TOPIC, SHARD_TOPIC_PREFIX,
-    TOPIC_ENCODING_POSTFIX, TOPIC_PREFIX,
+    Config as NetworkConfig, SecureChannel, ATTESTER_SLASHING_TOPIC,
+    BEACON_AGGREGATE_AND_PROOF_TOPIC, BEACON_ATTESTATION_TOPIC, BEACON_BLOCK_TOPIC,
+    PROPOSER_SLASHING_TOPIC, SHARD_TOPIC_PREFIX, TOPIC_ENCODING_POSTFIX, TOPIC_PREFIX,
+    VOLUNTARY_EXIT_TOPIC,
 };
 pub use libp2p::enr::Enr;
 pub use libp2p::gossipsub::{Topic, TopicHash};
@@ -29,3 +34,4 @@ pub use libp2p::{
 pub use rpc::RPCEvent;
 pub use service::Libp2pEvent;
 pub use service::Service;
+pub use subnets::{SubnetId, SubnetSubscription, ATTESTATION_SUBNET_COUNT};
@@ -17,4 +17,8 @@ lazy_static! {
         "libp2p_peer_disconnect_event_total",
         "Count of libp2p peer disconnect events"
     );
+    pub static ref RPC_RATE_LIMITED_REQUESTS: Result<IntCounter> = try_create_int_counter(
+        "libp2p_rpc_rate_limited_requests_total",
+        "Count of inbound RPC requests rejected by the rate limiter"
+    );
 }
@@ -0,0 +1,306 @@
+//! A persistent database of known peers.
+//!
+//! Discovered ENRs are stored along with information about the quality of each peer. The
+//! database is saved to the `network_dir` and reloaded on startup, so that a restarted node can
+//! reconnect to known peers without first searching from the boot nodes.
+
+use libp2p::enr::Enr;
+use libp2p::PeerId;
+use serde_derive::{Deserialize, Serialize};
+use slog::{debug, warn};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The filename of the peer database within the `network_dir`.
+pub const PEER_DB_FILENAME: &str = "peers.json";
+/// The maximum number of peers stored in the database. Once exceeded, the lowest quality peers
+/// are evicted.
+pub const MAX_KNOWN_PEERS: usize = 1_000;
+/// Peers that have not been seen for this many seconds are considered stale and evicted.
+pub const STALE_PEER_SECONDS: u64 = 7 * 24 * 60 * 60;
+/// The bounds of a peer's score.
+const MAX_SCORE: i32 = 100;
+const MIN_SCORE: i32 = -100;
+
+/// The information stored about a single peer.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeerRecord {
+    /// The base64 encoded ENR of the peer.
+    pub enr: String,
+    /// The unix time, in seconds, at which the peer was last discovered or connected.
+    pub last_seen: u64,
+    /// A measure of the peer's quality. Increased by successful connections and decreased by
+    /// failed dials.
+    pub score: i32,
+    /// The number of times we have successfully connected to the peer.
+    pub successful_connections: u64,
+}
+
+/// A database of known peers, indexed by `PeerId`.
+pub struct PeerDB {
+    /// The known peers and their decoded ENRs.
+    peers: HashMap<PeerId, (Enr, PeerRecord)>,
+    /// The file the database is persisted to.
+    path: PathBuf,
+    /// The maximum number of peers that are stored.
+    max_peers: usize,
+    /// Logger for the peer database.
+    log: slog::Logger,
+}
+
+impl PeerDB {
+    /// Loads the database from the `network_dir`, evicting stale entries. If no database exists,
+    /// or it cannot be read, an empty database is returned.
+    pub fn load(network_dir: &Path, log: &slog::Logger) -> Self {
+        let path = network_dir.join(PEER_DB_FILENAME);
+        let mut db = PeerDB {
+            peers: HashMap::new(),
+            path,
+            max_peers: MAX_KNOWN_PEERS,
+            log: log.clone(),
+        };
+
+        let records: Vec<PeerRecord> = match File::open(&db.path) {
+            Ok(file) => match serde_json::from_reader(file) {
+                Ok(records) => records,
+                Err(e) => {
+                    warn!(log, "Could not decode the peer database"; "error" => format!("{}", e));
+                    Vec::new()
+                }
+            },
+            Err(_) => {
+                debug!(log, "No peer database found"; "file" => format!("{:?}", db.path));
+                Vec::new()
+            }
+        };
+
+        for record in records {
+            match Enr::from_str(&record.enr) {
+                Ok(enr) => {
+                    db.peers.insert(enr.peer_id(), (enr, record));
+                }
+                Err(e) => {
+                    debug!(log, "Invalid ENR in peer database"; "error" => format!("{:?}", e))
+                }
+            }
+        }
+
+        db.prune_at(unix_now());
+        debug!(log, "Peer database loaded"; "peers" => db.peers.len());
+        db
+    }
+
+    /// The number of known peers.
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Returns `true` if there are no known peers.
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// Returns the record of a known peer.
+    pub fn get(&self, peer_id: &PeerId) -> Option<&PeerRecord> {
+        self.peers.get(peer_id).map(|(_, record)| record)
+    }
+
+    /// Adds a newly discovered ENR, or updates the ENR of a known peer if the new record has a
+    /// higher sequence number.
+    pub fn insert_enr(&mut self, enr: Enr) {
+        let now = unix_now();
+        let peer_id = enr.peer_id();
+
+        match self.peers.get_mut(&peer_id) {
+            Some((known_enr, record)) => {
+                if enr.seq() > known_enr.seq() {
+                    record.enr = enr.to_base64();
+                    *known_enr = enr;
+                }
+                record.last_seen = now;
+            }
+            None => {
+                let record = PeerRecord {
+                    enr: enr.to_base64(),
+                    last_seen: now,
+                    score: 0,
+                    successful_connections: 0,
+                };
+                self.peers.insert(peer_id, (enr, record));
+            }
+        }
+    }
+
+    /// Records a successful connection to a known peer.
+    pub fn on_connected(&mut self, peer_id: &PeerId) {
+        if let Some((_, record)) = self.peers.get_mut(peer_id) {
+            record.last_seen = unix_now();
+            record.successful_connections += 1;
+            record.score = std::cmp::min(record.score + 1, MAX_SCORE);
+        }
+    }
+
+    /// Records the disconnection of a known peer.
+    pub fn on_disconnected(&mut self, peer_id: &PeerId) {
+        if let Some((_, record)) = self.peers.get_mut(peer_id) {
+            record.last_seen = unix_now();
+        }
+    }
+
+    /// Records a failed attempt to dial a known peer.
+    pub fn on_dial_failure(&mut self, peer_id: &PeerId) {
+        if let Some((_, record)) = self.peers.get_mut(peer_id) {
+            record.score = std::cmp::max(record.score - 1, MIN_SCORE);
+        }
+    }
+
+    /// Returns up to `count` ENRs of the highest quality peers, ordered by score and then by the
+    /// time they were last seen.
+    pub fn best_enrs(&self, count: usize) -> Vec<Enr> {
+        let mut peers: Vec<&(Enr, PeerRecord)> = self.peers.values().collect();
+        peers.sort_by(|(_, a), (_, b)| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| b.last_seen.cmp(&a.last_seen))
+        });
+        peers
+            .into_iter()
+            .take(count)
+            .map(|(enr, _)| enr.clone())
+            .collect()
+    }
+
+    /// Evicts stale peers and, if the database is over capacity, the lowest quality peers.
+    pub fn prune(&mut self) {
+        self.prune_at(unix_now());
+    }
+
+    fn prune_at(&mut self, now: u64) {
+        self.peers
+            .retain(|_, (_, record)| now.saturating_sub(record.last_seen) < STALE_PEER_SECONDS);
+
+        if self.peers.len() > self.max_peers {
+            let keep: Vec<PeerId> = self
+                .best_enrs(self.max_peers)
+                .iter()
+                .map(Enr::peer_id)
+                .collect();
+            self.peers.retain(|peer_id, _| keep.contains(peer_id));
+        }
+    }
+
+    /// Writes the database to disk.
+    pub fn save(&mut self) {
+        self.prune();
+
+        let records: Vec<&PeerRecord> = self.peers.values().map(|(_, record)| record).collect();
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+
+        match File::create(&self.path)
+            .map_err(|e| format!("{}", e))
+            .and_then(|file| serde_json::to_writer(file, &records).map_err(|e| format!("{}", e)))
+        {
+            Ok(()) => debug!(self.log, "Peer database written to disk"; "peers" => records.len()),
+            Err(e) => warn!(
+                self.log,
+                "Could not write peer database to file";
+                "file" => format!("{:?}", self.path),
+                "error" => e
+            ),
+        }
+    }
+}
+
+/// The current unix time in seconds.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_else(|_| 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::enr::EnrBuilder;
+    use libp2p::identity::Keypair;
+    use slog::{o, Discard};
+
+    fn log() -> slog::Logger {
+        slog::Logger::root(Discard, o!())
+    }
+
+    fn random_enr() -> Enr {
+        EnrBuilder::new("v4")
+            .ip("127.0.0.1".parse().unwrap())
+            .tcp(9000)
+            .build(&Keypair::generate_secp256k1())
+            .expect("ENR should build")
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("peer_db_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = temp_dir("save_and_load");
+        let mut db = PeerDB::load(&dir, &log());
+        assert!(db.is_empty());
+
+        let enr = random_enr();
+        db.insert_enr(enr.clone());
+        db.on_connected(&enr.peer_id());
+        db.save();
+
+        let loaded = PeerDB::load(&dir, &log());
+        assert_eq!(loaded.len(), 1);
+        let record = loaded.get(&enr.peer_id()).expect("peer should be stored");
+        assert_eq!(record.successful_connections, 1);
+        assert_eq!(record.score, 1);
+        assert_eq!(loaded.best_enrs(1), vec![enr]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn evicts_stale_peers() {
+        let mut db = PeerDB::load(&temp_dir("evicts_stale_peers"), &log());
+        let enr = random_enr();
+        db.insert_enr(enr.clone());
+
+        let last_seen = db.get(&enr.peer_id()).unwrap().last_seen;
+        db.prune_at(last_seen + STALE_PEER_SECONDS - 1);
+        assert_eq!(db.len(), 1);
+        db.prune_at(last_seen + STALE_PEER_SECONDS);
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn evicts_lowest_scoring_peers_over_capacity() {
+        let mut db = PeerDB::load(&temp_dir("evicts_lowest_scoring"), &log());
+        db.max_peers = 2;
+
+        let good = random_enr();
+        let ok = random_enr();
+        let bad = random_enr();
+        for enr in &[good.clone(), ok.clone(), bad.clone()] {
+            db.insert_enr(enr.clone());
+        }
+        db.on_connected(&good.peer_id());
+        db.on_dial_failure(&bad.peer_id());
+
+        db.prune();
+        assert_eq!(db.len(), 2);
+        assert!(db.get(&good.peer_id()).is_some());
+        assert!(db.get(&ok.peer_id()).is_some());
+        assert!(db.get(&bad.peer_id()).is_none());
+    }
+}
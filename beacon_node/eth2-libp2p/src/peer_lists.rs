@@ -0,0 +1,357 @@
+//! Trusted and denied peers.
+//!
+//! Trusted peers are always dialed, are reconnected to when they disconnect and do not count
+//! towards `max_peers`. They are also exempt from being disconnected for faults. This allows a
+//! node to maintain connections to its sentry nodes.
+//!
+//! Denied peers, identified by their `PeerId` or by the IP address they connect from, are banned
+//! and never dialed.
+
+use crate::NetworkConfig;
+use libp2p::core::ConnectedPoint;
+use libp2p::multiaddr::Protocol;
+use libp2p::{Multiaddr, PeerId};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// A range of IP addresses, in CIDR notation (e.g. `10.0.0.0/8`). A single IP address is a range
+/// with a full-length prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IpRange {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    /// Returns `true` if `ip` is within the range.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(range), IpAddr::V4(ip)) => {
+                prefix_matches(&range.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(range), IpAddr::V6(ip)) => {
+                prefix_matches(&range.octets(), &ip.octets(), self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns `true` if the first `prefix_len` bits of `a` and `b` are equal.
+fn prefix_matches(a: &[u8], b: &[u8], prefix_len: u8) -> bool {
+    let full_bytes = (prefix_len / 8) as usize;
+    let remaining_bits = prefix_len % 8;
+
+    if a[..full_bytes] != b[..full_bytes] {
+        return false;
+    }
+
+    if remaining_bits == 0 {
+        return true;
+    }
+
+    let mask = 0xff_u8 << (8 - remaining_bits);
+    a[full_bytes] & mask == b[full_bytes] & mask
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '/');
+        let address: IpAddr = parts
+            .next()
+            .unwrap_or_else(|| "")
+            .parse()
+            .map_err(|_| format!("Invalid IP range: {}", s))?;
+        let max_prefix_len = if address.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match parts.next() {
+            Some(prefix_len) => prefix_len
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix_len| *prefix_len <= max_prefix_len)
+                .ok_or_else(|| format!("Invalid IP range prefix: {}", s))?,
+            None => max_prefix_len,
+        };
+
+        Ok(IpRange {
+            address,
+            prefix_len,
+        })
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+/// The trusted and denied peers of the node.
+#[derive(Default)]
+pub struct PeerLists {
+    /// Trusted peers, along with the address they are dialed on. Peers without an address are
+    /// dialed on any address known to discovery.
+    trusted_peers: HashMap<PeerId, Option<Multiaddr>>,
+    /// Peers that are banned.
+    denied_peers: HashSet<PeerId>,
+    /// IP ranges from which connections are rejected.
+    denied_ip_ranges: Vec<IpRange>,
+}
+
+impl PeerLists {
+    /// Builds the lists from the network configuration.
+    pub fn from_config(config: &NetworkConfig) -> Result<Self, String> {
+        let mut lists = PeerLists::default();
+
+        for multiaddr in &config.trusted_peers {
+            lists.add_trusted_peer(multiaddr.clone())?;
+        }
+        for peer_id in &config.denied_peers {
+            lists.deny_peer(parse_peer_id(peer_id)?);
+        }
+        for ip_range in &config.denied_ip_ranges {
+            lists.deny_ip_range(ip_range.parse()?);
+        }
+
+        Ok(lists)
+    }
+
+    /// Returns `true` if the peer is trusted.
+    pub fn is_trusted(&self, peer_id: &PeerId) -> bool {
+        self.trusted_peers.contains_key(peer_id)
+    }
+
+    /// Returns the trusted peers and the address they are dialed on, if any.
+    pub fn trusted_peers(&self) -> &HashMap<PeerId, Option<Multiaddr>> {
+        &self.trusted_peers
+    }
+
+    /// Adds a trusted peer, identified by the `/p2p/` component of `multiaddr`. If the multiaddr
+    /// also contains a transport address, the peer is dialed on that address.
+    ///
+    /// Returns the `PeerId` of the trusted peer.
+    pub fn add_trusted_peer(&mut self, multiaddr: Multiaddr) -> Result<PeerId, String> {
+        let peer_id = peer_id_of(&multiaddr)
+            .ok_or_else(|| format!("Trusted peer has no /p2p/ component: {}", multiaddr))?;
+        let address = dial_address(multiaddr);
+        self.trusted_peers.insert(peer_id.clone(), address);
+        Ok(peer_id)
+    }
+
+    /// Removes a trusted peer. Returns `false` if the peer was not trusted.
+    pub fn remove_trusted_peer(&mut self, peer_id: &PeerId) -> bool {
+        self.trusted_peers.remove(peer_id).is_some()
+    }
+
+    /// Returns the denied peers.
+    pub fn denied_peers(&self) -> &HashSet<PeerId> {
+        &self.denied_peers
+    }
+
+    /// Adds a peer to the deny list.
+    pub fn deny_peer(&mut self, peer_id: PeerId) {
+        self.denied_peers.insert(peer_id);
+    }
+
+    /// Removes a peer from the deny list. Returns `false` if the peer was not denied.
+    pub fn allow_peer(&mut self, peer_id: &PeerId) -> bool {
+        self.denied_peers.remove(peer_id)
+    }
+
+    /// Returns the denied IP ranges.
+    pub fn denied_ip_ranges(&self) -> &[IpRange] {
+        &self.denied_ip_ranges
+    }
+
+    /// Adds an IP range to the deny list.
+    pub fn deny_ip_range(&mut self, ip_range: IpRange) {
+        if !self.denied_ip_ranges.contains(&ip_range) {
+            self.denied_ip_ranges.push(ip_range);
+        }
+    }
+
+    /// Removes an IP range from the deny list. Returns `false` if the range was not denied.
+    pub fn allow_ip_range(&mut self, ip_range: &IpRange) -> bool {
+        let len = self.denied_ip_ranges.len();
+        self.denied_ip_ranges.retain(|range| range != ip_range);
+        self.denied_ip_ranges.len() != len
+    }
+
+    /// Returns `true` if the peer may be dialed.
+    pub fn is_dialable(&self, peer_id: &PeerId) -> bool {
+        !self.denied_peers.contains(peer_id)
+    }
+
+    /// Returns `true` if a connection to the peer on the given endpoint must be rejected.
+    ///
+    /// Trusted peers are never rejected on the basis of their IP address.
+    pub fn is_denied_connection(&self, peer_id: &PeerId, endpoint: &ConnectedPoint) -> bool {
+        if self.denied_peers.contains(peer_id) {
+            return true;
+        }
+        if self.is_trusted(peer_id) {
+            return false;
+        }
+
+        let address = match endpoint {
+            ConnectedPoint::Dialer { address } => address,
+            ConnectedPoint::Listener { send_back_addr, .. } => send_back_addr,
+        };
+        match ip_of(address) {
+            Some(ip) => self
+                .denied_ip_ranges
+                .iter()
+                .any(|range| range.contains(&ip)),
+            None => false,
+        }
+    }
+}
+
+/// Parses a trusted peer from either a base58 encoded `PeerId` or a multiaddr with a `/p2p/`
+/// component.
+pub fn parse_trusted_peer(s: &str) -> Result<Multiaddr, String> {
+    let multiaddr = match PeerId::from_str(s) {
+        Ok(peer_id) => Multiaddr::empty().with(Protocol::P2p(peer_id.into())),
+        Err(_) => s
+            .parse::<Multiaddr>()
+            .map_err(|_| format!("Invalid trusted peer: {}", s))?,
+    };
+
+    if peer_id_of(&multiaddr).is_none() {
+        return Err(format!("Trusted peer has no /p2p/ component: {}", s));
+    }
+    Ok(multiaddr)
+}
+
+/// Parses a base58 encoded `PeerId`.
+pub fn parse_peer_id(s: &str) -> Result<PeerId, String> {
+    PeerId::from_str(s).map_err(|_| format!("Invalid peer id: {}", s))
+}
+
+/// Returns the `PeerId` of the `/p2p/` component of a multiaddr.
+fn peer_id_of(multiaddr: &Multiaddr) -> Option<PeerId> {
+    multiaddr.iter().find_map(|protocol| match protocol {
+        Protocol::P2p(multihash) => PeerId::from_multihash(multihash).ok(),
+        _ => None,
+    })
+}
+
+/// Returns the multiaddr without its `/p2p/` component, or `None` if only the `/p2p/` component
+/// is present.
+fn dial_address(multiaddr: Multiaddr) -> Option<Multiaddr> {
+    let address: Multiaddr = multiaddr
+        .iter()
+        .filter(|protocol| match protocol {
+            Protocol::P2p(_) => false,
+            _ => true,
+        })
+        .collect();
+
+    if address.iter().next().is_some() {
+        Some(address)
+    } else {
+        None
+    }
+}
+
+/// Returns the IP address of a multiaddr.
+fn ip_of(multiaddr: &Multiaddr) -> Option<IpAddr> {
+    multiaddr.iter().find_map(|protocol| match protocol {
+        Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+        Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+
+    fn random_peer_id() -> PeerId {
+        PeerId::from(Keypair::generate_secp256k1().public())
+    }
+
+    fn listener(address: &str) -> ConnectedPoint {
+        ConnectedPoint::Listener {
+            listen_addr: "/ip4/127.0.0.1/tcp/9000".parse().unwrap(),
+            send_back_addr: address.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn ip_range_contains() {
+        let range: IpRange = "10.1.0.0/16".parse().unwrap();
+        assert!(range.contains(&"10.1.200.3".parse().unwrap()));
+        assert!(!range.contains(&"10.2.0.1".parse().unwrap()));
+        assert!(!range.contains(&"::1".parse().unwrap()));
+
+        let range: IpRange = "192.168.1.128/25".parse().unwrap();
+        assert!(range.contains(&"192.168.1.200".parse().unwrap()));
+        assert!(!range.contains(&"192.168.1.100".parse().unwrap()));
+
+        let single: IpRange = "1.2.3.4".parse().unwrap();
+        assert_eq!(single.to_string(), "1.2.3.4/32");
+        assert!(single.contains(&"1.2.3.4".parse().unwrap()));
+        assert!(!single.contains(&"1.2.3.5".parse().unwrap()));
+
+        assert!("1.2.3.4/33".parse::<IpRange>().is_err());
+        assert!("not an ip".parse::<IpRange>().is_err());
+    }
+
+    #[test]
+    fn trusted_peers() {
+        let peer_id = random_peer_id();
+        let bare = parse_trusted_peer(&peer_id.to_base58()).unwrap();
+        let with_address = parse_trusted_peer(&format!(
+            "/ip4/10.0.0.1/tcp/9000/p2p/{}",
+            peer_id.to_base58()
+        ))
+        .unwrap();
+        assert!(parse_trusted_peer("/ip4/10.0.0.1/tcp/9000").is_err());
+
+        let mut lists = PeerLists::default();
+        assert_eq!(lists.add_trusted_peer(bare).unwrap(), peer_id);
+        assert_eq!(lists.trusted_peers()[&peer_id], None);
+        lists.add_trusted_peer(with_address).unwrap();
+        assert_eq!(
+            lists.trusted_peers()[&peer_id],
+            Some("/ip4/10.0.0.1/tcp/9000".parse().unwrap())
+        );
+
+        assert!(lists.is_trusted(&peer_id));
+        assert!(lists.remove_trusted_peer(&peer_id));
+        assert!(!lists.is_trusted(&peer_id));
+    }
+
+    #[test]
+    fn denied_connections() {
+        let mut lists = PeerLists::default();
+        let denied = random_peer_id();
+        let trusted = random_peer_id();
+        let other = random_peer_id();
+
+        lists.deny_peer(denied.clone());
+        lists.deny_ip_range("10.0.0.0/8".parse().unwrap());
+        lists
+            .add_trusted_peer(parse_trusted_peer(&trusted.to_base58()).unwrap())
+            .unwrap();
+
+        let local = listener("/ip4/127.0.0.1/tcp/4000");
+        let denied_range = listener("/ip4/10.3.2.1/tcp/4000");
+
+        assert!(lists.is_denied_connection(&denied, &local));
+        assert!(!lists.is_dialable(&denied));
+        assert!(!lists.is_denied_connection(&other, &local));
+        assert!(lists.is_denied_connection(&other, &denied_range));
+        assert!(!lists.is_denied_connection(&trusted, &denied_range));
+
+        assert!(lists.allow_peer(&denied));
+        assert!(lists.allow_ip_range(&"10.0.0.0/8".parse().unwrap()));
+        assert!(!lists.is_denied_connection(&denied, &local));
+        assert!(!lists.is_denied_connection(&other, &denied_range));
+    }
+}
@@ -47,6 +47,7 @@ impl Encoder for SSZInboundCodec {
             }
             RPCErrorResponse::InvalidRequest(err) => err.as_ssz_bytes(),
             RPCErrorResponse::ServerError(err) => err.as_ssz_bytes(),
+            RPCErrorResponse::ResourceUnavailable(err) => err.as_ssz_bytes(),
             RPCErrorResponse::Unknown(err) => err.as_ssz_bytes(),
         };
 
@@ -143,6 +143,8 @@ pub enum RPCErrorResponse {
     Success(RPCResponse),
     InvalidRequest(ErrorMessage),
     ServerError(ErrorMessage),
+    /// The request cannot currently be served, e.g. the peer has exceeded its rate limit.
+    ResourceUnavailable(ErrorMessage),
     Unknown(ErrorMessage),
 }
 
@@ -153,6 +155,7 @@ impl RPCErrorResponse {
             RPCErrorResponse::Success(_) => 0,
             RPCErrorResponse::InvalidRequest(_) => 1,
             RPCErrorResponse::ServerError(_) => 2,
+            RPCErrorResponse::ResourceUnavailable(_) => 3,
             RPCErrorResponse::Unknown(_) => 255,
         }
     }
@@ -170,6 +173,7 @@ impl RPCErrorResponse {
         match response_code {
             1 => RPCErrorResponse::InvalidRequest(err),
             2 => RPCErrorResponse::ServerError(err),
+            3 => RPCErrorResponse::ResourceUnavailable(err),
             _ => RPCErrorResponse::Unknown(err),
         }
     }
@@ -211,6 +215,9 @@ impl std::fmt::Display for RPCErrorResponse {
             RPCErrorResponse::Success(res) => write!(f, "{}", res),
             RPCErrorResponse::InvalidRequest(err) => write!(f, "Invalid Request: {:?}", err),
             RPCErrorResponse::ServerError(err) => write!(f, "Server Error: {:?}", err),
+            RPCErrorResponse::ResourceUnavailable(err) => {
+                write!(f, "Resource Unavailable: {:?}", err)
+            }
             RPCErrorResponse::Unknown(err) => write!(f, "Unknown Error: {:?}", err),
         }
     }
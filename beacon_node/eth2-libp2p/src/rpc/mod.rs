@@ -4,6 +4,7 @@
 //! direct peer-to-peer communication primarily for sending/receiving chain information for
 //! syncing.
 
+use crate::metrics;
 use futures::prelude::*;
 use handler::RPCHandler;
 use libp2p::core::ConnectedPoint;
@@ -13,14 +14,18 @@ use libp2p::swarm::{
 use libp2p::{Multiaddr, PeerId};
 pub use methods::{ErrorMessage, HelloMessage, RPCErrorResponse, RPCResponse, RequestId};
 pub use protocol::{RPCError, RPCProtocol, RPCRequest};
-use slog::o;
+use rate_limiter::RPCRateLimiter;
+pub use rate_limiter::{RateLimitedErr, DEFAULT_MAX_REQUEST_BLOCKS};
+use slog::{debug, o};
 use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncRead, AsyncWrite};
 
 pub(crate) mod codec;
 mod handler;
 pub mod methods;
 mod protocol;
+mod rate_limiter;
 // mod request_response;
 
 /// The return type used in the behaviour and the resultant event from the protocols handler.
@@ -57,24 +62,33 @@ impl std::fmt::Display for RPCEvent {
     }
 }
 
+/// The interval at which fully replenished rate limiting buckets are removed.
+const RATE_LIMITER_PRUNE_INTERVAL: u64 = 30;
+
 /// Implements the libp2p `NetworkBehaviour` trait and therefore manages network-level
 /// logic.
 pub struct RPC<TSubstream> {
     /// Queue of events to processed.
     events: Vec<NetworkBehaviourAction<RPCEvent, RPCMessage>>,
+    /// Rate limits inbound requests per peer and protocol.
+    rate_limiter: RPCRateLimiter,
+    /// The next time the rate limiter should be pruned.
+    next_prune: Instant,
     /// Pins the generic substream.
     marker: PhantomData<(TSubstream)>,
     /// Slog logger for RPC behaviour.
-    _log: slog::Logger,
+    log: slog::Logger,
 }
 
 impl<TSubstream> RPC<TSubstream> {
-    pub fn new(log: &slog::Logger) -> Self {
+    pub fn new(max_request_blocks: u64, log: &slog::Logger) -> Self {
         let log = log.new(o!("Service" => "Libp2p-RPC"));
         RPC {
             events: Vec::new(),
+            rate_limiter: RPCRateLimiter::new(max_request_blocks),
+            next_prune: Instant::now() + Duration::from_secs(RATE_LIMITER_PRUNE_INTERVAL),
             marker: PhantomData,
-            _log: log,
+            log,
         }
     }
 
@@ -126,6 +140,38 @@ where
         source: PeerId,
         event: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent,
     ) {
+        // reject any inbound requests that exceed the peer's quota
+        if let RPCEvent::Request(id, ref request) = event {
+            if let Err(e) = self.rate_limiter.allows(&source, request) {
+                debug!(
+                    self.log,
+                    "Inbound request rate limited";
+                    "peer" => format!("{:?}", source),
+                    "request" => format!("{}", request),
+                    "reason" => format!("{}", e),
+                );
+                metrics::inc_counter(&metrics::RPC_RATE_LIMITED_REQUESTS);
+
+                // GOODBYE requests have no response stream, they are simply dropped.
+                if request.expect_response() {
+                    let error_message = ErrorMessage {
+                        error_message: e.to_string().into_bytes(),
+                    };
+                    let response = match e {
+                        RateLimitedErr::TooLarge => RPCErrorResponse::InvalidRequest(error_message),
+                        RateLimitedErr::TooSoon(_) => {
+                            RPCErrorResponse::ResourceUnavailable(error_message)
+                        }
+                    };
+                    self.events.push(NetworkBehaviourAction::SendEvent {
+                        peer_id: source,
+                        event: RPCEvent::Response(id, response),
+                    });
+                }
+                return;
+            }
+        }
+
         // send the event to the user
         self.events
             .push(NetworkBehaviourAction::GenerateEvent(RPCMessage::RPC(
@@ -142,6 +188,11 @@ where
             Self::OutEvent,
         >,
     > {
+        if Instant::now() >= self.next_prune {
+            self.rate_limiter.prune();
+            self.next_prune = Instant::now() + Duration::from_secs(RATE_LIMITER_PRUNE_INTERVAL);
+        }
+
         if !self.events.is_empty() {
             return Async::Ready(self.events.remove(0));
         }
@@ -0,0 +1,308 @@
+//! Per-peer, per-protocol rate limiting of inbound RPC requests.
+//!
+//! Each peer is given a token bucket for every RPC protocol. Requests consume tokens from the
+//! bucket and tokens are replenished linearly over time. `BeaconBlocks` requests consume one token
+//! per requested block, so that the limit bounds the amount of block data (and therefore disk I/O)
+//! a single peer can cause us to serve.
+
+use super::methods::BeaconBlocksRequest;
+use super::protocol::RPCRequest;
+use fnv::FnvHashMap;
+use libp2p::PeerId;
+use std::time::{Duration, Instant};
+
+/// The default maximum number of blocks that may be requested in a single `BeaconBlocks` request.
+pub const DEFAULT_MAX_REQUEST_BLOCKS: u64 = 1024;
+
+/// The number of `Hello` requests a peer may send per `HELLO_QUOTA_PERIOD` seconds.
+const HELLO_QUOTA: u64 = 2;
+const HELLO_QUOTA_PERIOD: u64 = 10;
+/// The number of `Goodbye` requests a peer may send per `GOODBYE_QUOTA_PERIOD` seconds.
+const GOODBYE_QUOTA: u64 = 1;
+const GOODBYE_QUOTA_PERIOD: u64 = 10;
+/// The number of blocks a peer may request via `BeaconBlocks` per `BLOCKS_QUOTA_PERIOD` seconds.
+const BLOCKS_QUOTA_PERIOD: u64 = 10;
+/// The number of block roots a peer may request via `RecentBeaconBlocks` per
+/// `RECENT_BLOCKS_QUOTA_PERIOD` seconds.
+const RECENT_BLOCKS_QUOTA: u64 = 128;
+const RECENT_BLOCKS_QUOTA_PERIOD: u64 = 10;
+
+/// The reason a request was rejected by the `RPCRateLimiter`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitedErr {
+    /// The request asks for more than the configured maximum and can never be served.
+    TooLarge,
+    /// The peer has exhausted its quota for this protocol. The request may succeed after the
+    /// given duration.
+    TooSoon(Duration),
+}
+
+impl std::fmt::Display for RateLimitedErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateLimitedErr::TooLarge => write!(f, "Request exceeds the maximum allowed size"),
+            RateLimitedErr::TooSoon(wait) => {
+                write!(f, "Rate limit exceeded, retry in {} ms", wait.as_millis())
+            }
+        }
+    }
+}
+
+/// The RPC protocols that are subject to rate limiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Protocol {
+    Hello,
+    Goodbye,
+    BeaconBlocks,
+    RecentBeaconBlocks,
+}
+
+/// A number of tokens that may be consumed over a period of time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quota {
+    /// The maximum number of tokens in the bucket. This is also the maximum burst.
+    pub max_tokens: u64,
+    /// The time taken to replenish an empty bucket.
+    pub replenish_all_every: Duration,
+}
+
+impl Quota {
+    pub fn new(max_tokens: u64, replenish_all_every: Duration) -> Self {
+        Quota {
+            max_tokens,
+            replenish_all_every,
+        }
+    }
+
+    /// The time required to replenish `tokens` tokens.
+    fn time_for(&self, tokens: u64) -> Duration {
+        if self.max_tokens == 0 {
+            return self.replenish_all_every;
+        }
+        let nanos =
+            self.replenish_all_every.as_nanos() * u128::from(tokens) / u128::from(self.max_tokens);
+        Duration::from_nanos(nanos as u64)
+    }
+}
+
+/// A token bucket for a single peer and protocol.
+struct Bucket {
+    /// The number of tokens currently available.
+    tokens: u64,
+    /// The last time the tokens were replenished.
+    last_update: Instant,
+}
+
+impl Bucket {
+    fn full(quota: &Quota, now: Instant) -> Self {
+        Bucket {
+            tokens: quota.max_tokens,
+            last_update: now,
+        }
+    }
+
+    /// Adds the tokens that have accumulated since the last update.
+    fn replenish(&mut self, quota: &Quota, now: Instant) {
+        if self.tokens >= quota.max_tokens {
+            self.tokens = quota.max_tokens;
+            self.last_update = now;
+            return;
+        }
+
+        let elapsed = now.duration_since(self.last_update);
+        let period = quota.replenish_all_every.as_nanos().max(1);
+        let new_tokens = (elapsed.as_nanos() * u128::from(quota.max_tokens) / period) as u64;
+
+        if new_tokens > 0 {
+            self.tokens = std::cmp::min(quota.max_tokens, self.tokens + new_tokens);
+            // Only advance by the time that was "spent" on whole tokens so that partial progress
+            // towards the next token is not lost.
+            self.last_update += quota.time_for(new_tokens);
+            if self.tokens == quota.max_tokens {
+                self.last_update = now;
+            }
+        }
+    }
+}
+
+/// Rate limits inbound RPC requests on a per-peer, per-protocol basis.
+pub struct RPCRateLimiter {
+    /// The maximum number of blocks that can be requested in a single `BeaconBlocks` request.
+    max_request_blocks: u64,
+    /// The quota for each rate limited protocol.
+    quotas: FnvHashMap<Protocol, Quota>,
+    /// The token buckets of each peer.
+    buckets: FnvHashMap<(PeerId, Protocol), Bucket>,
+}
+
+impl RPCRateLimiter {
+    pub fn new(max_request_blocks: u64) -> Self {
+        let mut quotas = FnvHashMap::default();
+        quotas.insert(
+            Protocol::Hello,
+            Quota::new(HELLO_QUOTA, Duration::from_secs(HELLO_QUOTA_PERIOD)),
+        );
+        quotas.insert(
+            Protocol::Goodbye,
+            Quota::new(GOODBYE_QUOTA, Duration::from_secs(GOODBYE_QUOTA_PERIOD)),
+        );
+        quotas.insert(
+            Protocol::BeaconBlocks,
+            Quota::new(max_request_blocks, Duration::from_secs(BLOCKS_QUOTA_PERIOD)),
+        );
+        quotas.insert(
+            Protocol::RecentBeaconBlocks,
+            Quota::new(
+                RECENT_BLOCKS_QUOTA,
+                Duration::from_secs(RECENT_BLOCKS_QUOTA_PERIOD),
+            ),
+        );
+
+        RPCRateLimiter {
+            max_request_blocks,
+            quotas,
+            buckets: FnvHashMap::default(),
+        }
+    }
+
+    /// Checks whether `peer_id` may be served `request`, consuming tokens if so.
+    pub fn allows(&mut self, peer_id: &PeerId, request: &RPCRequest) -> Result<(), RateLimitedErr> {
+        self.allows_at(peer_id, request, Instant::now())
+    }
+
+    fn allows_at(
+        &mut self,
+        peer_id: &PeerId,
+        request: &RPCRequest,
+        now: Instant,
+    ) -> Result<(), RateLimitedErr> {
+        let (protocol, tokens) = match request {
+            RPCRequest::Hello(_) => (Protocol::Hello, 1),
+            RPCRequest::Goodbye(_) => (Protocol::Goodbye, 1),
+            RPCRequest::BeaconBlocks(BeaconBlocksRequest { count, .. }) => {
+                if *count > self.max_request_blocks {
+                    return Err(RateLimitedErr::TooLarge);
+                }
+                (Protocol::BeaconBlocks, std::cmp::max(*count, 1))
+            }
+            RPCRequest::RecentBeaconBlocks(req) => {
+                let count = req.block_roots.len() as u64;
+                if count > RECENT_BLOCKS_QUOTA {
+                    return Err(RateLimitedErr::TooLarge);
+                }
+                (Protocol::RecentBeaconBlocks, std::cmp::max(count, 1))
+            }
+        };
+
+        let quota = self.quotas[&protocol];
+        let bucket = self
+            .buckets
+            .entry((peer_id.clone(), protocol))
+            .or_insert_with(|| Bucket::full(&quota, now));
+
+        bucket.replenish(&quota, now);
+
+        if bucket.tokens >= tokens {
+            bucket.tokens -= tokens;
+            Ok(())
+        } else {
+            let missing = tokens - bucket.tokens;
+            let wait = quota
+                .time_for(missing)
+                .checked_sub(now.duration_since(bucket.last_update))
+                .unwrap_or_else(|| Duration::from_secs(0));
+            Err(RateLimitedErr::TooSoon(wait))
+        }
+    }
+
+    /// Removes any buckets that have fully replenished. These are equivalent to a fresh bucket.
+    ///
+    /// This is the only way buckets are removed. In particular, the buckets of a peer are retained
+    /// when it disconnects, so that a peer cannot reset its quota by reconnecting.
+    pub fn prune(&mut self) {
+        self.prune_at(Instant::now())
+    }
+
+    fn prune_at(&mut self, now: Instant) {
+        let quotas = &self.quotas;
+        self.buckets.retain(|(_, protocol), bucket| {
+            let quota = &quotas[protocol];
+            bucket.replenish(quota, now);
+            bucket.tokens < quota.max_tokens
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::methods::RecentBeaconBlocksRequest;
+    use types::Hash256;
+
+    fn blocks_request(count: u64) -> RPCRequest {
+        RPCRequest::BeaconBlocks(BeaconBlocksRequest {
+            head_block_root: Hash256::zero(),
+            start_slot: 0,
+            count,
+            step: 1,
+        })
+    }
+
+    #[test]
+    fn rejects_oversized_requests() {
+        let mut limiter = RPCRateLimiter::new(64);
+        let peer = PeerId::random();
+
+        assert_eq!(
+            limiter.allows(&peer, &blocks_request(65)),
+            Err(RateLimitedErr::TooLarge)
+        );
+        assert_eq!(limiter.allows(&peer, &blocks_request(64)), Ok(()));
+
+        let roots = RPCRequest::RecentBeaconBlocks(RecentBeaconBlocksRequest {
+            block_roots: vec![Hash256::zero(); RECENT_BLOCKS_QUOTA as usize + 1],
+        });
+        assert_eq!(limiter.allows(&peer, &roots), Err(RateLimitedErr::TooLarge));
+    }
+
+    #[test]
+    fn exhausts_and_replenishes() {
+        let mut limiter = RPCRateLimiter::new(64);
+        let peer = PeerId::random();
+        let other_peer = PeerId::random();
+        let now = Instant::now();
+
+        assert_eq!(limiter.allows_at(&peer, &blocks_request(40), now), Ok(()));
+        match limiter.allows_at(&peer, &blocks_request(40), now) {
+            Err(RateLimitedErr::TooSoon(_)) => {}
+            other => panic!("expected rate limit, got {:?}", other),
+        }
+
+        // Other peers have their own quota.
+        assert_eq!(
+            limiter.allows_at(&other_peer, &blocks_request(40), now),
+            Ok(())
+        );
+
+        // After a full period the bucket is replenished.
+        let later = now + Duration::from_secs(BLOCKS_QUOTA_PERIOD);
+        assert_eq!(limiter.allows_at(&peer, &blocks_request(64), later), Ok(()));
+    }
+
+    #[test]
+    fn prune_only_removes_replenished_buckets() {
+        let mut limiter = RPCRateLimiter::new(64);
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        assert_eq!(limiter.allows_at(&peer, &blocks_request(64), now), Ok(()));
+
+        // An exhausted bucket is retained, so the peer remains limited.
+        limiter.prune_at(now);
+        assert!(limiter.allows_at(&peer, &blocks_request(1), now).is_err());
+
+        // Once replenished, the bucket is removed.
+        limiter.prune_at(now + Duration::from_secs(BLOCKS_QUOTA_PERIOD));
+        assert!(limiter.buckets.is_empty());
+    }
+}
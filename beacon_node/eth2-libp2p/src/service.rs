@@ -2,26 +2,33 @@ use crate::behaviour::{Behaviour, BehaviourEvent, PubsubMessage};
 use crate::config::*;
 use crate::error;
 use crate::multiaddr::Protocol;
+use crate::peer_lists::{parse_trusted_peer, IpRange};
 use crate::rpc::RPCEvent;
+use crate::subnets::{SubnetId, SubnetSubscription, ATTESTATION_SUBNET_COUNT};
 use crate::NetworkConfig;
 use crate::{Topic, TopicHash};
 use futures::prelude::*;
-use futures::Stream;
+use futures::{future, Stream};
 use libp2p::core::{
-    identity::Keypair, multiaddr::Multiaddr, muxing::StreamMuxerBox, nodes::Substream,
-    transport::boxed::Boxed,
+    either::EitherOutput, identity::Keypair, multiaddr::Multiaddr, muxing::StreamMuxerBox,
+    nodes::Substream, transport::boxed::Boxed,
 };
-use libp2p::{core, secio, PeerId, Swarm, Transport};
+use libp2p::{core, noise, secio, PeerId, Swarm, Transport};
+use rand::seq::SliceRandom;
 use slog::{crit, debug, info, trace, warn};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::{Error, ErrorKind};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio_timer::Interval;
 
 type Libp2pStream = Boxed<(PeerId, StreamMuxerBox), Error>;
 type Libp2pBehaviour = Behaviour<Substream<StreamMuxerBox>>;
 
 const NETWORK_KEY_FILENAME: &str = "key";
+/// The interval (in seconds) at which expired short-lived subnet subscriptions are removed.
+const SUBNET_EXPIRY_CHECK_INTERVAL: u64 = 1;
 
 /// The configuration and state of the libp2p components for the beacon node.
 pub struct Service {
@@ -30,6 +37,13 @@ pub struct Service {
     pub swarm: Swarm<Libp2pStream, Libp2pBehaviour>,
     /// This node's PeerId.
     pub local_peer_id: PeerId,
+    /// The attestation subnets this node is subscribed to for its lifetime.
+    long_lived_subnets: Vec<SubnetId>,
+    /// Attestation subnets subscribed to for our validators' duties and the time at which the
+    /// subscription expires.
+    short_lived_subnets: HashMap<SubnetId, Instant>,
+    /// Interval at which expired short-lived subnet subscriptions are removed.
+    subnet_expiry_check: Interval,
     /// The libp2p logger handle.
     pub log: slog::Logger,
 }
@@ -48,11 +62,23 @@ impl Service {
         let local_peer_id = PeerId::from(local_keypair.public());
         info!(log, "Libp2p Service"; "peer_id" => format!("{:?}", local_peer_id));
 
+        // select the attestation subnets we will subscribe to for the lifetime of the node
+        let long_lived_subnets = {
+            let mut all_subnets: Vec<SubnetId> =
+                (0..ATTESTATION_SUBNET_COUNT).map(SubnetId::new).collect();
+            if !config.subscribe_all_subnets {
+                all_subnets.shuffle(&mut rand::thread_rng());
+                all_subnets.truncate(config.long_lived_subnet_count);
+                all_subnets.sort();
+            }
+            all_subnets
+        };
+
         let mut swarm = {
-            // Set up the transport - tcp/ws with secio and mplex/yamux
-            let transport = build_transport(local_keypair.clone());
+            // Set up the transport - tcp/ws with noise/secio and mplex/yamux
+            let transport = build_transport(local_keypair.clone(), config.secure_channel);
             // Lighthouse network behaviour
-            let behaviour = Behaviour::new(&local_keypair, &config, &log)?;
+            let behaviour = Behaviour::new(&local_keypair, &config, &long_lived_subnets, &log)?;
             Swarm::new(transport, behaviour, local_peer_id.clone())
         };
 
@@ -122,11 +148,17 @@ impl Service {
             ))
         };
         topics.push(topic_builder(BEACON_BLOCK_TOPIC));
-        topics.push(topic_builder(BEACON_ATTESTATION_TOPIC));
+        topics.push(topic_builder(BEACON_AGGREGATE_AND_PROOF_TOPIC));
         topics.push(topic_builder(VOLUNTARY_EXIT_TOPIC));
         topics.push(topic_builder(PROPOSER_SLASHING_TOPIC));
         topics.push(topic_builder(ATTESTER_SLASHING_TOPIC));
 
+        // Unaggregated attestations are only received on our long-lived attestation subnets and
+        // the short-lived subnets of our validators' duties, never on a global topic.
+        for subnet_id in &long_lived_subnets {
+            topics.push(subnet_id.topic());
+        }
+
         // Add any topics specified by the user
         topics.append(
             &mut config
@@ -148,12 +180,194 @@ impl Service {
         }
         info!(log, "Subscribed to topics"; "topics" => format!("{:?}", subscribed_topics.iter().map(|t| format!("{}", t)).collect::<Vec<String>>()));
 
+        // ban the denied peers
+        let denied_peers: Vec<PeerId> = swarm
+            .discovery()
+            .peer_lists()
+            .denied_peers()
+            .iter()
+            .cloned()
+            .collect();
+        for peer_id in denied_peers {
+            Swarm::ban_peer_id(&mut swarm, peer_id);
+        }
+
         Ok(Service {
             local_peer_id,
             swarm,
+            long_lived_subnets,
+            short_lived_subnets: HashMap::new(),
+            subnet_expiry_check: Interval::new_interval(Duration::from_secs(
+                SUBNET_EXPIRY_CHECK_INTERVAL,
+            )),
             log,
         })
     }
+
+    /// Returns the attestation subnets this node is subscribed to for its lifetime.
+    pub fn long_lived_subnets(&self) -> &[SubnetId] {
+        &self.long_lived_subnets
+    }
+
+    /// Subscribes to the given attestation subnets until their expiry. Peers are searched for on
+    /// each subnet so that attestations can be published and received.
+    pub fn subscribe_to_subnets(&mut self, subscriptions: Vec<SubnetSubscription>) {
+        for SubnetSubscription { subnet_id, expiry } in subscriptions {
+            // search for peers on the subnet, even if it is one of our long-lived subnets
+            self.swarm.discover_subnet_peers(subnet_id, expiry);
+
+            if self.long_lived_subnets.contains(&subnet_id) {
+                continue;
+            }
+
+            match self.short_lived_subnets.get_mut(&subnet_id) {
+                Some(current_expiry) => {
+                    if *current_expiry < expiry {
+                        *current_expiry = expiry;
+                    }
+                }
+                None => {
+                    if self.swarm.subscribe(subnet_id.topic()) {
+                        debug!(self.log, "Subscribed to subnet"; "subnet" => format!("{}", subnet_id));
+                    } else {
+                        warn!(self.log, "Could not subscribe to subnet"; "subnet" => format!("{}", subnet_id));
+                    }
+                    self.short_lived_subnets.insert(subnet_id, expiry);
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if the peer is trusted. Trusted peers are not disconnected for faults.
+    pub fn is_trusted_peer(&self, peer_id: &PeerId) -> bool {
+        self.swarm.discovery().peer_lists().is_trusted(peer_id)
+    }
+
+    /// Returns the trusted peers and the address they are dialed on, if any.
+    pub fn trusted_peers(&self) -> Vec<(PeerId, Option<Multiaddr>)> {
+        self.swarm
+            .discovery()
+            .peer_lists()
+            .trusted_peers()
+            .iter()
+            .map(|(peer_id, address)| (peer_id.clone(), address.clone()))
+            .collect()
+    }
+
+    /// Adds a trusted peer, given as a peer id or a multiaddr with a `/p2p/` component, and
+    /// dials it. A denied peer is removed from the deny list.
+    pub fn add_trusted_peer(&mut self, peer: &str) -> Result<PeerId, String> {
+        let peer_id = self
+            .swarm
+            .discovery_mut()
+            .add_trusted_peer(parse_trusted_peer(peer)?)?;
+        self.allow_peer(&peer_id);
+        info!(self.log, "Trusted peer added"; "peer_id" => format!("{:?}", peer_id));
+        Ok(peer_id)
+    }
+
+    /// Removes a trusted peer. Returns `false` if the peer was not trusted.
+    pub fn remove_trusted_peer(&mut self, peer_id: &PeerId) -> bool {
+        self.swarm
+            .discovery_mut()
+            .peer_lists_mut()
+            .remove_trusted_peer(peer_id)
+    }
+
+    /// Returns the denied peers.
+    pub fn denied_peers(&self) -> Vec<PeerId> {
+        self.swarm
+            .discovery()
+            .peer_lists()
+            .denied_peers()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Bans a peer, disconnecting it if it is connected. The peer is no longer trusted.
+    pub fn deny_peer(&mut self, peer_id: PeerId) {
+        let peer_lists = self.swarm.discovery_mut().peer_lists_mut();
+        peer_lists.remove_trusted_peer(&peer_id);
+        peer_lists.deny_peer(peer_id.clone());
+        info!(self.log, "Peer denied"; "peer_id" => format!("{:?}", peer_id));
+        Swarm::ban_peer_id(&mut self.swarm, peer_id);
+    }
+
+    /// Removes a peer from the deny list. Returns `false` if the peer was not denied.
+    pub fn allow_peer(&mut self, peer_id: &PeerId) -> bool {
+        let denied = self
+            .swarm
+            .discovery_mut()
+            .peer_lists_mut()
+            .allow_peer(peer_id);
+        if denied {
+            Swarm::unban_peer_id(&mut self.swarm, peer_id.clone());
+        }
+        denied
+    }
+
+    /// Returns the denied IP ranges.
+    pub fn denied_ip_ranges(&self) -> Vec<IpRange> {
+        self.swarm
+            .discovery()
+            .peer_lists()
+            .denied_ip_ranges()
+            .to_vec()
+    }
+
+    /// Rejects any future connections from the IP range and disconnects any connected peers
+    /// within it. Trusted peers are not disconnected.
+    pub fn deny_ip_range(&mut self, ip_range: IpRange) {
+        info!(self.log, "IP range denied"; "ip_range" => format!("{}", ip_range));
+        self.swarm.discovery_mut().deny_ip_range(ip_range);
+        self.disconnect_rejected_peers();
+    }
+
+    /// Removes an IP range from the deny list. Returns `false` if the range was not denied.
+    pub fn allow_ip_range(&mut self, ip_range: &IpRange) -> bool {
+        self.swarm
+            .discovery_mut()
+            .peer_lists_mut()
+            .allow_ip_range(ip_range)
+    }
+
+    /// Disconnects peers whose connections were rejected by discovery, or which are connected from
+    /// a denied IP range. The peers are banned and, unless they are denied, immediately unbanned,
+    /// which closes their connections.
+    fn disconnect_rejected_peers(&mut self) {
+        for peer_id in self.swarm.discovery_mut().take_rejected_peers() {
+            debug!(self.log, "Disconnecting rejected peer"; "peer_id" => format!("{:?}", peer_id));
+            Swarm::ban_peer_id(&mut self.swarm, peer_id.clone());
+            if !self
+                .swarm
+                .discovery()
+                .peer_lists()
+                .denied_peers()
+                .contains(&peer_id)
+            {
+                Swarm::unban_peer_id(&mut self.swarm, peer_id);
+            }
+        }
+    }
+
+    /// Unsubscribes from any short-lived subnets whose subscription has expired.
+    fn remove_expired_subnets(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<SubnetId> = self
+            .short_lived_subnets
+            .iter()
+            .filter(|(_, expiry)| **expiry <= now)
+            .map(|(subnet_id, _)| *subnet_id)
+            .collect();
+
+        for subnet_id in expired {
+            self.short_lived_subnets.remove(&subnet_id);
+            if self.swarm.unsubscribe(subnet_id.topic()) {
+                debug!(self.log, "Unsubscribed from subnet"; "subnet" => format!("{}", subnet_id));
+            }
+        }
+    }
 }
 
 impl Stream for Service {
@@ -161,47 +375,74 @@ impl Stream for Service {
     type Error = crate::error::Error;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        // remove any expired subnet subscriptions
+        while let Ok(Async::Ready(Some(_))) = self.subnet_expiry_check.poll() {
+            self.remove_expired_subnets();
+        }
+
         loop {
             match self.swarm.poll() {
-                Ok(Async::Ready(Some(event))) => match event {
-                    BehaviourEvent::GossipMessage {
-                        id,
-                        source,
-                        topics,
-                        message,
-                    } => {
-                        trace!(self.log, "Gossipsub message received"; "service" => "Swarm");
-                        return Ok(Async::Ready(Some(Libp2pEvent::PubsubMessage {
+                Ok(Async::Ready(Some(event))) => {
+                    // disconnect peers as soon as discovery rejects their connection, so that the
+                    // connection is never reported to the router
+                    self.disconnect_rejected_peers();
+
+                    match event {
+                        BehaviourEvent::GossipMessage {
                             id,
                             source,
                             topics,
                             message,
-                        })));
+                        } => {
+                            if self.swarm.discovery().is_rejected(&source) {
+                                continue;
+                            }
+                            trace!(self.log, "Gossipsub message received"; "service" => "Swarm");
+                            return Ok(Async::Ready(Some(Libp2pEvent::PubsubMessage {
+                                id,
+                                source,
+                                topics,
+                                message,
+                            })));
+                        }
+                        BehaviourEvent::RPC(peer_id, event) => {
+                            if self.swarm.discovery().is_rejected(&peer_id) {
+                                continue;
+                            }
+                            return Ok(Async::Ready(Some(Libp2pEvent::RPC(peer_id, event))));
+                        }
+                        BehaviourEvent::PeerDialed(peer_id) => {
+                            if self.swarm.discovery().is_rejected(&peer_id) {
+                                continue;
+                            }
+                            return Ok(Async::Ready(Some(Libp2pEvent::PeerDialed(peer_id))));
+                        }
+                        BehaviourEvent::PeerDisconnected(peer_id) => {
+                            return Ok(Async::Ready(Some(Libp2pEvent::PeerDisconnected(peer_id))));
+                        }
                     }
-                    BehaviourEvent::RPC(peer_id, event) => {
-                        return Ok(Async::Ready(Some(Libp2pEvent::RPC(peer_id, event))));
-                    }
-                    BehaviourEvent::PeerDialed(peer_id) => {
-                        return Ok(Async::Ready(Some(Libp2pEvent::PeerDialed(peer_id))));
-                    }
-                    BehaviourEvent::PeerDisconnected(peer_id) => {
-                        return Ok(Async::Ready(Some(Libp2pEvent::PeerDisconnected(peer_id))));
-                    }
-                },
+                }
                 Ok(Async::Ready(None)) => unreachable!("Swarm stream shouldn't end"),
                 Ok(Async::NotReady) => break,
                 _ => break,
             }
         }
+
+        self.disconnect_rejected_peers();
+
         Ok(Async::NotReady)
     }
 }
 
-/// The implementation supports TCP/IP, WebSockets over TCP/IP, secio as the encryption layer, and
-/// mplex or yamux as the multiplexing layer.
-fn build_transport(local_private_key: Keypair) -> Boxed<(PeerId, StreamMuxerBox), Error> {
-    // TODO: The Wire protocol currently doesn't specify encryption and this will need to be customised
-    // in the future.
+/// The implementation supports TCP/IP, WebSockets over TCP/IP, Noise and/or secio as the
+/// encryption layer, and mplex or yamux as the multiplexing layer.
+///
+/// The offered secure channels are selected by `secure_channel`. When both are offered, Noise is
+/// preferred and secio is negotiated only if the remote does not support Noise.
+fn build_transport(
+    local_private_key: Keypair,
+    secure_channel: SecureChannel,
+) -> Boxed<(PeerId, StreamMuxerBox), Error> {
     let transport = libp2p::tcp::TcpConfig::new().nodelay(true);
     let transport = libp2p::dns::DnsConfig::new(transport);
     #[cfg(feature = "libp2p-websocket")]
@@ -209,20 +450,85 @@ fn build_transport(local_private_key: Keypair) -> Boxed<(PeerId, StreamMuxerBox)
         let trans_clone = transport.clone();
         transport.or_transport(websocket::WsConfig::new(trans_clone))
     };
+
+    // Authentication
+    let transport = transport
+        .and_then(move |stream, endpoint| {
+            let noise = generate_noise_config(&local_private_key);
+            let secio = secio::SecioConfig::new(local_private_key.clone());
+            let version = core::upgrade::Version::V1;
+            match secure_channel {
+                SecureChannel::NoiseWithSecioFallback => future::Either::A(future::Either::A(
+                    core::upgrade::apply(
+                        stream,
+                        core::upgrade::SelectUpgrade::new(noise, secio),
+                        endpoint,
+                        version,
+                    )
+                    .map(|output| match output {
+                        // Noise was negotiated
+                        EitherOutput::First((remote_id, output)) => {
+                            (EitherOutput::First(output), remote_id)
+                        }
+                        // Secio was negotiated
+                        EitherOutput::Second((remote_id, output)) => {
+                            (EitherOutput::Second(output), remote_id)
+                        }
+                    })
+                    .map_err(upgrade_error),
+                )),
+                SecureChannel::Noise => future::Either::A(future::Either::B(
+                    core::upgrade::apply(stream, noise, endpoint, version)
+                        .map(|(remote_id, output)| (EitherOutput::First(output), remote_id))
+                        .map_err(upgrade_error),
+                )),
+                SecureChannel::Secio => future::Either::B(
+                    core::upgrade::apply(stream, secio, endpoint, version)
+                        .map(|(remote_id, output)| (EitherOutput::Second(output), remote_id))
+                        .map_err(upgrade_error),
+                ),
+            }
+        })
+        .timeout(Duration::from_secs(20));
+
+    // Multiplexing
     transport
-        .upgrade(core::upgrade::Version::V1)
-        .authenticate(secio::SecioConfig::new(local_private_key))
-        .multiplex(core::upgrade::SelectUpgrade::new(
-            libp2p::yamux::Config::default(),
-            libp2p::mplex::MplexConfig::new(),
-        ))
-        .map(|(peer, muxer), _| (peer, core::muxing::StreamMuxerBox::new(muxer)))
-        .timeout(Duration::from_secs(20))
+        .and_then(move |(stream, peer_id), endpoint| {
+            let peer_id2 = peer_id.clone();
+            let upgrade = core::upgrade::SelectUpgrade::new(
+                libp2p::yamux::Config::default(),
+                libp2p::mplex::MplexConfig::new(),
+            )
+            .map_inbound(move |muxer| (peer_id, muxer))
+            .map_outbound(move |muxer| (peer_id2, muxer));
+
+            core::upgrade::apply(stream, upgrade, endpoint, core::upgrade::Version::V1)
+                .map(|(peer_id, muxer)| (peer_id, core::muxing::StreamMuxerBox::new(muxer)))
+        })
         .timeout(Duration::from_secs(20))
         .map_err(|err| Error::new(ErrorKind::Other, err))
         .boxed()
 }
 
+/// Generates an authenticated Noise (XX handshake) configuration. The static DH key is signed by
+/// the node's identity key, so that the remote `PeerId` is known once the handshake completes.
+fn generate_noise_config(
+    identity_keypair: &Keypair,
+) -> noise::NoiseAuthenticated<noise::XX, noise::X25519, ()> {
+    let static_dh_keys = noise::Keypair::<noise::X25519>::new()
+        .into_authentic(identity_keypair)
+        .expect("signing can fail only once during starting a node");
+    noise::NoiseConfig::xx(static_dh_keys).into_authenticated()
+}
+
+/// Converts a failed secure channel upgrade into an I/O error.
+fn upgrade_error<E>(err: E) -> Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    Error::new(ErrorKind::Other, err)
+}
+
 /// Events that can be obtained from polling the Libp2p Service.
 pub enum Libp2pEvent {
     /// An RPC response request has been received on the swarm.
@@ -308,3 +614,90 @@ fn load_private_key(config: &NetworkConfig, log: &slog::Logger) -> Keypair {
     }
     local_private_key
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::ping::{Ping, PingConfig, PingEvent};
+    use tokio_timer::Delay;
+
+    /// Builds two in-process swarms offering the given secure channels, dials the listener from
+    /// the dialer and returns `true` if a ping succeeds over the resulting connection.
+    fn ping_over_transport(listener: SecureChannel, dialer: SecureChannel) -> bool {
+        let port = unused_port::unused_tcp_port().expect("should find unused port");
+        let build_swarm = |secure_channel| {
+            let keypair = Keypair::generate_secp256k1();
+            let peer_id = PeerId::from(keypair.public());
+            let transport = build_transport(keypair, secure_channel);
+            Swarm::new(transport, Ping::new(PingConfig::new()), peer_id)
+        };
+
+        let mut listener_swarm = build_swarm(listener);
+        let mut dialer_swarm = build_swarm(dialer);
+
+        let listen_addr: Multiaddr = format!("/ip4/127.0.0.1/tcp/{}", port)
+            .parse()
+            .expect("valid multiaddr");
+        Swarm::listen_on(&mut listener_swarm, listen_addr.clone()).expect("should listen");
+        Swarm::dial_addr(&mut dialer_swarm, listen_addr).expect("should dial");
+
+        let mut timeout = Delay::new(Instant::now() + Duration::from_secs(5));
+        let ping = future::poll_fn(move || -> Poll<bool, ()> {
+            while let Ok(Async::Ready(Some(_))) = listener_swarm.poll() {}
+            loop {
+                match dialer_swarm.poll() {
+                    Ok(Async::Ready(Some(PingEvent { result: Ok(_), .. }))) => {
+                        return Ok(Async::Ready(true))
+                    }
+                    Ok(Async::Ready(Some(_))) => {}
+                    _ => break,
+                }
+            }
+            match timeout.poll() {
+                Ok(Async::NotReady) => Ok(Async::NotReady),
+                _ => Ok(Async::Ready(false)),
+            }
+        });
+
+        tokio::runtime::Runtime::new()
+            .expect("should build runtime")
+            .block_on(ping)
+            .expect("ping future cannot fail")
+    }
+
+    #[test]
+    fn noise_connects() {
+        assert!(ping_over_transport(
+            SecureChannel::Noise,
+            SecureChannel::Noise
+        ));
+    }
+
+    #[test]
+    fn noise_preferred_with_fallback() {
+        assert!(ping_over_transport(
+            SecureChannel::NoiseWithSecioFallback,
+            SecureChannel::Noise
+        ));
+    }
+
+    #[test]
+    fn secio_fallback_connects() {
+        assert!(ping_over_transport(
+            SecureChannel::NoiseWithSecioFallback,
+            SecureChannel::Secio
+        ));
+        assert!(ping_over_transport(
+            SecureChannel::Secio,
+            SecureChannel::NoiseWithSecioFallback
+        ));
+    }
+
+    #[test]
+    fn mismatched_secure_channels_do_not_connect() {
+        assert!(!ping_over_transport(
+            SecureChannel::Secio,
+            SecureChannel::Noise
+        ));
+    }
+}
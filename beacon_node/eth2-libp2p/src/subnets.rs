@@ -0,0 +1,156 @@
+//! Attestation subnets.
+//!
+//! Attestations are split across `ATTESTATION_SUBNET_COUNT` gossipsub topics. An attestation is
+//! published on the subnet derived from the shard of its crosslink committee. Each node subscribes
+//! to a number of long-lived, randomly selected subnets which it advertises in its ENR, and
+//! additionally to short-lived subnets that its validators require for their attestation duties.
+
+use crate::config::{SHARD_TOPIC_PREFIX, TOPIC_ENCODING_POSTFIX, TOPIC_PREFIX};
+use crate::Topic;
+use libp2p::enr::Enr;
+use serde_derive::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// The number of attestation subnets.
+pub const ATTESTATION_SUBNET_COUNT: u64 = 64;
+/// The ENR field that advertises the long-lived attestation subnets of a node.
+pub const ATTESTATION_SUBNETS_ENR_KEY: &str = "attnets";
+/// The postfix of an attestation subnet topic name.
+pub const ATTESTATION_SUBNET_POSTFIX: &str = "_beacon_attestation";
+
+/// The identifier of an attestation subnet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SubnetId(u64);
+
+impl SubnetId {
+    /// Creates a new `SubnetId`, wrapping the identifier into the valid subnet range.
+    pub fn new(id: u64) -> Self {
+        SubnetId(id % ATTESTATION_SUBNET_COUNT)
+    }
+
+    /// Returns the subnet on which attestations for the crosslink committee of `shard` are
+    /// published.
+    pub fn from_shard(shard: u64) -> Self {
+        SubnetId::new(shard)
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// The gossipsub topic of this subnet, of the form
+    /// `/eth2/shard{subnet_id}_beacon_attestation/ssz`.
+    pub fn topic(self) -> Topic {
+        Topic::new(format!(
+            "/{}/{}{}{}/{}",
+            TOPIC_PREFIX,
+            SHARD_TOPIC_PREFIX,
+            self.0,
+            ATTESTATION_SUBNET_POSTFIX,
+            TOPIC_ENCODING_POSTFIX
+        ))
+    }
+
+    /// Parses the subnet from the topic part of a gossipsub topic, e.g.
+    /// `shard3_beacon_attestation`.
+    pub fn from_topic_name(topic_name: &str) -> Option<Self> {
+        if !topic_name.starts_with(SHARD_TOPIC_PREFIX)
+            || !topic_name.ends_with(ATTESTATION_SUBNET_POSTFIX)
+        {
+            return None;
+        }
+
+        topic_name[SHARD_TOPIC_PREFIX.len()..topic_name.len() - ATTESTATION_SUBNET_POSTFIX.len()]
+            .parse::<u64>()
+            .ok()
+            .filter(|id| *id < ATTESTATION_SUBNET_COUNT)
+            .map(SubnetId)
+    }
+}
+
+impl std::fmt::Display for SubnetId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A request to be subscribed to an attestation subnet until `expiry`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SubnetSubscription {
+    pub subnet_id: SubnetId,
+    pub expiry: Instant,
+}
+
+/// Encodes a set of subnets as the bitfield stored in the ENR under
+/// `ATTESTATION_SUBNETS_ENR_KEY`. Bit `i` is set if the node is subscribed to subnet `i`.
+pub fn subnets_to_enr_bytes(subnets: &[SubnetId]) -> Vec<u8> {
+    let mut bytes = vec![0; (ATTESTATION_SUBNET_COUNT as usize + 7) / 8];
+    for subnet in subnets {
+        let i = subnet.as_u64() as usize;
+        bytes[i / 8] |= 1 << (i % 8);
+    }
+    bytes
+}
+
+/// Decodes the subnets from a bitfield produced by `subnets_to_enr_bytes`.
+pub fn subnets_from_enr_bytes(bytes: &[u8]) -> Vec<SubnetId> {
+    (0..ATTESTATION_SUBNET_COUNT)
+        .filter(|i| {
+            bytes
+                .get(*i as usize / 8)
+                .map_or(false, |byte| byte & (1 << (i % 8)) != 0)
+        })
+        .map(SubnetId)
+        .collect()
+}
+
+/// Returns `true` if the given ENR advertises a long-lived subscription to `subnet_id`.
+pub fn enr_has_subnet(enr: &Enr, subnet_id: SubnetId) -> bool {
+    enr.get(ATTESTATION_SUBNETS_ENR_KEY).map_or(false, |bytes| {
+        subnets_from_enr_bytes(bytes).contains(&subnet_id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topic_round_trip() {
+        for i in 0..ATTESTATION_SUBNET_COUNT {
+            let subnet = SubnetId::new(i);
+            let topic = subnet.topic();
+            let parts: Vec<&str> = topic.hash().as_str().split('/').collect();
+            assert_eq!(parts.len(), 4);
+            assert_eq!(SubnetId::from_topic_name(parts[2]), Some(subnet));
+        }
+    }
+
+    #[test]
+    fn invalid_topic_names() {
+        assert_eq!(SubnetId::from_topic_name("beacon_attestation"), None);
+        assert_eq!(SubnetId::from_topic_name("shard_beacon_attestation"), None);
+        assert_eq!(
+            SubnetId::from_topic_name("shard64_beacon_attestation"),
+            None
+        );
+        assert_eq!(SubnetId::from_topic_name("shard1_beacon_block"), None);
+    }
+
+    #[test]
+    fn enr_bitfield_round_trip() {
+        let subnets = vec![SubnetId::new(0), SubnetId::new(9), SubnetId::new(63)];
+        let bytes = subnets_to_enr_bytes(&subnets);
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(subnets_from_enr_bytes(&bytes), subnets);
+        assert!(subnets_from_enr_bytes(&[]).is_empty());
+    }
+
+    #[test]
+    fn shards_wrap_into_subnets() {
+        assert_eq!(
+            SubnetId::from_shard(ATTESTATION_SUBNET_COUNT + 3),
+            SubnetId::new(3)
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use eth1::{Config as Eth1Config, DepositLog, Service as Eth1Service, DEPOSIT_CONTRACT_TREE_DEPTH};
+use futures::{Future, Stream};
+use int_to_bytes::int_to_bytes32;
+use merkle_proof::MerkleTree;
+use parking_lot::Mutex;
+use slog::{debug, error, info, Logger};
+use state_processing::{
+    eth2_genesis_time, initialize_beacon_state_from_eth1, is_valid_genesis_state,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::timer::Interval;
+use tree_hash::TreeHash;
+use types::{BeaconState, ChainSpec, Deposit, EthSpec, Hash256};
+
+/// The progress of the search for a valid genesis eth1 block.
+#[derive(Debug, Default)]
+struct Progress {
+    /// The highest eth1 block that has been evaluated as a genesis candidate.
+    highest_processed_block: Option<u64>,
+    /// The highest deposit count of a candidate with a valid genesis time but too few active
+    /// validators.
+    insufficient_deposit_count: Option<u64>,
+}
+
+/// Follows the deposit contract of an eth1 node, evaluating each eth1 block as a genesis candidate
+/// until a valid genesis state is found.
+///
+/// Cheap to clone, all clones share the same eth1 caches and progress.
+#[derive(Clone)]
+pub struct Eth1GenesisService {
+    core: Eth1Service,
+    progress: Arc<Mutex<Progress>>,
+    log: Logger,
+}
+
+impl Eth1GenesisService {
+    /// Creates a new service. No requests are made until `wait_for_genesis_state` is called.
+    pub fn new(config: Eth1Config, log: Logger) -> Self {
+        // Every block after the deployment of the deposit contract is a genesis candidate, so none
+        // may be skipped. Blocks are instead dropped from the cache once they've been evaluated.
+        let config = Eth1Config {
+            block_cache_truncation: None,
+            max_blocks_per_update: None,
+            ..config
+        };
+
+        Self {
+            core: Eth1Service::new(config, log.clone()),
+            progress: Arc::new(Mutex::new(Progress::default())),
+            log,
+        }
+    }
+
+    /// Returns the underlying eth1 service.
+    pub fn core(&self) -> &Eth1Service {
+        &self.core
+    }
+
+    /// Returns a future which updates the eth1 caches every `update_interval` and resolves with
+    /// the genesis state of the first eth1 block that produces a valid one.
+    ///
+    /// Failures to contact the eth1 node are logged and retried at the next interval.
+    pub fn wait_for_genesis_state<E: EthSpec>(
+        &self,
+        update_interval: Duration,
+        spec: ChainSpec,
+    ) -> impl Future<Item = BeaconState<E>, Error = String> {
+        let service = self.clone();
+
+        Interval::new(Instant::now(), update_interval)
+            .map_err(|e| format!("Failed to trigger eth1 genesis update: {:?}", e))
+            .and_then(move |_| {
+                let service = service.clone();
+                let spec = spec.clone();
+
+                service.core.update().then(move |result| {
+                    match result {
+                        Ok((logs_imported, blocks_imported)) => debug!(
+                            service.log,
+                            "Updated eth1 cache for genesis";
+                            "logs_imported" => logs_imported,
+                            "blocks_imported" => blocks_imported,
+                        ),
+                        Err(e) => error!(
+                            service.log,
+                            "Failed to update eth1 cache for genesis";
+                            "error" => format!("{:?}", e)
+                        ),
+                    };
+
+                    service.scan_new_blocks::<E>(&spec)
+                })
+            })
+            .filter_map(|genesis_state| genesis_state)
+            .into_future()
+            .map_err(|(e, _stream)| e)
+            .and_then(|(genesis_state, _stream)| {
+                genesis_state.ok_or_else(|| "Eth1 genesis update interval ended".to_string())
+            })
+    }
+
+    /// Evaluates each block in the cache that has not already been evaluated, returning the
+    /// genesis state of the first that produces a valid one.
+    ///
+    /// The cheap checks (genesis time and deposit count) are performed before a candidate state
+    /// is built.
+    fn scan_new_blocks<E: EthSpec>(
+        &self,
+        spec: &ChainSpec,
+    ) -> Result<Option<BeaconState<E>>, String> {
+        let mut progress = self.progress.lock();
+        let mut blocks = self.core.blocks().write();
+        let deposits = self.core.deposits().read();
+
+        let highest_processed_block = progress.highest_processed_block;
+        let new_blocks = blocks
+            .iter()
+            .filter(|block| highest_processed_block.map_or(true, |highest| block.number > highest));
+
+        for block in new_blocks {
+            progress.highest_processed_block = Some(block.number);
+
+            if eth2_genesis_time(block.timestamp, spec) < spec.min_genesis_time
+                || block.deposit_count < spec.min_genesis_active_validator_count
+                || progress
+                    .insufficient_deposit_count
+                    .map_or(false, |count| block.deposit_count <= count)
+            {
+                continue;
+            }
+
+            let genesis_deposits =
+                genesis_deposits(deposits.cache.iter().take(block.deposit_count as usize))?;
+            let genesis_state = initialize_beacon_state_from_eth1(
+                block.hash,
+                block.timestamp,
+                genesis_deposits,
+                spec,
+            )
+            .map_err(|e| format!("Unable to initialize genesis state: {:?}", e))?;
+
+            if is_valid_genesis_state(&genesis_state, spec) {
+                info!(
+                    self.log,
+                    "Found valid genesis eth1 block";
+                    "genesis_time" => genesis_state.genesis_time,
+                    "deposit_count" => block.deposit_count,
+                    "eth1_block_number" => block.number,
+                );
+
+                return Ok(Some(genesis_state));
+            }
+
+            // The genesis time is valid, so there were too few active validators. That can only
+            // change once there are more deposits.
+            progress.insufficient_deposit_count = Some(block.deposit_count);
+        }
+
+        // Only the highest block is kept, so the next update continues from it.
+        blocks.truncate(1);
+
+        debug!(
+            self.log,
+            "No valid genesis eth1 block";
+            "highest_processed_block" => format!("{:?}", progress.highest_processed_block),
+            "deposit_count" => deposits.cache.len(),
+        );
+
+        Ok(None)
+    }
+}
+
+/// Returns the given `deposit_logs` as `Deposits` suitable for `initialize_beacon_state_from_eth1`.
+///
+/// The proof of the `i`th deposit is against the deposit root of the first `i + 1` deposits, as
+/// that is the deposit root of the state when it is processed.
+pub fn genesis_deposits<'a>(
+    deposit_logs: impl IntoIterator<Item = &'a DepositLog>,
+) -> Result<Vec<Deposit>, String> {
+    let mut tree = MerkleTree::create(&[], DEPOSIT_CONTRACT_TREE_DEPTH);
+
+    deposit_logs
+        .into_iter()
+        .enumerate()
+        .map(|(i, log)| {
+            let leaf = Hash256::from_slice(&log.deposit_data.tree_hash_root());
+            tree.push_leaf(leaf, DEPOSIT_CONTRACT_TREE_DEPTH)
+                .map_err(|e| format!("Failed to push deposit leaf: {:?}", e))?;
+
+            let (_leaf, mut proof) = tree.generate_proof(i, DEPOSIT_CONTRACT_TREE_DEPTH);
+            proof.push(Hash256::from_slice(&int_to_bytes32(i as u64 + 1)));
+
+            Ok(Deposit {
+                proof: proof.into(),
+                data: log.deposit_data.clone(),
+            })
+        })
+        .collect()
+}
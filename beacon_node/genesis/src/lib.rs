@@ -0,0 +1,3 @@
+mod eth1_genesis_service;
+
+pub use eth1_genesis_service::{genesis_deposits, Eth1GenesisService};
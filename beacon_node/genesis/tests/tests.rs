@@ -0,0 +1,184 @@
+#![cfg(test)]
+use eth1::test_utils::{MockEth1Node, DEPOSIT_CONTRACT_ADDRESS};
+use eth1::Config as Eth1Config;
+use genesis::Eth1GenesisService;
+use slog::Logger;
+use state_processing::is_valid_genesis_state;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::timer::Timeout;
+use types::test_utils::{generate_deterministic_keypairs, TestingDepositBuilder};
+use types::{
+    BeaconState, ChainSpec, DepositData, Epoch, EthSpec, Fork, MinimalEthSpec, PublicKeyBytes,
+};
+
+type E = MinimalEthSpec;
+
+const FOLLOW_DISTANCE: u64 = 4;
+const SECONDS_PER_BLOCK: u64 = 15;
+const VALIDATOR_COUNT: usize = 8;
+
+fn null_logger() -> Logger {
+    Logger::root(slog::Discard, slog::o!())
+}
+
+fn spec() -> ChainSpec {
+    let mut spec = E::default_spec();
+    spec.min_genesis_active_validator_count = VALIDATOR_COUNT as u64;
+    spec
+}
+
+fn service(node: &MockEth1Node) -> Eth1GenesisService {
+    Eth1GenesisService::new(
+        Eth1Config {
+            endpoint: node.endpoint(),
+            deposit_contract_address: DEPOSIT_CONTRACT_ADDRESS.to_string(),
+            follow_distance: FOLLOW_DISTANCE,
+            ..Eth1Config::default()
+        },
+        null_logger(),
+    )
+}
+
+/// The earliest eth1 timestamp that produces a valid genesis time.
+fn earliest_valid_timestamp(spec: &ChainSpec) -> u64 {
+    spec.min_genesis_time - 2 * spec.seconds_per_day
+}
+
+/// Returns valid, full-balance deposits for the deterministic keypairs.
+fn deposit_fixture(n: usize, spec: &ChainSpec) -> Vec<DepositData> {
+    generate_deterministic_keypairs(n)
+        .iter()
+        .map(|keypair| {
+            let mut builder =
+                TestingDepositBuilder::new(keypair.pk.clone(), spec.max_effective_balance);
+            builder.sign(keypair, Epoch::new(0), &Fork::default(), spec);
+            builder.build().data
+        })
+        .collect()
+}
+
+/// Adds one deposit from `deposits` to each of `deposits.len()` new blocks.
+fn replay(node: &MockEth1Node, deposits: &[DepositData]) {
+    for deposit in deposits {
+        node.add_blocks(1, SECONDS_PER_BLOCK);
+        node.add_deposit(deposit);
+    }
+}
+
+fn wait_for_genesis_state(
+    runtime: &mut Runtime,
+    service: &Eth1GenesisService,
+    spec: &ChainSpec,
+    timeout_millis: u64,
+) -> Result<BeaconState<E>, String> {
+    runtime
+        .block_on(Timeout::new(
+            service.wait_for_genesis_state::<E>(Duration::from_millis(10), spec.clone()),
+            Duration::from_millis(timeout_millis),
+        ))
+        .map_err(|e| format!("{:?}", e))
+}
+
+#[test]
+fn waits_for_genesis_time() {
+    let mut runtime = Runtime::new().expect("should create runtime");
+    let spec = spec();
+
+    // All deposits are made before the earliest valid timestamp, with `earliest_block`
+    // being the first block at that timestamp.
+    let earliest_block = VALIDATOR_COUNT as u64 + 2;
+    let node = MockEth1Node::new(
+        earliest_valid_timestamp(&spec) - earliest_block * SECONDS_PER_BLOCK,
+        null_logger(),
+    );
+    let deposits = deposit_fixture(VALIDATOR_COUNT, &spec);
+    replay(&node, &deposits);
+    node.add_blocks(
+        (earliest_block + FOLLOW_DISTANCE - node.block_number()) as usize,
+        SECONDS_PER_BLOCK,
+    );
+
+    let service = service(&node);
+    let state = wait_for_genesis_state(&mut runtime, &service, &spec, 10_000)
+        .expect("should find genesis state");
+
+    assert!(is_valid_genesis_state(&state, &spec));
+    assert_eq!(state.genesis_time, spec.min_genesis_time);
+    assert_eq!(state.eth1_data.block_hash, node.block_hash(earliest_block));
+    assert_eq!(state.eth1_data.deposit_count, VALIDATOR_COUNT as u64);
+    assert_eq!(
+        Some(state.eth1_data.deposit_root),
+        service
+            .core()
+            .deposits()
+            .read()
+            .cache
+            .deposit_root(VALIDATOR_COUNT as u64)
+    );
+    assert_eq!(state.validators.len(), VALIDATOR_COUNT);
+    for (validator, deposit) in state.validators.iter().zip(deposits.iter()) {
+        assert_eq!(
+            PublicKeyBytes::from(validator.pubkey.clone()),
+            deposit.pubkey
+        );
+        assert_eq!(validator.activation_epoch, E::genesis_epoch());
+    }
+}
+
+#[test]
+fn waits_for_validators() {
+    let mut runtime = Runtime::new().expect("should create runtime");
+    let spec = spec();
+    let node = MockEth1Node::new(earliest_valid_timestamp(&spec), null_logger());
+    let deposits = deposit_fixture(VALIDATOR_COUNT, &spec);
+
+    replay(&node, &deposits[..VALIDATOR_COUNT - 1]);
+    node.add_blocks(FOLLOW_DISTANCE as usize, SECONDS_PER_BLOCK);
+
+    let service = service(&node);
+    assert!(
+        wait_for_genesis_state(&mut runtime, &service, &spec, 500).is_err(),
+        "should not find genesis with too few validators"
+    );
+
+    replay(&node, &deposits[VALIDATOR_COUNT - 1..]);
+    let genesis_block = node.block_number();
+    node.add_blocks(FOLLOW_DISTANCE as usize, SECONDS_PER_BLOCK);
+
+    let state = wait_for_genesis_state(&mut runtime, &service, &spec, 10_000)
+        .expect("should find genesis state");
+
+    assert!(is_valid_genesis_state(&state, &spec));
+    assert_eq!(state.eth1_data.block_hash, node.block_hash(genesis_block));
+    assert_eq!(state.validators.len(), VALIDATOR_COUNT);
+}
+
+#[test]
+fn ignores_invalid_deposits() {
+    let mut runtime = Runtime::new().expect("should create runtime");
+    let spec = spec();
+    let node = MockEth1Node::new(earliest_valid_timestamp(&spec), null_logger());
+    let mut deposits = deposit_fixture(VALIDATOR_COUNT + 1, &spec);
+
+    // A deposit with an invalid signature is included in the deposit tree, but does not add a
+    // validator.
+    deposits[0].signature = deposits[1].signature.clone();
+    replay(&node, &deposits[..VALIDATOR_COUNT]);
+    node.add_blocks(FOLLOW_DISTANCE as usize, SECONDS_PER_BLOCK);
+
+    let service = service(&node);
+    assert!(
+        wait_for_genesis_state(&mut runtime, &service, &spec, 500).is_err(),
+        "should not find genesis with an invalid deposit"
+    );
+
+    replay(&node, &deposits[VALIDATOR_COUNT..]);
+    node.add_blocks(FOLLOW_DISTANCE as usize, SECONDS_PER_BLOCK);
+
+    let state = wait_for_genesis_state(&mut runtime, &service, &spec, 10_000)
+        .expect("should find genesis state");
+
+    assert_eq!(state.eth1_data.deposit_count, VALIDATOR_COUNT as u64 + 1);
+    assert_eq!(state.validators.len(), VALIDATOR_COUNT);
+}
@@ -5,5 +5,5 @@ pub mod service;
 pub mod sync;
 
 pub use eth2_libp2p::NetworkConfig;
-pub use service::NetworkMessage;
 pub use service::Service;
+pub use service::{attestation_subnet_subscription, NetworkMessage};
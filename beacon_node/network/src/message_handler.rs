@@ -13,7 +13,9 @@ use slog::{debug, o, trace, warn};
 use ssz::{Decode, DecodeError};
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use types::{Attestation, AttesterSlashing, BeaconBlock, ProposerSlashing, VoluntaryExit};
+use types::{
+    AggregateAndProof, Attestation, AttesterSlashing, BeaconBlock, ProposerSlashing, VoluntaryExit,
+};
 
 /// Handles messages received from the network and client and organises syncing. This
 /// functionality of this struct is to validate an decode messages from the network before
@@ -146,9 +148,22 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
     ) {
         // an error could have occurred.
         match error_response {
-            RPCErrorResponse::InvalidRequest(error) => warn!(self.log, "Peer indicated invalid request";"peer_id" => format!("{:?}", peer_id), "error" => error.as_string()),
-            RPCErrorResponse::ServerError(error) => warn!(self.log, "Peer internal server error";"peer_id" => format!("{:?}", peer_id), "error" => error.as_string()),
-            RPCErrorResponse::Unknown(error) => warn!(self.log, "Unknown peer error";"peer" => format!("{:?}", peer_id), "error" => error.as_string()),
+            RPCErrorResponse::InvalidRequest(error) => {
+                warn!(self.log, "Peer indicated invalid request";"peer_id" => format!("{:?}", peer_id), "error" => error.as_string());
+                self.message_processor.on_rpc_error(peer_id, request_id);
+            }
+            RPCErrorResponse::ServerError(error) => {
+                warn!(self.log, "Peer internal server error";"peer_id" => format!("{:?}", peer_id), "error" => error.as_string());
+                self.message_processor.on_rpc_error(peer_id, request_id);
+            }
+            RPCErrorResponse::ResourceUnavailable(error) => {
+                warn!(self.log, "Peer rejected request, resource unavailable";"peer_id" => format!("{:?}", peer_id), "error" => error.as_string());
+                self.message_processor.on_rpc_error(peer_id, request_id);
+            }
+            RPCErrorResponse::Unknown(error) => {
+                warn!(self.log, "Unknown peer error";"peer" => format!("{:?}", peer_id), "error" => error.as_string());
+                self.message_processor.on_rpc_error(peer_id, request_id);
+            }
             RPCErrorResponse::Success(response) => {
                 match response {
                     RPCResponse::Hello(hello_message) => {
@@ -167,6 +182,7 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
                             Err(e) => {
                                 // TODO: Down-vote Peer
                                 warn!(self.log, "Peer sent invalid BEACON_BLOCKS response";"peer" => format!("{:?}", peer_id), "error" => format!("{:?}", e));
+                                self.message_processor.on_rpc_error(peer_id, request_id);
                             }
                         }
                     }
@@ -182,6 +198,7 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
                             Err(e) => {
                                 // TODO: Down-vote Peer
                                 warn!(self.log, "Peer sent invalid BEACON_BLOCKS response";"peer" => format!("{:?}", peer_id), "error" => format!("{:?}", e));
+                                self.message_processor.on_rpc_error(peer_id, request_id);
                             }
                         }
                     }
@@ -192,8 +209,8 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
 
     /// Handle various RPC errors
     fn handle_rpc_error(&mut self, peer_id: PeerId, request_id: RequestId, error: RPCError) {
-        //TODO: Handle error correctly
         warn!(self.log, "RPC Error"; "Peer" => format!("{:?}", peer_id), "request_id" => format!("{}", request_id), "Error" => format!("{:?}", error));
+        self.message_processor.on_rpc_error(peer_id, request_id);
     }
 
     /// Handle RPC messages
@@ -215,15 +232,32 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
             },
             PubsubMessage::Attestation(message) => match self.decode_gossip_attestation(message) {
                 Ok(attestation) => {
-                    // TODO: Apply more sophisticated validation and decoding logic
-                    self.propagate_message(id, peer_id.clone());
-                    self.message_processor
-                        .on_attestation_gossip(peer_id, attestation);
+                    let should_forward_on = self
+                        .message_processor
+                        .on_attestation_gossip(peer_id.clone(), attestation);
+                    if should_forward_on {
+                        self.propagate_message(id, peer_id);
+                    }
                 }
                 Err(e) => {
                     debug!(self.log, "Invalid gossiped attestation"; "peer_id" => format!("{}", peer_id), "Error" => format!("{:?}", e));
                 }
             },
+            PubsubMessage::AggregateAndProof(message) => {
+                match self.decode_gossip_aggregate_and_proof(message) {
+                    Ok(aggregate_and_proof) => {
+                        let should_forward_on = self
+                            .message_processor
+                            .on_aggregate_and_proof_gossip(peer_id.clone(), aggregate_and_proof);
+                        if should_forward_on {
+                            self.propagate_message(id, peer_id);
+                        }
+                    }
+                    Err(e) => {
+                        debug!(self.log, "Invalid gossiped aggregate"; "peer_id" => format!("{}", peer_id), "Error" => format!("{:?}", e));
+                    }
+                }
+            }
             PubsubMessage::VoluntaryExit(message) => match self.decode_gossip_exit(message) {
                 Ok(_exit) => {
                     // TODO: Apply more sophisticated validation and decoding logic
@@ -309,6 +343,14 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
         Attestation::from_ssz_bytes(&beacon_block)
     }
 
+    fn decode_gossip_aggregate_and_proof(
+        &self,
+        aggregate_and_proof: Vec<u8>,
+    ) -> Result<AggregateAndProof<T::EthSpec>, DecodeError> {
+        //TODO: Apply verification before decoding.
+        AggregateAndProof::from_ssz_bytes(&aggregate_and_proof)
+    }
+
     fn decode_gossip_exit(&self, voluntary_exit: Vec<u8>) -> Result<VoluntaryExit, DecodeError> {
         //TODO: Apply verification before decoding.
         VoluntaryExit::from_ssz_bytes(&voluntary_exit)
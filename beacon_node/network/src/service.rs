@@ -3,17 +3,22 @@ use crate::message_handler::{HandlerMessage, MessageHandler};
 use crate::NetworkConfig;
 use beacon_chain::{BeaconChain, BeaconChainTypes};
 use core::marker::PhantomData;
+use eth2_libp2p::rpc::methods::GoodbyeReason;
+use eth2_libp2p::rpc::RPCRequest;
 use eth2_libp2p::Service as LibP2PService;
 use eth2_libp2p::Topic;
 use eth2_libp2p::{Enr, Libp2pEvent, Multiaddr, PeerId, Swarm};
-use eth2_libp2p::{PubsubMessage, RPCEvent};
+use eth2_libp2p::{PubsubMessage, RPCEvent, SubnetId, SubnetSubscription};
 use futures::prelude::*;
 use futures::Stream;
 use parking_lot::Mutex;
 use slog::{debug, info, o, trace};
+use slot_clock::SlotClock;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::runtime::TaskExecutor;
 use tokio::sync::{mpsc, oneshot};
+use types::Slot;
 
 /// Service that handles communication between internal services and the eth2_libp2p network service.
 pub struct Service<T: BeaconChainTypes> {
@@ -107,6 +112,11 @@ impl<T: BeaconChainTypes + 'static> Service<T> {
             .collect()
     }
 
+    /// Returns the attestation subnets this node is subscribed to for its lifetime.
+    pub fn long_lived_subnets(&self) -> Vec<SubnetId> {
+        self.libp2p_service.lock().long_lived_subnets().to_vec()
+    }
+
     /// Provides a reference to the underlying libp2p service.
     pub fn libp2p_service(&self) -> Arc<Mutex<LibP2PService>> {
         self.libp2p_service.clone()
@@ -156,7 +166,17 @@ fn network_service(
                 Ok(Async::Ready(Some(message))) => match message {
                     NetworkMessage::RPC(peer_id, rpc_event) => {
                         trace!(log, "{}", rpc_event);
-                        libp2p_service.lock().swarm.send_rpc(peer_id, rpc_event);
+                        let mut libp2p = libp2p_service.lock();
+                        if let RPCEvent::Request(_, RPCRequest::Goodbye(GoodbyeReason::Fault)) =
+                            rpc_event
+                        {
+                            // trusted peers are never disconnected for faults
+                            if libp2p.is_trusted_peer(&peer_id) {
+                                debug!(log, "Not disconnecting trusted peer"; "peer_id" => format!("{:?}", peer_id));
+                                continue;
+                            }
+                        }
+                        libp2p.swarm.send_rpc(peer_id, rpc_event);
                     }
                     NetworkMessage::Propagate {
                         propagation_source,
@@ -175,6 +195,10 @@ fn network_service(
                         debug!(log, "Sending pubsub message"; "topics" => format!("{:?}",topics));
                         libp2p_service.lock().swarm.publish(&topics, message);
                     }
+                    NetworkMessage::Subscribe { subscriptions } => {
+                        trace!(log, "Subscribing to attestation subnets"; "count" => subscriptions.len());
+                        libp2p_service.lock().subscribe_to_subnets(subscriptions);
+                    }
                 },
                 Ok(Async::NotReady) => break,
                 Ok(Async::Ready(None)) => {
@@ -244,4 +268,34 @@ pub enum NetworkMessage {
         propagation_source: PeerId,
         message_id: String,
     },
+    /// Subscribe to attestation subnets until the given expiry.
+    Subscribe {
+        subscriptions: Vec<SubnetSubscription>,
+    },
+}
+
+/// Builds a subscription to the attestation subnet of `shard` which lasts until the end of
+/// `duty_slot`.
+///
+/// Returns `None` if the slot has already passed or the slot clock is unavailable.
+pub fn attestation_subnet_subscription<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    shard: u64,
+    duty_slot: Slot,
+) -> Option<SubnetSubscription> {
+    let current_slot = beacon_chain.slot().ok()?;
+    if duty_slot < current_slot {
+        return None;
+    }
+
+    let slot_clock = &beacon_chain.slot_clock;
+    let slots_after_current = (duty_slot - current_slot).as_u64() as u32;
+    let expiry = Instant::now()
+        + slot_clock.duration_to_next_slot()?
+        + slot_clock.slot_duration() * slots_after_current;
+
+    Some(SubnetSubscription {
+        subnet_id: SubnetId::from_shard(shard),
+        expiry,
+    })
 }
@@ -0,0 +1,629 @@
+//! Lookups of blocks by their root.
+//!
+//! Blocks are requested by root, via `RecentBeaconBlocks` requests, in two cases:
+//!
+//! - A block is received whose parent is unknown. The parent, and any further unknown ancestors,
+//!   are requested until a known ancestor is reached, after which the downloaded blocks are
+//!   imported from the oldest.
+//! - An attestation or aggregate is received that votes for an unknown `beacon_block_root`. The
+//!   message is queued whilst the block (and any unknown ancestors) is looked up, and is processed
+//!   again once the block has been imported. Queued unaggregated attestations that are then valid
+//!   are published on their subnet, as they were not forwarded when they were received.
+//!   Aggregates were forwarded once their aggregator was verified, but are published again on the
+//!   aggregate topic as peers may have dropped them for the same unknown block.
+//!
+//! Each lookup requests the block from the peers that referenced it first, then from the other
+//! fully sync'd peers. A peer that fails to return the block is not asked for it again. A lookup
+//! is dropped after `LOOKUP_FAIL_TOLERANCE` failed requests, or when no untried peers remain.
+
+use super::manager::SLOT_IMPORT_TOLERANCE;
+use super::simple_sync::NetworkContext;
+use beacon_chain::{
+    AttestationProcessingOutcome, BeaconChain, BeaconChainError, BeaconChainTypes,
+    BlockProcessingOutcome,
+};
+use eth2_libp2p::rpc::methods::RecentBeaconBlocksRequest;
+use eth2_libp2p::rpc::RequestId;
+use eth2_libp2p::PeerId;
+use slog::{debug, trace, warn};
+use std::collections::HashSet;
+use std::sync::Weak;
+use types::{AggregateAndProof, Attestation, BeaconBlock, EthSpec, Hash256};
+
+/// How many failed requests a lookup tolerates before it is dropped.
+const LOOKUP_FAIL_TOLERANCE: usize = 3;
+/// The maximum depth we will search for a parent block. In principle we should have sync'd any
+/// canonical chain to its head once the peer connects. A chain should not appear where it's depth
+/// is further back than the most recent head slot.
+const PARENT_DEPTH_TOLERANCE: usize = SLOT_IMPORT_TOLERANCE * 2;
+/// The maximum number of concurrent lookups. Further unknown blocks are ignored.
+const MAX_LOOKUPS: usize = 32;
+/// The maximum number of attestations queued on a single lookup.
+const MAX_QUEUED_ATTESTATIONS: usize = 1_024;
+
+/// A gossip message that votes for an unknown block, queued until the block has been imported.
+pub enum QueuedAttestation<T: EthSpec> {
+    /// An unaggregated attestation, received on a subnet topic.
+    Unaggregated(Attestation<T>),
+    /// An aggregate and the proof of its aggregator, received on the aggregate topic.
+    Aggregate(AggregateAndProof<T>),
+}
+
+impl<T: EthSpec> QueuedAttestation<T> {
+    /// The root of the block the message votes for.
+    fn beacon_block_root(&self) -> Hash256 {
+        match self {
+            QueuedAttestation::Unaggregated(attestation) => attestation.data.beacon_block_root,
+            QueuedAttestation::Aggregate(aggregate_and_proof) => {
+                aggregate_and_proof.aggregate.data.beacon_block_root
+            }
+        }
+    }
+
+    /// Processes the message, returning the outcome. Aggregates are processed along with the
+    /// proof of their aggregator.
+    fn process<U: BeaconChainTypes<EthSpec = T>>(
+        &self,
+        chain: &BeaconChain<U>,
+    ) -> Result<AttestationProcessingOutcome, BeaconChainError> {
+        match self {
+            QueuedAttestation::Unaggregated(attestation) => {
+                chain.process_attestation(attestation.clone())
+            }
+            QueuedAttestation::Aggregate(aggregate_and_proof) => {
+                chain.process_aggregate_and_proof(aggregate_and_proof.clone())
+            }
+        }
+    }
+
+    /// Publishes the message on the topic it was received on.
+    fn publish(&self, network: &mut NetworkContext) {
+        match self {
+            QueuedAttestation::Unaggregated(attestation) => {
+                network.publish_attestation(attestation)
+            }
+            QueuedAttestation::Aggregate(aggregate_and_proof) => {
+                network.publish_aggregate_and_proof(aggregate_and_proof)
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+/// The current state of a block lookup.
+enum LookupState {
+    /// The block is queued to be requested from a peer but has not yet been requested.
+    Queued,
+    /// The block has been requested from `peer_id` and we are awaiting a response.
+    Pending {
+        request_id: RequestId,
+        peer_id: PeerId,
+    },
+    /// The lookup has failed and will be dropped.
+    Failed,
+}
+
+/// A lookup of a block by its root, along with any of its unknown ancestors.
+struct BlockLookup<T: EthSpec> {
+    /// The root of the block currently being looked up.
+    block_root: Hash256,
+    /// The blocks that have been downloaded, each being the parent of the one before it. The
+    /// last block is the child of `block_root`.
+    downloaded_blocks: Vec<BeaconBlock<T>>,
+    /// Attestations and aggregates to be processed once the lookup is complete, along with the
+    /// peer that sent each of them.
+    attestations: Vec<(PeerId, QueuedAttestation<T>)>,
+    /// The peers that referenced the block. These peers are asked for the block first.
+    source_peers: Vec<PeerId>,
+    /// The peers that have failed to return `block_root`.
+    failed_peers: HashSet<PeerId>,
+    /// The number of failed requests. If too many requests fail, the lookup is dropped.
+    failed_attempts: usize,
+    /// The current state of the lookup.
+    state: LookupState,
+}
+
+impl<T: EthSpec> BlockLookup<T> {
+    fn new(block_root: Hash256, peer_id: PeerId) -> Self {
+        BlockLookup {
+            block_root,
+            downloaded_blocks: vec![],
+            attestations: vec![],
+            source_peers: vec![peer_id],
+            failed_peers: HashSet::new(),
+            failed_attempts: 0,
+            state: LookupState::Queued,
+        }
+    }
+
+    /// Returns `true` if the block with `block_root` is being looked up or has been downloaded
+    /// by this lookup.
+    fn contains(&self, block_root: Hash256) -> bool {
+        self.block_root == block_root
+            || self
+                .downloaded_blocks
+                .iter()
+                .any(|block| block.canonical_root() == block_root)
+    }
+
+    /// Returns `true` if the lookup is awaiting the response to `request_id`.
+    fn is_awaiting(&self, request_id: RequestId) -> bool {
+        match self.state {
+            LookupState::Pending {
+                request_id: pending_id,
+                ..
+            } => pending_id == request_id,
+            _ => false,
+        }
+    }
+
+    fn add_source_peer(&mut self, peer_id: PeerId) {
+        if !self.source_peers.contains(&peer_id) {
+            self.source_peers.push(peer_id);
+        }
+    }
+
+    /// Selects the peer to request the block from. Peers that referenced the block are preferred
+    /// over the other fully sync'd peers and peers that have already failed are never selected.
+    fn next_peer(&self, full_peers: &HashSet<PeerId>) -> Option<PeerId> {
+        self.source_peers
+            .iter()
+            .chain(full_peers.iter())
+            .find(|peer_id| !self.failed_peers.contains(peer_id))
+            .cloned()
+    }
+
+    /// The pending request has failed. The block is queued to be requested from another peer.
+    fn request_failed(&mut self) {
+        if let LookupState::Pending { peer_id, .. } = &self.state {
+            self.failed_peers.insert(peer_id.clone());
+        }
+        self.failed_attempts += 1;
+        self.state = LookupState::Queued;
+    }
+}
+
+/// Manages the lookups of blocks by root, for blocks with unknown parents and for attestations
+/// that vote for unknown blocks.
+pub struct BlockLookups<T: BeaconChainTypes> {
+    /// A weak reference to the underlying beacon chain.
+    beacon_chain: Weak<BeaconChain<T>>,
+    /// The current lookups.
+    lookups: Vec<BlockLookup<T::EthSpec>>,
+    /// The logger for the block lookups.
+    log: slog::Logger,
+}
+
+impl<T: BeaconChainTypes> BlockLookups<T> {
+    pub fn new(beacon_chain: Weak<BeaconChain<T>>, log: slog::Logger) -> Self {
+        BlockLookups {
+            beacon_chain,
+            lookups: vec![],
+            log,
+        }
+    }
+
+    /// Returns `true` if there are no current lookups.
+    pub fn is_empty(&self) -> bool {
+        self.lookups.is_empty()
+    }
+
+    /// A block with an unknown parent has been received from `peer_id`. The block's ancestors are
+    /// looked up.
+    pub fn search_parent(&mut self, peer_id: PeerId, block: BeaconBlock<T::EthSpec>) {
+        let block_root = block.canonical_root();
+
+        // make sure this block is not already being searched for
+        if let Some(lookup) = self
+            .lookups
+            .iter_mut()
+            .find(|lookup| lookup.contains(block_root))
+        {
+            lookup.add_source_peer(peer_id);
+            return;
+        }
+
+        if self.lookups.len() >= MAX_LOOKUPS {
+            debug!(
+                self.log, "Too many block lookups, ignoring unknown parent";
+                "block" => format!("{}", block_root),
+            );
+            return;
+        }
+
+        let mut lookup = BlockLookup::new(block.parent_root, peer_id);
+        lookup.downloaded_blocks.push(block);
+        self.lookups.push(lookup);
+    }
+
+    /// An attestation or aggregate that votes for an unknown block has been received from
+    /// `peer_id`. The message is queued and the block is looked up.
+    pub fn search_attestation_block(
+        &mut self,
+        network: &mut NetworkContext,
+        peer_id: PeerId,
+        attestation: QueuedAttestation<T::EthSpec>,
+    ) {
+        let block_root = attestation.beacon_block_root();
+
+        if let Some(lookup) = self
+            .lookups
+            .iter_mut()
+            .find(|lookup| lookup.contains(block_root))
+        {
+            lookup.add_source_peer(peer_id.clone());
+            if lookup.attestations.len() < MAX_QUEUED_ATTESTATIONS {
+                lookup.attestations.push((peer_id, attestation));
+            } else {
+                trace!(
+                    self.log, "Attestation queue full, dropping attestation";
+                    "block" => format!("{}", block_root),
+                );
+            }
+            return;
+        }
+
+        let chain = match self.beacon_chain.upgrade() {
+            Some(chain) => chain,
+            None => return,
+        };
+
+        // The block may have been imported since the attestation was processed.
+        if chain
+            .store
+            .exists::<BeaconBlock<T::EthSpec>>(&block_root)
+            .unwrap_or_else(|_| false)
+        {
+            self.process_attestations(network, &chain, vec![(peer_id, attestation)]);
+            return;
+        }
+
+        if self.lookups.len() >= MAX_LOOKUPS {
+            debug!(
+                self.log, "Too many block lookups, ignoring attestation";
+                "block" => format!("{}", block_root),
+            );
+            return;
+        }
+
+        let mut lookup = BlockLookup::new(block_root, peer_id.clone());
+        lookup.attestations.push((peer_id, attestation));
+        self.lookups.push(lookup);
+    }
+
+    /// Requests the block of each queued lookup from a peer. Lookups that have failed too many
+    /// times, have searched too deep or have no peers left to try are dropped.
+    pub fn send_requests(&mut self, network: &mut NetworkContext, full_peers: &HashSet<PeerId>) {
+        // only search for blocks if there are peers to search from, failed lookups are removed
+        // regardless
+        if !full_peers.is_empty() {
+            self.request_blocks(network, full_peers);
+        }
+
+        // remove any failed lookups
+        let log = &self.log;
+        self.lookups.retain(|lookup| {
+            if lookup.state == LookupState::Failed {
+                debug!(log, "Block lookup failed";
+                    "block" => format!("{}", lookup.block_root),
+                    "ancestors_found" => lookup.downloaded_blocks.len(),
+                    "dropped_attestations" => lookup.attestations.len(),
+                );
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Requests the block of each queued lookup from a peer, marking lookups that cannot be
+    /// requested as failed.
+    fn request_blocks(&mut self, network: &mut NetworkContext, full_peers: &HashSet<PeerId>) {
+        for lookup in self
+            .lookups
+            .iter_mut()
+            .filter(|lookup| lookup.state == LookupState::Queued)
+        {
+            if lookup.failed_attempts >= LOOKUP_FAIL_TOLERANCE
+                || lookup.downloaded_blocks.len() >= PARENT_DEPTH_TOLERANCE
+            {
+                lookup.state = LookupState::Failed;
+                continue;
+            }
+
+            let peer_id = match lookup.next_peer(full_peers) {
+                Some(peer_id) => peer_id,
+                None => {
+                    lookup.state = LookupState::Failed;
+                    continue;
+                }
+            };
+
+            let request = RecentBeaconBlocksRequest {
+                block_roots: vec![lookup.block_root],
+            };
+            let request_id = network.recent_beacon_blocks_request(peer_id.clone(), request);
+            lookup.state = LookupState::Pending {
+                request_id,
+                peer_id,
+            };
+        }
+    }
+
+    /// A `RecentBeaconBlocks` response has been received.
+    ///
+    /// Returns `false` if the response does not belong to any lookup.
+    pub fn blocks_response(
+        &mut self,
+        network: &mut NetworkContext,
+        peer_id: PeerId,
+        request_id: RequestId,
+        mut blocks: Vec<BeaconBlock<T::EthSpec>>,
+    ) -> bool {
+        let index = match self
+            .lookups
+            .iter()
+            .position(|lookup| lookup.is_awaiting(request_id))
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let lookup = &mut self.lookups[index];
+
+        // currently only support a single block lookup. An empty response indicates the peer
+        // didn't have the requested block, try another peer.
+        if blocks.len() != 1 {
+            //TODO: Potentially downvote the peer if it sent more than one block
+            debug!(self.log, "Peer did not return the requested block";
+                "peer_id" => format!("{:?}", peer_id),
+                "blocks" => blocks.len(),
+            );
+            lookup.request_failed();
+            return true;
+        }
+
+        let block = blocks.pop().expect("must exist");
+        let block_root = block.canonical_root();
+        if block_root != lookup.block_root {
+            debug!(self.log, "Peer sent an unrequested block";
+                "peer_id" => format!("{:?}", peer_id),
+                "received_block" => format!("{}", block_root),
+                "expected_block" => format!("{}", lookup.block_root),
+            );
+            lookup.request_failed();
+            network.downvote_peer(peer_id);
+            return true;
+        }
+
+        lookup.downloaded_blocks.push(block);
+        self.process_downloaded_blocks(network, index, peer_id);
+        true
+    }
+
+    /// An RPC request has failed. If the request belongs to a lookup, the block is requested from
+    /// another peer.
+    pub fn inject_error(&mut self, request_id: RequestId) {
+        if let Some(lookup) = self
+            .lookups
+            .iter_mut()
+            .find(|lookup| lookup.is_awaiting(request_id))
+        {
+            lookup.request_failed();
+        }
+    }
+
+    /// A peer has disconnected. Requests to the peer are retried with other peers.
+    pub fn peer_disconnect(&mut self, peer_id: &PeerId) {
+        for lookup in self.lookups.iter_mut() {
+            lookup
+                .source_peers
+                .retain(|source_peer| source_peer != peer_id);
+            let requested_from_peer = match &lookup.state {
+                LookupState::Pending {
+                    peer_id: pending_peer,
+                    ..
+                } => pending_peer == peer_id,
+                _ => false,
+            };
+            if requested_from_peer {
+                lookup.request_failed();
+            }
+        }
+    }
+
+    /// Imports the downloaded blocks of the lookup at `index`, starting from the oldest. If the
+    /// parent of the oldest block is unknown, the parent is queued to be looked up. Once all
+    /// blocks are imported, the queued attestations are processed and the lookup is removed.
+    fn process_downloaded_blocks(
+        &mut self,
+        network: &mut NetworkContext,
+        index: usize,
+        peer_id: PeerId,
+    ) {
+        let chain = match self.beacon_chain.upgrade() {
+            Some(chain) => chain,
+            None => return,
+        };
+        let lookup = &mut self.lookups[index];
+
+        while let Some(block) = lookup.downloaded_blocks.pop() {
+            match chain.process_block(block.clone()) {
+                Ok(BlockProcessingOutcome::ParentUnknown { parent }) => {
+                    // need to keep looking for parents, the peer that sent the block is likely
+                    // to know its parent
+                    lookup.downloaded_blocks.push(block);
+                    lookup.block_root = parent;
+                    lookup.failed_peers.clear();
+                    lookup.add_source_peer(peer_id);
+                    lookup.state = LookupState::Queued;
+                    return;
+                }
+                Ok(BlockProcessingOutcome::Processed { .. })
+                | Ok(BlockProcessingOutcome::BlockIsAlreadyKnown) => {}
+                Ok(outcome) => {
+                    // the block is invalid or from a future slot, neither it nor its descendants
+                    // can be imported
+                    debug!(
+                        self.log, "Invalid block in lookup";
+                        "outcome" => format!("{:?}", outcome),
+                        "peer" => format!("{:?}", peer_id),
+                    );
+                    lookup.state = LookupState::Failed;
+                    network.downvote_peer(peer_id);
+                    return;
+                }
+                Err(e) => {
+                    warn!(
+                        self.log, "Block lookup processing error";
+                        "error" => format!("{:?}", e)
+                    );
+                    lookup.state = LookupState::Failed;
+                    return;
+                }
+            }
+        }
+
+        let lookup = self.lookups.swap_remove(index);
+        debug!(
+            self.log, "Block lookup complete";
+            "block" => format!("{}", lookup.block_root),
+            "queued_attestations" => lookup.attestations.len(),
+        );
+        self.process_attestations(network, &chain, lookup.attestations);
+    }
+
+    /// Processes the attestations and aggregates that were queued whilst their block was looked
+    /// up. Valid messages are published on the topic they were received on.
+    fn process_attestations(
+        &self,
+        network: &mut NetworkContext,
+        chain: &BeaconChain<T>,
+        attestations: Vec<(PeerId, QueuedAttestation<T::EthSpec>)>,
+    ) {
+        for (peer_id, attestation) in attestations {
+            match attestation.process(chain) {
+                Ok(AttestationProcessingOutcome::Processed) => attestation.publish(network),
+                Ok(outcome) => trace!(
+                    self.log,
+                    "Processed queued attestation";
+                    "peer" => format!("{:?}", peer_id),
+                    "outcome" => format!("{:?}", outcome)
+                ),
+                Err(e) => warn!(
+                    self.log,
+                    "Error processing queued attestation";
+                    "peer" => format!("{:?}", peer_id),
+                    "error" => format!("{:?}", e)
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::NetworkMessage;
+    use beacon_chain::test_utils::CommonTypes;
+    use eth2_libp2p::{
+        PubsubMessage, Topic, BEACON_AGGREGATE_AND_PROOF_TOPIC, TOPIC_ENCODING_POSTFIX,
+        TOPIC_PREFIX,
+    };
+    use futures::Stream;
+    use lmd_ghost::ThreadSafeReducedTree;
+    use slog::{o, Discard, Logger};
+    use ssz::Encode;
+    use store::MemoryStore;
+    use tokio::sync::mpsc;
+    use types::test_utils::{SeedableRng, TestRandom, XorShiftRng};
+    use types::MinimalEthSpec;
+
+    type TestTypes =
+        CommonTypes<ThreadSafeReducedTree<MemoryStore, MinimalEthSpec>, MinimalEthSpec>;
+
+    fn lookup(source_peer: PeerId) -> BlockLookup<MinimalEthSpec> {
+        BlockLookup::new(Hash256::zero(), source_peer)
+    }
+
+    #[test]
+    fn source_peers_are_tried_first() {
+        let source_peer = PeerId::random();
+        let full_peers: HashSet<PeerId> = (0..3).map(|_| PeerId::random()).collect();
+
+        assert_eq!(
+            lookup(source_peer.clone()).next_peer(&full_peers),
+            Some(source_peer)
+        );
+    }
+
+    #[test]
+    fn failed_peers_are_not_retried() {
+        let source_peer = PeerId::random();
+        let other_peer = PeerId::random();
+        let full_peers: HashSet<PeerId> = vec![source_peer.clone(), other_peer.clone()]
+            .into_iter()
+            .collect();
+        let mut lookup = lookup(source_peer.clone());
+
+        lookup.state = LookupState::Pending {
+            request_id: 1,
+            peer_id: source_peer,
+        };
+        lookup.request_failed();
+        assert_eq!(lookup.state, LookupState::Queued);
+        assert_eq!(lookup.failed_attempts, 1);
+        assert_eq!(lookup.next_peer(&full_peers), Some(other_peer.clone()));
+
+        lookup.state = LookupState::Pending {
+            request_id: 2,
+            peer_id: other_peer,
+        };
+        lookup.request_failed();
+        assert_eq!(lookup.next_peer(&full_peers), None);
+    }
+
+    #[test]
+    fn failed_lookups_are_removed_without_peers() {
+        let (network_send, _network_recv) = mpsc::unbounded_channel();
+        let log = Logger::root(Discard, o!());
+        let mut network = NetworkContext::new(network_send, log.clone());
+        let mut lookups = BlockLookups::<TestTypes>::new(Weak::new(), log);
+
+        let mut failed_lookup = lookup(PeerId::random());
+        failed_lookup.state = LookupState::Failed;
+        lookups.lookups.push(failed_lookup);
+
+        lookups.send_requests(&mut network, &HashSet::new());
+        assert!(lookups.is_empty());
+    }
+
+    #[test]
+    fn queued_aggregates_are_published_on_the_aggregate_topic() {
+        let (network_send, network_recv) = mpsc::unbounded_channel();
+        let mut network = NetworkContext::new(network_send, Logger::root(Discard, o!()));
+        let mut rng = XorShiftRng::from_seed([42; 16]);
+        let aggregate_and_proof = AggregateAndProof::<MinimalEthSpec>::random_for_test(&mut rng);
+
+        QueuedAttestation::Aggregate(aggregate_and_proof.clone()).publish(&mut network);
+        drop(network);
+
+        let published: Vec<NetworkMessage> = network_recv.wait().filter_map(Result::ok).collect();
+        assert_eq!(published.len(), 1);
+        match &published[0] {
+            NetworkMessage::Publish { topics, message } => {
+                assert_eq!(
+                    topics,
+                    &vec![Topic::new(format!(
+                        "/{}/{}/{}",
+                        TOPIC_PREFIX, BEACON_AGGREGATE_AND_PROOF_TOPIC, TOPIC_ENCODING_POSTFIX
+                    ))]
+                );
+                assert_eq!(
+                    message,
+                    &PubsubMessage::AggregateAndProof(aggregate_and_proof.as_ssz_bytes())
+                );
+            }
+            _ => panic!("the aggregate should be published"),
+        }
+    }
+}
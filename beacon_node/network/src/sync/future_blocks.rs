@@ -0,0 +1,350 @@
+//! A queue for gossip blocks that arrive shortly before their slot.
+//!
+//! Clocks are never perfectly synchronised, so a proposer whose clock runs slightly fast may
+//! publish its block before our slot clock has reached the block's slot. Such blocks cannot be
+//! imported yet. Rather than being dropped, they are held in a delay queue and processed again
+//! once our slot clock reaches their slot.
+
+use super::manager::SyncMessage;
+use beacon_chain::{BeaconChain, BeaconChainTypes, BlockProcessingOutcome};
+use eth2_libp2p::PeerId;
+use futures::prelude::*;
+use slog::{debug, info, trace, warn};
+use slot_clock::SlotClock;
+use std::collections::HashSet;
+use std::sync::Weak;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::timer::DelayQueue;
+use types::{BeaconBlock, EthSpec, Hash256, Slot};
+
+/// The maximum number of blocks held in the queue. Further early blocks are dropped.
+const MAXIMUM_QUEUED_BLOCKS: usize = 16;
+
+/// A gossip block that has arrived before its slot.
+#[derive(Debug)]
+pub struct FutureBlock<T: EthSpec> {
+    /// The peer that sent us the block.
+    pub peer_id: PeerId,
+    /// The block.
+    pub block: BeaconBlock<T>,
+    /// The slot our slot clock was at when the block was received.
+    pub present_slot: Slot,
+}
+
+/// Holds early blocks until their slot and then attempts to import them.
+struct FutureBlockQueue<T: BeaconChainTypes> {
+    /// A weak reference to the underlying beacon chain.
+    chain: Weak<BeaconChain<T>>,
+    /// A receiving channel for blocks sent by the message processor.
+    input_channel: mpsc::UnboundedReceiver<FutureBlock<T::EthSpec>>,
+    /// The blocks waiting for their slot.
+    queue: DelayQueue<(PeerId, BeaconBlock<T::EthSpec>)>,
+    /// The roots of the blocks in `queue`, used to ignore duplicates.
+    queued_roots: HashSet<Hash256>,
+    /// A channel to the sync manager, which looks up the parents of blocks that cannot be
+    /// imported.
+    sync_send: mpsc::UnboundedSender<SyncMessage<T::EthSpec>>,
+    /// The logger for the queue.
+    log: slog::Logger,
+}
+
+/// Spawns a task which holds early blocks until their slot. The task holds a weak reference to
+/// the beacon chain and ends when the chain is dropped or the exit channel is triggered.
+pub fn spawn<T: BeaconChainTypes>(
+    executor: &tokio::runtime::TaskExecutor,
+    beacon_chain: Weak<BeaconChain<T>>,
+    sync_send: mpsc::UnboundedSender<SyncMessage<T::EthSpec>>,
+    log: slog::Logger,
+) -> (
+    mpsc::UnboundedSender<FutureBlock<T::EthSpec>>,
+    oneshot::Sender<()>,
+) {
+    let (queue_exit, exit_rx) = oneshot::channel();
+    let (queue_send, queue_recv) = mpsc::unbounded_channel();
+
+    let future_block_queue = FutureBlockQueue {
+        chain: beacon_chain,
+        input_channel: queue_recv,
+        queue: DelayQueue::new(),
+        queued_roots: HashSet::new(),
+        sync_send,
+        log: log.clone(),
+    };
+
+    executor.spawn(
+        future_block_queue
+            .select(exit_rx.then(|_| Ok(())))
+            .then(move |_| {
+                info!(log, "Future block queue shutdown");
+                Ok(())
+            }),
+    );
+
+    (queue_send, queue_exit)
+}
+
+impl<T: BeaconChainTypes> FutureBlockQueue<T> {
+    /// Adds a block to the queue, to be processed at the start of its slot.
+    fn queue_block(&mut self, future_block: FutureBlock<T::EthSpec>) {
+        let chain = match self.chain.upgrade() {
+            Some(chain) => chain,
+            None => return,
+        };
+
+        let delay = match duration_to_slot(
+            &chain.slot_clock,
+            future_block.present_slot,
+            future_block.block.slot,
+        ) {
+            Some(delay) => delay,
+            None => {
+                warn!(self.log, "Unable to read slot clock, dropping future block");
+                return;
+            }
+        };
+
+        let block_root = future_block.block.canonical_root();
+        match reserve_root(&mut self.queued_roots, block_root) {
+            Ok(()) => {}
+            Err(QueueError::Duplicate) => return,
+            Err(QueueError::QueueFull) => {
+                debug!(
+                    self.log, "Future block queue full, dropping block";
+                    "block_slot" => future_block.block.slot,
+                    "block_root" => format!("{}", block_root),
+                );
+                return;
+            }
+        }
+
+        debug!(
+            self.log, "Queued block from future slot";
+            "peer" => format!("{:?}", future_block.peer_id),
+            "block_slot" => future_block.block.slot,
+            "present_slot" => future_block.present_slot,
+            "delay_ms" => delay.as_millis() as u64,
+        );
+
+        self.queue
+            .insert((future_block.peer_id, future_block.block), delay);
+    }
+
+    /// Attempts to import a block whose slot has been reached.
+    fn process_block(&mut self, peer_id: PeerId, block: BeaconBlock<T::EthSpec>) {
+        self.queued_roots.remove(&block.canonical_root());
+
+        let chain = match self.chain.upgrade() {
+            Some(chain) => chain,
+            None => return,
+        };
+
+        match chain.process_block(block.clone()) {
+            Ok(BlockProcessingOutcome::Processed { block_root }) => {
+                trace!(
+                    self.log, "Future block processed";
+                    "block_slot" => block.slot,
+                    "block_root" => format!("{}", block_root),
+                );
+            }
+            Ok(BlockProcessingOutcome::ParentUnknown { .. }) => {
+                // Inform the sync manager to find parents for this block
+                trace!(self.log, "Future block with unknown parent";
+                        "peer_id" => format!("{:?}", peer_id));
+                self.sync_send
+                    .try_send(SyncMessage::UnknownBlock(peer_id, block))
+                    .unwrap_or_else(|_| {
+                        warn!(self.log, "Could not send message to the sync service")
+                    });
+            }
+            Ok(outcome) => {
+                debug!(
+                    self.log, "Future block not imported";
+                    "outcome" => format!("{:?}", outcome),
+                    "peer" => format!("{:?}", peer_id),
+                    "block_slot" => block.slot,
+                );
+            }
+            Err(e) => {
+                warn!(
+                    self.log, "Error processing future block";
+                    "error" => format!("{:?}", e),
+                    "block_slot" => block.slot,
+                );
+            }
+        }
+    }
+}
+
+impl<T: BeaconChainTypes> Future for FutureBlockQueue<T> {
+    type Item = ();
+    type Error = String;
+
+    fn poll(&mut self) -> Result<Async<Self::Item>, Self::Error> {
+        // queue any newly received blocks
+        loop {
+            match self.input_channel.poll() {
+                Ok(Async::Ready(Some(future_block))) => self.queue_block(future_block),
+                Ok(Async::NotReady) => break,
+                Ok(Async::Ready(None)) => {
+                    return Err("Future block queue channel closed".into());
+                }
+                Err(e) => {
+                    return Err(format!("Future block queue channel error: {:?}", e));
+                }
+            }
+        }
+
+        // process any blocks whose slot has been reached
+        loop {
+            match self.queue.poll() {
+                Ok(Async::Ready(Some(expired))) => {
+                    let (peer_id, block) = expired.into_inner();
+                    self.process_block(peer_id, block);
+                }
+                // the queue is either empty or waiting for the next block's slot
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+                Err(e) => {
+                    return Err(format!("Future block queue timer error: {:?}", e));
+                }
+            }
+        }
+
+        // Shutdown the task if the chain has terminated
+        if self.chain.upgrade().is_none() {
+            return Ok(Async::Ready(()));
+        }
+
+        Ok(Async::NotReady)
+    }
+}
+
+/// The reason a block was not added to the queue.
+#[derive(Debug, PartialEq)]
+enum QueueError {
+    /// A block with the same root is already queued.
+    Duplicate,
+    /// `MAXIMUM_QUEUED_BLOCKS` blocks are already queued.
+    QueueFull,
+}
+
+/// Records `block_root` as queued, unless it is already queued or the queue is full.
+fn reserve_root(
+    queued_roots: &mut HashSet<Hash256>,
+    block_root: Hash256,
+) -> Result<(), QueueError> {
+    if queued_roots.contains(&block_root) {
+        Err(QueueError::Duplicate)
+    } else if queued_roots.len() >= MAXIMUM_QUEUED_BLOCKS {
+        Err(QueueError::QueueFull)
+    } else {
+        queued_roots.insert(block_root);
+        Ok(())
+    }
+}
+
+/// Returns the duration from now until the start of `block_slot`, given that the slot clock
+/// currently reads `present_slot`.
+fn duration_to_slot<S: SlotClock>(
+    slot_clock: &S,
+    present_slot: Slot,
+    block_slot: Slot,
+) -> Option<Duration> {
+    let slots_after_next = block_slot
+        .as_u64()
+        .saturating_sub(present_slot.as_u64() + 1);
+    Some(slot_clock.duration_to_next_slot()? + slot_clock.slot_duration() * slots_after_next as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A slot clock that is always 500ms from the next slot, with 6 second slots.
+    struct FixedSlotClock;
+
+    impl SlotClock for FixedSlotClock {
+        fn new(_genesis_slot: Slot, _genesis_duration: Duration, _slot_duration: Duration) -> Self {
+            FixedSlotClock
+        }
+
+        fn now(&self) -> Option<Slot> {
+            Some(Slot::new(10))
+        }
+
+        fn slot_duration(&self) -> Duration {
+            Duration::from_secs(6)
+        }
+
+        fn duration_to_next_slot(&self) -> Option<Duration> {
+            Some(Duration::from_millis(500))
+        }
+    }
+
+    fn delay(present_slot: u64, block_slot: u64) -> Option<Duration> {
+        duration_to_slot(
+            &FixedSlotClock,
+            Slot::new(present_slot),
+            Slot::new(block_slot),
+        )
+    }
+
+    #[test]
+    fn delay_until_next_slot() {
+        assert_eq!(delay(10, 11), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn delay_until_later_slot() {
+        assert_eq!(delay(10, 13), Some(Duration::from_millis(12_500)));
+    }
+
+    #[test]
+    fn no_extra_delay_for_present_or_past_slot() {
+        assert_eq!(delay(10, 10), Some(Duration::from_millis(500)));
+        assert_eq!(delay(10, 9), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn duplicate_roots_are_dropped() {
+        let mut queued_roots = HashSet::new();
+        let block_root = Hash256::from_low_u64_be(1);
+
+        assert_eq!(reserve_root(&mut queued_roots, block_root), Ok(()));
+        assert_eq!(
+            reserve_root(&mut queued_roots, block_root),
+            Err(QueueError::Duplicate)
+        );
+        assert_eq!(queued_roots.len(), 1);
+    }
+
+    #[test]
+    fn queue_is_capped() {
+        let mut queued_roots = HashSet::new();
+
+        for i in 0..MAXIMUM_QUEUED_BLOCKS as u64 {
+            assert_eq!(
+                reserve_root(&mut queued_roots, Hash256::from_low_u64_be(i)),
+                Ok(())
+            );
+        }
+        assert_eq!(
+            reserve_root(
+                &mut queued_roots,
+                Hash256::from_low_u64_be(u64::max_value())
+            ),
+            Err(QueueError::QueueFull)
+        );
+        assert_eq!(queued_roots.len(), MAXIMUM_QUEUED_BLOCKS);
+
+        // Processing a block frees its place in the queue.
+        queued_roots.remove(&Hash256::from_low_u64_be(0));
+        assert_eq!(
+            reserve_root(
+                &mut queued_roots,
+                Hash256::from_low_u64_be(u64::max_value())
+            ),
+            Ok(())
+        );
+    }
+}
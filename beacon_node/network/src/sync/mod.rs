@@ -1,4 +1,7 @@
+mod block_lookups;
+mod future_blocks;
 mod manager;
+mod range_sync;
 /// Syncing for lighthouse.
 ///
 /// Stores the various syncing methods for the beacon chain.
@@ -0,0 +1,142 @@
+use eth2_libp2p::rpc::methods::BeaconBlocksRequest;
+use eth2_libp2p::PeerId;
+use types::{BeaconBlock, EthSpec, Hash256, Slot};
+
+/// The identifier of a batch. Batch ids are sequential and a chain's batches are processed in id
+/// order.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BatchId(pub u64);
+
+impl BatchId {
+    /// Returns the id that follows this one.
+    pub fn next(self) -> Self {
+        BatchId(self.0 + 1)
+    }
+}
+
+impl std::fmt::Display for BatchId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A range of sequential blocks that is requested from a single peer in a single `BeaconBlocks`
+/// request.
+pub struct Batch<T: EthSpec> {
+    /// The id of the batch.
+    pub id: BatchId,
+    /// The requested start slot of the batch, inclusive.
+    pub start_slot: Slot,
+    /// The requested end slot of the batch, exclusive.
+    pub end_slot: Slot,
+    /// The root of the head block of the chain that is being downloaded.
+    pub head_root: Hash256,
+    /// The peer that is currently assigned to the batch.
+    pub current_peer: PeerId,
+    /// The number of times this batch has failed to download or process.
+    pub retries: u8,
+    /// The blocks that have been downloaded for this batch.
+    pub downloaded_blocks: Vec<BeaconBlock<T>>,
+}
+
+impl<T: EthSpec> Batch<T> {
+    pub fn new(
+        id: BatchId,
+        start_slot: Slot,
+        end_slot: Slot,
+        head_root: Hash256,
+        peer_id: PeerId,
+    ) -> Self {
+        Batch {
+            id,
+            start_slot,
+            end_slot,
+            head_root,
+            current_peer: peer_id,
+            retries: 0,
+            downloaded_blocks: Vec::new(),
+        }
+    }
+
+    /// The `BeaconBlocks` request that downloads this batch.
+    pub fn to_blocks_request(&self) -> BeaconBlocksRequest {
+        BeaconBlocksRequest {
+            head_block_root: self.head_root,
+            start_slot: self.start_slot.as_u64(),
+            count: (self.end_slot - self.start_slot).as_u64(),
+            step: 1,
+        }
+    }
+
+    /// Returns `true` if `slot` is within the range of this batch.
+    pub fn contains_slot(&self, slot: Slot) -> bool {
+        self.start_slot <= slot && slot < self.end_slot
+    }
+
+    /// Verifies that a response to this batch only contains blocks within the requested range,
+    /// in strictly increasing slot order.
+    ///
+    /// The validity of the blocks themselves is verified when they are processed.
+    pub fn verify_response(&self, blocks: &[BeaconBlock<T>]) -> Result<(), String> {
+        if let Some(block) = blocks.iter().find(|block| !self.contains_slot(block.slot)) {
+            return Err(format!(
+                "Block at slot {} is outside the requested range {}..{}",
+                block.slot, self.start_slot, self.end_slot
+            ));
+        }
+
+        if blocks.windows(2).any(|pair| pair[0].slot >= pair[1].slot) {
+            return Err("Blocks are not in increasing slot order".into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::MinimalEthSpec;
+
+    fn block_at(slot: u64) -> BeaconBlock<MinimalEthSpec> {
+        let mut block = BeaconBlock::empty(&MinimalEthSpec::default_spec());
+        block.slot = Slot::new(slot);
+        block
+    }
+
+    fn batch() -> Batch<MinimalEthSpec> {
+        Batch::new(
+            BatchId(0),
+            Slot::new(16),
+            Slot::new(32),
+            Hash256::zero(),
+            PeerId::random(),
+        )
+    }
+
+    #[test]
+    fn request_covers_batch() {
+        let request = batch().to_blocks_request();
+        assert_eq!(request.start_slot, 16);
+        assert_eq!(request.count, 16);
+    }
+
+    #[test]
+    fn verify_response() {
+        let batch = batch();
+
+        assert!(batch.verify_response(&[]).is_ok());
+        assert!(batch
+            .verify_response(&[block_at(16), block_at(20), block_at(31)])
+            .is_ok());
+
+        assert!(batch.verify_response(&[block_at(15)]).is_err());
+        assert!(batch.verify_response(&[block_at(32)]).is_err());
+        assert!(batch
+            .verify_response(&[block_at(20), block_at(20)])
+            .is_err());
+        assert!(batch
+            .verify_response(&[block_at(21), block_at(20)])
+            .is_err());
+    }
+}
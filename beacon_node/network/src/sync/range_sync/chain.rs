@@ -0,0 +1,500 @@
+use super::batch::{Batch, BatchId};
+use crate::sync::simple_sync::{NetworkContext, FUTURE_SLOT_TOLERANCE};
+use beacon_chain::{BeaconChain, BeaconChainTypes, BlockProcessingOutcome};
+use eth2_libp2p::rpc::RequestId;
+use eth2_libp2p::PeerId;
+use slog::{debug, info, trace, warn};
+use std::collections::{HashMap, HashSet};
+use std::sync::Weak;
+use types::{BeaconBlock, EthSpec, Hash256, Slot};
+
+/// The number of epochs of blocks that are requested in a single batch.
+pub const EPOCHS_PER_BATCH: u64 = 2;
+
+/// The maximum number of batches that may be downloaded ahead of the next batch to be processed.
+/// This bounds the number of blocks held in memory while awaiting processing.
+const BATCH_BUFFER_SIZE: u64 = 5;
+
+/// The number of times a single batch may fail to download or process before the whole chain is
+/// considered invalid and dropped.
+const MAX_BATCH_RETRIES: u8 = 3;
+
+/// The result of updating a `SyncingChain`.
+#[derive(PartialEq, Debug)]
+pub enum ProcessingResult {
+    /// The chain is still being synced.
+    KeepChain,
+    /// The chain has been fully synced, or has no peers remaining, and should be removed.
+    RemoveChain,
+    /// The chain has failed validation and should be removed. Its peers served invalid blocks
+    /// and should be penalised.
+    FailedChain,
+}
+
+/// Whether a `SyncingChain` is actively requesting batches.
+#[derive(PartialEq, Debug)]
+pub enum ChainSyncingState {
+    /// The chain is not requesting new batches. Responses to batches that are already pending
+    /// are still handled.
+    Stopped,
+    /// The chain is requesting batches from its peers.
+    Syncing,
+}
+
+/// A chain of blocks that is downloaded, in batches, from a pool of peers.
+///
+/// The range between `start_slot` and `target_head_slot` is split into batches of
+/// `EPOCHS_PER_BATCH` epochs. Each idle peer in the pool is assigned the next batch to download,
+/// so that batches are downloaded in parallel. Downloaded batches are then processed strictly in
+/// order. A batch that fails to download, or that contains invalid blocks, is re-requested from a
+/// different peer in the pool.
+pub struct SyncingChain<T: BeaconChainTypes> {
+    /// The slot from which this chain is downloaded.
+    pub start_slot: Slot,
+    /// The slot of the head block this chain is downloading to.
+    pub target_head_slot: Slot,
+    /// The root of the head block this chain is downloading to.
+    pub target_head_root: Hash256,
+    /// The batches that have been requested and are awaiting a response, keyed by request id.
+    pending_batches: HashMap<RequestId, Batch<T::EthSpec>>,
+    /// The batches that have been downloaded and are awaiting processing.
+    completed_batches: Vec<Batch<T::EthSpec>>,
+    /// The peers from which this chain is downloaded.
+    pub peer_pool: HashSet<PeerId>,
+    /// The id of the next batch to be requested.
+    to_be_downloaded_id: BatchId,
+    /// The id of the next batch to be processed.
+    to_be_processed_id: BatchId,
+    /// Whether the chain is currently requesting batches.
+    pub state: ChainSyncingState,
+    /// A weak reference to the underlying beacon chain.
+    chain: Weak<BeaconChain<T>>,
+    /// The logger for the chain.
+    log: slog::Logger,
+}
+
+impl<T: BeaconChainTypes> SyncingChain<T> {
+    pub fn new(
+        start_slot: Slot,
+        target_head_slot: Slot,
+        target_head_root: Hash256,
+        peer_id: PeerId,
+        chain: Weak<BeaconChain<T>>,
+        log: slog::Logger,
+    ) -> Self {
+        let mut peer_pool = HashSet::new();
+        peer_pool.insert(peer_id);
+
+        SyncingChain {
+            start_slot,
+            target_head_slot,
+            target_head_root,
+            pending_batches: HashMap::new(),
+            completed_batches: Vec::new(),
+            peer_pool,
+            to_be_downloaded_id: BatchId(0),
+            to_be_processed_id: BatchId(0),
+            state: ChainSyncingState::Stopped,
+            chain,
+            log,
+        }
+    }
+
+    /// The number of slots in a single batch.
+    fn slots_per_batch() -> u64 {
+        EPOCHS_PER_BATCH * T::EthSpec::slots_per_epoch()
+    }
+
+    /// Starts, or resumes, requesting batches from the peers in the pool.
+    pub fn start_syncing(&mut self, network: &mut NetworkContext) {
+        if self.state != ChainSyncingState::Syncing {
+            debug!(self.log, "Syncing chain started";
+                "start_slot" => self.start_slot,
+                "target_head_slot" => self.target_head_slot,
+                "peers" => self.peer_pool.len());
+            self.state = ChainSyncingState::Syncing;
+        }
+        self.request_batches(network);
+    }
+
+    /// Stops requesting new batches. The chain can be resumed with `start_syncing`.
+    pub fn stop_syncing(&mut self) {
+        if self.state != ChainSyncingState::Stopped {
+            debug!(self.log, "Syncing chain stopped";
+                "target_head_slot" => self.target_head_slot,
+                "peers" => self.peer_pool.len());
+            self.state = ChainSyncingState::Stopped;
+        }
+    }
+
+    /// Returns `true` if the chain is waiting on the response to `request_id`.
+    pub fn has_request(&self, request_id: RequestId) -> bool {
+        self.pending_batches.contains_key(&request_id)
+    }
+
+    /// Adds a peer to the pool and, if the chain is syncing, assigns it a batch to download.
+    pub fn add_peer(&mut self, network: &mut NetworkContext, peer_id: PeerId) {
+        self.peer_pool.insert(peer_id);
+        self.request_batches(network);
+    }
+
+    /// Removes a peer from the pool, re-requesting any batches it was downloading from the
+    /// remaining peers.
+    ///
+    /// If no peers remain, the chain can no longer be synced and should be removed.
+    pub fn remove_peer(
+        &mut self,
+        network: &mut NetworkContext,
+        peer_id: &PeerId,
+    ) -> ProcessingResult {
+        if !self.peer_pool.remove(peer_id) {
+            return ProcessingResult::KeepChain;
+        }
+
+        if self.peer_pool.is_empty() {
+            debug!(self.log, "No peers remaining for syncing chain";
+                "target_head_slot" => self.target_head_slot);
+            return ProcessingResult::RemoveChain;
+        }
+
+        let orphaned_requests: Vec<RequestId> = self
+            .pending_batches
+            .iter()
+            .filter(|(_, batch)| &batch.current_peer == peer_id)
+            .map(|(request_id, _)| *request_id)
+            .collect();
+
+        for request_id in orphaned_requests {
+            if let Some(mut batch) = self.pending_batches.remove(&request_id) {
+                if let Some(new_peer) = self.select_peer(None) {
+                    batch.current_peer = new_peer;
+                }
+                self.send_batch(network, batch);
+            }
+        }
+
+        self.request_batches(network);
+        ProcessingResult::KeepChain
+    }
+
+    /// Handles a `BeaconBlocks` response.
+    ///
+    /// Returns `None` if the request does not belong to this chain.
+    pub fn on_block_response(
+        &mut self,
+        network: &mut NetworkContext,
+        request_id: RequestId,
+        blocks: Vec<BeaconBlock<T::EthSpec>>,
+    ) -> Option<ProcessingResult> {
+        let mut batch = self.pending_batches.remove(&request_id)?;
+
+        if let Err(e) = batch.verify_response(&blocks) {
+            warn!(self.log, "Peer returned an invalid batch";
+                "peer" => format!("{:?}", batch.current_peer),
+                "batch_id" => batch.id,
+                "error" => e);
+            network.downvote_peer(batch.current_peer.clone());
+            return Some(self.retry_batch(network, batch));
+        }
+
+        if blocks.is_empty() && batch.contains_slot(self.target_head_slot) {
+            // The batch must contain at least the target head block.
+            debug!(self.log, "Peer did not return blocks it claimed to possess";
+                "peer" => format!("{:?}", batch.current_peer),
+                "batch_id" => batch.id);
+            return Some(self.retry_batch(network, batch));
+        }
+
+        trace!(self.log, "Batch downloaded";
+            "batch_id" => batch.id,
+            "start_slot" => batch.start_slot,
+            "blocks" => blocks.len());
+
+        batch.downloaded_blocks = blocks;
+        self.completed_batches.push(batch);
+
+        let result = self.process_completed_batches(network);
+        if result == ProcessingResult::KeepChain {
+            self.request_batches(network);
+        }
+        Some(result)
+    }
+
+    /// Handles a failed `BeaconBlocks` request by re-requesting the batch from another peer.
+    ///
+    /// Returns `None` if the request does not belong to this chain.
+    pub fn inject_error(
+        &mut self,
+        network: &mut NetworkContext,
+        request_id: RequestId,
+    ) -> Option<ProcessingResult> {
+        let batch = self.pending_batches.remove(&request_id)?;
+        debug!(self.log, "Batch request failed";
+            "peer" => format!("{:?}", batch.current_peer),
+            "batch_id" => batch.id);
+        Some(self.retry_batch(network, batch))
+    }
+
+    /// Processes the completed batches that are next in order. Batches downloaded out of order
+    /// are kept until all prior batches are processed.
+    fn process_completed_batches(&mut self, network: &mut NetworkContext) -> ProcessingResult {
+        loop {
+            let position = match self
+                .completed_batches
+                .iter()
+                .position(|batch| batch.id == self.to_be_processed_id)
+            {
+                Some(position) => position,
+                None => return ProcessingResult::KeepChain,
+            };
+
+            let mut batch = self.completed_batches.swap_remove(position);
+            let blocks = std::mem::replace(&mut batch.downloaded_blocks, Vec::new());
+            let block_count = blocks.len();
+
+            match process_blocks(self.chain.clone(), blocks, &self.log) {
+                Ok(()) => {
+                    debug!(self.log, "Batch processed successfully";
+                        "batch_id" => batch.id,
+                        "start_slot" => batch.start_slot,
+                        "end_slot" => batch.end_slot,
+                        "blocks" => block_count);
+                    self.to_be_processed_id = self.to_be_processed_id.next();
+
+                    if batch.end_slot > self.target_head_slot {
+                        info!(self.log, "Chain synced";
+                            "start_slot" => self.start_slot,
+                            "target_head_slot" => self.target_head_slot,
+                            "peers" => self.peer_pool.len());
+                        // Re-hello the peers to ensure we are up to their latest head.
+                        for peer_id in self.peer_pool.iter() {
+                            network.hello_peer(self.chain.clone(), peer_id.clone());
+                        }
+                        return ProcessingResult::RemoveChain;
+                    }
+                }
+                Err(e) => {
+                    warn!(self.log, "Batch processing failed";
+                        "peer" => format!("{:?}", batch.current_peer),
+                        "batch_id" => batch.id,
+                        "start_slot" => batch.start_slot,
+                        "error" => e);
+                    network.downvote_peer(batch.current_peer.clone());
+                    return self.retry_batch(network, batch);
+                }
+            }
+        }
+    }
+
+    /// Re-requests a failed batch, preferably from a different peer.
+    ///
+    /// If the batch has failed too many times, the chain is considered invalid and should be
+    /// removed, penalising its peers.
+    fn retry_batch(
+        &mut self,
+        network: &mut NetworkContext,
+        mut batch: Batch<T::EthSpec>,
+    ) -> ProcessingResult {
+        batch.retries += 1;
+        if batch.retries > MAX_BATCH_RETRIES {
+            warn!(self.log, "Batch failed too many times, dropping chain";
+                "batch_id" => batch.id,
+                "start_slot" => batch.start_slot,
+                "target_head_slot" => self.target_head_slot);
+            return ProcessingResult::FailedChain;
+        }
+
+        batch.downloaded_blocks.clear();
+        if let Some(new_peer) = self.select_peer(Some(&batch.current_peer)) {
+            batch.current_peer = new_peer;
+        }
+
+        debug!(self.log, "Re-requesting batch";
+            "peer" => format!("{:?}", batch.current_peer),
+            "batch_id" => batch.id,
+            "retries" => batch.retries);
+        self.send_batch(network, batch);
+        ProcessingResult::KeepChain
+    }
+
+    /// Assigns new batches to every idle peer in the pool, while the number of batches ahead of
+    /// processing is within `BATCH_BUFFER_SIZE`.
+    fn request_batches(&mut self, network: &mut NetworkContext) {
+        if self.state != ChainSyncingState::Syncing {
+            return;
+        }
+
+        while let Some(peer_id) = self.idle_peer() {
+            match self.next_batch(peer_id) {
+                Some(batch) => self.send_batch(network, batch),
+                None => break,
+            }
+        }
+    }
+
+    /// Creates the next batch to download, if any.
+    fn next_batch(&mut self, peer_id: PeerId) -> Option<Batch<T::EthSpec>> {
+        if self.to_be_downloaded_id.0 >= self.to_be_processed_id.0 + BATCH_BUFFER_SIZE {
+            return None;
+        }
+
+        let start_slot = self.start_slot + self.to_be_downloaded_id.0 * Self::slots_per_batch();
+        if start_slot > self.target_head_slot {
+            return None;
+        }
+        let end_slot = start_slot + Self::slots_per_batch();
+
+        let batch = Batch::new(
+            self.to_be_downloaded_id,
+            start_slot,
+            end_slot,
+            self.target_head_root,
+            peer_id,
+        );
+        self.to_be_downloaded_id = self.to_be_downloaded_id.next();
+        Some(batch)
+    }
+
+    /// Sends the `BeaconBlocks` request of a batch to its current peer.
+    fn send_batch(&mut self, network: &mut NetworkContext, batch: Batch<T::EthSpec>) {
+        let request_id =
+            network.beacon_blocks_request(batch.current_peer.clone(), batch.to_blocks_request());
+        self.pending_batches.insert(request_id, batch);
+    }
+
+    /// Returns a peer of the pool that is not downloading a batch.
+    fn idle_peer(&self) -> Option<PeerId> {
+        self.peer_pool
+            .iter()
+            .find(|peer_id| !self.is_downloading(peer_id))
+            .cloned()
+    }
+
+    /// Selects a peer to re-request a batch from, preferring idle peers and avoiding `exclude`.
+    fn select_peer(&self, exclude: Option<&PeerId>) -> Option<PeerId> {
+        let candidates = || {
+            self.peer_pool
+                .iter()
+                .filter(move |peer_id| Some(*peer_id) != exclude)
+        };
+
+        candidates()
+            .find(|peer_id| !self.is_downloading(peer_id))
+            .or_else(|| candidates().next())
+            .cloned()
+    }
+
+    /// Returns `true` if the peer has been assigned a batch that it has not yet returned.
+    fn is_downloading(&self, peer_id: &PeerId) -> bool {
+        self.pending_batches
+            .values()
+            .any(|batch| &batch.current_peer == peer_id)
+    }
+}
+
+/// Processes a sequence of blocks, in order, on the beacon chain.
+fn process_blocks<T: BeaconChainTypes>(
+    weak_chain: Weak<BeaconChain<T>>,
+    blocks: Vec<BeaconBlock<T::EthSpec>>,
+    log: &slog::Logger,
+) -> Result<(), String> {
+    for block in blocks {
+        if let Some(chain) = weak_chain.upgrade() {
+            let processing_result = chain.process_block(block.clone());
+
+            if let Ok(outcome) = processing_result {
+                match outcome {
+                    BlockProcessingOutcome::Processed { block_root } => {
+                        // The block was valid and we processed it successfully.
+                        trace!(
+                            log, "Imported block from network";
+                            "slot" => block.slot,
+                            "block_root" => format!("{}", block_root),
+                        );
+                    }
+                    BlockProcessingOutcome::ParentUnknown { parent } => {
+                        // blocks should be sequential and all parents should exist
+                        trace!(
+                            log, "Parent block is unknown";
+                            "parent_root" => format!("{}", parent),
+                            "baby_block_slot" => block.slot,
+                        );
+                        return Err(format!(
+                            "Block at slot {} has an unknown parent.",
+                            block.slot
+                        ));
+                    }
+                    BlockProcessingOutcome::BlockIsAlreadyKnown => {
+                        // this block is already known to us, move to the next
+                        debug!(
+                            log, "Imported a block that is already known";
+                            "block_slot" => block.slot,
+                        );
+                    }
+                    BlockProcessingOutcome::FutureSlot {
+                        present_slot,
+                        block_slot,
+                    } => {
+                        if present_slot + FUTURE_SLOT_TOLERANCE >= block_slot {
+                            // The block is too far in the future, drop it.
+                            trace!(
+                                log, "Block is ahead of our slot clock";
+                                "msg" => "block for future slot rejected, check your time",
+                                "present_slot" => present_slot,
+                                "block_slot" => block_slot,
+                                "FUTURE_SLOT_TOLERANCE" => FUTURE_SLOT_TOLERANCE,
+                            );
+                            return Err(format!(
+                                "Block at slot {} is too far in the future",
+                                block.slot
+                            ));
+                        } else {
+                            // The block is in the future, but not too far.
+                            trace!(
+                                log, "Block is slightly ahead of our slot clock, ignoring.";
+                                "present_slot" => present_slot,
+                                "block_slot" => block_slot,
+                                "FUTURE_SLOT_TOLERANCE" => FUTURE_SLOT_TOLERANCE,
+                            );
+                        }
+                    }
+                    BlockProcessingOutcome::WouldRevertFinalizedSlot { .. } => {
+                        trace!(
+                            log, "Finalized or earlier block processed";
+                            "outcome" => format!("{:?}", outcome),
+                        );
+                        // block reached our finalized slot or was earlier, move to the next block
+                    }
+                    BlockProcessingOutcome::GenesisBlock => {
+                        trace!(
+                            log, "Genesis block was processed";
+                            "outcome" => format!("{:?}", outcome),
+                        );
+                    }
+                    _ => {
+                        warn!(
+                            log, "Invalid block received";
+                            "msg" => "peer sent invalid block",
+                            "outcome" => format!("{:?}", outcome),
+                        );
+                        return Err(format!("Invalid block at slot {}", block.slot));
+                    }
+                }
+            } else {
+                warn!(
+                    log, "BlockProcessingFailure";
+                    "msg" => "unexpected condition in processing block.",
+                    "outcome" => format!("{:?}", processing_result)
+                );
+                return Err(format!(
+                    "Unexpected block processing error: {:?}",
+                    processing_result
+                ));
+            }
+        } else {
+            return Ok(()); // terminate early due to dropped beacon chain
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,480 @@
+use super::chain::{ProcessingResult, SyncingChain};
+use crate::sync::simple_sync::{NetworkContext, PeerSyncInfo};
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use eth2_libp2p::rpc::RequestId;
+use eth2_libp2p::PeerId;
+use slog::debug;
+use std::collections::VecDeque;
+use std::sync::Weak;
+use store::Store;
+use types::{BeaconBlock, EthSpec, Hash256, Slot};
+
+/// The maximum number of head chains that are synced in parallel.
+const PARALLEL_HEAD_CHAINS: usize = 2;
+
+/// The maximum number of failed chain targets that are remembered and ignored.
+const MAX_FAILED_CHAINS: usize = 64;
+
+/// The two kinds of chain that are synced.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ChainType {
+    /// A chain that downloads up to a finalized checkpoint that is ahead of ours.
+    Finalized,
+    /// A chain that downloads up to a head, from peers that share our finalized checkpoint.
+    Head,
+}
+
+/// A collection of the competing chains that are being synced.
+///
+/// Peers are grouped into chains by the target they report in their `HelloMessage`. Finalized
+/// chains are always synced before head chains, and only the finalized chain with the most peers
+/// is synced at a time. Once there are no finalized chains remaining, up to
+/// `PARALLEL_HEAD_CHAINS` head chains are synced, again preferring those with the most peers.
+///
+/// Chains that fail validation are removed and their peers penalised. Their targets are
+/// remembered, so that peers reporting the same invalid target do not start a new chain.
+pub struct ChainCollection<T: BeaconChainTypes> {
+    /// The chains that sync to a finalized checkpoint.
+    finalized_chains: Vec<SyncingChain<T>>,
+    /// The chains that sync to a head.
+    head_chains: Vec<SyncingChain<T>>,
+    /// The `(target_root, target_slot)` of recently failed chains, oldest first. Bounded by
+    /// `MAX_FAILED_CHAINS`.
+    failed_chains: VecDeque<(Hash256, Slot)>,
+}
+
+impl<T: BeaconChainTypes> ChainCollection<T> {
+    pub fn new() -> Self {
+        ChainCollection {
+            finalized_chains: Vec::new(),
+            head_chains: Vec::new(),
+            failed_chains: VecDeque::new(),
+        }
+    }
+
+    /// Returns `true` if there are finalized chains to sync. Head chains are not synced until
+    /// all finalized chains are complete.
+    pub fn has_finalized_chains(&self) -> bool {
+        !self.finalized_chains.is_empty()
+    }
+
+    /// Returns `true` if there are no chains to sync.
+    pub fn is_empty(&self) -> bool {
+        self.finalized_chains.is_empty() && self.head_chains.is_empty()
+    }
+
+    /// Returns the chain of the given type with the given target root, if it exists.
+    pub fn get_chain_mut(
+        &mut self,
+        chain_type: ChainType,
+        target_root: Hash256,
+    ) -> Option<&mut SyncingChain<T>> {
+        self.chains_mut(chain_type)
+            .iter_mut()
+            .find(|chain| chain.target_head_root == target_root)
+    }
+
+    /// Returns `true` if a chain to the given target has recently failed.
+    pub fn is_failed_chain(&self, target_root: Hash256, target_slot: Slot) -> bool {
+        self.failed_chains.contains(&(target_root, target_slot))
+    }
+
+    /// Adds a new chain of the given type, syncing from `start_slot` to the target.
+    ///
+    /// The chain is not started, this is done by `update_finalized` or `update_head_chains`. No
+    /// chain is created if a chain to the same target has recently failed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_chain(
+        &mut self,
+        chain_type: ChainType,
+        start_slot: Slot,
+        target_slot: Slot,
+        target_root: Hash256,
+        peer_id: PeerId,
+        beacon_chain: Weak<BeaconChain<T>>,
+        log: &slog::Logger,
+    ) {
+        if self.is_failed_chain(target_root, target_slot) {
+            debug!(log, "Ignoring previously failed chain";
+                "type" => format!("{:?}", chain_type),
+                "target_slot" => target_slot,
+                "target_root" => format!("{}", target_root),
+                "peer" => format!("{:?}", peer_id));
+            return;
+        }
+
+        debug!(log, "New syncing chain";
+            "type" => format!("{:?}", chain_type),
+            "start_slot" => start_slot,
+            "target_slot" => target_slot,
+            "target_root" => format!("{}", target_root),
+            "peer" => format!("{:?}", peer_id));
+
+        let chain = SyncingChain::new(
+            start_slot,
+            target_slot,
+            target_root,
+            peer_id,
+            beacon_chain,
+            log.clone(),
+        );
+        self.chains_mut(chain_type).push(chain);
+    }
+
+    /// Syncs the finalized chain with the most peers, stopping any other finalized chain.
+    ///
+    /// This prevents a minority of peers on a competing chain from dictating which chain we
+    /// sync.
+    pub fn update_finalized(&mut self, network: &mut NetworkContext) {
+        let best_index = match index_with_most_peers(&self.finalized_chains) {
+            Some(index) => index,
+            None => return,
+        };
+
+        for (index, chain) in self.finalized_chains.iter_mut().enumerate() {
+            if index == best_index {
+                chain.start_syncing(network);
+            } else {
+                chain.stop_syncing();
+            }
+        }
+
+        // Head chains are not synced whilst there are finalized chains.
+        for chain in self.head_chains.iter_mut() {
+            chain.stop_syncing();
+        }
+    }
+
+    /// If there are no finalized chains, syncs the `PARALLEL_HEAD_CHAINS` head chains with the
+    /// most peers, stopping any others.
+    pub fn update_head_chains(&mut self, network: &mut NetworkContext) {
+        if !self.finalized_chains.is_empty() {
+            return;
+        }
+
+        self.head_chains
+            .sort_by(|a, b| b.peer_pool.len().cmp(&a.peer_pool.len()));
+
+        for (index, chain) in self.head_chains.iter_mut().enumerate() {
+            if index < PARALLEL_HEAD_CHAINS {
+                chain.start_syncing(network);
+            } else {
+                chain.stop_syncing();
+            }
+        }
+    }
+
+    /// Removes a peer from every chain, removing any chain that is left without peers.
+    pub fn remove_peer(&mut self, network: &mut NetworkContext, peer_id: &PeerId) {
+        for &chain_type in &[ChainType::Finalized, ChainType::Head] {
+            let chains = self.chains_mut(chain_type);
+            let mut index = 0;
+            while index < chains.len() {
+                if chains[index].remove_peer(network, peer_id) == ProcessingResult::RemoveChain {
+                    chains.remove(index);
+                } else {
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    /// Handles a `BeaconBlocks` response.
+    ///
+    /// Returns `None` if the request does not belong to any chain.
+    pub fn on_block_response(
+        &mut self,
+        network: &mut NetworkContext,
+        request_id: RequestId,
+        blocks: Vec<BeaconBlock<T::EthSpec>>,
+    ) -> Option<ProcessingResult> {
+        let (chain_type, index) = self.find_request(request_id)?;
+        let result =
+            self.chains_mut(chain_type)[index].on_block_response(network, request_id, blocks)?;
+        self.handle_result(network, chain_type, index, &result);
+        Some(result)
+    }
+
+    /// Handles a failed `BeaconBlocks` request.
+    ///
+    /// Returns `None` if the request does not belong to any chain.
+    pub fn inject_error(
+        &mut self,
+        network: &mut NetworkContext,
+        request_id: RequestId,
+    ) -> Option<ProcessingResult> {
+        let (chain_type, index) = self.find_request(request_id)?;
+        let result = self.chains_mut(chain_type)[index].inject_error(network, request_id)?;
+        self.handle_result(network, chain_type, index, &result);
+        Some(result)
+    }
+
+    /// Removes chains whose targets we have already reached. The peers of removed chains are sent
+    /// a `Hello` so that they may be assigned to a relevant chain.
+    pub fn purge_outdated_chains(
+        &mut self,
+        network: &mut NetworkContext,
+        beacon_chain: Weak<BeaconChain<T>>,
+        log: &slog::Logger,
+    ) {
+        let chain = match beacon_chain.upgrade() {
+            Some(chain) => chain,
+            None => return,
+        };
+
+        let local_finalized_slot = PeerSyncInfo::from(&chain)
+            .finalized_epoch
+            .start_slot(T::EthSpec::slots_per_epoch());
+
+        for &chain_type in &[ChainType::Finalized, ChainType::Head] {
+            let is_outdated = |syncing_chain: &SyncingChain<T>| {
+                syncing_chain.target_head_slot <= local_finalized_slot
+                    || (chain_type == ChainType::Head
+                        && chain
+                            .store
+                            .exists::<BeaconBlock<T::EthSpec>>(&syncing_chain.target_head_root)
+                            .unwrap_or_else(|_| false))
+            };
+
+            let chains = self.chains_mut(chain_type);
+            let (outdated, current): (Vec<_>, Vec<_>) = chains.drain(..).partition(is_outdated);
+            *chains = current;
+
+            for syncing_chain in outdated {
+                debug!(log, "Purging outdated chain";
+                    "type" => format!("{:?}", chain_type),
+                    "target_slot" => syncing_chain.target_head_slot,
+                    "peers" => syncing_chain.peer_pool.len());
+                for peer_id in syncing_chain.peer_pool {
+                    network.hello_peer(beacon_chain.clone(), peer_id);
+                }
+            }
+        }
+    }
+
+    /// Returns the type and index of the chain that made the request.
+    fn find_request(&self, request_id: RequestId) -> Option<(ChainType, usize)> {
+        self.finalized_chains
+            .iter()
+            .position(|chain| chain.has_request(request_id))
+            .map(|index| (ChainType::Finalized, index))
+            .or_else(|| {
+                self.head_chains
+                    .iter()
+                    .position(|chain| chain.has_request(request_id))
+                    .map(|index| (ChainType::Head, index))
+            })
+    }
+
+    /// Removes the chain at `index` if it has completed or failed.
+    ///
+    /// The peers of a failed chain are downvoted and its target is remembered, evicting the
+    /// oldest failed target once `MAX_FAILED_CHAINS` are stored.
+    fn handle_result(
+        &mut self,
+        network: &mut NetworkContext,
+        chain_type: ChainType,
+        index: usize,
+        result: &ProcessingResult,
+    ) {
+        match result {
+            ProcessingResult::KeepChain => {}
+            ProcessingResult::RemoveChain => {
+                self.chains_mut(chain_type).remove(index);
+            }
+            ProcessingResult::FailedChain => {
+                let chain = self.chains_mut(chain_type).remove(index);
+                for peer_id in chain.peer_pool {
+                    network.downvote_peer(peer_id);
+                }
+
+                if self.failed_chains.len() >= MAX_FAILED_CHAINS {
+                    self.failed_chains.pop_front();
+                }
+                self.failed_chains
+                    .push_back((chain.target_head_root, chain.target_head_slot));
+            }
+        }
+    }
+
+    fn chains_mut(&mut self, chain_type: ChainType) -> &mut Vec<SyncingChain<T>> {
+        match chain_type {
+            ChainType::Finalized => &mut self.finalized_chains,
+            ChainType::Head => &mut self.head_chains,
+        }
+    }
+}
+
+/// Returns the index of the chain with the most peers.
+fn index_with_most_peers<T: BeaconChainTypes>(chains: &[SyncingChain<T>]) -> Option<usize> {
+    chains
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, chain)| chain.peer_pool.len())
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::chain::ChainSyncingState;
+    use super::*;
+    use crate::service::NetworkMessage;
+    use beacon_chain::test_utils::CommonTypes;
+    use eth2_libp2p::rpc::{RPCEvent, RPCRequest};
+    use futures::Stream;
+    use lmd_ghost::ThreadSafeReducedTree;
+    use slog::{o, Discard, Logger};
+    use store::MemoryStore;
+    use tokio::sync::mpsc;
+    use types::MinimalEthSpec;
+
+    /// The maximum number of requests failed before a chain is expected to have failed.
+    const MAX_ATTEMPTS: RequestId = 16;
+
+    type TestTypes =
+        CommonTypes<ThreadSafeReducedTree<MemoryStore, MinimalEthSpec>, MinimalEthSpec>;
+
+    fn log() -> Logger {
+        Logger::root(Discard, o!())
+    }
+
+    fn network() -> (NetworkContext, mpsc::UnboundedReceiver<NetworkMessage>) {
+        let (network_send, network_recv) = mpsc::unbounded_channel();
+        (NetworkContext::new(network_send, log()), network_recv)
+    }
+
+    /// Adds a chain to `target_root` with `peer_count` peers.
+    fn add_chain(
+        chains: &mut ChainCollection<TestTypes>,
+        network: &mut NetworkContext,
+        chain_type: ChainType,
+        target_root: Hash256,
+        peer_count: usize,
+    ) {
+        chains.new_chain(
+            chain_type,
+            Slot::new(0),
+            Slot::new(64),
+            target_root,
+            PeerId::random(),
+            Weak::new(),
+            &log(),
+        );
+        let chain = chains
+            .get_chain_mut(chain_type, target_root)
+            .expect("chain should exist");
+        for _ in 1..peer_count {
+            chain.add_peer(network, PeerId::random());
+        }
+    }
+
+    fn is_syncing(
+        chains: &mut ChainCollection<TestTypes>,
+        chain_type: ChainType,
+        target_root: Hash256,
+    ) -> bool {
+        chains
+            .get_chain_mut(chain_type, target_root)
+            .expect("chain should exist")
+            .state
+            == ChainSyncingState::Syncing
+    }
+
+    #[test]
+    fn finalized_chain_with_most_peers_is_synced() {
+        let (mut network, _network_recv) = network();
+        let mut chains = ChainCollection::new();
+        let (minority, majority) = (Hash256::from_low_u64_be(1), Hash256::from_low_u64_be(2));
+
+        add_chain(&mut chains, &mut network, ChainType::Finalized, minority, 1);
+        add_chain(&mut chains, &mut network, ChainType::Finalized, majority, 3);
+        add_chain(
+            &mut chains,
+            &mut network,
+            ChainType::Head,
+            Hash256::zero(),
+            5,
+        );
+        chains.update_finalized(&mut network);
+        chains.update_head_chains(&mut network);
+
+        assert!(is_syncing(&mut chains, ChainType::Finalized, majority));
+        assert!(!is_syncing(&mut chains, ChainType::Finalized, minority));
+        assert!(
+            !is_syncing(&mut chains, ChainType::Head, Hash256::zero()),
+            "head chains should wait for finalized chains"
+        );
+    }
+
+    #[test]
+    fn head_chains_with_most_peers_are_synced() {
+        let (mut network, _network_recv) = network();
+        let mut chains = ChainCollection::new();
+        let roots: Vec<Hash256> = (1..=3).map(Hash256::from_low_u64_be).collect();
+
+        for (index, root) in roots.iter().enumerate() {
+            add_chain(&mut chains, &mut network, ChainType::Head, *root, index + 1);
+        }
+        chains.update_head_chains(&mut network);
+
+        assert!(!is_syncing(&mut chains, ChainType::Head, roots[0]));
+        assert!(is_syncing(&mut chains, ChainType::Head, roots[1]));
+        assert!(is_syncing(&mut chains, ChainType::Head, roots[2]));
+    }
+
+    #[test]
+    fn failed_chains_are_removed_and_ignored() {
+        let (mut network, network_recv) = network();
+        let mut chains = ChainCollection::new();
+        let target_root = Hash256::from_low_u64_be(1);
+
+        add_chain(
+            &mut chains,
+            &mut network,
+            ChainType::Finalized,
+            target_root,
+            1,
+        );
+        let peer_id = chains
+            .get_chain_mut(ChainType::Finalized, target_root)
+            .and_then(|chain| chain.peer_pool.iter().next().cloned())
+            .expect("chain should have a peer");
+        chains.update_finalized(&mut network);
+
+        // Each failed request is re-requested with the next request id, until the batch has
+        // failed too many times.
+        let result = (1..=MAX_ATTEMPTS)
+            .filter_map(|request_id| chains.inject_error(&mut network, request_id))
+            .find(|result| *result != ProcessingResult::KeepChain)
+            .expect("chain should be removed within MAX_ATTEMPTS requests");
+        assert_eq!(result, ProcessingResult::FailedChain);
+        assert!(chains.is_empty());
+        assert!(chains.is_failed_chain(target_root, Slot::new(64)));
+
+        chains.new_chain(
+            ChainType::Finalized,
+            Slot::new(0),
+            Slot::new(64),
+            target_root,
+            PeerId::random(),
+            Weak::new(),
+            &log(),
+        );
+        assert!(
+            !chains.has_finalized_chains(),
+            "a chain to a failed target should not be created"
+        );
+
+        drop(network);
+        let downvoted = network_recv
+            .wait()
+            .filter_map(Result::ok)
+            .any(|message| match message {
+                NetworkMessage::RPC(peer, RPCEvent::Request(_, RPCRequest::Goodbye(_))) => {
+                    peer == peer_id
+                }
+                _ => false,
+            });
+        assert!(downvoted, "peers of a failed chain should be downvoted");
+    }
+}
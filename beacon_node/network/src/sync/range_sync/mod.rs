@@ -0,0 +1,12 @@
+//! Long-range (batch) syncing.
+//!
+//! Used when the local node is far behind its peers. The range of blocks to download is split
+//! into fixed-size batches which are downloaded in parallel from a pool of peers and processed in
+//! order.
+
+mod batch;
+mod chain;
+mod chain_collection;
+mod range;
+
+pub use range::RangeSync;
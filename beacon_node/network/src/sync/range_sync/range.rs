@@ -0,0 +1,192 @@
+use super::chain::ProcessingResult;
+use super::chain_collection::{ChainCollection, ChainType};
+use crate::sync::simple_sync::{NetworkContext, PeerSyncInfo};
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use eth2_libp2p::rpc::RequestId;
+use eth2_libp2p::PeerId;
+use slog::{debug, trace};
+use std::collections::HashSet;
+use std::sync::Weak;
+use types::{BeaconBlock, EthSpec};
+
+/// Manages the long-range (batch) syncing of the beacon chain.
+///
+/// Peers may report different finalized checkpoints or heads, so peers are grouped into competing
+/// `SyncingChain`s by their sync target:
+///
+/// - Peers with a finalized epoch later than ours are added to a finalized chain, which syncs up
+///   to their finalized checkpoint.
+/// - Peers that share our finalized epoch but have a later head are added to a head chain, which
+///   syncs up to their head.
+///
+/// Finalized chains are synced before head chains. Chains with the most peers are prioritised, so
+/// that a minority of peers on a fork cannot hijack sync. Chains that fail validation are dropped,
+/// their peers are penalised and their targets are ignored.
+pub struct RangeSync<T: BeaconChainTypes> {
+    /// A weak reference to the underlying beacon chain.
+    beacon_chain: Weak<BeaconChain<T>>,
+    /// The competing chains being synced.
+    chains: ChainCollection<T>,
+    /// Peers that are ahead of our head but share our finalized checkpoint, that have connected
+    /// while a finalized chain is syncing. These peers are sent a `Hello` once the finalized
+    /// chains are complete, as our finalized checkpoint may have changed.
+    awaiting_head_peers: HashSet<PeerId>,
+    /// The logger for the range sync.
+    log: slog::Logger,
+}
+
+impl<T: BeaconChainTypes> RangeSync<T> {
+    pub fn new(beacon_chain: Weak<BeaconChain<T>>, log: slog::Logger) -> Self {
+        RangeSync {
+            beacon_chain,
+            chains: ChainCollection::new(),
+            awaiting_head_peers: HashSet::new(),
+            log,
+        }
+    }
+
+    /// Returns `true` if any chain is being synced.
+    pub fn is_syncing(&self) -> bool {
+        !self.chains.is_empty()
+    }
+
+    /// A peer with a head sufficiently ahead of ours has connected.
+    ///
+    /// The peer is added to the finalized or head chain that matches its sync target, creating
+    /// a new chain if none exists.
+    pub fn add_peer(
+        &mut self,
+        network: &mut NetworkContext,
+        peer_id: PeerId,
+        remote: PeerSyncInfo,
+    ) {
+        let chain = match self.beacon_chain.upgrade() {
+            Some(chain) => chain,
+            None => {
+                trace!(
+                    self.log,
+                    "Chain dropped. Peer not considered for range sync"
+                );
+                return;
+            }
+        };
+
+        let local = PeerSyncInfo::from(&chain);
+        let local_finalized_slot = local
+            .finalized_epoch
+            .start_slot(T::EthSpec::slots_per_epoch());
+
+        // Remove the peer from any chain it was previously assigned to, its target may have
+        // changed.
+        self.awaiting_head_peers.remove(&peer_id);
+        self.chains.remove_peer(network, &peer_id);
+
+        if remote.finalized_epoch > local.finalized_epoch {
+            let target_slot = remote
+                .finalized_epoch
+                .start_slot(T::EthSpec::slots_per_epoch());
+
+            match self
+                .chains
+                .get_chain_mut(ChainType::Finalized, remote.finalized_root)
+            {
+                Some(syncing_chain) => {
+                    debug!(self.log, "Adding peer to finalized chain";
+                        "peer" => format!("{:?}", peer_id),
+                        "target_slot" => syncing_chain.target_head_slot,
+                        "peers" => syncing_chain.peer_pool.len() + 1);
+                    syncing_chain.add_peer(network, peer_id);
+                }
+                None => self.chains.new_chain(
+                    ChainType::Finalized,
+                    local_finalized_slot,
+                    target_slot,
+                    remote.finalized_root,
+                    peer_id,
+                    self.beacon_chain.clone(),
+                    &self.log,
+                ),
+            }
+        } else if self.chains.has_finalized_chains() {
+            // Our finalized checkpoint may change once the finalized chains are synced, decide
+            // which head chain this peer belongs to once they are complete.
+            debug!(self.log, "Waiting for finalized sync to complete";
+                "peer" => format!("{:?}", peer_id));
+            self.awaiting_head_peers.insert(peer_id);
+        } else {
+            match self.chains.get_chain_mut(ChainType::Head, remote.head_root) {
+                Some(syncing_chain) => {
+                    debug!(self.log, "Adding peer to head chain";
+                        "peer" => format!("{:?}", peer_id),
+                        "target_slot" => syncing_chain.target_head_slot,
+                        "peers" => syncing_chain.peer_pool.len() + 1);
+                    syncing_chain.add_peer(network, peer_id);
+                }
+                None => self.chains.new_chain(
+                    ChainType::Head,
+                    local_finalized_slot,
+                    remote.head_slot,
+                    remote.head_root,
+                    peer_id,
+                    self.beacon_chain.clone(),
+                    &self.log,
+                ),
+            }
+        }
+
+        self.update_chains(network);
+    }
+
+    /// A `BeaconBlocks` response has been received.
+    pub fn blocks_response(
+        &mut self,
+        network: &mut NetworkContext,
+        peer_id: PeerId,
+        request_id: RequestId,
+        blocks: Vec<BeaconBlock<T::EthSpec>>,
+    ) {
+        match self.chains.on_block_response(network, request_id, blocks) {
+            Some(ProcessingResult::RemoveChain) | Some(ProcessingResult::FailedChain) => {
+                self.update_chains(network)
+            }
+            Some(ProcessingResult::KeepChain) => {}
+            None => debug!(self.log, "BeaconBlocks response unknown";
+                "peer" => format!("{:?}", peer_id),
+                "request_id" => request_id),
+        }
+    }
+
+    /// A `BeaconBlocks` request has failed. Requests that are not part of range sync are ignored.
+    pub fn inject_error(&mut self, network: &mut NetworkContext, request_id: RequestId) {
+        match self.chains.inject_error(network, request_id) {
+            Some(ProcessingResult::RemoveChain) | Some(ProcessingResult::FailedChain) => {
+                self.update_chains(network)
+            }
+            Some(ProcessingResult::KeepChain) | None => {}
+        }
+    }
+
+    /// A peer has disconnected.
+    pub fn remove_peer(&mut self, network: &mut NetworkContext, peer_id: &PeerId) {
+        self.awaiting_head_peers.remove(peer_id);
+        self.chains.remove_peer(network, peer_id);
+        self.update_chains(network);
+    }
+
+    /// Removes chains that are no longer relevant and selects which chains are synced.
+    ///
+    /// Once there are no finalized chains remaining, any peers awaiting the completion of
+    /// finalized sync are sent a `Hello` so they can be assigned to a head chain.
+    fn update_chains(&mut self, network: &mut NetworkContext) {
+        self.chains
+            .purge_outdated_chains(network, self.beacon_chain.clone(), &self.log);
+        self.chains.update_finalized(network);
+
+        if !self.chains.has_finalized_chains() {
+            for peer_id in self.awaiting_head_peers.drain() {
+                network.hello_peer(self.beacon_chain.clone(), peer_id);
+            }
+            self.chains.update_head_chains(network);
+        }
+    }
+}
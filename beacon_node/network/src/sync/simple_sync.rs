@@ -1,3 +1,4 @@
+use super::future_blocks::{self, FutureBlock};
 use super::manager::SyncMessage;
 use crate::service::NetworkMessage;
 use beacon_chain::{
@@ -5,17 +6,17 @@ use beacon_chain::{
 };
 use eth2_libp2p::rpc::methods::*;
 use eth2_libp2p::rpc::{RPCEvent, RPCRequest, RPCResponse, RequestId};
-use eth2_libp2p::PeerId;
+use eth2_libp2p::{
+    PeerId, PubsubMessage, SubnetId, Topic, BEACON_AGGREGATE_AND_PROOF_TOPIC,
+    TOPIC_ENCODING_POSTFIX, TOPIC_PREFIX,
+};
 use slog::{debug, error, info, o, trace, warn};
 use ssz::Encode;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use store::Store;
 use tokio::sync::{mpsc, oneshot};
 use tree_hash::SignedRoot;
-use types::{Attestation, BeaconBlock, Epoch, EthSpec, Hash256, Slot};
-
-//TODO: Put a maximum limit on the number of block that can be requested.
-//TODO: Rate limit requests
+use types::{AggregateAndProof, Attestation, BeaconBlock, Epoch, EthSpec, Hash256, Slot};
 
 /// If a block is more than `FUTURE_SLOT_TOLERANCE` slots ahead of our slot clock, we drop it.
 /// Otherwise we queue it.
@@ -24,6 +25,12 @@ pub(crate) const FUTURE_SLOT_TOLERANCE: u64 = 1;
 const SHOULD_FORWARD_GOSSIP_BLOCK: bool = true;
 const SHOULD_NOT_FORWARD_GOSSIP_BLOCK: bool = false;
 
+const SHOULD_FORWARD_GOSSIP_ATTESTATION: bool = true;
+const SHOULD_NOT_FORWARD_GOSSIP_ATTESTATION: bool = false;
+
+const SHOULD_FORWARD_GOSSIP_AGGREGATE: bool = true;
+const SHOULD_NOT_FORWARD_GOSSIP_AGGREGATE: bool = false;
+
 /// Keeps track of syncing information for known connected peers.
 #[derive(Clone, Copy, Debug)]
 pub struct PeerSyncInfo {
@@ -61,6 +68,10 @@ pub struct MessageProcessor<T: BeaconChainTypes> {
     sync_send: mpsc::UnboundedSender<SyncMessage<T::EthSpec>>,
     /// A oneshot channel for destroying the sync thread.
     _sync_exit: oneshot::Sender<()>,
+    /// A channel to the task holding gossip blocks that arrived before their slot.
+    future_block_send: mpsc::UnboundedSender<FutureBlock<T::EthSpec>>,
+    /// A oneshot channel for destroying the future block task.
+    _future_block_exit: oneshot::Sender<()>,
     /// A nextwork context to return and handle RPC requests.
     network: NetworkContext,
     /// The `RPCHandler` logger.
@@ -86,10 +97,20 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
             sync_logger,
         );
 
+        // spawn the task holding early gossip blocks
+        let (future_block_send, _future_block_exit) = future_blocks::spawn(
+            executor,
+            Arc::downgrade(&beacon_chain),
+            sync_send.clone(),
+            log.new(o!("Service"=> "Future Blocks")),
+        );
+
         MessageProcessor {
             chain: beacon_chain,
             sync_send,
             _sync_exit,
+            future_block_send,
+            _future_block_exit,
             network: NetworkContext::new(network_send, log.clone()),
             log: log.clone(),
         }
@@ -362,6 +383,13 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
         });
     }
 
+    /// Handle a failed RPC request, or an error response from a peer.
+    ///
+    /// Informs the sync manager so that the request can be retried.
+    pub fn on_rpc_error(&mut self, peer_id: PeerId, request_id: RequestId) {
+        self.send_to_sync(SyncMessage::RPCError(peer_id, request_id));
+    }
+
     /// Handle a `RecentBeaconBlocks` response from the peer.
     pub fn on_recent_beacon_blocks_response(
         &mut self,
@@ -407,7 +435,21 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
                     present_slot,
                     block_slot,
                 } if present_slot + FUTURE_SLOT_TOLERANCE >= block_slot => {
-                    //TODO: Decide the logic here
+                    // The block is slightly early, likely due to clock skew. Queue it to be
+                    // processed once our slot clock reaches its slot.
+                    trace!(self.log, "Block from future slot received";
+                            "peer_id" => format!("{:?}",peer_id),
+                            "present_slot" => present_slot,
+                            "block_slot" => block_slot);
+                    self.future_block_send
+                        .try_send(FutureBlock {
+                            peer_id,
+                            block,
+                            present_slot,
+                        })
+                        .unwrap_or_else(|_| {
+                            warn!(self.log, "Could not send block to the future block queue")
+                        });
                     SHOULD_FORWARD_GOSSIP_BLOCK
                 }
                 BlockProcessingOutcome::BlockIsAlreadyKnown => SHOULD_FORWARD_GOSSIP_BLOCK,
@@ -446,9 +488,24 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
 
     /// Process a gossip message declaring a new attestation.
     ///
-    /// Not currently implemented.
-    pub fn on_attestation_gossip(&mut self, _peer_id: PeerId, msg: Attestation<T::EthSpec>) {
+    /// Attestations that vote for an unknown block are queued by the sync manager, which looks up
+    /// the block and processes the attestation again once the block is imported.
+    ///
+    /// Returns a `bool` which, if `true`, indicates we should forward the attestation to our
+    /// peers. Only valid attestations are forwarded. Queued attestations are not forwarded, the
+    /// sync manager publishes them if they are valid once their block is imported.
+    pub fn on_attestation_gossip(&mut self, peer_id: PeerId, msg: Attestation<T::EthSpec>) -> bool {
         match self.chain.process_attestation(msg.clone()) {
+            Ok(AttestationProcessingOutcome::UnknownHeadBlock { beacon_block_root }) => {
+                trace!(
+                    self.log,
+                    "Attestation for unknown block received";
+                    "peer_id" => format!("{:?}", peer_id),
+                    "block" => format!("{}", beacon_block_root)
+                );
+                self.send_to_sync(SyncMessage::UnknownAttestationBlock(peer_id, msg));
+                SHOULD_NOT_FORWARD_GOSSIP_ATTESTATION
+            }
             Ok(outcome) => {
                 info!(
                     self.log,
@@ -457,12 +514,15 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
                     "outcome" => format!("{:?}", outcome)
                 );
 
-                if outcome != AttestationProcessingOutcome::Processed {
+                if outcome == AttestationProcessingOutcome::Processed {
+                    SHOULD_FORWARD_GOSSIP_ATTESTATION
+                } else {
                     trace!(
                         self.log,
                         "Invalid gossip attestation ssz";
                         "ssz" => format!("0x{}", hex::encode(msg.as_ssz_bytes())),
                     );
+                    SHOULD_NOT_FORWARD_GOSSIP_ATTESTATION
                 }
             }
             Err(e) => {
@@ -472,6 +532,60 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
                     "ssz" => format!("0x{}", hex::encode(msg.as_ssz_bytes())),
                 );
                 error!(self.log, "Invalid gossip attestation"; "error" => format!("{:?}", e));
+                SHOULD_NOT_FORWARD_GOSSIP_ATTESTATION
+            }
+        }
+    }
+
+    /// Verifies the aggregator of an `AggregateAndProof` and applies its aggregate to the beacon
+    /// chain.
+    ///
+    /// Returns a `bool` which, if `true`, indicates we should forward the aggregate to our peers.
+    /// Only aggregates from valid aggregators are forwarded.
+    pub fn on_aggregate_and_proof_gossip(
+        &mut self,
+        peer_id: PeerId,
+        msg: AggregateAndProof<T::EthSpec>,
+    ) -> bool {
+        match self.chain.process_aggregate_and_proof(msg.clone()) {
+            Ok(AttestationProcessingOutcome::Processed) => {
+                trace!(
+                    self.log,
+                    "Processed aggregate";
+                    "peer_id" => format!("{:?}", peer_id),
+                    "aggregator_index" => msg.aggregator_index,
+                );
+                SHOULD_FORWARD_GOSSIP_AGGREGATE
+            }
+            Ok(AttestationProcessingOutcome::UnknownHeadBlock { beacon_block_root }) => {
+                // The aggregator has been verified. The whole message is queued so that the
+                // aggregate is replayed, and republished, along with the proof of its aggregator.
+                trace!(
+                    self.log,
+                    "Aggregate for unknown block received";
+                    "peer_id" => format!("{:?}", peer_id),
+                    "block" => format!("{}", beacon_block_root)
+                );
+                self.send_to_sync(SyncMessage::UnknownAggregateBlock(peer_id, msg));
+                SHOULD_FORWARD_GOSSIP_AGGREGATE
+            }
+            Ok(outcome) => {
+                debug!(
+                    self.log,
+                    "Invalid gossip aggregate";
+                    "peer_id" => format!("{:?}", peer_id),
+                    "outcome" => format!("{:?}", outcome)
+                );
+                trace!(
+                    self.log,
+                    "Invalid gossip aggregate ssz";
+                    "ssz" => format!("0x{}", hex::encode(msg.as_ssz_bytes())),
+                );
+                SHOULD_NOT_FORWARD_GOSSIP_AGGREGATE
+            }
+            Err(e) => {
+                error!(self.log, "Erroneous gossip aggregate"; "error" => format!("{:?}", e));
+                SHOULD_NOT_FORWARD_GOSSIP_AGGREGATE
             }
         }
     }
@@ -494,13 +608,120 @@ pub(crate) fn hello_message<T: BeaconChainTypes>(beacon_chain: &BeaconChain<T>)
 pub struct NetworkContext {
     /// The network channel to relay messages to the Network service.
     network_send: mpsc::UnboundedSender<NetworkMessage>,
+    /// The next request id to assign to an outbound request. Request id's are an internal
+    /// accounting mechanism and are never sent to any peers. The id `0` is reserved for requests
+    /// that do not expect a tracked response.
+    request_id: RequestId,
     /// Logger for the `NetworkContext`.
     log: slog::Logger,
 }
 
 impl NetworkContext {
     pub fn new(network_send: mpsc::UnboundedSender<NetworkMessage>, log: slog::Logger) -> Self {
-        Self { network_send, log }
+        Self {
+            network_send,
+            request_id: 1,
+            log,
+        }
+    }
+
+    /// Sends a `Hello` request to the peer, if the beacon chain still exists.
+    pub fn hello_peer<T: BeaconChainTypes>(
+        &mut self,
+        chain: Weak<BeaconChain<T>>,
+        peer_id: PeerId,
+    ) {
+        if let Some(chain) = chain.upgrade() {
+            trace!(
+                self.log,
+                "RPC Request";
+                "method" => "HELLO",
+                "peer" => format!("{:?}", peer_id)
+            );
+            self.send_rpc_request(None, peer_id, RPCRequest::Hello(hello_message(&chain)));
+        }
+    }
+
+    /// Sends a `BeaconBlocks` request to the peer and returns the id assigned to the request.
+    pub fn beacon_blocks_request(
+        &mut self,
+        peer_id: PeerId,
+        request: BeaconBlocksRequest,
+    ) -> RequestId {
+        trace!(
+            self.log,
+            "RPC Request";
+            "method" => "BeaconBlocks",
+            "start_slot" => request.start_slot,
+            "count" => request.count,
+            "peer" => format!("{:?}", peer_id)
+        );
+        self.send_tracked_rpc_request(peer_id, RPCRequest::BeaconBlocks(request))
+    }
+
+    /// Sends a `RecentBeaconBlocks` request to the peer and returns the id assigned to the
+    /// request.
+    pub fn recent_beacon_blocks_request(
+        &mut self,
+        peer_id: PeerId,
+        request: RecentBeaconBlocksRequest,
+    ) -> RequestId {
+        trace!(
+            self.log,
+            "RPC Request";
+            "method" => "RecentBeaconBlocks",
+            "count" => request.block_roots.len(),
+            "peer" => format!("{:?}", peer_id)
+        );
+        self.send_tracked_rpc_request(peer_id, RPCRequest::RecentBeaconBlocks(request))
+    }
+
+    /// Publishes an attestation on the gossipsub topic of its subnet.
+    pub fn publish_attestation<E: EthSpec>(&mut self, attestation: &Attestation<E>) {
+        let topic = SubnetId::from_shard(attestation.data.crosslink.shard).topic();
+        let message = PubsubMessage::Attestation(attestation.as_ssz_bytes());
+
+        self.network_send
+            .try_send(NetworkMessage::Publish {
+                topics: vec![topic],
+                message,
+            })
+            .unwrap_or_else(|_| {
+                warn!(
+                    self.log,
+                    "Could not send attestation to the network service"
+                )
+            });
+    }
+
+    /// Publishes an aggregate, along with the proof of its aggregator, on the aggregate topic.
+    pub fn publish_aggregate_and_proof<E: EthSpec>(
+        &mut self,
+        aggregate_and_proof: &AggregateAndProof<E>,
+    ) {
+        let topic = Topic::new(format!(
+            "/{}/{}/{}",
+            TOPIC_PREFIX, BEACON_AGGREGATE_AND_PROOF_TOPIC, TOPIC_ENCODING_POSTFIX
+        ));
+        let message = PubsubMessage::AggregateAndProof(aggregate_and_proof.as_ssz_bytes());
+
+        self.network_send
+            .try_send(NetworkMessage::Publish {
+                topics: vec![topic],
+                message,
+            })
+            .unwrap_or_else(|_| warn!(self.log, "Could not send aggregate to the network service"));
+    }
+
+    /// Disconnects a peer that has behaved badly.
+    pub fn downvote_peer(&mut self, peer_id: PeerId) {
+        trace!(
+            self.log,
+            "Peer downvoted";
+            "peer" => format!("{:?}", peer_id)
+        );
+        // TODO: Implement reputation
+        self.disconnect(peer_id, GoodbyeReason::Fault);
     }
 
     pub fn disconnect(&mut self, peer_id: PeerId, reason: GoodbyeReason) {
@@ -525,6 +746,15 @@ impl NetworkContext {
         self.send_rpc_event(peer_id, RPCEvent::Request(request_id, rpc_request));
     }
 
+    /// Sends a request with a newly assigned request id, so that the response can be matched to
+    /// the request.
+    fn send_tracked_rpc_request(&mut self, peer_id: PeerId, rpc_request: RPCRequest) -> RequestId {
+        let request_id = self.request_id;
+        self.request_id += 1;
+        self.send_rpc_request(Some(request_id), peer_id, rpc_request);
+        request_id
+    }
+
     //TODO: Handle Error responses
     pub fn send_rpc_response(
         &mut self,
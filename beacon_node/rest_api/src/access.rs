@@ -0,0 +1,314 @@
+use crate::config::Config;
+use crate::ApiError;
+use hyper::header::{self, HeaderValue};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use std::fs;
+use std::path::Path;
+
+/// The methods permitted by cross-origin requests.
+const READ_METHODS: &str = "GET, OPTIONS";
+const ALL_METHODS: &str = "GET, POST, DELETE, OPTIONS";
+/// The request headers permitted by cross-origin requests.
+const ALLOWED_HEADERS: &str = "authorization, content-type, accept";
+/// The number of seconds a browser may cache the response to a preflight request.
+const PREFLIGHT_MAX_AGE: &str = "600";
+
+/// The endpoints a bearer token may access.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    /// Endpoints which do not modify the node, i.e., `GET` requests.
+    Read,
+    /// All endpoints, including those which publish to the network.
+    Write,
+}
+
+/// Decides whether each request is permitted by the authentication, write and CORS settings of
+/// the `Config`.
+#[derive(Debug, Clone)]
+pub struct AccessControl {
+    /// Empty if authentication is disabled.
+    tokens: Vec<(String, Scope)>,
+    allow_writes: bool,
+    cors_allowed_origins: Vec<String>,
+}
+
+impl AccessControl {
+    /// Builds the access control for `config`, reading the token files it references.
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut tokens = vec![];
+
+        if let Some(path) = &config.read_tokens_file {
+            tokens.extend(read_tokens(path)?.into_iter().map(|t| (t, Scope::Read)));
+        }
+
+        if let Some(path) = &config.write_tokens_file {
+            tokens.extend(read_tokens(path)?.into_iter().map(|t| (t, Scope::Write)));
+        }
+
+        if (config.read_tokens_file.is_some() || config.write_tokens_file.is_some())
+            && tokens.is_empty()
+        {
+            return Err("API token files are configured but contain no tokens".to_string());
+        }
+
+        Ok(Self {
+            tokens,
+            allow_writes: config.allow_writes,
+            cors_allowed_origins: config.cors_allowed_origins.clone(),
+        })
+    }
+
+    /// Returns `true` if requests must present a bearer token.
+    pub fn requires_authentication(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// Returns `Ok(())` if `req` may be routed to its handler.
+    ///
+    /// Requests without a valid token are `Unauthorized`, whilst requests which need the write
+    /// scope are `Forbidden` if writes are disabled or the token only has the read scope.
+    pub fn authorize(&self, req: &Request<Body>) -> Result<(), ApiError> {
+        let is_write = is_write(req.method());
+
+        if is_write && !self.allow_writes {
+            return Err(ApiError::Forbidden(
+                "Write endpoints are disabled on this node".to_string(),
+            ));
+        }
+
+        if !self.requires_authentication() {
+            return Ok(());
+        }
+
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                let mut parts = value.splitn(2, ' ');
+                match (parts.next(), parts.next()) {
+                    (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("bearer") => {
+                        Some(token.trim())
+                    }
+                    _ => None,
+                }
+            })
+            .ok_or_else(|| ApiError::Unauthorized("A bearer token is required".to_string()))?;
+
+        match self.scope_of(token) {
+            Some(Scope::Write) => Ok(()),
+            Some(Scope::Read) if !is_write => Ok(()),
+            Some(Scope::Read) => Err(ApiError::Forbidden(
+                "The bearer token does not permit writes".to_string(),
+            )),
+            None => Err(ApiError::Unauthorized("Unknown bearer token".to_string())),
+        }
+    }
+
+    /// Returns the widest scope granted to `token`, if any.
+    ///
+    /// Compares `token` against every known token in constant time, so the response time does
+    /// not reveal how much of a token was guessed correctly.
+    fn scope_of(&self, token: &str) -> Option<Scope> {
+        self.tokens
+            .iter()
+            .fold(None, |scope, (known, known_scope)| {
+                if constant_time_eq(known.as_bytes(), token.as_bytes()) {
+                    match scope {
+                        Some(Scope::Write) => scope,
+                        _ => Some(*known_scope),
+                    }
+                } else {
+                    scope
+                }
+            })
+    }
+
+    /// Returns the value of the `Access-Control-Allow-Origin` header for a request from `origin`,
+    /// or `None` if the origin is not permitted.
+    fn allowed_origin(&self, origin: Option<&HeaderValue>) -> Option<HeaderValue> {
+        let origin = origin?;
+
+        if self.cors_allowed_origins.iter().any(|o| o == "*") {
+            Some(HeaderValue::from_static("*"))
+        } else if self
+            .cors_allowed_origins
+            .iter()
+            .any(|o| o.as_bytes() == origin.as_bytes())
+        {
+            Some(origin.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Adds the CORS headers to `response`, if the request was from a permitted `origin`.
+    pub fn add_cors_headers(&self, origin: Option<&HeaderValue>, response: &mut Response<Body>) {
+        if let Some(allowed_origin) = self.allowed_origin(origin) {
+            let headers = response.headers_mut();
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allowed_origin);
+            headers.insert(header::VARY, HeaderValue::from_static("origin"));
+        }
+    }
+
+    /// Returns the response to a CORS preflight (`OPTIONS`) request from `origin`.
+    pub fn preflight_response(
+        &self,
+        origin: Option<&HeaderValue>,
+    ) -> Result<Response<Body>, ApiError> {
+        if self.allowed_origin(origin).is_none() {
+            return Err(ApiError::Forbidden(
+                "Cross-origin requests are not permitted from this origin".to_string(),
+            ));
+        }
+
+        let methods = if self.allow_writes {
+            ALL_METHODS
+        } else {
+            READ_METHODS
+        };
+
+        let mut response = Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .header(header::ACCESS_CONTROL_ALLOW_METHODS, methods)
+            .header(header::ACCESS_CONTROL_ALLOW_HEADERS, ALLOWED_HEADERS)
+            .header(header::ACCESS_CONTROL_MAX_AGE, PREFLIGHT_MAX_AGE)
+            .body(Body::empty())
+            .map_err(|e| ApiError::ServerError(format!("Unable to build response: {:?}", e)))?;
+
+        self.add_cors_headers(origin, &mut response);
+
+        Ok(response)
+    }
+}
+
+/// Returns `true` if a request with `method` may modify the node or publish to the network.
+fn is_write(method: &Method) -> bool {
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => false,
+        _ => true,
+    }
+}
+
+/// Reads the tokens in the file at `path`, one per line. Empty lines and lines starting with `#`
+/// are ignored.
+fn read_tokens(path: &Path) -> Result<Vec<String>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Unable to read API tokens from {:?}: {}", path, e))?;
+
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access_control(allow_writes: bool) -> AccessControl {
+        AccessControl {
+            tokens: vec![
+                ("reader".to_string(), Scope::Read),
+                ("writer".to_string(), Scope::Write),
+            ],
+            allow_writes,
+            cors_allowed_origins: vec!["https://dashboard.example.com".to_string()],
+        }
+    }
+
+    fn request(method: Method, token: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder();
+        builder.method(method).uri("/beacon/head");
+        if let Some(token) = token {
+            builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn token_scopes() {
+        let access = access_control(true);
+
+        assert!(access
+            .authorize(&request(Method::GET, Some("reader")))
+            .is_ok());
+        assert!(access
+            .authorize(&request(Method::GET, Some("writer")))
+            .is_ok());
+        assert!(access
+            .authorize(&request(Method::POST, Some("writer")))
+            .is_ok());
+
+        match access.authorize(&request(Method::POST, Some("reader"))) {
+            Err(ApiError::Forbidden(_)) => {}
+            other => panic!("read token should not permit writes: {:?}", other),
+        }
+        match access.authorize(&request(Method::GET, None)) {
+            Err(ApiError::Unauthorized(_)) => {}
+            other => panic!("a token should be required: {:?}", other),
+        }
+        match access.authorize(&request(Method::GET, Some("readerx"))) {
+            Err(ApiError::Unauthorized(_)) => {}
+            other => panic!("an unknown token should be rejected: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn writes_disabled() {
+        let access = access_control(false);
+
+        assert!(access
+            .authorize(&request(Method::GET, Some("writer")))
+            .is_ok());
+        match access.authorize(&request(Method::DELETE, Some("writer"))) {
+            Err(ApiError::Forbidden(_)) => {}
+            other => panic!("writes should be disabled: {:?}", other),
+        }
+
+        let open = AccessControl {
+            tokens: vec![],
+            allow_writes: false,
+            cors_allowed_origins: vec![],
+        };
+        assert!(open.authorize(&request(Method::GET, None)).is_ok());
+        assert!(open.authorize(&request(Method::POST, None)).is_err());
+    }
+
+    #[test]
+    fn cors_origins() {
+        let access = access_control(true);
+        let allowed = HeaderValue::from_static("https://dashboard.example.com");
+        let denied = HeaderValue::from_static("https://evil.example.com");
+
+        assert_eq!(access.allowed_origin(Some(&allowed)), Some(allowed.clone()));
+        assert_eq!(access.allowed_origin(Some(&denied)), None);
+        assert_eq!(access.allowed_origin(None), None);
+        assert!(access.preflight_response(Some(&denied)).is_err());
+
+        let preflight = access
+            .preflight_response(Some(&allowed))
+            .expect("should permit preflight from allowed origin");
+        assert_eq!(preflight.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            preflight.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some(&allowed)
+        );
+
+        let any = AccessControl {
+            tokens: vec![],
+            allow_writes: true,
+            cors_allowed_origins: vec!["*".to_string()],
+        };
+        assert_eq!(
+            any.allowed_origin(Some(&denied)),
+            Some(HeaderValue::from_static("*"))
+        );
+    }
+}
@@ -1,26 +1,15 @@
 use crate::helpers::*;
 use crate::response_builder::ResponseBuilder;
-use crate::{ApiError, ApiResult, UrlQuery};
+use crate::{ApiError, ApiResult};
 use beacon_chain::{BeaconChain, BeaconChainTypes};
 use hyper::{Body, Request};
-use serde::Serialize;
-use ssz_derive::Encode;
+use rest_types::{
+    BlockId, BlockResponse, Committee, HeadResponse, Proposer, RewardDelta, StateId, StateResponse,
+    ValidatorBalance, ValidatorRewards, ValidatorStatus,
+};
+use state_processing::per_epoch_processing::{get_epoch_reward_breakdowns, Delta};
 use std::sync::Arc;
-use store::Store;
-use types::{BeaconBlock, BeaconState, Epoch, EthSpec, Hash256, Slot, Validator};
-
-#[derive(Serialize, Encode)]
-pub struct HeadResponse {
-    pub slot: Slot,
-    pub block_root: Hash256,
-    pub state_root: Hash256,
-    pub finalized_slot: Slot,
-    pub finalized_block_root: Hash256,
-    pub justified_slot: Slot,
-    pub justified_block_root: Hash256,
-    pub previous_justified_slot: Slot,
-    pub previous_justified_block_root: Hash256,
-}
+use types::{EthSpec, Slot, Validator};
 
 /// HTTP handler to return a `BeaconBlock` at a given `root` or `slot`.
 pub fn get_head<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
@@ -58,44 +47,16 @@ pub fn get_head<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult
     ResponseBuilder::new(&req)?.body(&head)
 }
 
-#[derive(Serialize, Encode)]
-#[serde(bound = "T: EthSpec")]
-pub struct BlockResponse<T: EthSpec> {
-    pub root: Hash256,
-    pub beacon_block: BeaconBlock<T>,
-}
-
-/// HTTP handler to return a `BeaconBlock` at a given `root` or `slot`.
+/// HTTP handler to return the `BeaconBlock` identified by the `block_id` query parameter.
+///
+/// The `root` and `slot` parameters are also accepted in place of `block_id`. Returns the head
+/// block if no block is identified.
 pub fn get_block<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
-    let beacon_chain = req
-        .extensions()
-        .get::<Arc<BeaconChain<T>>>()
-        .ok_or_else(|| ApiError::ServerError("Beacon chain extension missing".to_string()))?;
-
-    let query_params = ["root", "slot"];
-    let (key, value) = UrlQuery::from_request(&req)?.first_of(&query_params)?;
-
-    let block_root = match (key.as_ref(), value) {
-        ("slot", value) => {
-            let target = parse_slot(&value)?;
-
-            block_root_at_slot(&beacon_chain, target).ok_or_else(|| {
-                ApiError::NotFound(format!("Unable to find BeaconBlock for slot {:?}", target))
-            })?
-        }
-        ("root", value) => parse_root(&value)?,
-        _ => return Err(ApiError::ServerError("Unexpected query parameter".into())),
-    };
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
 
-    let block = beacon_chain
-        .store
-        .get::<BeaconBlock<T::EthSpec>>(&block_root)?
-        .ok_or_else(|| {
-            ApiError::NotFound(format!(
-                "Unable to find BeaconBlock for root {:?}",
-                block_root
-            ))
-        })?;
+    let block_id = parse_id(&req, &["block_id", "root", "slot"])?.unwrap_or(BlockId::Head);
+    let block_root = block_root_from_id(&beacon_chain, block_id)?;
+    let block = block_from_root(&beacon_chain, block_root)?;
 
     let response = BlockResponse {
         root: block_root,
@@ -105,50 +66,39 @@ pub fn get_block<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult
     ResponseBuilder::new(&req)?.body(&response)
 }
 
-/// HTTP handler to return a `BeaconBlock` root at a given `slot`.
+/// HTTP handler to return the root of the `BeaconBlock` identified by the `block_id` (or `slot`)
+/// query parameter.
 pub fn get_block_root<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
     let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
 
-    let slot_string = UrlQuery::from_request(&req)?.only_one("slot")?;
-    let target = parse_slot(&slot_string)?;
-
-    let root = block_root_at_slot(&beacon_chain, target).ok_or_else(|| {
-        ApiError::NotFound(format!("Unable to find BeaconBlock for slot {:?}", target))
-    })?;
+    let block_id = parse_id(&req, &["block_id", "slot"])?.unwrap_or(BlockId::Head);
+    let root = block_root_from_id(&beacon_chain, block_id)?;
 
     ResponseBuilder::new(&req)?.body(&root)
 }
 
-/// HTTP handler to return the `Fork` of the current head.
+/// HTTP handler to return the `Fork` of the state identified by the `state_id` query parameter,
+/// which defaults to the head.
 pub fn get_fork<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
     let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
-    ResponseBuilder::new(&req)?.body(&beacon_chain.head().beacon_state.fork)
+    let (_root, state) = state_from_id(&beacon_chain, state_id_from_request(&req)?)?;
+
+    ResponseBuilder::new(&req)?.body(&state.fork)
 }
 
 /// HTTP handler to return the set of validators for an `Epoch`
 ///
-/// The `Epoch` parameter can be any epoch number. If it is not specified,
-/// the current epoch is assumed.
+/// The `Epoch` parameter can be any epoch number. If it is not specified, the current epoch of
+/// the state is assumed. The validators are read from the state identified by the `state_id`
+/// query parameter, which defaults to the head.
 pub fn get_validators<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
     let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+    let (_root, state) = state_from_id(&beacon_chain, state_id_from_request(&req)?)?;
 
-    let epoch = match UrlQuery::from_request(&req) {
-        // We have some parameters, so make sure it's the epoch one and parse it
-        Ok(query) => query
-            .only_one("epoch")?
-            .parse::<u64>()
-            .map(Epoch::from)
-            .map_err(|e| {
-                ApiError::BadRequest(format!("Invalid epoch parameter, must be a u64. {:?}", e))
-            })?,
-        // In this case, our url query did not contain any parameters, so we take the default
-        Err(_) => beacon_chain.epoch().map_err(|e| {
-            ApiError::ServerError(format!("Unable to determine current epoch: {:?}", e))
-        })?,
-    };
+    let epoch = epoch_from_request(&req)?.unwrap_or_else(|| state.current_epoch());
 
-    let all_validators = &beacon_chain.head().beacon_state.validators;
-    let active_vals: Vec<Validator> = all_validators
+    let active_vals: Vec<Validator> = state
+        .validators
         .iter()
         .filter(|v| v.is_active_at(epoch))
         .cloned()
@@ -157,50 +107,156 @@ pub fn get_validators<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiR
     ResponseBuilder::new(&req)?.body(&active_vals)
 }
 
-#[derive(Serialize, Encode)]
-#[serde(bound = "T: EthSpec")]
-pub struct StateResponse<T: EthSpec> {
-    pub root: Hash256,
-    pub beacon_state: BeaconState<T>,
+/// HTTP handler to return the balance and status of every validator in the state identified by
+/// the `state_id` query parameter, which defaults to the head.
+///
+/// The status of each validator is determined at the current epoch of the state.
+pub fn get_validator_balances<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+    let (_root, state) = state_from_id(&beacon_chain, state_id_from_request(&req)?)?;
+    let epoch = state.current_epoch();
+
+    let balances: Vec<ValidatorBalance> = state
+        .validators
+        .iter()
+        .zip(state.balances.iter())
+        .enumerate()
+        .map(|(validator_index, (validator, balance))| ValidatorBalance {
+            validator_index,
+            pubkey: validator.pubkey.clone(),
+            balance: *balance,
+            effective_balance: validator.effective_balance,
+            status: ValidatorStatus::from_validator(validator, epoch, &beacon_chain.spec),
+        })
+        .collect();
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&balances)
 }
 
-/// HTTP handler to return a `BeaconState` at a given `root` or `slot`.
+/// HTTP handler to return the rewards and penalties applied to every validator at the start of
+/// the `epoch` query parameter, by duty.
 ///
-/// Will not return a state if the request slot is in the future. Will return states higher than
-/// the current head by skipping slots.
-pub fn get_state<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+/// The epoch defaults to the current epoch of the head. The rewards are those of the canonical
+/// epoch transition into `epoch`, which reward the duties of `epoch - 2`.
+pub fn get_validator_rewards<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
     let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
-    let head_state = beacon_chain.head().beacon_state;
+    let current_epoch = beacon_chain.head().beacon_state.current_epoch();
+
+    let epoch = epoch_from_request(&req)?.unwrap_or(current_epoch);
+    if epoch == T::EthSpec::genesis_epoch() {
+        return Err(ApiError::BadRequest(
+            "There is no epoch transition into the genesis epoch".to_string(),
+        ));
+    } else if epoch > current_epoch {
+        return Err(ApiError::BadRequest(format!(
+            "The epoch transition into epoch {} has not occurred, the current epoch is {}",
+            epoch, current_epoch
+        )));
+    }
+
+    // The state at the last slot of the previous epoch, prior to the epoch transition.
+    let (_root, state) = state_at_slot(
+        &beacon_chain,
+        epoch.start_slot(T::EthSpec::slots_per_epoch()) - 1,
+    )?;
+
+    let rewards: Vec<ValidatorRewards> = get_epoch_reward_breakdowns(&state, &beacon_chain.spec)
+        .map_err(|e| ApiError::ServerError(format!("Unable to compute rewards: {:?}", e)))?
+        .into_iter()
+        .enumerate()
+        .map(|(validator_index, breakdown)| ValidatorRewards {
+            validator_index,
+            source: reward_delta(breakdown.source),
+            inclusion_delay: reward_delta(breakdown.inclusion_delay),
+            target: reward_delta(breakdown.target),
+            head: reward_delta(breakdown.head),
+            inactivity: reward_delta(breakdown.inactivity),
+            crosslink: reward_delta(breakdown.crosslink),
+            proposer: reward_delta(breakdown.proposer),
+        })
+        .collect();
 
-    let (key, value) = match UrlQuery::from_request(&req) {
-        Ok(query) => {
-            // We have *some* parameters, just check them.
-            let query_params = ["root", "slot"];
-            query.first_of(&query_params)?
-        }
-        Err(ApiError::BadRequest(_)) => {
-            // No parameters provided at all, use current slot.
-            (String::from("slot"), head_state.slot.to_string())
-        }
-        Err(e) => {
-            return Err(e);
+    ResponseBuilder::new(&req)?.body(&rewards)
+}
+
+fn reward_delta(delta: Delta) -> RewardDelta {
+    RewardDelta {
+        rewards: delta.rewards,
+        penalties: delta.penalties,
+    }
+}
+
+/// HTTP handler to return every crosslink committee of the `epoch` query parameter, ordered by
+/// slot and then shard.
+///
+/// The committees are read from the state identified by the `state_id` query parameter, which
+/// defaults to the head. The epoch defaults to the current epoch of that state and may be its
+/// previous, current or next epoch.
+pub fn get_committees<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+    let (_root, mut state) = state_from_id(&beacon_chain, state_id_from_request(&req)?)?;
+
+    let epoch = epoch_from_request(&req)?.unwrap_or_else(|| state.current_epoch());
+    let relative_epoch = relative_epoch(&state, epoch)?;
+    state.build_committee_cache(relative_epoch, &beacon_chain.spec)?;
+
+    let mut committees = vec![];
+    for slot in epoch.slot_iter(T::EthSpec::slots_per_epoch()) {
+        for committee in state.get_crosslink_committees_at_slot(slot)? {
+            committees.push(Committee {
+                slot: committee.slot,
+                shard: committee.shard,
+                committee: committee.committee.to_vec(),
+            });
         }
-    };
+    }
+
+    ResponseBuilder::new(&req)?.body(&committees)
+}
 
-    let (root, state): (Hash256, BeaconState<T::EthSpec>) = match (key.as_ref(), value) {
-        ("slot", value) => state_at_slot(&beacon_chain, parse_slot(&value)?)?,
-        ("root", value) => {
-            let root = &parse_root(&value)?;
+/// HTTP handler to return the block proposer of every slot of the `epoch` query parameter.
+///
+/// The proposers are computed from the state identified by the `state_id` query parameter, which
+/// defaults to the head. The epoch defaults to the current epoch of that state and may be its
+/// previous, current or next epoch. The proposers of the next epoch may change if effective
+/// balances change at the epoch transition.
+pub fn get_proposers<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+    let (_root, mut state) = state_from_id(&beacon_chain, state_id_from_request(&req)?)?;
+
+    let epoch = epoch_from_request(&req)?.unwrap_or_else(|| state.current_epoch());
+    let relative_epoch = relative_epoch(&state, epoch)?;
+    state.build_committee_cache(relative_epoch, &beacon_chain.spec)?;
+
+    let proposers = epoch
+        .slot_iter(T::EthSpec::slots_per_epoch())
+        .map(|slot| {
+            Ok(Proposer {
+                slot,
+                validator_index: state.get_beacon_proposer_index(
+                    slot,
+                    relative_epoch,
+                    &beacon_chain.spec,
+                )?,
+            })
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
+
+    ResponseBuilder::new(&req)?.body(&proposers)
+}
 
-            let state = beacon_chain
-                .store
-                .get(root)?
-                .ok_or_else(|| ApiError::NotFound(format!("No state for root: {:?}", root)))?;
+/// HTTP handler to return the `BeaconState` identified by the `state_id` query parameter.
+///
+/// The `root` and `slot` parameters are also accepted in place of `state_id`. Returns the head
+/// state if no state is identified.
+///
+/// Will not return a state if the request slot is in the future. Will return states higher than
+/// the current head by skipping slots.
+pub fn get_state<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
 
-            (*root, state)
-        }
-        _ => return Err(ApiError::ServerError("Unexpected query parameter".into())),
-    };
+    let state_id = parse_id(&req, &["state_id", "root", "slot"])?.unwrap_or(StateId::Head);
+    let (root, state) = state_from_id(&beacon_chain, state_id)?;
 
     let response = StateResponse {
         root,
@@ -210,31 +266,29 @@ pub fn get_state<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult
     ResponseBuilder::new(&req)?.body(&response)
 }
 
-/// HTTP handler to return a `BeaconState` root at a given `slot`.
+/// HTTP handler to return the root of the `BeaconState` identified by the `state_id` (or `slot`)
+/// query parameter.
 ///
 /// Will not return a state if the request slot is in the future. Will return states higher than
 /// the current head by skipping slots.
 pub fn get_state_root<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
     let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
 
-    let slot_string = UrlQuery::from_request(&req)?.only_one("slot")?;
-    let slot = parse_slot(&slot_string)?;
-
-    let root = state_root_at_slot(&beacon_chain, slot)?;
+    let state_id = parse_id(&req, &["state_id", "slot"])?.unwrap_or(StateId::Head);
+    let root = state_root_from_id(&beacon_chain, state_id)?;
 
     ResponseBuilder::new(&req)?.body(&root)
 }
 
-/// HTTP handler to return the highest finalized slot.
+/// HTTP handler to return the finalized checkpoint of the state identified by the `state_id`
+/// query parameter, which defaults to the head.
 pub fn get_current_finalized_checkpoint<T: BeaconChainTypes + 'static>(
     req: Request<Body>,
 ) -> ApiResult {
     let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
-    let head_state = beacon_chain.head().beacon_state;
-
-    let checkpoint = head_state.finalized_checkpoint.clone();
+    let (_root, state) = state_from_id(&beacon_chain, state_id_from_request(&req)?)?;
 
-    ResponseBuilder::new(&req)?.body(&checkpoint)
+    ResponseBuilder::new(&req)?.body(&state.finalized_checkpoint)
 }
 
 /// HTTP handler to return a `BeaconState` at the genesis block.
@@ -1,6 +1,7 @@
 use clap::ArgMatches;
 use serde::{Deserialize, Serialize};
 use std::net::Ipv4Addr;
+use std::path::PathBuf;
 
 /// HTTP REST API Configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +12,23 @@ pub struct Config {
     pub listen_address: Ipv4Addr,
     /// The port the REST API HTTP server will listen on.
     pub port: u16,
+    /// If set (along with `tls_key`), the server only accepts HTTPS connections, using the PEM
+    /// encoded certificate chain in this file.
+    pub tls_cert: Option<PathBuf>,
+    /// The PEM encoded private key for `tls_cert`.
+    pub tls_key: Option<PathBuf>,
+    /// A file of bearer tokens (one per line) which may use the read-only (`GET`) endpoints.
+    ///
+    /// If neither `read_tokens_file` nor `write_tokens_file` is set, no authentication is
+    /// required.
+    pub read_tokens_file: Option<PathBuf>,
+    /// A file of bearer tokens (one per line) which may use all endpoints.
+    pub write_tokens_file: Option<PathBuf>,
+    /// If `false`, all endpoints which modify the node or publish to the network are rejected.
+    pub allow_writes: bool,
+    /// The origins that browsers are permitted to make cross-origin requests from. A `*` permits
+    /// any origin. Empty by default, which permits no cross-origin requests.
+    pub cors_allowed_origins: Vec<String>,
 }
 
 impl Default for Config {
@@ -19,6 +37,12 @@ impl Default for Config {
             enabled: true,
             listen_address: Ipv4Addr::new(127, 0, 0, 1),
             port: 5052,
+            tls_cert: None,
+            tls_key: None,
+            read_tokens_file: None,
+            write_tokens_file: None,
+            allow_writes: true,
+            cors_allowed_origins: vec![],
         }
     }
 }
@@ -41,6 +65,39 @@ impl Config {
                 .map_err(|_| "api-port is not a valid u16.")?;
         }
 
+        if let Some(tls_cert) = args.value_of("api-tls-cert") {
+            self.tls_cert = Some(PathBuf::from(tls_cert));
+        }
+
+        if let Some(tls_key) = args.value_of("api-tls-key") {
+            self.tls_key = Some(PathBuf::from(tls_key));
+        }
+
+        if self.tls_cert.is_some() != self.tls_key.is_some() {
+            return Err("api-tls-cert and api-tls-key must be supplied together.");
+        }
+
+        if let Some(path) = args.value_of("api-read-tokens") {
+            self.read_tokens_file = Some(PathBuf::from(path));
+        }
+
+        if let Some(path) = args.value_of("api-write-tokens") {
+            self.write_tokens_file = Some(PathBuf::from(path));
+        }
+
+        if args.is_present("api-disable-writes") {
+            self.allow_writes = false;
+        }
+
+        if let Some(origins) = args.value_of("api-allow-origin") {
+            self.cors_allowed_origins = origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(String::from)
+                .collect();
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,105 @@
+use crate::helpers::get_beacon_chain_from_request;
+use crate::response_builder::ResponseBuilder;
+use crate::{ApiError, ApiResult};
+use beacon_chain::BeaconChainTypes;
+use hyper::{Body, Request};
+use rest_types::{
+    ChainDumpEntry, CommitteeCacheSize, ForkChoiceNode, ForkChoiceResponse, LatestMessage,
+    StateCacheResponse,
+};
+use types::{BeaconState, EthSpec, RelativeEpoch};
+
+/// HTTP handler to return the nodes, weights and latest messages of the fork choice.
+pub fn get_fork_choice<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+
+    let dump = beacon_chain
+        .fork_choice
+        .dump()
+        .map_err(|e| ApiError::ServerError(format!("Unable to dump fork choice: {:?}", e)))?;
+
+    let response = ForkChoiceResponse {
+        head_block_root: beacon_chain.head().beacon_block_root,
+        root: dump.root,
+        root_slot: dump.root_slot,
+        nodes: dump
+            .nodes
+            .into_iter()
+            .map(|node| ForkChoiceNode {
+                block_root: node.block_root,
+                slot: node.slot,
+                parent_root: node.parent_root,
+                children: node.children,
+                weight: node.weight,
+                best_child: node.best_child,
+            })
+            .collect(),
+        latest_messages: dump
+            .latest_messages
+            .into_iter()
+            .map(|message| LatestMessage {
+                validator_index: message.validator_index,
+                block_root: message.block_root,
+                slot: message.slot,
+            })
+            .collect(),
+    };
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&response)
+}
+
+/// HTTP handler to return a summary of each block in the canonical chain, from genesis to the
+/// head.
+///
+/// Reads every canonical block and state from the database, this is an expensive operation.
+pub fn get_chain_dump<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+
+    let dump = beacon_chain
+        .chain_dump()
+        .map_err(|e| ApiError::ServerError(format!("Unable to dump chain: {:?}", e)))?
+        .into_iter()
+        .map(|checkpoint| ChainDumpEntry {
+            slot: checkpoint.beacon_block.slot,
+            block_root: checkpoint.beacon_block_root,
+            parent_root: checkpoint.beacon_block.parent_root,
+            state_root: checkpoint.beacon_state_root,
+            current_justified_checkpoint: checkpoint.beacon_state.current_justified_checkpoint,
+            finalized_checkpoint: checkpoint.beacon_state.finalized_checkpoint,
+        })
+        .collect::<Vec<_>>();
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&dump)
+}
+
+/// HTTP handler to return the sizes of the caches of the head state.
+pub fn get_state_cache<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+    let head = beacon_chain.head();
+    let state = &head.beacon_state;
+
+    let response = StateCacheResponse {
+        slot: state.slot,
+        state_root: head.beacon_state_root,
+        previous_committee_cache: committee_cache_size(state, RelativeEpoch::Previous),
+        current_committee_cache: committee_cache_size(state, RelativeEpoch::Current),
+        next_committee_cache: committee_cache_size(state, RelativeEpoch::Next),
+        pubkey_cache_len: state.pubkey_cache.len(),
+        validator_count: state.validators.len(),
+        exit_cache_len: state.exit_cache.len(),
+    };
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&response)
+}
+
+fn committee_cache_size<E: EthSpec>(
+    state: &BeaconState<E>,
+    relative_epoch: RelativeEpoch,
+) -> CommitteeCacheSize {
+    let cache = &state.committee_caches[BeaconState::<E>::cache_index(relative_epoch)];
+
+    CommitteeCacheSize {
+        initialized_epoch: cache.initialized_epoch(),
+        active_validator_count: cache.active_validator_indices().len(),
+    }
+}
@@ -9,6 +9,8 @@ pub enum ApiError {
     NotImplemented(String),
     BadRequest(String),
     NotFound(String),
+    Unauthorized(String),
+    Forbidden(String),
     UnsupportedType(String),
     ImATeapot(String),       // Just in case.
     ProcessingError(String), // A 202 error, for when a block/attestation cannot be processed, but still transmitted.
@@ -24,6 +26,8 @@ impl ApiError {
             ApiError::NotImplemented(desc) => (StatusCode::NOT_IMPLEMENTED, desc),
             ApiError::BadRequest(desc) => (StatusCode::BAD_REQUEST, desc),
             ApiError::NotFound(desc) => (StatusCode::NOT_FOUND, desc),
+            ApiError::Unauthorized(desc) => (StatusCode::UNAUTHORIZED, desc),
+            ApiError::Forbidden(desc) => (StatusCode::FORBIDDEN, desc),
             ApiError::UnsupportedType(desc) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, desc),
             ApiError::ImATeapot(desc) => (StatusCode::IM_A_TEAPOT, desc),
             ApiError::ProcessingError(desc) => (StatusCode::ACCEPTED, desc),
@@ -34,9 +38,16 @@ impl ApiError {
 impl Into<Response<Body>> for ApiError {
     fn into(self) -> Response<Body> {
         let status_code = self.status_code();
-        Response::builder()
+        let mut builder = Response::builder();
+        builder
             .status(status_code.0)
-            .header("content-type", "text/plain; charset=utf-8")
+            .header("content-type", "text/plain; charset=utf-8");
+
+        if status_code.0 == StatusCode::UNAUTHORIZED {
+            builder.header("www-authenticate", "Bearer");
+        }
+
+        builder
             .body(Body::from(status_code.1))
             .expect("Response should always be created.")
     }
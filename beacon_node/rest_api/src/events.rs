@@ -0,0 +1,108 @@
+use crate::{ApiError, ApiResult};
+use beacon_chain::events::{EventBroadcaster, EventKind, EventTopic};
+use beacon_chain::BeaconChainTypes;
+use futures::Stream;
+use hyper::{Body, Request, Response, StatusCode};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::timer::Interval;
+use types::EthSpec;
+
+/// The number of events buffered for each event stream before events are dropped.
+const STREAM_CAPACITY: usize = 64;
+
+/// The interval at which a comment is sent on idle event streams, which prevents reverse proxies
+/// from closing the connection.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// HTTP handler to stream chain events as server-sent events.
+///
+/// The optional `topics` query parameter is a comma-separated list of the topics to subscribe to,
+/// e.g., `/events?topics=head,finalized_checkpoint`. All topics are streamed if it is absent.
+///
+/// Each event is sent with its topic as the event name and its JSON-encoded contents as its data.
+/// A client which does not keep up with the stream misses events, rather than being disconnected.
+pub fn get_events<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let topics = parse_topics(&req)?;
+    let events = req
+        .extensions()
+        .get::<EventBroadcaster<T::EthSpec>>()
+        .ok_or_else(|| ApiError::ServerError("Event broadcaster extension missing".into()))?;
+
+    let event_stream = events
+        .subscribe(topics, STREAM_CAPACITY)
+        .map_err(|_| "Event subscription ended".to_string())
+        .and_then(|event| event_to_message(&event));
+
+    let keep_alive_stream = Interval::new_interval(KEEP_ALIVE_INTERVAL)
+        .map(|_| ":\n\n".to_string())
+        .map_err(|e| format!("Event stream timer failed: {:?}", e));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-cache")
+        // Prevents nginx from buffering the stream.
+        .header("x-accel-buffering", "no")
+        .body(Body::wrap_stream(event_stream.select(keep_alive_stream)))
+        .map_err(|e| ApiError::ServerError(format!("Failed to build response: {:?}", e)))
+}
+
+/// Parses the comma-separated `topics` query parameters of `req`.
+///
+/// Returns `None` if no topics are specified.
+fn parse_topics(req: &Request<Body>) -> Result<Option<HashSet<EventTopic>>, ApiError> {
+    let query = match req.uri().query() {
+        Some(query) => query,
+        None => return Ok(None),
+    };
+
+    let mut topics = HashSet::new();
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        if key != "topics" {
+            return Err(ApiError::BadRequest(format!(
+                "Unknown query parameter: {}",
+                key
+            )));
+        }
+
+        for topic in value.split(',').filter(|topic| !topic.is_empty()) {
+            topics.insert(topic.parse().map_err(|e| {
+                ApiError::BadRequest(format!("{}. Valid topics are: {}", e, valid_topics()))
+            })?);
+        }
+    }
+
+    if topics.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(topics))
+    }
+}
+
+/// Returns a comma-separated list of all event topics.
+fn valid_topics() -> String {
+    EventTopic::all()
+        .iter()
+        .map(|topic| topic.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Encodes `event` as a server-sent event message.
+fn event_to_message<E: EthSpec>(event: &EventKind<E>) -> Result<String, String> {
+    // `EventKind` is serialized as `{ "event": <name>, "data": <contents> }`, only the contents
+    // are sent as the data of the message.
+    let mut json =
+        serde_json::to_value(event).map_err(|e| format!("Unable to serialize event: {:?}", e))?;
+    let data = json
+        .get_mut("data")
+        .map(serde_json::Value::take)
+        .unwrap_or(serde_json::Value::Null);
+
+    Ok(format!(
+        "event: {}\ndata: {}\n\n",
+        event.topic().as_str(),
+        data
+    ))
+}
@@ -1,20 +1,24 @@
-use crate::{ApiError, ApiResult};
+use crate::{ApiError, ApiResult, UrlQuery};
 use beacon_chain::{BeaconChain, BeaconChainTypes};
-use bls::PublicKey;
-use eth2_libp2p::{PubsubMessage, Topic};
-use eth2_libp2p::{
-    BEACON_ATTESTATION_TOPIC, BEACON_BLOCK_TOPIC, TOPIC_ENCODING_POSTFIX, TOPIC_PREFIX,
-};
+use bls::{PublicKey, Signature};
+use eth2_libp2p::{PubsubMessage, SubnetId, Topic};
+use eth2_libp2p::{BEACON_BLOCK_TOPIC, TOPIC_ENCODING_POSTFIX, TOPIC_PREFIX};
+use futures::{Future, Stream};
 use hex;
 use http::header;
 use hyper::{Body, Request};
 use network::NetworkMessage;
 use parking_lot::RwLock;
+use rest_types::{BlockId, StateId};
+use serde::de::DeserializeOwned;
 use ssz::Encode;
+use std::str::FromStr;
 use std::sync::Arc;
 use store::{iter::AncestorIter, Store};
 use tokio::sync::mpsc;
-use types::{Attestation, BeaconBlock, BeaconState, EthSpec, Hash256, RelativeEpoch, Slot};
+use types::{
+    Attestation, BeaconBlock, BeaconState, Checkpoint, Epoch, EthSpec, Hash256, RelativeEpoch, Slot,
+};
 
 /// Parse a slot from a `0x` preixed string.
 ///
@@ -26,6 +30,39 @@ pub fn parse_slot(string: &str) -> Result<Slot, ApiError> {
         .map_err(|e| ApiError::BadRequest(format!("Unable to parse slot: {:?}", e)))
 }
 
+/// Parse an epoch from a string.
+///
+/// E.g., `"1234"`
+pub fn parse_epoch(string: &str) -> Result<Epoch, ApiError> {
+    string.parse::<u64>().map(Epoch::from).map_err(|e| {
+        ApiError::BadRequest(format!("Invalid epoch parameter, must be a u64. {:?}", e))
+    })
+}
+
+/// Returns the epoch in the `epoch` query parameter of `req`, if any.
+pub fn epoch_from_request(req: &Request<Body>) -> Result<Option<Epoch>, ApiError> {
+    UrlQuery::from_request(req)
+        .ok()
+        .and_then(|query| query.first_of_opt(&["epoch"]))
+        .map(|(_key, value)| parse_epoch(&value))
+        .transpose()
+}
+
+/// Returns `epoch` relative to the current epoch of `state`.
+///
+/// Only the previous, current and next epochs of a state have committees.
+pub fn relative_epoch<E: EthSpec>(
+    state: &BeaconState<E>,
+    epoch: Epoch,
+) -> Result<RelativeEpoch, ApiError> {
+    RelativeEpoch::from_epoch(state.current_epoch(), epoch).map_err(|e| {
+        ApiError::BadRequest(format!(
+            "Epoch must be the previous, current or next epoch of the state: {:?}",
+            e
+        ))
+    })
+}
+
 /// Checks the provided request to ensure that the `content-type` header.
 ///
 /// The content-type header should either be omitted, in which case JSON is assumed, or it should
@@ -76,6 +113,155 @@ pub fn parse_pubkey(string: &str) -> Result<PublicKey, ApiError> {
     }
 }
 
+/// Parse a Signature from a `0x` prefixed hex string
+pub fn parse_signature(string: &str) -> Result<Signature, ApiError> {
+    const PREFIX: &str = "0x";
+    if string.starts_with(PREFIX) {
+        let signature_bytes = hex::decode(string.trim_start_matches(PREFIX))
+            .map_err(|e| ApiError::BadRequest(format!("Invalid hex string: {:?}", e)))?;
+        Signature::from_bytes(signature_bytes.as_slice())
+            .map_err(|e| ApiError::BadRequest(format!("Unable to deserialize signature: {:?}.", e)))
+    } else {
+        Err(ApiError::BadRequest(
+            "Signature must have a  '0x' prefix".to_string(),
+        ))
+    }
+}
+
+/// Returns the selection proof in the `selection_proof` query parameter of `req`, if any.
+pub fn selection_proof_from_request(req: &Request<Body>) -> Result<Option<Signature>, ApiError> {
+    UrlQuery::from_request(req)
+        .ok()
+        .and_then(|query| query.first_of_opt(&["selection_proof"]))
+        .map(|(_key, value)| parse_signature(&value))
+        .transpose()
+}
+
+/// Parses the value of the first of `keys` in the query of `req` as a `StateId` or `BlockId`.
+///
+/// Returns `None` if the request has none of `keys`.
+pub fn parse_id<I: FromStr<Err = String>>(
+    req: &Request<Body>,
+    keys: &[&str],
+) -> Result<Option<I>, ApiError> {
+    let query = match UrlQuery::from_request(req) {
+        Ok(query) => query,
+        // The request has no query at all.
+        Err(_) => return Ok(None),
+    };
+
+    query
+        .first_of_opt(keys)
+        .map(|(_key, value)| value.parse().map_err(ApiError::BadRequest))
+        .transpose()
+}
+
+/// Returns the `StateId` given in the `state_id` query parameter of `req`, defaulting to the
+/// head state.
+pub fn state_id_from_request(req: &Request<Body>) -> Result<StateId, ApiError> {
+    Ok(parse_id(req, &["state_id"])?.unwrap_or(StateId::Head))
+}
+
+/// Returns the root of the block identified by `block_id`.
+///
+/// Only a `BlockId::Slot` is checked against the chain, the block of a `BlockId::Root` may not
+/// exist.
+pub fn block_root_from_id<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    block_id: BlockId,
+) -> Result<Hash256, ApiError> {
+    match block_id {
+        BlockId::Head => Ok(beacon_chain.head().beacon_block_root),
+        BlockId::Genesis => Ok(beacon_chain.genesis_block_root),
+        BlockId::Finalized => Ok(checkpoint_block_root(
+            beacon_chain,
+            &beacon_chain.head().beacon_state.finalized_checkpoint,
+        )),
+        BlockId::Justified => Ok(checkpoint_block_root(
+            beacon_chain,
+            &beacon_chain
+                .head()
+                .beacon_state
+                .current_justified_checkpoint,
+        )),
+        BlockId::Slot(slot) => block_root_at_slot(beacon_chain, slot).ok_or_else(|| {
+            ApiError::NotFound(format!("Unable to find BeaconBlock for slot {:?}", slot))
+        }),
+        BlockId::Root(root) => Ok(root),
+    }
+}
+
+/// Returns the block root of `checkpoint`.
+///
+/// Checkpoints are zero until the first justification, they then refer to the genesis block.
+fn checkpoint_block_root<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    checkpoint: &Checkpoint,
+) -> Hash256 {
+    if checkpoint.root == Hash256::zero() {
+        beacon_chain.genesis_block_root
+    } else {
+        checkpoint.root
+    }
+}
+
+/// Returns the `BeaconBlock` with the given `root` from the store.
+pub fn block_from_root<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    root: Hash256,
+) -> Result<BeaconBlock<T::EthSpec>, ApiError> {
+    beacon_chain
+        .store
+        .get::<BeaconBlock<T::EthSpec>>(&root)?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("Unable to find BeaconBlock for root {:?}", root))
+        })
+}
+
+/// Returns the root of the state identified by `state_id`.
+///
+/// The states of the genesis, finalized and justified blocks are the post-states of those
+/// blocks.
+pub fn state_root_from_id<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    state_id: StateId,
+) -> Result<Hash256, ApiError> {
+    let block_id = match state_id {
+        StateId::Head => return Ok(beacon_chain.head().beacon_state_root),
+        StateId::Slot(slot) => return state_root_at_slot(beacon_chain, slot),
+        StateId::Root(root) => return Ok(root),
+        StateId::Genesis => BlockId::Genesis,
+        StateId::Finalized => BlockId::Finalized,
+        StateId::Justified => BlockId::Justified,
+    };
+
+    let block_root = block_root_from_id(beacon_chain, block_id)?;
+    Ok(block_from_root(beacon_chain, block_root)?.state_root)
+}
+
+/// Returns the state identified by `state_id` and its root.
+pub fn state_from_id<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    state_id: StateId,
+) -> Result<(Hash256, BeaconState<T::EthSpec>), ApiError> {
+    match state_id {
+        StateId::Head => {
+            let head = beacon_chain.head();
+            Ok((head.beacon_state_root, head.beacon_state))
+        }
+        StateId::Slot(slot) => state_at_slot(beacon_chain, slot),
+        _ => {
+            let root = state_root_from_id(beacon_chain, state_id)?;
+            let state = beacon_chain
+                .store
+                .get(&root)?
+                .ok_or_else(|| ApiError::NotFound(format!("No state for root: {:?}", root)))?;
+
+            Ok((root, state))
+        }
+    }
+}
+
 /// Returns the root of the `BeaconBlock` in the canonical chain of `beacon_chain` at the given
 /// `slot`, if possible.
 ///
@@ -188,6 +374,20 @@ pub fn state_root_at_slot<T: BeaconChainTypes>(
     }
 }
 
+/// Parses the body of a request as JSON.
+pub fn parse_json_body<T: DeserializeOwned>(
+    req: Request<Body>,
+) -> impl Future<Item = T, Error = ApiError> {
+    req.into_body()
+        .concat2()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get request body: {:?}", e)))
+        .and_then(|chunk| {
+            serde_json::from_slice::<T>(&chunk).map_err(|e| {
+                ApiError::BadRequest(format!("Unable to deserialize JSON body: {:?}", e))
+            })
+        })
+}
+
 pub fn implementation_pending_response(_req: Request<Body>) -> ApiResult {
     Err(ApiError::NotImplemented(
         "API endpoint has not yet been implemented, but is planned to be soon.".to_owned(),
@@ -240,16 +440,34 @@ pub fn publish_beacon_block_to_network<T: BeaconChainTypes + 'static>(
     Ok(())
 }
 
+/// Publishes an operation (e.g., a `VoluntaryExit`) on the gossipsub `topic`.
+pub fn publish_operation_to_network(
+    chan: Arc<RwLock<mpsc::UnboundedSender<NetworkMessage>>>,
+    topic: &str,
+    message: PubsubMessage,
+) -> Result<(), ApiError> {
+    let topic_string = format!("/{}/{}/{}", TOPIC_PREFIX, topic, TOPIC_ENCODING_POSTFIX);
+
+    if let Err(e) = chan.write().try_send(NetworkMessage::Publish {
+        topics: vec![Topic::new(topic_string)],
+        message,
+    }) {
+        return Err(ApiError::ServerError(format!(
+            "Unable to send operation to network: {:?}",
+            e
+        )));
+    }
+
+    Ok(())
+}
+
 pub fn publish_attestation_to_network<T: BeaconChainTypes + 'static>(
     chan: Arc<RwLock<mpsc::UnboundedSender<NetworkMessage>>>,
     attestation: Attestation<T::EthSpec>,
 ) -> Result<(), ApiError> {
-    // create the network topic to send on
-    let topic_string = format!(
-        "/{}/{}/{}",
-        TOPIC_PREFIX, BEACON_ATTESTATION_TOPIC, TOPIC_ENCODING_POSTFIX
-    );
-    let topic = Topic::new(topic_string);
+    // create the network topic to send on. Only the attestation subnet is used, nodes which are
+    // not subscribed to it receive the attestation from an aggregator.
+    let topic = SubnetId::from_shard(attestation.data.crosslink.shard).topic();
     let message = PubsubMessage::Attestation(attestation.as_ssz_bytes());
 
     // Publish the attestation to the p2p network via gossipsub.
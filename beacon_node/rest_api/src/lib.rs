@@ -4,32 +4,44 @@ mod macros;
 extern crate lazy_static;
 extern crate network as client_network;
 
+mod access;
 mod beacon;
 mod config;
+mod debug;
 mod error;
+mod events;
 mod helpers;
 mod metrics;
 mod network;
 mod node;
+mod pool;
+mod proof;
 mod response_builder;
 mod spec;
+mod tls;
 mod url_query;
 mod validator;
 
+use access::AccessControl;
+use beacon_chain::events::EventBroadcaster;
 use beacon_chain::{BeaconChain, BeaconChainTypes};
 use client_network::NetworkMessage;
 use client_network::Service as NetworkService;
 use error::{ApiError, ApiResult};
 use eth2_config::Eth2Config;
 use futures::future::IntoFuture;
+use futures::Stream;
+use hyper::header;
 use hyper::rt::Future;
 use hyper::service::Service;
 use hyper::{Body, Method, Request, Response, Server};
 use parking_lot::RwLock;
-use slog::{info, o, warn};
+use slog::{debug, info, o, warn};
+use std::net::SocketAddr;
 use std::ops::Deref;
 use std::path::PathBuf;
 use std::sync::Arc;
+use tokio::net::TcpListener;
 use tokio::runtime::TaskExecutor;
 use tokio::sync::mpsc;
 use url_query::UrlQuery;
@@ -39,13 +51,18 @@ pub use config::Config as ApiConfig;
 
 type BoxFut = Box<dyn Future<Item = Response<Body>, Error = ApiError> + Send>;
 
+/// The number of TLS handshakes that may be in progress at once.
+const TLS_HANDSHAKE_CONCURRENCY: usize = 16;
+
 pub struct ApiService<T: BeaconChainTypes + 'static> {
     log: slog::Logger,
     beacon_chain: Arc<BeaconChain<T>>,
     db_path: DBPath,
     network_service: Arc<NetworkService<T>>,
     network_channel: Arc<RwLock<mpsc::UnboundedSender<NetworkMessage>>>,
+    events: EventBroadcaster<T::EthSpec>,
     eth2_config: Arc<Eth2Config>,
+    access_control: Arc<AccessControl>,
 }
 
 pub struct NetworkInfo<T: BeaconChainTypes> {
@@ -71,6 +88,33 @@ impl<T: BeaconChainTypes> Service for ApiService<T> {
         metrics::inc_counter(&metrics::REQUEST_COUNT);
         let timer = metrics::start_timer(&metrics::REQUEST_RESPONSE_TIME);
 
+        let origin = req.headers().get(header::ORIGIN).cloned();
+
+        // Answer CORS preflight requests before authentication, browsers do not send credentials
+        // with them.
+        if req.method() == Method::OPTIONS {
+            let response = self
+                .access_control
+                .preflight_response(origin.as_ref())
+                .unwrap_or_else(Into::into);
+            metrics::stop_timer(timer);
+            return Box::new(futures::future::ok(response));
+        }
+
+        if let Err(e) = self.access_control.authorize(&req) {
+            debug!(
+                self.log,
+                "Request denied";
+                "path" => req.uri().path(),
+                "error" => format!("{:?}", e)
+            );
+            let mut response: Response<Body> = e.into();
+            self.access_control
+                .add_cors_headers(origin.as_ref(), &mut response);
+            metrics::stop_timer(timer);
+            return Box::new(futures::future::ok(response));
+        }
+
         // Add all the useful bits into the request, so that we can pull them out in the individual
         // functions.
         req.extensions_mut()
@@ -84,6 +128,8 @@ impl<T: BeaconChainTypes> Service for ApiService<T> {
             .insert::<Arc<RwLock<mpsc::UnboundedSender<NetworkMessage>>>>(
                 self.network_channel.clone(),
             );
+        req.extensions_mut()
+            .insert::<EventBroadcaster<T::EthSpec>>(self.events.clone());
         req.extensions_mut()
             .insert::<Arc<Eth2Config>>(self.eth2_config.clone());
 
@@ -109,6 +155,27 @@ impl<T: BeaconChainTypes> Service for ApiService<T> {
             (&Method::GET, "/network/listen_addresses") => {
                 into_boxfut(network::get_listen_addresses::<T>(req))
             }
+            (&Method::GET, "/network/trusted_peers") => {
+                into_boxfut(network::get_trusted_peers::<T>(req))
+            }
+            (&Method::POST, "/network/trusted_peers") => network::post_trusted_peer::<T>(req),
+            (&Method::DELETE, "/network/trusted_peers") => {
+                into_boxfut(network::delete_trusted_peer::<T>(req))
+            }
+            (&Method::GET, "/network/denied_peers") => {
+                into_boxfut(network::get_denied_peers::<T>(req))
+            }
+            (&Method::POST, "/network/denied_peers") => network::post_denied_peer::<T>(req),
+            (&Method::DELETE, "/network/denied_peers") => {
+                into_boxfut(network::delete_denied_peer::<T>(req))
+            }
+            (&Method::GET, "/network/denied_ip_ranges") => {
+                into_boxfut(network::get_denied_ip_ranges::<T>(req))
+            }
+            (&Method::POST, "/network/denied_ip_ranges") => network::post_denied_ip_range::<T>(req),
+            (&Method::DELETE, "/network/denied_ip_ranges") => {
+                into_boxfut(network::delete_denied_ip_range::<T>(req))
+            }
 
             // Methods for Beacon Node
             (&Method::GET, "/beacon/head") => into_boxfut(beacon::get_head::<T>(req)),
@@ -125,7 +192,34 @@ impl<T: BeaconChainTypes> Service for ApiService<T> {
                 into_boxfut(helpers::implementation_pending_response(req))
             }
 
+            // Methods for the operation pool
+            (&Method::GET, "/beacon/pool/voluntary_exits") => {
+                into_boxfut(pool::get_voluntary_exits::<T>(req))
+            }
+            (&Method::POST, "/beacon/pool/voluntary_exits") => pool::post_voluntary_exit::<T>(req),
+            (&Method::GET, "/beacon/pool/proposer_slashings") => {
+                into_boxfut(pool::get_proposer_slashings::<T>(req))
+            }
+            (&Method::POST, "/beacon/pool/proposer_slashings") => {
+                pool::post_proposer_slashing::<T>(req)
+            }
+            (&Method::GET, "/beacon/pool/attester_slashings") => {
+                into_boxfut(pool::get_attester_slashings::<T>(req))
+            }
+            (&Method::POST, "/beacon/pool/attester_slashings") => {
+                pool::post_attester_slashing::<T>(req)
+            }
+
+            (&Method::GET, "/beacon/committees") => into_boxfut(beacon::get_committees::<T>(req)),
+            (&Method::GET, "/beacon/proposers") => into_boxfut(beacon::get_proposers::<T>(req)),
+
             (&Method::GET, "/beacon/validators") => into_boxfut(beacon::get_validators::<T>(req)),
+            (&Method::GET, "/beacon/validators/balances") => {
+                into_boxfut(beacon::get_validator_balances::<T>(req))
+            }
+            (&Method::GET, "/beacon/validators/rewards") => {
+                into_boxfut(beacon::get_validator_rewards::<T>(req))
+            }
             (&Method::GET, "/beacon/validators/indicies") => {
                 into_boxfut(helpers::implementation_pending_response(req))
             }
@@ -156,7 +250,17 @@ impl<T: BeaconChainTypes> Service for ApiService<T> {
             (&Method::GET, "/beacon/state/genesis") => {
                 into_boxfut(beacon::get_genesis_state::<T>(req))
             }
-            //TODO: Add aggreggate/filtered state lookups here, e.g. /beacon/validators/balances
+
+            // Merkle proofs against the state root
+            (&Method::GET, "/beacon/state/proof") => {
+                into_boxfut(proof::get_state_field_proof::<T>(req))
+            }
+            (&Method::GET, "/beacon/validators/proof") => {
+                into_boxfut(proof::get_validator_proof::<T>(req))
+            }
+            (&Method::GET, "/beacon/block_header/proof") => {
+                into_boxfut(proof::get_block_header_proof::<T>(req))
+            }
 
             // Methods for bootstrap and checking configuration
             (&Method::GET, "/spec") => into_boxfut(spec::get_spec::<T>(req)),
@@ -168,14 +272,21 @@ impl<T: BeaconChainTypes> Service for ApiService<T> {
             }
             (&Method::GET, "/spec/eth2_config") => into_boxfut(spec::get_eth2_config::<T>(req)),
 
+            (&Method::GET, "/debug/fork_choice") => into_boxfut(debug::get_fork_choice::<T>(req)),
+            (&Method::GET, "/debug/chain_dump") => into_boxfut(debug::get_chain_dump::<T>(req)),
+            (&Method::GET, "/debug/state_cache") => into_boxfut(debug::get_state_cache::<T>(req)),
+
             (&Method::GET, "/metrics") => into_boxfut(metrics::get_prometheus::<T>(req)),
 
+            // Server-sent event stream of chain events
+            (&Method::GET, "/events") => into_boxfut(events::get_events::<T>(req)),
+
             _ => Box::new(futures::future::err(ApiError::NotFound(
                 "Request path and/or method not found.".to_owned(),
             ))),
         };
 
-        let response = match result.wait() {
+        let mut response = match result.wait() {
             // Return the `hyper::Response`.
             Ok(response) => {
                 metrics::inc_counter(&metrics::SUCCESS_COUNT);
@@ -189,6 +300,9 @@ impl<T: BeaconChainTypes> Service for ApiService<T> {
             }
         };
 
+        self.access_control
+            .add_cors_headers(origin.as_ref(), &mut response);
+
         metrics::stop_timer(timer);
 
         Box::new(futures::future::ok(response))
@@ -200,10 +314,11 @@ pub fn start_server<T: BeaconChainTypes>(
     executor: &TaskExecutor,
     beacon_chain: Arc<BeaconChain<T>>,
     network_info: NetworkInfo<T>,
+    events: EventBroadcaster<T::EthSpec>,
     db_path: PathBuf,
     eth2_config: Eth2Config,
     log: &slog::Logger,
-) -> Result<exit_future::Signal, hyper::Error> {
+) -> Result<exit_future::Signal, String> {
     let log = log.new(o!("Service" => "Api"));
 
     // build a channel to kill the HTTP server
@@ -217,8 +332,11 @@ pub fn start_server<T: BeaconChainTypes>(
 
     let db_path = DBPath(db_path);
 
+    let access_control = Arc::new(AccessControl::from_config(config)?);
+    let requires_authentication = access_control.requires_authentication();
+
     // Get the address to bind to
-    let bind_addr = (config.listen_address, config.port).into();
+    let bind_addr: SocketAddr = (config.listen_address, config.port).into();
 
     // Clone our stateful objects, for use in service closure.
     let server_log = log.clone();
@@ -232,26 +350,68 @@ pub fn start_server<T: BeaconChainTypes>(
             db_path: db_path.clone(),
             network_service: network_info.network_service.clone(),
             network_channel: Arc::new(RwLock::new(network_info.network_chan.clone())),
+            events: events.clone(),
             eth2_config: eth2_config.clone(),
+            access_control: access_control.clone(),
         })
     };
 
     let log_clone = log.clone();
-    let server = Server::bind(&bind_addr)
-        .serve(service)
-        .with_graceful_shutdown(server_exit)
-        .map_err(move |e| {
-            warn!(
-            log_clone,
-            "API failed to start, Unable to bind"; "address" => format!("{:?}", e)
-            )
-        });
+    let log_server_error = move |e| {
+        warn!(
+        log_clone,
+        "API server failed"; "error" => format!("{:?}", e)
+        )
+    };
+
+    let server: Box<dyn Future<Item = (), Error = ()> + Send> =
+        match (&config.tls_cert, &config.tls_key) {
+            (Some(cert_path), Some(key_path)) => {
+                let acceptor = tls::tls_acceptor(cert_path, key_path)?;
+                let listener = TcpListener::bind(&bind_addr)
+                    .map_err(|e| format!("Unable to bind to {}: {:?}", bind_addr, e))?;
+
+                let handshake_log = log.clone();
+                let incoming = listener
+                    .incoming()
+                    .map(move |stream| {
+                        let log = handshake_log.clone();
+                        // A failed handshake only drops that connection, not the server.
+                        acceptor.accept(stream).then(move |result| {
+                            if let Err(e) = &result {
+                                debug!(log, "TLS handshake failed"; "error" => format!("{}", e));
+                            }
+                            Ok::<_, std::io::Error>(result.ok())
+                        })
+                    })
+                    .buffer_unordered(TLS_HANDSHAKE_CONCURRENCY)
+                    .filter_map(|stream| stream);
+
+                Box::new(
+                    Server::builder(incoming)
+                        .serve(service)
+                        .with_graceful_shutdown(server_exit)
+                        .map_err(log_server_error),
+                )
+            }
+            (None, None) => Box::new(
+                Server::try_bind(&bind_addr)
+                    .map_err(|e| format!("Unable to bind to {}: {:?}", bind_addr, e))?
+                    .serve(service)
+                    .with_graceful_shutdown(server_exit)
+                    .map_err(log_server_error),
+            ),
+            _ => return Err("A TLS certificate and key must be supplied together".to_string()),
+        };
 
     info!(
     log,
     "REST API started";
     "address" => format!("{}", config.listen_address),
     "port" => config.port,
+    "tls" => config.tls_cert.is_some(),
+    "authentication" => requires_authentication,
+    "writes" => config.allow_writes,
     );
 
     executor.spawn(server);
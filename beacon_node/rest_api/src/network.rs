@@ -1,9 +1,13 @@
-use crate::error::ApiResult;
+use crate::error::{ApiError, ApiResult};
+use crate::helpers::{check_content_type_for_json, parse_json_body};
 use crate::response_builder::ResponseBuilder;
-use crate::NetworkService;
+use crate::{BoxFut, NetworkService, UrlQuery};
 use beacon_chain::BeaconChainTypes;
+use eth2_libp2p::peer_lists::{parse_peer_id, IpRange};
 use eth2_libp2p::{Multiaddr, PeerId};
+use futures::future::Future;
 use hyper::{Body, Request};
+use rest_types::TrustedPeer;
 use std::sync::Arc;
 
 /// HTTP handler to return the list of libp2p multiaddr the client is listening on.
@@ -76,3 +80,158 @@ pub fn get_peer_list<T: BeaconChainTypes>(req: Request<Body>) -> ApiResult {
         .collect();
     ResponseBuilder::new(&req)?.body_no_ssz(&connected_peers)
 }
+
+/// HTTP handler to return the list of trusted peers.
+pub fn get_trusted_peers<T: BeaconChainTypes>(req: Request<Body>) -> ApiResult {
+    let trusted_peers: Vec<TrustedPeer> = network_service::<T>(&req)
+        .libp2p_service()
+        .lock()
+        .trusted_peers()
+        .into_iter()
+        .map(|(peer_id, address)| TrustedPeer {
+            peer_id: peer_id.to_base58(),
+            address: address.map(|address| address.to_string()),
+        })
+        .collect();
+    ResponseBuilder::new(&req)?.body_no_ssz(&trusted_peers)
+}
+
+/// HTTP handler to add a trusted peer.
+///
+/// The body is a JSON string containing either a peer id or a multiaddr with a `/p2p/` component.
+/// Returns the peer id of the trusted peer.
+pub fn post_trusted_peer<T: BeaconChainTypes + 'static>(req: Request<Body>) -> BoxFut {
+    try_future!(check_content_type_for_json(&req));
+    let network = network_service::<T>(&req);
+    let response_builder = ResponseBuilder::new(&req);
+
+    Box::new(parse_json_body::<String>(req).and_then(move |peer| {
+        let peer_id = network
+            .libp2p_service()
+            .lock()
+            .add_trusted_peer(&peer)
+            .map_err(ApiError::BadRequest)?;
+        response_builder?.body_no_ssz(&peer_id.to_base58())
+    }))
+}
+
+/// HTTP handler to remove the trusted peer given by the `peer_id` query parameter.
+pub fn delete_trusted_peer<T: BeaconChainTypes>(req: Request<Body>) -> ApiResult {
+    let peer_id = peer_id_from_query(&req)?;
+    if network_service::<T>(&req)
+        .libp2p_service()
+        .lock()
+        .remove_trusted_peer(&peer_id)
+    {
+        ResponseBuilder::new(&req)?.body_no_ssz(&())
+    } else {
+        Err(ApiError::NotFound(format!(
+            "Peer is not trusted: {}",
+            peer_id
+        )))
+    }
+}
+
+/// HTTP handler to return the list of denied peer ids.
+pub fn get_denied_peers<T: BeaconChainTypes>(req: Request<Body>) -> ApiResult {
+    let denied_peers: Vec<String> = network_service::<T>(&req)
+        .libp2p_service()
+        .lock()
+        .denied_peers()
+        .iter()
+        .map(PeerId::to_base58)
+        .collect();
+    ResponseBuilder::new(&req)?.body_no_ssz(&denied_peers)
+}
+
+/// HTTP handler to deny (ban) a peer, disconnecting it if it is connected.
+///
+/// The body is a JSON string containing the peer id.
+pub fn post_denied_peer<T: BeaconChainTypes + 'static>(req: Request<Body>) -> BoxFut {
+    try_future!(check_content_type_for_json(&req));
+    let network = network_service::<T>(&req);
+    let response_builder = ResponseBuilder::new(&req);
+
+    Box::new(parse_json_body::<String>(req).and_then(move |peer_id| {
+        let peer_id = parse_peer_id(&peer_id).map_err(ApiError::BadRequest)?;
+        network.libp2p_service().lock().deny_peer(peer_id);
+        response_builder?.body_no_ssz(&())
+    }))
+}
+
+/// HTTP handler to remove the peer given by the `peer_id` query parameter from the deny list.
+pub fn delete_denied_peer<T: BeaconChainTypes>(req: Request<Body>) -> ApiResult {
+    let peer_id = peer_id_from_query(&req)?;
+    if network_service::<T>(&req)
+        .libp2p_service()
+        .lock()
+        .allow_peer(&peer_id)
+    {
+        ResponseBuilder::new(&req)?.body_no_ssz(&())
+    } else {
+        Err(ApiError::NotFound(format!(
+            "Peer is not denied: {}",
+            peer_id
+        )))
+    }
+}
+
+/// HTTP handler to return the list of denied IP ranges, in CIDR notation.
+pub fn get_denied_ip_ranges<T: BeaconChainTypes>(req: Request<Body>) -> ApiResult {
+    let denied_ip_ranges: Vec<String> = network_service::<T>(&req)
+        .libp2p_service()
+        .lock()
+        .denied_ip_ranges()
+        .iter()
+        .map(IpRange::to_string)
+        .collect();
+    ResponseBuilder::new(&req)?.body_no_ssz(&denied_ip_ranges)
+}
+
+/// HTTP handler to reject future connections from an IP range.
+///
+/// The body is a JSON string containing the IP range in CIDR notation (e.g., `10.0.0.0/8`).
+pub fn post_denied_ip_range<T: BeaconChainTypes + 'static>(req: Request<Body>) -> BoxFut {
+    try_future!(check_content_type_for_json(&req));
+    let network = network_service::<T>(&req);
+    let response_builder = ResponseBuilder::new(&req);
+
+    Box::new(parse_json_body::<String>(req).and_then(move |ip_range| {
+        let ip_range = ip_range.parse::<IpRange>().map_err(ApiError::BadRequest)?;
+        network.libp2p_service().lock().deny_ip_range(ip_range);
+        response_builder?.body_no_ssz(&())
+    }))
+}
+
+/// HTTP handler to remove the IP range given by the `ip_range` query parameter from the deny
+/// list.
+pub fn delete_denied_ip_range<T: BeaconChainTypes>(req: Request<Body>) -> ApiResult {
+    let ip_range = UrlQuery::from_request(&req)?
+        .only_one("ip_range")?
+        .parse::<IpRange>()
+        .map_err(ApiError::BadRequest)?;
+    if network_service::<T>(&req)
+        .libp2p_service()
+        .lock()
+        .allow_ip_range(&ip_range)
+    {
+        ResponseBuilder::new(&req)?.body_no_ssz(&())
+    } else {
+        Err(ApiError::NotFound(format!(
+            "IP range is not denied: {}",
+            ip_range
+        )))
+    }
+}
+
+fn network_service<T: BeaconChainTypes>(req: &Request<Body>) -> Arc<NetworkService<T>> {
+    req.extensions()
+        .get::<Arc<NetworkService<T>>>()
+        .expect("The network service should always be there, we put it there")
+        .clone()
+}
+
+/// Parses the `peer_id` query parameter of a request.
+fn peer_id_from_query(req: &Request<Body>) -> Result<PeerId, ApiError> {
+    parse_peer_id(&UrlQuery::from_request(req)?.only_one("peer_id")?).map_err(ApiError::BadRequest)
+}
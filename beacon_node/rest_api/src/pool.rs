@@ -0,0 +1,136 @@
+use crate::helpers::{
+    check_content_type_for_json, get_beacon_chain_from_request, get_logger_from_request,
+    parse_json_body, publish_operation_to_network,
+};
+use crate::response_builder::ResponseBuilder;
+use crate::{ApiError, ApiResult, BoxFut, NetworkMessage};
+use beacon_chain::BeaconChainTypes;
+use eth2_libp2p::{
+    PubsubMessage, ATTESTER_SLASHING_TOPIC, PROPOSER_SLASHING_TOPIC, VOLUNTARY_EXIT_TOPIC,
+};
+use futures::future::Future;
+use hyper::{Body, Request};
+use parking_lot::RwLock;
+use slog::info;
+use ssz::Encode;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use types::{AttesterSlashing, ProposerSlashing, VoluntaryExit};
+
+/// HTTP handler to return all voluntary exits in the operation pool.
+pub fn get_voluntary_exits<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+    ResponseBuilder::new(&req)?.body(&beacon_chain.op_pool.get_all_voluntary_exits())
+}
+
+/// HTTP handler to validate a JSON `VoluntaryExit`, add it to the operation pool and publish it
+/// to the network.
+pub fn post_voluntary_exit<T: BeaconChainTypes + 'static>(req: Request<Body>) -> BoxFut {
+    try_future!(check_content_type_for_json(&req));
+    let beacon_chain = try_future!(get_beacon_chain_from_request::<T>(&req));
+    let log = get_logger_from_request(&req);
+    let network_chan = network_channel(&req);
+    let response_builder = ResponseBuilder::new(&req);
+
+    Box::new(parse_json_body::<VoluntaryExit>(req).and_then(move |exit| {
+        beacon_chain
+            .process_voluntary_exit(exit.clone())
+            .map_err(|e| ApiError::BadRequest(format!("Invalid voluntary exit: {:?}", e)))?;
+
+        info!(
+            log,
+            "Publishing voluntary exit from API";
+            "validator_index" => exit.validator_index,
+            "epoch" => exit.epoch,
+        );
+        publish_operation_to_network(
+            network_chan,
+            VOLUNTARY_EXIT_TOPIC,
+            PubsubMessage::VoluntaryExit(exit.as_ssz_bytes()),
+        )?;
+
+        response_builder?.body_no_ssz(&())
+    }))
+}
+
+/// HTTP handler to return all proposer slashings in the operation pool.
+pub fn get_proposer_slashings<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+    ResponseBuilder::new(&req)?.body(&beacon_chain.op_pool.get_all_proposer_slashings())
+}
+
+/// HTTP handler to validate a JSON `ProposerSlashing`, add it to the operation pool and publish
+/// it to the network.
+pub fn post_proposer_slashing<T: BeaconChainTypes + 'static>(req: Request<Body>) -> BoxFut {
+    try_future!(check_content_type_for_json(&req));
+    let beacon_chain = try_future!(get_beacon_chain_from_request::<T>(&req));
+    let log = get_logger_from_request(&req);
+    let network_chan = network_channel(&req);
+    let response_builder = ResponseBuilder::new(&req);
+
+    Box::new(
+        parse_json_body::<ProposerSlashing>(req).and_then(move |slashing| {
+            beacon_chain
+                .process_proposer_slashing(slashing.clone())
+                .map_err(|e| ApiError::BadRequest(format!("Invalid proposer slashing: {:?}", e)))?;
+
+            info!(
+                log,
+                "Publishing proposer slashing from API";
+                "proposer_index" => slashing.proposer_index,
+            );
+            publish_operation_to_network(
+                network_chan,
+                PROPOSER_SLASHING_TOPIC,
+                PubsubMessage::ProposerSlashing(slashing.as_ssz_bytes()),
+            )?;
+
+            response_builder?.body_no_ssz(&())
+        }),
+    )
+}
+
+/// HTTP handler to return all attester slashings in the operation pool.
+pub fn get_attester_slashings<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+    ResponseBuilder::new(&req)?.body(&beacon_chain.op_pool.get_all_attester_slashings())
+}
+
+/// HTTP handler to validate a JSON `AttesterSlashing`, add it to the operation pool and publish
+/// it to the network.
+pub fn post_attester_slashing<T: BeaconChainTypes + 'static>(req: Request<Body>) -> BoxFut {
+    try_future!(check_content_type_for_json(&req));
+    let beacon_chain = try_future!(get_beacon_chain_from_request::<T>(&req));
+    let log = get_logger_from_request(&req);
+    let network_chan = network_channel(&req);
+    let response_builder = ResponseBuilder::new(&req);
+
+    Box::new(
+        parse_json_body::<AttesterSlashing<T::EthSpec>>(req).and_then(move |slashing| {
+            beacon_chain
+                .process_attester_slashing(slashing.clone())
+                .map_err(|e| ApiError::BadRequest(format!("Invalid attester slashing: {:?}", e)))?;
+
+            info!(
+                log,
+                "Publishing attester slashing from API";
+                "target_epoch" => slashing.attestation_1.data.target.epoch,
+            );
+            publish_operation_to_network(
+                network_chan,
+                ATTESTER_SLASHING_TOPIC,
+                PubsubMessage::AttesterSlashing(slashing.as_ssz_bytes()),
+            )?;
+
+            response_builder?.body_no_ssz(&())
+        }),
+    )
+}
+
+/// Returns the channel to the network service that was placed in the request.
+fn network_channel(req: &Request<Body>) -> Arc<RwLock<mpsc::UnboundedSender<NetworkMessage>>> {
+    req.extensions()
+        .get::<Arc<RwLock<mpsc::UnboundedSender<NetworkMessage>>>>()
+        .expect("Should always get the network channel from the request, since we put it in there.")
+        .clone()
+}
@@ -0,0 +1,343 @@
+//! Endpoints which return parts of a `BeaconState` alongside a Merkle proof against the state
+//! root, allowing light clients to verify them without trusting this node.
+
+use crate::helpers::*;
+use crate::response_builder::ResponseBuilder;
+use crate::{ApiError, ApiResult, UrlQuery};
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use hyper::{Body, Request};
+use merkle_proof::MerkleTree;
+use rest_types::{BlockHeaderProof, MerkleProof, StateFieldProof, ValidatorProof};
+use tree_hash::TreeHash;
+use types::{BeaconState, EthSpec, Hash256, Slot, Unsigned};
+
+/// The depth of the tree formed by the field roots of a `BeaconState`.
+const STATE_FIELDS_DEPTH: usize = 5;
+
+/// HTTP handler to return the `field` of the state identified by the `state_id` query parameter,
+/// with a proof of its root against the state root.
+pub fn get_state_field_proof<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+    let (state_root, state) = state_from_id(&beacon_chain, state_id_from_request(&req)?)?;
+
+    let (_key, field) = UrlQuery::from_request(&req)?.first_of(&["field"])?;
+    let fields = state_fields(&state);
+    let field_index = fields.index(&field)?;
+
+    let value = serde_json::to_value(&state)
+        .map_err(|e| ApiError::ServerError(format!("Unable to serialize state: {:?}", e)))?
+        .get(&field)
+        .cloned()
+        .ok_or_else(|| ApiError::ServerError(format!("Field {} not serialized", field)))?;
+
+    let branch = Branch::new(&fields.roots(), field_index, STATE_FIELDS_DEPTH);
+
+    let response = StateFieldProof {
+        field,
+        value,
+        proof: branch.into_proof(state_root)?,
+    };
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&response)
+}
+
+/// HTTP handler to return the validator with the `validator_index` query parameter in the state
+/// identified by the `state_id` query parameter, with a proof against the state root.
+pub fn get_validator_proof<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+    let (state_root, state) = state_from_id(&beacon_chain, state_id_from_request(&req)?)?;
+
+    let (_key, value) = UrlQuery::from_request(&req)?.first_of(&["validator_index"])?;
+    let validator_index = value.parse::<usize>().map_err(|e| {
+        ApiError::BadRequest(format!("Invalid validator index, must be a usize. {:?}", e))
+    })?;
+    let validator = state
+        .validators
+        .get(validator_index)
+        .cloned()
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("No validator with index {}", validator_index))
+        })?;
+
+    let validator_roots: Vec<Hash256> = state
+        .validators
+        .iter()
+        .map(|validator| Hash256::from_slice(&validator.tree_hash_root()))
+        .collect();
+
+    let branch = Branch::new(
+        &validator_roots,
+        validator_index,
+        depth_of::<<T::EthSpec as EthSpec>::ValidatorRegistryLimit>(),
+    )
+    .mix_in_length(state.validators.len())
+    .extend_into_state(&state, "validators")?;
+
+    let response = ValidatorProof {
+        validator_index,
+        validator,
+        proof: branch.into_proof(state_root)?,
+    };
+
+    ResponseBuilder::new(&req)?.body(&response)
+}
+
+/// HTTP handler to return the header of the block at the `slot` query parameter, with a proof of
+/// its root against the root of the state identified by the `state_id` query parameter.
+///
+/// Blocks of the last `SLOTS_PER_HISTORICAL_ROOT` slots are proven via the `block_roots` of the
+/// state, older blocks via its `historical_roots`.
+pub fn get_block_header_proof<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
+    let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
+    let (state_root, state) = state_from_id(&beacon_chain, state_id_from_request(&req)?)?;
+
+    let (_key, value) = UrlQuery::from_request(&req)?.first_of(&["slot"])?;
+    let slot = parse_slot(&value)?;
+
+    if slot >= state.slot {
+        return Err(ApiError::BadRequest(format!(
+            "Slot {} is not prior to the state slot {}",
+            slot, state.slot
+        )));
+    }
+
+    let slots_per_historical_root = T::EthSpec::slots_per_historical_root();
+    let roots_depth = depth_of::<<T::EthSpec as EthSpec>::SlotsPerHistoricalRoot>();
+    let roots_index = slot.as_usize() % slots_per_historical_root;
+
+    let (block_root, branch) = if state.slot <= slot + slots_per_historical_root as u64 {
+        let block_roots = state.block_roots.to_vec();
+        let branch = Branch::new(&block_roots, roots_index, roots_depth)
+            .extend_into_state(&state, "block_roots")?;
+
+        (block_roots[roots_index], branch)
+    } else {
+        historical_block_root_branch(&beacon_chain, &state, slot)?
+    };
+
+    let header = block_from_root(&beacon_chain, block_root)?.block_header();
+
+    let response = BlockHeaderProof {
+        slot,
+        header,
+        proof: branch.into_proof(state_root)?,
+    };
+
+    ResponseBuilder::new(&req)?.body(&response)
+}
+
+/// Returns the root of the block at `slot` and its branch through the `historical_roots` of
+/// `state`.
+fn historical_block_root_branch<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    state: &BeaconState<T::EthSpec>,
+    slot: Slot,
+) -> Result<(Hash256, Branch), ApiError> {
+    let slots_per_historical_root = T::EthSpec::slots_per_historical_root();
+    let period = slot.as_usize() / slots_per_historical_root;
+
+    if period >= state.historical_roots.len() {
+        return Err(ApiError::NotFound(format!(
+            "Slot {} is not in the historical roots of the state",
+            slot
+        )));
+    }
+
+    // The `HistoricalBatch` of a period is appended during the transition into the first slot of
+    // the next period, the state at that slot still holds all the roots of the batch.
+    let (_root, batch_state) = state_at_slot(
+        beacon_chain,
+        Slot::from((period + 1) * slots_per_historical_root),
+    )?;
+
+    let block_roots = batch_state.block_roots.to_vec();
+    let roots_index = slot.as_usize() % slots_per_historical_root;
+    let batch_roots = [
+        Hash256::from_slice(&batch_state.block_roots.tree_hash_root()),
+        Hash256::from_slice(&batch_state.state_roots.tree_hash_root()),
+    ];
+
+    let branch = Branch::new(
+        &block_roots,
+        roots_index,
+        depth_of::<<T::EthSpec as EthSpec>::SlotsPerHistoricalRoot>(),
+    )
+    // The `block_roots` are the first of the two fields of a `HistoricalBatch`.
+    .extend(&batch_roots, 0, 1)
+    .extend(
+        &state.historical_roots.to_vec(),
+        period,
+        depth_of::<<T::EthSpec as EthSpec>::HistoricalRootsLimit>(),
+    )
+    .mix_in_length(state.historical_roots.len())
+    .extend_into_state(state, "historical_roots")?;
+
+    Ok((block_roots[roots_index], branch))
+}
+
+/// A Merkle branch from a leaf up to the root of some (sub)tree.
+struct Branch {
+    leaf: Hash256,
+    /// The sibling of each node on the path from `leaf`, ordered bottom-up.
+    siblings: Vec<Hash256>,
+    /// The position of `leaf` in the tree, where bit `i` is set if the node at height `i` of the
+    /// path is a right child.
+    index: u64,
+}
+
+impl Branch {
+    /// Returns the branch of `leaves[index]` in the tree of `depth` with the given `leaves`.
+    fn new(leaves: &[Hash256], index: usize, depth: usize) -> Self {
+        let (leaf, siblings) = MerkleTree::create(leaves, depth).generate_proof(index, depth);
+
+        Self {
+            leaf,
+            siblings,
+            index: index as u64,
+        }
+    }
+
+    /// Extends the branch into the tree of `depth` with the given `leaves`, where the root of the
+    /// branch is `leaves[index]`.
+    fn extend(mut self, leaves: &[Hash256], index: usize, depth: usize) -> Self {
+        let (_root, siblings) = MerkleTree::create(leaves, depth).generate_proof(index, depth);
+
+        self.index |= (index as u64) << self.siblings.len();
+        self.siblings.extend(siblings);
+        self
+    }
+
+    /// Extends the branch from the root of the `field` of `state` to the state root.
+    fn extend_into_state<E: EthSpec>(
+        self,
+        state: &BeaconState<E>,
+        field: &str,
+    ) -> Result<Self, ApiError> {
+        let fields = state_fields(state);
+        let index = fields.index(field)?;
+        Ok(self.extend(&fields.roots(), index, STATE_FIELDS_DEPTH))
+    }
+
+    /// Extends the branch from the root of the items of a list to the root of the list, which
+    /// mixes in the `length` of the list as the right sibling.
+    fn mix_in_length(mut self, length: usize) -> Self {
+        let mut length_chunk = [0; 32];
+        length_chunk[..8].copy_from_slice(&(length as u64).to_le_bytes());
+
+        self.siblings.push(Hash256::from(length_chunk));
+        self
+    }
+
+    /// Converts the branch into a `MerkleProof` against `state_root`, checking that it is valid.
+    fn into_proof(self, state_root: Hash256) -> Result<MerkleProof, ApiError> {
+        let proof = MerkleProof {
+            state_root,
+            leaf: self.leaf,
+            generalized_index: (1 << self.siblings.len()) | self.index,
+            branch: self.siblings,
+        };
+
+        if proof.verify() {
+            Ok(proof)
+        } else {
+            Err(ApiError::ServerError(
+                "Unable to build a valid proof against the state root".to_string(),
+            ))
+        }
+    }
+}
+
+/// Returns the depth of a tree with `N` leaves, where `N` is a power of two.
+fn depth_of<N: Unsigned>() -> usize {
+    N::to_usize().trailing_zeros() as usize
+}
+
+/// The name and tree hash root of each field of a `BeaconState`.
+struct StateFields(Vec<(&'static str, Vec<u8>)>);
+
+impl StateFields {
+    /// Returns the index of the field `name`.
+    fn index(&self, name: &str) -> Result<usize, ApiError> {
+        self.0
+            .iter()
+            .position(|(field, _root)| *field == name)
+            .ok_or_else(|| ApiError::BadRequest(format!("Unknown state field {:?}", name)))
+    }
+
+    /// Returns the tree hash root of each field, in order.
+    fn roots(&self) -> Vec<Hash256> {
+        self.0
+            .iter()
+            .map(|(_field, root)| Hash256::from_slice(root))
+            .collect()
+    }
+}
+
+/// Returns the name and tree hash root of each field of `state`, in the order they are hashed.
+///
+/// Must be kept in sync with the definition of `BeaconState`.
+fn state_fields<E: EthSpec>(state: &BeaconState<E>) -> StateFields {
+    StateFields(vec![
+        ("genesis_time", state.genesis_time.tree_hash_root()),
+        ("slot", state.slot.tree_hash_root()),
+        ("fork", state.fork.tree_hash_root()),
+        (
+            "latest_block_header",
+            state.latest_block_header.tree_hash_root(),
+        ),
+        ("block_roots", state.block_roots.tree_hash_root()),
+        ("state_roots", state.state_roots.tree_hash_root()),
+        ("historical_roots", state.historical_roots.tree_hash_root()),
+        ("eth1_data", state.eth1_data.tree_hash_root()),
+        ("eth1_data_votes", state.eth1_data_votes.tree_hash_root()),
+        (
+            "eth1_deposit_index",
+            state.eth1_deposit_index.tree_hash_root(),
+        ),
+        ("validators", state.validators.tree_hash_root()),
+        ("balances", state.balances.tree_hash_root()),
+        ("start_shard", state.start_shard.tree_hash_root()),
+        ("randao_mixes", state.randao_mixes.tree_hash_root()),
+        (
+            "active_index_roots",
+            state.active_index_roots.tree_hash_root(),
+        ),
+        (
+            "compact_committees_roots",
+            state.compact_committees_roots.tree_hash_root(),
+        ),
+        ("slashings", state.slashings.tree_hash_root()),
+        (
+            "previous_epoch_attestations",
+            state.previous_epoch_attestations.tree_hash_root(),
+        ),
+        (
+            "current_epoch_attestations",
+            state.current_epoch_attestations.tree_hash_root(),
+        ),
+        (
+            "previous_crosslinks",
+            state.previous_crosslinks.tree_hash_root(),
+        ),
+        (
+            "current_crosslinks",
+            state.current_crosslinks.tree_hash_root(),
+        ),
+        (
+            "justification_bits",
+            state.justification_bits.tree_hash_root(),
+        ),
+        (
+            "previous_justified_checkpoint",
+            state.previous_justified_checkpoint.tree_hash_root(),
+        ),
+        (
+            "current_justified_checkpoint",
+            state.current_justified_checkpoint.tree_hash_root(),
+        ),
+        (
+            "finalized_checkpoint",
+            state.finalized_checkpoint.tree_hash_root(),
+        ),
+    ])
+}
@@ -0,0 +1,41 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use tokio_rustls::rustls::{NoClientAuth, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+/// Builds a `TlsAcceptor` from the PEM encoded certificate chain at `cert_path` and the PEM
+/// encoded private key (PKCS #8 or RSA) at `key_path`.
+pub fn tls_acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor, String> {
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| format!("Unable to open {:?}: {}", path, e))
+    };
+
+    let cert_chain = certs(&mut open(cert_path)?)
+        .map_err(|_| format!("Unable to parse certificates in {:?}", cert_path))?;
+    if cert_chain.is_empty() {
+        return Err(format!("No certificates found in {:?}", cert_path));
+    }
+
+    let mut keys = pkcs8_private_keys(&mut open(key_path)?)
+        .map_err(|_| format!("Unable to parse private key in {:?}", key_path))?;
+    if keys.is_empty() {
+        keys = rsa_private_keys(&mut open(key_path)?)
+            .map_err(|_| format!("Unable to parse private key in {:?}", key_path))?;
+    }
+    let key = keys
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("No private key found in {:?}", key_path))?;
+
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(cert_chain, key)
+        .map_err(|e| format!("Invalid certificate or private key: {:?}", e))?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
@@ -23,16 +23,20 @@ impl<'a> UrlQuery<'a> {
     /// Returns the first `(key, value)` pair found where the `key` is in `keys`.
     ///
     /// If no match is found, an `InvalidQueryParams` error is returned.
-    pub fn first_of(mut self, keys: &[&str]) -> Result<(String, String), ApiError> {
+    pub fn first_of(self, keys: &[&str]) -> Result<(String, String), ApiError> {
+        self.first_of_opt(keys).ok_or_else(|| {
+            ApiError::BadRequest(format!(
+                "URL query must contain at least one of the following keys: {:?}",
+                keys
+            ))
+        })
+    }
+
+    /// Returns the first `(key, value)` pair found where the `key` is in `keys`, if any.
+    pub fn first_of_opt(mut self, keys: &[&str]) -> Option<(String, String)> {
         self.0
             .find(|(key, _value)| keys.contains(&&**key))
             .map(|(key, value)| (key.into_owned(), value.into_owned()))
-            .ok_or_else(|| {
-                ApiError::BadRequest(format!(
-                    "URL query must contain at least one of the following keys: {:?}",
-                    keys
-                ))
-            })
     }
 
     /// Returns the value for `key`, if and only if `key` is the only key present in the query
@@ -1,6 +1,7 @@
 use crate::helpers::{
     check_content_type_for_json, get_beacon_chain_from_request, get_logger_from_request,
     parse_pubkey, publish_attestation_to_network, publish_beacon_block_to_network,
+    selection_proof_from_request, state_from_id, state_id_from_request,
 };
 use crate::response_builder::ResponseBuilder;
 use crate::{ApiError, ApiResult, BoxFut, UrlQuery};
@@ -11,43 +12,23 @@ use futures::stream::Stream;
 use hyper::{Body, Request};
 use network::NetworkMessage;
 use parking_lot::RwLock;
-use serde::{Deserialize, Serialize};
+use rest_types::ValidatorDuty;
 use slog::{info, trace, warn};
 use std::sync::Arc;
 use tokio;
 use tokio::sync::mpsc;
 use types::beacon_state::EthSpec;
-use types::{Attestation, BeaconBlock, BitList, Epoch, RelativeEpoch, Shard, Slot};
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ValidatorDuty {
-    /// The validator's BLS public key, uniquely identifying them. _48-bytes, hex encoded with 0x prefix, case insensitive._
-    pub validator_pubkey: String,
-    /// The slot at which the validator must attest.
-    pub attestation_slot: Option<Slot>,
-    /// The shard in which the validator must attest.
-    pub attestation_shard: Option<Shard>,
-    /// The slot in which a validator must propose a block, or `null` if block production is not required.
-    pub block_proposal_slot: Option<Slot>,
-}
-
-impl ValidatorDuty {
-    pub fn new() -> ValidatorDuty {
-        ValidatorDuty {
-            validator_pubkey: "".to_string(),
-            attestation_slot: None,
-            attestation_shard: None,
-            block_proposal_slot: None,
-        }
-    }
-}
+use types::{Attestation, BeaconBlock, BitList, Epoch, RelativeEpoch, Slot};
 
 /// HTTP Handler to retrieve a the duties for a set of validators during a particular epoch
+///
+/// The duties are computed from the state identified by the `state_id` query parameter, which
+/// defaults to the head.
 pub fn get_validator_duties<T: BeaconChainTypes + 'static>(req: Request<Body>) -> ApiResult {
     let log = get_logger_from_request(&req);
     slog::trace!(log, "Validator duties requested of API: {:?}", &req);
     let beacon_chain = get_beacon_chain_from_request::<T>(&req)?;
-    let mut head_state = beacon_chain.head().beacon_state;
+    let (_root, mut head_state) = state_from_id(&beacon_chain, state_id_from_request(&req)?)?;
 
     slog::trace!(log, "Got head state from request.");
     // Parse and check query parameters
@@ -356,8 +337,12 @@ pub fn get_new_attestation<T: BeaconChainTypes + 'static>(req: Request<Body>) ->
 }
 
 /// HTTP Handler to publish an Attestation, which has been signed by a validator.
+///
+/// If the `selection_proof` query parameter is supplied and selects the validator to aggregate,
+/// the node publishes the aggregate of its committee later in the slot.
 pub fn publish_attestation<T: BeaconChainTypes + 'static>(req: Request<Body>) -> BoxFut {
     try_future!(check_content_type_for_json(&req));
+    let selection_proof = try_future!(selection_proof_from_request(&req));
     let log = get_logger_from_request(&req);
     let beacon_chain = try_future!(get_beacon_chain_from_request::<T>(&req));
     // Get the network sending channel from the request, for later transmission
@@ -391,7 +376,17 @@ pub fn publish_attestation<T: BeaconChainTypes + 'static>(req: Request<Body>) ->
                 Ok(AttestationProcessingOutcome::Processed) => {
                     // Block was processed, publish via gossipsub
                     info!(log, "Processed valid attestation from API, transmitting to network.");
-                    publish_attestation_to_network::<T>(network_chan, attestation)
+                    publish_attestation_to_network::<T>(network_chan, attestation.clone())?;
+
+                    // If the validator is selected to aggregate, the client publishes its
+                    // aggregate later in the slot.
+                    if let Some(selection_proof) = selection_proof {
+                        if let Err(e) = beacon_chain.register_aggregation_duty(&attestation, selection_proof) {
+                            warn!(log, "Failed to register aggregation duty"; "error" => format!("{:?}", e));
+                        }
+                    }
+
+                    Ok(())
                 }
                 Ok(outcome) => {
                     warn!(log, "Attestation could not be processed, but is being sent to the network anyway."; "outcome" => format!("{:?}", outcome));
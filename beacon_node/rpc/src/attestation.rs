@@ -1,7 +1,6 @@
-use beacon_chain::{BeaconChain, BeaconChainError, BeaconChainTypes};
+use beacon_chain::{AttestationProcessingOutcome, BeaconChain, BeaconChainError, BeaconChainTypes};
 use eth2_libp2p::PubsubMessage;
-use eth2_libp2p::Topic;
-use eth2_libp2p::{BEACON_ATTESTATION_TOPIC, TOPIC_ENCODING_POSTFIX, TOPIC_PREFIX};
+use eth2_libp2p::SubnetId;
 use futures::Future;
 use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
 use network::NetworkMessage;
@@ -14,7 +13,7 @@ use slog::{error, info, trace, warn};
 use ssz::{ssz_encode, Decode, Encode};
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use types::{Attestation, Slot};
+use types::{Attestation, Signature, Slot};
 
 #[derive(Clone)]
 pub struct AttestationServiceInstance<T: BeaconChainTypes> {
@@ -98,8 +97,25 @@ impl<T: BeaconChainTypes> AttestationService for AttestationServiceInstance<T> {
             }
         };
 
+        let selection_proof = match req.get_selection_proof() {
+            bytes if bytes.is_empty() => None,
+            bytes => match Signature::from_ssz_bytes(bytes) {
+                Ok(selection_proof) => Some(selection_proof),
+                Err(_) => {
+                    let log_clone = self.log.clone();
+                    let f = sink
+                        .fail(RpcStatus::new(
+                            RpcStatusCode::InvalidArgument,
+                            Some("Invalid selection proof".to_string()),
+                        ))
+                        .map_err(move |_| warn!(log_clone, "failed to reply {:?}", req));
+                    return ctx.spawn(f);
+                }
+            },
+        };
+
         match self.chain.process_attestation(attestation.clone()) {
-            Ok(_) => {
+            Ok(outcome) => {
                 // Attestation was successfully processed.
                 info!(
                     self.log,
@@ -108,12 +124,9 @@ impl<T: BeaconChainTypes> AttestationService for AttestationServiceInstance<T> {
                     "shard" => attestation.data.crosslink.shard,
                 );
 
-                // valid attestation, propagate to the network
-                let topic_string = format!(
-                    "/{}/{}/{}",
-                    TOPIC_PREFIX, BEACON_ATTESTATION_TOPIC, TOPIC_ENCODING_POSTFIX
-                );
-                let topic = Topic::new(topic_string);
+                // valid attestation, propagate to the network on its subnet. Nodes which are not
+                // subscribed to the subnet receive it from an aggregator.
+                let topic = SubnetId::from_shard(attestation.data.crosslink.shard).topic();
                 let message = PubsubMessage::Attestation(attestation.as_ssz_bytes());
 
                 self.network_chan
@@ -129,6 +142,23 @@ impl<T: BeaconChainTypes> AttestationService for AttestationServiceInstance<T> {
                         );
                     });
 
+                // If the validator is selected to aggregate, the client publishes its aggregate
+                // later in the slot.
+                if let (AttestationProcessingOutcome::Processed, Some(selection_proof)) =
+                    (outcome, selection_proof)
+                {
+                    if let Err(e) = self
+                        .chain
+                        .register_aggregation_duty(&attestation, selection_proof)
+                    {
+                        warn!(
+                            self.log,
+                            "Failed to register aggregation duty";
+                            "error" => format!("{:?}", e)
+                        );
+                    }
+                }
+
                 resp.set_success(true);
             }
             Err(BeaconChainError::AttestationValidationError(e)) => {
@@ -55,6 +55,7 @@ pub fn start_server<T: BeaconChainTypes + Clone + 'static>(
     let validator_service = {
         let instance = ValidatorServiceInstance {
             chain: beacon_chain.clone(),
+            network_chan: network_chan.clone(),
             log: log.clone(),
         };
         create_validator_service(instance)
@@ -2,16 +2,22 @@ use beacon_chain::{BeaconChain, BeaconChainTypes};
 use bls::PublicKey;
 use futures::Future;
 use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
-use protos::services::{ActiveValidator, GetDutiesRequest, GetDutiesResponse, ValidatorDuty};
+use network::{attestation_subnet_subscription, NetworkMessage};
+use protos::services::{
+    ActiveValidator, GetDutiesRequest, GetDutiesResponse, SubscribeToSubnetsRequest,
+    SubscribeToSubnetsResponse, ValidatorDuty,
+};
 use protos::services_grpc::ValidatorService;
-use slog::{trace, warn};
+use slog::{error, trace, warn};
 use ssz::Decode;
 use std::sync::Arc;
-use types::{Epoch, EthSpec, RelativeEpoch};
+use tokio::sync::mpsc;
+use types::{Epoch, EthSpec, RelativeEpoch, Slot};
 
 #[derive(Clone)]
 pub struct ValidatorServiceInstance<T: BeaconChainTypes> {
     pub chain: Arc<BeaconChain<T>>,
+    pub network_chan: mpsc::UnboundedSender<NetworkMessage>,
     pub log: slog::Logger,
 }
 
@@ -175,4 +181,50 @@ impl<T: BeaconChainTypes> ValidatorService for ValidatorServiceInstance<T> {
             .map_err(move |e| println!("failed to reply {:?}: {:?}", req, e));
         ctx.spawn(f)
     }
+
+    /// Subscribes the beacon node to the attestation subnets of the given duties. Each
+    /// subscription lasts until the end of the duty's slot.
+    fn subscribe_to_subnets(
+        &mut self,
+        ctx: RpcContext,
+        req: SubscribeToSubnetsRequest,
+        sink: UnarySink<SubscribeToSubnetsResponse>,
+    ) {
+        trace!(self.log, "RPC request"; "endpoint" => "SubscribeToSubnets", "duties" => req.get_duties().len());
+
+        let subscriptions = req
+            .get_duties()
+            .iter()
+            .filter_map(|duty| {
+                attestation_subnet_subscription(
+                    &self.chain,
+                    duty.get_attestation_shard(),
+                    Slot::from(duty.get_attestation_slot()),
+                )
+            })
+            .collect();
+
+        let mut resp = SubscribeToSubnetsResponse::new();
+        match self
+            .network_chan
+            .try_send(NetworkMessage::Subscribe { subscriptions })
+        {
+            Ok(()) => resp.set_success(true),
+            Err(e) => {
+                error!(
+                    self.log,
+                    "Failed to send subnet subscriptions to the network";
+                    "error" => format!("{:?}", e)
+                );
+                resp.set_success(false);
+                resp.set_msg(b"Unable to contact the network service".to_vec());
+            }
+        }
+
+        let log_clone = self.log.clone();
+        let f = sink
+            .success(resp)
+            .map_err(move |e| warn!(log_clone, "Failed to reply {:?}: {:?}", req, e));
+        ctx.spawn(f)
+    }
 }
@@ -27,10 +27,8 @@ pub fn get_configs(cli_args: &ArgMatches, core_log: Logger) -> Result<Config> {
 
     let mut builder = ConfigBuilder::new(cli_args, core_log)?;
 
-    if let Some(server) = cli_args.value_of("eth1-server") {
-        builder.set_eth1_backend_method(Eth1BackendMethod::Web3 {
-            server: server.into(),
-        })
+    if cli_args.is_present("eth1-server") {
+        builder.set_eth1_backend_method(Eth1BackendMethod::Web3)
     } else {
         builder.set_eth1_backend_method(Eth1BackendMethod::Interop)
     }
@@ -183,6 +181,13 @@ fn process_testnet_subcommand(
                 genesis_time,
             })
         }
+        ("deposit-contract", Some(_)) => {
+            if let Eth1BackendMethod::Interop = builder.client_config.eth1_backend_method {
+                return Err("The deposit-contract method requires --eth1-server.".into());
+            }
+
+            builder.set_beacon_chain_start_method(BeaconChainStartMethod::DepositContract)
+        }
         ("file", Some(cli_args)) => {
             let file = cli_args
                 .value_of("file")
@@ -116,6 +116,55 @@ fn main() {
                 .help("One or more comma-delimited multiaddrs to manually connect to a libp2p peer without an ENR.")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("subnets")
+                .long("subnets")
+                .value_name("INTEGER")
+                .help("The number of randomly selected attestation subnets to subscribe to for the lifetime of the node (default 1).")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("subscribe-all-subnets")
+                .long("subscribe-all-subnets")
+                .help("Subscribe to every attestation subnet.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("max-request-blocks")
+                .long("max-request-blocks")
+                .value_name("INTEGER")
+                .help("The maximum number of blocks a peer may request in a single BeaconBlocks request. Peers are also rate limited to this many blocks per 10 seconds.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("trusted-peers")
+                .long("trusted-peers")
+                .value_name("PEERS")
+                .help("One or more comma-delimited peer ids or multiaddrs (with a /p2p/ component) of trusted peers. Trusted peers are always connected to, are exempt from the peer limit and are not disconnected for faults.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("denied-peers")
+                .long("denied-peers")
+                .value_name("PEER_IDS")
+                .help("One or more comma-delimited peer ids that are banned.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("denied-ip-ranges")
+                .long("denied-ip-ranges")
+                .value_name("CIDRS")
+                .help("One or more comma-delimited IP ranges (e.g., 10.0.0.0/8) from which connections are rejected.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("secure-channel")
+                .long("secure-channel")
+                .value_name("PROTOCOL")
+                .help("The secure channel protocols offered to peers. By default Noise is offered, falling back to secio for peers that do not support it.")
+                .takes_value(true)
+                .possible_values(&["noise-with-secio-fallback", "noise", "secio"]),
+        )
         .arg(
             Arg::with_name("p2p-priv-key")
                 .long("p2p-priv-key")
@@ -169,6 +218,49 @@ fn main() {
                 .conflicts_with("port-bump")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("api-tls-cert")
+                .long("api-tls-cert")
+                .value_name("PATH")
+                .help("A PEM encoded certificate chain. If supplied, the RESTful HTTP API server only accepts HTTPS connections.")
+                .requires("api-tls-key")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("api-tls-key")
+                .long("api-tls-key")
+                .value_name("PATH")
+                .help("The PEM encoded private key of the certificate given in --api-tls-cert.")
+                .requires("api-tls-cert")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("api-read-tokens")
+                .long("api-read-tokens")
+                .value_name("PATH")
+                .help("A file of bearer tokens, one per line, which may use the read-only endpoints of the RESTful HTTP API. If this or --api-write-tokens is supplied, all requests must be authenticated.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("api-write-tokens")
+                .long("api-write-tokens")
+                .value_name("PATH")
+                .help("A file of bearer tokens, one per line, which may use all endpoints of the RESTful HTTP API, including publishing blocks and attestations.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("api-disable-writes")
+                .long("api-disable-writes")
+                .help("Reject all requests to the RESTful HTTP API which modify the node or publish to the network.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("api-allow-origin")
+                .long("api-allow-origin")
+                .value_name("ORIGINS")
+                .help("One or more comma-delimited origins (e.g., https://dashboard.example.com) from which browsers may make cross-origin requests to the RESTful HTTP API. Use * to allow any origin.")
+                .takes_value(true),
+        )
         /* Websocket related arguments */
         .arg(
             Arg::with_name("no-ws")
@@ -200,7 +292,23 @@ fn main() {
             Arg::with_name("eth1-server")
                 .long("eth1-server")
                 .value_name("SERVER")
-                .help("Specifies the server for a web3 connection to the Eth1 chain.")
+                .help("Specifies the server for a web3 connection to the Eth1 chain. If supplied, \
+                       deposits are read from the deposit contract of this eth1 node.")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("eth1-deposit-contract")
+                .long("eth1-deposit-contract")
+                .value_name("ADDRESS")
+                .help("The 0x-prefixed address of the deposit contract on the Eth1 chain.")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("eth1-deposit-contract-deploy-block")
+                .long("eth1-deposit-contract-deploy-block")
+                .value_name("BLOCK_NUMBER")
+                .help("The Eth1 block in which the deposit contract was deployed. Deposit logs \
+                       are not requested for earlier blocks.")
                 .takes_value(true)
         )
         /*
@@ -341,6 +449,15 @@ fn main() {
                     .required(true)
                     .help("The genesis time for the given state."))
             )
+            /*
+             * `deposit-contract`
+             *
+             * Start a new node, waiting for genesis to be triggered by the eth1 deposit contract.
+             */
+            .subcommand(SubCommand::with_name("deposit-contract")
+                .about("Follows the deposit contract of the eth1 node given by --eth1-server and \
+                        starts the chain once the deposits produce a valid genesis state.")
+            )
             /*
              * `yaml`
              *
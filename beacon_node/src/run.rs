@@ -1,4 +1,8 @@
-use client::{error, notifier, Client, ClientConfig, Eth1BackendMethod, Eth2Config};
+use beacon_chain::slot_clock::SystemTimeSlotClock;
+use client::{
+    error, notifier, CachingEth1Backend, Client, ClientConfig, Eth1BackendMethod, Eth1ChainBackend,
+    Eth2Config, InteropEth1ChainBackend,
+};
 use futures::sync::oneshot;
 use futures::Future;
 use slog::{error, info};
@@ -50,14 +54,37 @@ pub fn run_beacon_node(
 
     macro_rules! run_client {
         ($store: ty, $eth_spec: ty) => {
-            run::<$store, $eth_spec>(&db_path, client_config, eth2_config, executor, runtime, log)
+            match client_config.eth1_backend_method {
+                Eth1BackendMethod::Interop => {
+                    run::<$store, $eth_spec, InteropEth1ChainBackend<$eth_spec>>(
+                        &db_path,
+                        client_config,
+                        eth2_config,
+                        executor,
+                        runtime,
+                        log,
+                    )
+                }
+                Eth1BackendMethod::Web3 => {
+                    info!(
+                        log,
+                        "Following eth1 deposit contract";
+                        "endpoint" => &client_config.eth1.endpoint,
+                        "address" => &client_config.eth1.deposit_contract_address,
+                    );
+                    run::<$store, $eth_spec, CachingEth1Backend<$eth_spec>>(
+                        &db_path,
+                        client_config,
+                        eth2_config,
+                        executor,
+                        runtime,
+                        log,
+                    )
+                }
+            }
         };
     }
 
-    if let Eth1BackendMethod::Web3 { .. } = client_config.eth1_backend_method {
-        return Err("Starting from web3 backend is not supported for interop.".into());
-    }
-
     match (db_type.as_str(), spec_constants.as_str()) {
         ("disk", "minimal") => run_client!(DiskStore, MinimalEthSpec),
         ("disk", "mainnet") => run_client!(DiskStore, MainnetEthSpec),
@@ -73,7 +100,7 @@ pub fn run_beacon_node(
 }
 
 /// Performs the type-generic parts of launching a `BeaconChain`.
-fn run<S, E>(
+fn run<S, E, B>(
     db_path: &Path,
     client_config: ClientConfig,
     eth2_config: Eth2Config,
@@ -84,10 +111,11 @@ fn run<S, E>(
 where
     S: Store + Clone + 'static + OpenDatabase,
     E: EthSpec,
+    B: Eth1ChainBackend<E> + 'static,
 {
     let store = S::open_database(&db_path)?;
 
-    let client: Client<S, E> =
+    let client: Client<S, E, SystemTimeSlotClock, B> =
         Client::new(client_config, eth2_config, store, log.clone(), &executor)?;
 
     // run service until ctrl-c
@@ -1,55 +1,163 @@
-use beacon_chain::events::{EventHandler, EventKind};
-use futures::Future;
+use beacon_chain::events::{EventBroadcaster, EventKind, DEFAULT_SUBSCRIBER_CAPACITY};
+use futures::{Future, Stream};
+use parking_lot::RwLock;
 use slog::{debug, error, info, warn, Logger};
-use std::marker::PhantomData;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::thread;
 use tokio::runtime::TaskExecutor;
 use types::EthSpec;
-use ws::{Sender, WebSocket};
+use ws::{CloseCode, Handler, Handshake, Message, Sender, WebSocket};
 
 mod config;
+mod subscription;
 
 pub use config::Config;
+pub use subscription::{ClientMessage, ServerMessage, Subscription};
 
-pub struct WebSocketSender<T: EthSpec> {
-    sender: Option<Sender>,
-    _phantom: PhantomData<T>,
+/// The open connections of the server, keyed by connection id.
+type Connections = Arc<RwLock<HashMap<u32, Connection>>>;
+
+/// An open connection with a client.
+struct Connection {
+    sender: Sender,
+    subscription: Subscription,
+}
+
+/// Handles the messages of a single client, which may change its subscription.
+struct ConnectionHandler {
+    sender: Sender,
+    connections: Connections,
+    log: Logger,
 }
 
-impl<T: EthSpec> WebSocketSender<T> {
-    /// Creates a dummy websocket server that never starts and where all future calls are no-ops.
-    pub fn dummy() -> Self {
-        Self {
-            sender: None,
-            _phantom: PhantomData,
+impl ConnectionHandler {
+    /// Applies `message` to the subscription of this client, returning the response to send.
+    fn handle_message(&self, message: Message) -> ServerMessage {
+        let client_message = message
+            .as_text()
+            .map_err(|e| format!("Message is not text: {:?}", e))
+            .and_then(|text| {
+                serde_json::from_str::<ClientMessage>(text)
+                    .map_err(|e| format!("Invalid message: {:?}", e))
+            });
+
+        let subscription = match client_message {
+            Ok(ClientMessage::Subscribe(subscription)) => subscription,
+            Ok(ClientMessage::Unsubscribe) => Subscription::none(),
+            Err(e) => return ServerMessage::Error(e),
+        };
+
+        if let Some(connection) = self
+            .connections
+            .write()
+            .get_mut(&self.sender.connection_id())
+        {
+            connection.subscription = subscription.clone();
         }
+
+        ServerMessage::Subscribed(subscription)
     }
+}
 
-    pub fn send_string(&self, string: String) -> Result<(), String> {
-        if let Some(sender) = &self.sender {
-            sender
-                .send(string)
-                .map_err(|e| format!("Unable to broadcast to websocket clients: {:?}", e))
-        } else {
-            Ok(())
+impl Handler for ConnectionHandler {
+    fn on_open(&mut self, _shake: Handshake) -> ws::Result<()> {
+        self.connections.write().insert(
+            self.sender.connection_id(),
+            Connection {
+                sender: self.sender.clone(),
+                subscription: Subscription::default(),
+            },
+        );
+        Ok(())
+    }
+
+    fn on_message(&mut self, message: Message) -> ws::Result<()> {
+        let response = self.handle_message(message);
+
+        debug!(
+            self.log,
+            "Websocket client message";
+            "connection_id" => self.sender.connection_id(),
+            "response" => format!("{:?}", response)
+        );
+
+        match serde_json::to_string(&response) {
+            Ok(string) => self.sender.send(string),
+            Err(e) => {
+                warn!(
+                    self.log,
+                    "Unable to serialize websocket response";
+                    "error" => format!("{:?}", e)
+                );
+                Ok(())
+            }
         }
     }
+
+    fn on_close(&mut self, _code: CloseCode, _reason: &str) {
+        self.connections
+            .write()
+            .remove(&self.sender.connection_id());
+    }
 }
 
-impl<T: EthSpec> EventHandler<T> for WebSocketSender<T> {
-    fn register(&self, kind: EventKind<T>) -> Result<(), String> {
-        self.send_string(
-            serde_json::to_string(&kind)
-                .map_err(|e| format!("Unable to serialize event: {:?}", e))?,
-        )
+impl Drop for ConnectionHandler {
+    fn drop(&mut self) {
+        // Connections may end without a close handshake, in which case `on_close` is not called.
+        self.connections
+            .write()
+            .remove(&self.sender.connection_id());
+    }
+}
+
+/// Sends `event` to each connection whose subscription allows it.
+fn send_event<T: EthSpec>(connections: &Connections, event: &EventKind<T>, log: &Logger) {
+    let serialize = |event: &EventKind<T>| {
+        serde_json::to_string(event).map_err(|e| format!("Unable to serialize event: {:?}", e))
+    };
+
+    // The unmodified event is serialized at most once, no matter how many clients receive it.
+    let mut serialized_event = None;
+
+    for connection in connections.read().values() {
+        let json = match connection.subscription.filter(event) {
+            None => continue,
+            Some(Cow::Borrowed(event)) => serialized_event
+                .get_or_insert_with(|| serialize(event))
+                .clone(),
+            Some(Cow::Owned(event)) => serialize(&event),
+        };
+
+        let result = json.and_then(|json| {
+            connection
+                .sender
+                .send(json)
+                .map_err(|e| format!("Unable to send event: {:?}", e))
+        });
+
+        if let Err(e) = result {
+            warn!(
+                log,
+                "Websocket event not sent";
+                "connection_id" => connection.sender.connection_id(),
+                "error" => e
+            );
+        }
     }
 }
 
+/// Starts a websocket server which sends the events published on `events` to its clients.
+///
+/// Each client receives all events, unless it sends a `ClientMessage` to change its
+/// `Subscription`.
 pub fn start_server<T: EthSpec>(
     config: &Config,
     executor: &TaskExecutor,
+    events: &EventBroadcaster<T>,
     log: &Logger,
-) -> Result<(WebSocketSender<T>, exit_future::Signal), String> {
+) -> Result<exit_future::Signal, String> {
     let server_string = format!("{}:{}", config.listen_address, config.port);
 
     info!(
@@ -58,37 +166,53 @@ pub fn start_server<T: EthSpec>(
         "listen_address" => &server_string
     );
 
-    // Create a server that simply ignores any incoming messages.
-    let server = WebSocket::new(|_| |_| Ok(()))
-        .map_err(|e| format!("Failed to initialize websocket server: {:?}", e))?;
+    let connections: Connections = Arc::new(RwLock::new(HashMap::new()));
 
-    let broadcaster = server.broadcaster();
+    // Create a server that gives each client its own handler, tracking its subscription.
+    let handler_connections = connections.clone();
+    let handler_log = log.clone();
+    let server = WebSocket::new(move |sender| ConnectionHandler {
+        sender,
+        connections: handler_connections.clone(),
+        log: handler_log.clone(),
+    })
+    .map_err(|e| format!("Failed to initialize websocket server: {:?}", e))?;
 
     // Produce a signal/channel that can gracefully shutdown the websocket server.
-    let exit_signal = {
-        let (exit_signal, exit) = exit_future::signal();
+    let (exit_signal, exit) = exit_future::signal();
 
-        let log_inner = log.clone();
-        let broadcaster_inner = server.broadcaster();
-        let exit_future = exit.and_then(move |_| {
-            if let Err(e) = broadcaster_inner.shutdown() {
-                warn!(
-                    log_inner,
-                    "Websocket server errored on shutdown";
-                    "error" => format!("{:?}", e)
-                );
-            } else {
-                info!(log_inner, "Websocket server shutdown");
-            }
-            Ok(())
-        });
+    let log_inner = log.clone();
+    let broadcaster_inner = server.broadcaster();
+    let exit_future = exit.clone().and_then(move |_| {
+        if let Err(e) = broadcaster_inner.shutdown() {
+            warn!(
+                log_inner,
+                "Websocket server errored on shutdown";
+                "error" => format!("{:?}", e)
+            );
+        } else {
+            info!(log_inner, "Websocket server shutdown");
+        }
+        Ok(())
+    });
 
-        // Place a future on the executor that will shutdown the websocket server when the
-        // application exits.
-        executor.spawn(exit_future);
+    // Place a future on the executor that will shutdown the websocket server when the
+    // application exits.
+    executor.spawn(exit_future);
 
-        exit_signal
-    };
+    // Forward chain events to the websocket clients until the application exits.
+    let log_inner = log.clone();
+    executor.spawn(
+        exit.until(
+            events
+                .subscribe(None, DEFAULT_SUBSCRIBER_CAPACITY)
+                .for_each(move |event| {
+                    send_event(&connections, &event, &log_inner);
+                    Ok(())
+                }),
+        )
+        .map(|_| ()),
+    );
 
     let log_inner = log.clone();
     let _handle = thread::spawn(move || match server.listen(server_string) {
@@ -107,11 +231,5 @@ pub fn start_server<T: EthSpec>(
         }
     });
 
-    Ok((
-        WebSocketSender {
-            sender: Some(broadcaster),
-            _phantom: PhantomData,
-        },
-        exit_signal,
-    ))
+    Ok(exit_signal)
 }
@@ -0,0 +1,221 @@
+use beacon_chain::events::{EventKind, EventTopic};
+use serde_derive::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use types::EthSpec;
+
+/// A message sent from a client to the server.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Replaces the subscription of the client.
+    Subscribe(Subscription),
+    /// Stops all events from being sent to the client.
+    Unsubscribe,
+}
+
+/// A message sent from the server to a client, in response to a `ClientMessage`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// The subscription of the client after the `ClientMessage` was applied.
+    Subscribed(Subscription),
+    /// The `ClientMessage` was invalid.
+    Error(String),
+}
+
+/// Determines which events are sent to a client.
+///
+/// Each filter which is `None` does not restrict the events sent. The default subscription, which
+/// each client starts with, receives all events.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Subscription {
+    /// Only events of these topics are sent.
+    pub topics: Option<HashSet<EventTopic>>,
+    /// Only blocks proposed by these validators are sent. Rejected blocks are not sent, since
+    /// their proposer is not known.
+    pub proposer_indices: Option<HashSet<u64>>,
+    /// Only attestations, operations and balance changes involving these validators are sent.
+    /// Rejected attestations and deposits are not sent, since they cannot be attributed to
+    /// validators.
+    pub validator_indices: Option<HashSet<u64>>,
+}
+
+impl Subscription {
+    /// A subscription which does not receive any events.
+    pub fn none() -> Self {
+        Self {
+            topics: Some(HashSet::new()),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the event that should be sent to a client with this subscription, or `None` if
+    /// `event` should not be sent.
+    ///
+    /// Balance changes are reduced to those of the subscribed validators, all other events are
+    /// either sent as-is or not at all.
+    pub fn filter<'a, T: EthSpec>(&self, event: &'a EventKind<T>) -> Option<Cow<'a, EventKind<T>>> {
+        if let Some(topics) = &self.topics {
+            if !topics.contains(&event.topic()) {
+                return None;
+            }
+        }
+
+        let is_wanted = match event {
+            EventKind::BeaconBlockImported { proposer_index, .. } => self
+                .proposer_indices
+                .as_ref()
+                .map_or(true, |indices| indices.contains(proposer_index)),
+            EventKind::BeaconBlockRejected { .. } => self.proposer_indices.is_none(),
+            EventKind::BeaconAttestationImported {
+                attesting_indices, ..
+            } => self.involves_validators(attesting_indices),
+            EventKind::BeaconAttestationRejected { .. } => self.validator_indices.is_none(),
+            EventKind::OperationPoolInsertion { operation } => {
+                self.involves_validators(&operation.validator_indices())
+            }
+            EventKind::ValidatorBalancesChanged { epoch, changes } => {
+                return match &self.validator_indices {
+                    Some(indices) => {
+                        let changes: Vec<_> = changes
+                            .iter()
+                            .filter(|change| indices.contains(&change.validator_index))
+                            .cloned()
+                            .collect();
+
+                        if changes.is_empty() {
+                            None
+                        } else {
+                            Some(Cow::Owned(EventKind::ValidatorBalancesChanged {
+                                epoch: *epoch,
+                                changes,
+                            }))
+                        }
+                    }
+                    None => Some(Cow::Borrowed(event)),
+                };
+            }
+            _ => true,
+        };
+
+        if is_wanted {
+            Some(Cow::Borrowed(event))
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if there is no validator filter or any of `validator_indices` is in it.
+    fn involves_validators(&self, validator_indices: &[u64]) -> bool {
+        self.validator_indices.as_ref().map_or(true, |indices| {
+            validator_indices
+                .iter()
+                .any(|index| indices.contains(index))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use beacon_chain::events::BalanceChange;
+    use types::{Epoch, Hash256, MinimalEthSpec};
+
+    type E = MinimalEthSpec;
+
+    fn balances_changed() -> EventKind<E> {
+        EventKind::ValidatorBalancesChanged {
+            epoch: Epoch::new(1),
+            changes: (0..4)
+                .map(|validator_index| BalanceChange {
+                    validator_index,
+                    previous_balance: 32,
+                    balance: 33,
+                })
+                .collect(),
+        }
+    }
+
+    fn finalization() -> EventKind<E> {
+        EventKind::BeaconFinalization {
+            epoch: Epoch::new(1),
+            root: Hash256::zero(),
+        }
+    }
+
+    #[test]
+    fn parse_client_messages() {
+        let message: ClientMessage = serde_json::from_str(
+            r#"{"subscribe": {"topics": ["block", "balance_changes"], "validator_indices": [1]}}"#,
+        )
+        .expect("should parse subscribe message");
+
+        assert_eq!(
+            message,
+            ClientMessage::Subscribe(Subscription {
+                topics: Some(
+                    vec![EventTopic::Block, EventTopic::BalanceChanges]
+                        .into_iter()
+                        .collect()
+                ),
+                proposer_indices: None,
+                validator_indices: Some(vec![1].into_iter().collect()),
+            })
+        );
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(r#""unsubscribe""#)
+                .expect("should parse unsubscribe message"),
+            ClientMessage::Unsubscribe
+        );
+        assert!(serde_json::from_str::<ClientMessage>(r#"{"subscribe": {"topic": []}}"#).is_err());
+    }
+
+    #[test]
+    fn filter_by_topic() {
+        let subscription = Subscription {
+            topics: Some(vec![EventTopic::FinalizedCheckpoint].into_iter().collect()),
+            ..Subscription::default()
+        };
+
+        assert!(subscription.filter(&finalization()).is_some());
+        assert!(subscription.filter(&balances_changed()).is_none());
+        assert!(Subscription::none().filter(&finalization()).is_none());
+        assert!(Subscription::default()
+            .filter(&balances_changed())
+            .is_some());
+    }
+
+    #[test]
+    fn balance_changes_are_reduced_to_subscribed_validators() {
+        let subscription = Subscription {
+            validator_indices: Some(vec![1, 3, 10].into_iter().collect()),
+            ..Subscription::default()
+        };
+
+        match subscription
+            .filter(&balances_changed())
+            .expect("should send balance changes")
+            .into_owned()
+        {
+            EventKind::ValidatorBalancesChanged { changes, .. } => assert_eq!(
+                changes
+                    .iter()
+                    .map(|change| change.validator_index)
+                    .collect::<Vec<_>>(),
+                vec![1, 3]
+            ),
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        let subscription = Subscription {
+            validator_indices: Some(vec![10].into_iter().collect()),
+            ..Subscription::default()
+        };
+        assert!(subscription.filter(&balances_changed()).is_none());
+
+        // Events unrelated to validators are unaffected by the validator filter.
+        assert!(subscription.filter(&finalization()).is_some());
+    }
+}
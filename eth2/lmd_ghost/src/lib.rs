@@ -1,13 +1,48 @@
+mod proto_array;
 mod reduced_tree;
 
 use std::sync::Arc;
 use store::Store;
-use types::{BeaconBlock, EthSpec, Hash256, Slot};
+use types::{BeaconBlock, Epoch, EthSpec, Hash256, Slot};
 
+pub use proto_array::ThreadSafeProtoArray;
 pub use reduced_tree::ThreadSafeReducedTree;
 
 pub type Result<T> = std::result::Result<T, String>;
 
+/// A snapshot of the internal state of some fork choice algorithm, for debugging and analysis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForkChoiceDump {
+    /// The root of the tree (i.e., the node without any parents).
+    pub root: Hash256,
+    pub root_slot: Slot,
+    /// All nodes in the tree, ordered by slot (then block root).
+    pub nodes: Vec<NodeDump>,
+    /// The latest message from each validator that has one, ordered by validator index.
+    pub latest_messages: Vec<LatestMessage>,
+}
+
+/// A single node in a `ForkChoiceDump`.
+///
+/// The `weight` and `best_child` fields reflect the last run of `find_head`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeDump {
+    pub block_root: Hash256,
+    pub slot: Slot,
+    pub parent_root: Option<Hash256>,
+    pub children: Vec<Hash256>,
+    pub weight: u64,
+    pub best_child: Option<Hash256>,
+}
+
+/// The latest block a validator has attested to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatestMessage {
+    pub validator_index: usize,
+    pub block_root: Hash256,
+    pub slot: Slot,
+}
+
 pub trait LmdGhost<S: Store, E: EthSpec>: Send + Sync {
     /// Create a new instance, with the given `store` and `finalized_root`.
     fn new(store: Arc<S>, finalized_block: &BeaconBlock<E>, finalized_root: Hash256) -> Self;
@@ -22,7 +57,16 @@ pub trait LmdGhost<S: Store, E: EthSpec>: Send + Sync {
     ) -> Result<()>;
 
     /// Process a block that was seen on the network.
-    fn process_block(&self, block: &BeaconBlock<E>, block_hash: Hash256) -> Result<()>;
+    ///
+    /// The `justified_epoch` and `finalized_epoch` are those of the post-state of the block.
+    /// Implementations which do not filter blocks by epoch may ignore them.
+    fn process_block(
+        &self,
+        block: &BeaconBlock<E>,
+        block_hash: Hash256,
+        justified_epoch: Epoch,
+        finalized_epoch: Epoch,
+    ) -> Result<()>;
 
     /// Returns the head of the chain, starting the search at `start_block_root` and moving upwards
     /// (in block height).
@@ -44,6 +88,18 @@ pub trait LmdGhost<S: Store, E: EthSpec>: Send + Sync {
         finalized_block_root: Hash256,
     ) -> Result<()>;
 
+    /// Provide the justified and finalized epochs of the head state, so that only blocks which
+    /// agree with them may become the head.
+    ///
+    /// Implementations which do not filter blocks by epoch may ignore this.
+    fn update_checkpoint_epochs(
+        &self,
+        _justified_epoch: Epoch,
+        _finalized_epoch: Epoch,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     /// Returns the latest message for a given validator index.
     fn latest_message(&self, validator_index: usize) -> Option<(Hash256, Slot)>;
 
@@ -52,4 +108,9 @@ pub trait LmdGhost<S: Store, E: EthSpec>: Send + Sync {
     /// Returns `Ok(())` if the underlying fork choice has maintained it's integrity,
     /// `Err(description)` otherwise.
     fn verify_integrity(&self) -> Result<()>;
+
+    /// Returns a snapshot of all nodes and latest messages known to the fork choice algorithm.
+    ///
+    /// Intended for debugging, this may be an expensive operation.
+    fn dump(&self) -> Result<ForkChoiceDump>;
 }
@@ -0,0 +1,679 @@
+//! An implementation of "proto-array" LMD GHOST fork choice.
+//!
+//! Blocks are stored in a flat array, where each block is stored after its parent. Rather than
+//! re-counting all votes when finding the head, the change in each validator's vote (or balance)
+//! since the previous run is applied as a weight "delta" to the voted-for block. A pass from the
+//! end of the array to the start then propagates the deltas to each ancestor, and a second pass
+//! updates the best child and best descendant of each block, so the head is found in `O(n)` of the
+//! number of blocks since finalization.
+//!
+//! Processed blocks are added using the justified and finalized epochs of their post-state, as
+//! supplied by the caller. Blocks which are not yet known are read from the store (along with any
+//! unknown ancestors) when they are first voted for or used as the starting point of `find_head`.
+use super::{ForkChoiceDump, LatestMessage, LmdGhost, NodeDump, Result as SuperResult};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use store::{Error as StoreError, Store};
+use types::{BeaconBlock, BeaconState, Epoch, EthSpec, Hash256, Slot};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    MissingBlock(Hash256),
+    MissingState(Hash256),
+    /// The block is not a descendant of the finalized root.
+    NotDescendantOfRoot(Hash256),
+    /// Applying a delta to the weight of the node at the given index would make it negative.
+    DeltaUnderflow(usize),
+    StoreError(StoreError),
+}
+
+impl From<StoreError> for Error {
+    fn from(e: StoreError) -> Error {
+        Error::StoreError(e)
+    }
+}
+
+pub struct ThreadSafeProtoArray<T, E> {
+    core: RwLock<ProtoArray<T, E>>,
+}
+
+impl<T, E> fmt::Debug for ThreadSafeProtoArray<T, E> {
+    /// `Debug` just defers to the implementation of `self.core`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.core.fmt(f)
+    }
+}
+
+impl<T, E> LmdGhost<T, E> for ThreadSafeProtoArray<T, E>
+where
+    T: Store,
+    E: EthSpec,
+{
+    fn new(store: Arc<T>, finalized_block: &BeaconBlock<E>, finalized_root: Hash256) -> Self {
+        ThreadSafeProtoArray {
+            core: RwLock::new(ProtoArray::new(store, finalized_block, finalized_root)),
+        }
+    }
+
+    fn process_attestation(
+        &self,
+        validator_index: usize,
+        block_hash: Hash256,
+        block_slot: Slot,
+    ) -> SuperResult<()> {
+        self.core
+            .write()
+            .process_attestation(validator_index, block_hash, block_slot)
+            .map_err(|e| format!("process_attestation failed: {:?}", e))
+    }
+
+    fn process_block(
+        &self,
+        block: &BeaconBlock<E>,
+        block_hash: Hash256,
+        justified_epoch: Epoch,
+        finalized_epoch: Epoch,
+    ) -> SuperResult<()> {
+        // Blocks which do not descend from the finalized root can never become the head.
+        match self
+            .core
+            .write()
+            .process_block(block, block_hash, justified_epoch, finalized_epoch)
+        {
+            Ok(_) | Err(Error::NotDescendantOfRoot(_)) => Ok(()),
+            Err(e) => Err(format!("process_block failed: {:?}", e)),
+        }
+    }
+
+    fn find_head<F>(
+        &self,
+        _start_block_slot: Slot,
+        start_block_root: Hash256,
+        weight_fn: F,
+    ) -> SuperResult<Hash256>
+    where
+        F: Fn(usize) -> Option<u64> + Copy,
+    {
+        self.core
+            .write()
+            .find_head(start_block_root, weight_fn)
+            .map_err(|e| format!("find_head failed: {:?}", e))
+    }
+
+    fn update_finalized_root(
+        &self,
+        _finalized_block: &BeaconBlock<E>,
+        finalized_block_root: Hash256,
+    ) -> SuperResult<()> {
+        self.core
+            .write()
+            .update_root(finalized_block_root)
+            .map_err(|e| format!("update_finalized_root failed: {:?}", e))
+    }
+
+    fn update_checkpoint_epochs(
+        &self,
+        justified_epoch: Epoch,
+        finalized_epoch: Epoch,
+    ) -> SuperResult<()> {
+        let mut core = self.core.write();
+        core.justified_epoch = justified_epoch;
+        core.finalized_epoch = finalized_epoch;
+        Ok(())
+    }
+
+    fn latest_message(&self, validator_index: usize) -> Option<(Hash256, Slot)> {
+        self.core.read().latest_message(validator_index)
+    }
+
+    fn verify_integrity(&self) -> std::result::Result<(), String> {
+        self.core.read().verify_integrity()
+    }
+
+    fn dump(&self) -> SuperResult<ForkChoiceDump> {
+        Ok(self.core.read().dump())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ProtoNode {
+    root: Hash256,
+    slot: Slot,
+    /// Always less than the index of this node, or `None` for the root node.
+    parent: Option<usize>,
+    /// The justified and finalized epochs of the post-state of the block.
+    justified_epoch: Epoch,
+    finalized_epoch: Epoch,
+    weight: u64,
+    best_child: Option<usize>,
+    best_descendant: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct VoteTracker {
+    /// The block whose weight currently includes the validator's balance, if any.
+    current_root: Option<Hash256>,
+    /// The latest block the validator has attested to.
+    next_root: Hash256,
+    next_slot: Slot,
+}
+
+struct ProtoArray<T, E> {
+    store: Arc<T>,
+    /// All blocks that descend from the root (i.e., the finalized block), parents first.
+    nodes: Vec<ProtoNode>,
+    /// Maps block roots to indices in `nodes`.
+    indices: HashMap<Hash256, usize>,
+    /// The latest vote of each validator, indexed by validator index.
+    votes: Vec<Option<VoteTracker>>,
+    /// The balance of each validator when weights were last applied.
+    balances: Vec<u64>,
+    /// Only blocks which agree with these epochs may become the head, unless they are zero.
+    justified_epoch: Epoch,
+    finalized_epoch: Epoch,
+    _phantom: PhantomData<E>,
+}
+
+impl<T, E> fmt::Debug for ProtoArray<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.nodes.fmt(f)
+    }
+}
+
+impl<T, E> ProtoArray<T, E>
+where
+    T: Store,
+    E: EthSpec,
+{
+    pub fn new(store: Arc<T>, finalized_block: &BeaconBlock<E>, finalized_root: Hash256) -> Self {
+        // The root node is never filtered out of `find_head`, so if its state is unavailable it is
+        // sufficient to treat it as viable for any epoch.
+        let (justified_epoch, finalized_epoch) = store
+            .get::<BeaconState<E>>(&finalized_block.state_root)
+            .ok()
+            .and_then(|state| state)
+            .map(|state| {
+                (
+                    state.current_justified_checkpoint.epoch,
+                    state.finalized_checkpoint.epoch,
+                )
+            })
+            .unwrap_or_else(|| (Epoch::new(0), Epoch::new(0)));
+
+        let mut indices = HashMap::new();
+        indices.insert(finalized_root, 0);
+
+        Self {
+            store,
+            nodes: vec![ProtoNode {
+                root: finalized_root,
+                slot: finalized_block.slot,
+                parent: None,
+                justified_epoch,
+                finalized_epoch,
+                weight: 0,
+                best_child: None,
+                best_descendant: None,
+            }],
+            indices,
+            votes: vec![],
+            balances: vec![],
+            justified_epoch: Epoch::new(0),
+            finalized_epoch: Epoch::new(0),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn process_attestation(
+        &mut self,
+        validator_index: usize,
+        block_hash: Hash256,
+        slot: Slot,
+    ) -> Result<()> {
+        if slot < self.root_slot() {
+            return Ok(());
+        }
+
+        // Votes for blocks which are not descendants of the root are stored, but never counted.
+        match self.import_block(block_hash) {
+            Ok(_) | Err(Error::NotDescendantOfRoot(_)) => (),
+            Err(e) => return Err(e),
+        }
+
+        if validator_index >= self.votes.len() {
+            self.votes.resize(validator_index + 1, None);
+        }
+
+        let vote = &mut self.votes[validator_index];
+        if vote.map_or(true, |vote| slot > vote.next_slot) {
+            *vote = Some(VoteTracker {
+                current_root: vote.and_then(|vote| vote.current_root),
+                next_root: block_hash,
+                next_slot: slot,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Applies all changes in votes and balances since the last call, returning the best
+    /// descendant of `start_block_root`.
+    pub fn find_head<F>(&mut self, start_block_root: Hash256, weight_fn: F) -> Result<Hash256>
+    where
+        F: Fn(usize) -> Option<u64> + Copy,
+    {
+        let start_index = self.import_block(start_block_root)?;
+
+        let deltas = self.compute_deltas(weight_fn);
+        self.apply_deltas(deltas)?;
+
+        let start_node = &self.nodes[start_index];
+        let head_index = start_node.best_descendant.unwrap_or(start_index);
+
+        Ok(self.nodes[head_index].root)
+    }
+
+    pub fn latest_message(&self, validator_index: usize) -> Option<(Hash256, Slot)> {
+        self.votes
+            .get(validator_index)
+            .and_then(|vote| *vote)
+            .map(|vote| (vote.next_root, vote.next_slot))
+    }
+
+    /// Adds `block`, with the given epochs of its post-state, returning the index of its node.
+    ///
+    /// The store is only read if the parent of the block is unknown, to add its unknown
+    /// ancestors.
+    pub fn process_block(
+        &mut self,
+        block: &BeaconBlock<E>,
+        block_root: Hash256,
+        justified_epoch: Epoch,
+        finalized_epoch: Epoch,
+    ) -> Result<usize> {
+        if let Some(index) = self.indices.get(&block_root) {
+            return Ok(*index);
+        }
+
+        // A block at or prior to the slot of the root cannot descend from it.
+        if block.slot <= self.root_slot() {
+            return Err(Error::NotDescendantOfRoot(block_root));
+        }
+
+        let parent_index = match self.indices.get(&block.parent_root) {
+            Some(index) => *index,
+            None => self.import_block(block.parent_root)?,
+        };
+
+        Ok(self.push_node(
+            block_root,
+            block.slot,
+            parent_index,
+            justified_epoch,
+            finalized_epoch,
+        ))
+    }
+
+    /// Returns the index of the node for `block_root`, first adding it (and any unknown ancestors)
+    /// from the store if required.
+    fn import_block(&mut self, block_root: Hash256) -> Result<usize> {
+        if let Some(index) = self.indices.get(&block_root) {
+            return Ok(*index);
+        }
+
+        // Collect all unknown ancestors, stopping at the first known one.
+        let mut unknown_blocks = vec![];
+        let mut current_root = block_root;
+        let mut parent_index = loop {
+            let block = self.get_block(current_root)?;
+
+            // A block at or prior to the slot of the root cannot descend from it.
+            if block.slot <= self.root_slot() {
+                return Err(Error::NotDescendantOfRoot(block_root));
+            }
+
+            let parent_root = block.parent_root;
+            unknown_blocks.push((current_root, block));
+
+            match self.indices.get(&parent_root) {
+                Some(index) => break *index,
+                None => current_root = parent_root,
+            }
+        };
+
+        for (root, block) in unknown_blocks.into_iter().rev() {
+            let state = self.get_state(block.state_root)?;
+            parent_index = self.push_node(
+                root,
+                block.slot,
+                parent_index,
+                state.current_justified_checkpoint.epoch,
+                state.finalized_checkpoint.epoch,
+            );
+        }
+
+        Ok(parent_index)
+    }
+
+    /// Appends a node for a block whose parent is at `parent_index`, returning its index.
+    fn push_node(
+        &mut self,
+        root: Hash256,
+        slot: Slot,
+        parent_index: usize,
+        justified_epoch: Epoch,
+        finalized_epoch: Epoch,
+    ) -> usize {
+        let index = self.nodes.len();
+
+        self.nodes.push(ProtoNode {
+            root,
+            slot,
+            parent: Some(parent_index),
+            justified_epoch,
+            finalized_epoch,
+            weight: 0,
+            best_child: None,
+            best_descendant: None,
+        });
+        self.indices.insert(root, index);
+
+        index
+    }
+
+    /// Returns the change in weight of each node caused by changes to votes and balances since
+    /// the last call, updating `self.votes` and `self.balances` to reflect the applied changes.
+    fn compute_deltas<F>(&mut self, weight_fn: F) -> Vec<i64>
+    where
+        F: Fn(usize) -> Option<u64> + Copy,
+    {
+        let mut deltas = vec![0; self.nodes.len()];
+        let indices = &self.indices;
+        let old_balances = &self.balances;
+
+        let new_balances: Vec<u64> = (0..self.votes.len())
+            .map(|validator_index| weight_fn(validator_index).unwrap_or(0))
+            .collect();
+
+        for (validator_index, vote) in self.votes.iter_mut().enumerate() {
+            let vote = match vote {
+                Some(vote) => vote,
+                None => continue,
+            };
+
+            let old_balance = old_balances.get(validator_index).copied().unwrap_or(0);
+            let new_balance = new_balances[validator_index];
+
+            if vote.current_root == Some(vote.next_root) && old_balance == new_balance {
+                continue;
+            }
+
+            if let Some(index) = vote.current_root.and_then(|root| indices.get(&root)) {
+                deltas[*index] -= old_balance as i64;
+            }
+
+            // If the vote is for an unknown (e.g., pruned) block, the balance is not counted.
+            vote.current_root = match indices.get(&vote.next_root) {
+                Some(index) => {
+                    deltas[*index] += new_balance as i64;
+                    Some(vote.next_root)
+                }
+                None => None,
+            };
+        }
+
+        self.balances = new_balances;
+
+        deltas
+    }
+
+    /// Applies `deltas` to the weight of each node and its ancestors, then recomputes the best
+    /// child and best descendant of each node.
+    ///
+    /// Each pass visits nodes from the highest index to the lowest, so each node is visited after
+    /// all of its descendants. Best children are only chosen once all weights are final.
+    fn apply_deltas(&mut self, mut deltas: Vec<i64>) -> Result<()> {
+        for index in (0..self.nodes.len()).rev() {
+            let delta = deltas[index];
+            let node = &mut self.nodes[index];
+
+            node.weight = if delta < 0 {
+                node.weight
+                    .checked_sub(delta.abs() as u64)
+                    .ok_or_else(|| Error::DeltaUnderflow(index))?
+            } else {
+                node.weight + delta as u64
+            };
+
+            if let Some(parent_index) = node.parent {
+                deltas[parent_index] += delta;
+            }
+        }
+
+        for node in self.nodes.iter_mut() {
+            node.best_child = None;
+            node.best_descendant = None;
+        }
+
+        for index in (0..self.nodes.len()).rev() {
+            if let Some(parent_index) = self.nodes[index].parent {
+                self.update_best_child_and_descendant(parent_index, index);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Considers `child_index` as the best child of `parent_index`. The best child leads to a
+    /// viable head, then has the highest weight, then the highest block root.
+    fn update_best_child_and_descendant(&mut self, parent_index: usize, child_index: usize) {
+        let child = &self.nodes[child_index];
+
+        if !self.leads_to_viable_head(child) {
+            return;
+        }
+
+        let child_is_better = match self.nodes[parent_index].best_child {
+            Some(best_child_index) => {
+                let best_child = &self.nodes[best_child_index];
+
+                if child.weight != best_child.weight {
+                    child.weight > best_child.weight
+                } else {
+                    child.root > best_child.root
+                }
+            }
+            None => true,
+        };
+
+        if child_is_better {
+            let best_descendant = child.best_descendant.unwrap_or(child_index);
+
+            let parent = &mut self.nodes[parent_index];
+            parent.best_child = Some(child_index);
+            parent.best_descendant = Some(best_descendant);
+        }
+    }
+
+    /// Returns `true` if `node` or its best descendant may be the head.
+    fn leads_to_viable_head(&self, node: &ProtoNode) -> bool {
+        let best_descendant_is_viable = node
+            .best_descendant
+            .map_or(false, |index| self.is_viable_for_head(&self.nodes[index]));
+
+        best_descendant_is_viable || self.is_viable_for_head(node)
+    }
+
+    /// Returns `true` if the justified and finalized epochs of `node` match those of fork choice.
+    ///
+    /// Zero epochs (i.e., before the first justification or finalization) match any node.
+    fn is_viable_for_head(&self, node: &ProtoNode) -> bool {
+        (node.justified_epoch == self.justified_epoch || self.justified_epoch == 0)
+            && (node.finalized_epoch == self.finalized_epoch || self.finalized_epoch == 0)
+    }
+
+    /// Sets the root node (the node without any parents) to `new_root`, removing all nodes which
+    /// do not descend from it.
+    pub fn update_root(&mut self, new_root: Hash256) -> Result<()> {
+        let root_index = self.import_block(new_root)?;
+
+        // Maps old indices to new indices. Parents are always visited before their children, so
+        // a node is retained if it is the new root or its parent was retained.
+        let mut new_indices: HashMap<usize, usize> = HashMap::new();
+        let mut nodes = Vec::with_capacity(self.nodes.len() - root_index);
+
+        for (old_index, node) in self.nodes.iter().enumerate().skip(root_index) {
+            let parent = if old_index == root_index {
+                None
+            } else {
+                match node.parent.and_then(|parent| new_indices.get(&parent)) {
+                    Some(parent) => Some(*parent),
+                    None => continue,
+                }
+            };
+
+            new_indices.insert(old_index, nodes.len());
+            nodes.push(ProtoNode {
+                parent,
+                ..node.clone()
+            });
+        }
+
+        // Descendants of retained nodes are also retained, so these are always present.
+        for node in nodes.iter_mut() {
+            node.best_child = node.best_child.and_then(|i| new_indices.get(&i).copied());
+            node.best_descendant = node
+                .best_descendant
+                .and_then(|i| new_indices.get(&i).copied());
+        }
+
+        self.indices = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.root, index))
+            .collect();
+        self.nodes = nodes;
+
+        Ok(())
+    }
+
+    /// Returns a snapshot of all nodes and all latest votes.
+    pub fn dump(&self) -> ForkChoiceDump {
+        let mut children: Vec<Vec<Hash256>> = vec![vec![]; self.nodes.len()];
+        for node in &self.nodes {
+            if let Some(parent_index) = node.parent {
+                children[parent_index].push(node.root);
+            }
+        }
+
+        let mut nodes: Vec<NodeDump> = self
+            .nodes
+            .iter()
+            .zip(children.into_iter())
+            .map(|(node, children)| NodeDump {
+                block_root: node.root,
+                slot: node.slot,
+                parent_root: node.parent.map(|index| self.nodes[index].root),
+                children,
+                weight: node.weight,
+                best_child: node.best_child.map(|index| self.nodes[index].root),
+            })
+            .collect();
+
+        nodes.sort_by_key(|node| (node.slot, node.block_root));
+
+        let latest_messages = self
+            .votes
+            .iter()
+            .enumerate()
+            .filter_map(|(validator_index, vote)| {
+                vote.map(|vote| LatestMessage {
+                    validator_index,
+                    block_root: vote.next_root,
+                    slot: vote.next_slot,
+                })
+            })
+            .collect();
+
+        ForkChoiceDump {
+            root: self.nodes[0].root,
+            root_slot: self.nodes[0].slot,
+            nodes,
+            latest_messages,
+        }
+    }
+
+    /// Verify the integrity of `self`. Returns `Ok(())` if the array has integrity, otherwise
+    /// returns `Err(description)`.
+    ///
+    /// Tries to detect the following erroneous conditions:
+    ///
+    /// - Nodes and indices which disagree.
+    /// - Any node other than the first without a parent, or with a parent after it.
+    /// - A best child or best descendant which does not descend from its node.
+    /// - A node with less weight than the sum of its children.
+    pub fn verify_integrity(&self) -> std::result::Result<(), String> {
+        if self.indices.len() != self.nodes.len() {
+            return Err(format!(
+                "Array has {} nodes but {} indices.",
+                self.nodes.len(),
+                self.indices.len()
+            ));
+        }
+
+        let mut children_weight = vec![0; self.nodes.len()];
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            if self.indices.get(&node.root) != Some(&index) {
+                return Err(format!("Node {} is not indexed by its root.", index));
+            }
+
+            match node.parent {
+                None if index == 0 => (),
+                Some(parent) if parent < index => children_weight[parent] += node.weight,
+                _ => return Err(format!("Node {} has an invalid parent.", index)),
+            }
+
+            if let Some(best_child) = node.best_child {
+                if self.nodes.get(best_child).and_then(|child| child.parent) != Some(index) {
+                    return Err(format!("Best child of node {} is not a child.", index));
+                }
+            }
+
+            if node.best_child.is_some() != node.best_descendant.is_some()
+                || node.best_descendant.map_or(false, |d| d < index)
+            {
+                return Err(format!("Node {} has an invalid best descendant.", index));
+            }
+        }
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            if node.weight < children_weight[index] {
+                return Err(format!("Node {} has less weight than its children.", index));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_block(&self, block_root: Hash256) -> Result<BeaconBlock<E>> {
+        self.store
+            .get::<BeaconBlock<E>>(&block_root)?
+            .ok_or_else(|| Error::MissingBlock(block_root))
+    }
+
+    fn get_state(&self, state_root: Hash256) -> Result<BeaconState<E>> {
+        self.store
+            .get::<BeaconState<E>>(&state_root)?
+            .ok_or_else(|| Error::MissingState(state_root))
+    }
+
+    fn root_slot(&self) -> Slot {
+        self.nodes[0].slot
+    }
+}
@@ -3,14 +3,14 @@
 //! This algorithm was conceived at IC3 Cornell, 2019.
 //!
 //! This implementation is incomplete and has known bugs. Do not use in production.
-use super::{LmdGhost, Result as SuperResult};
+use super::{ForkChoiceDump, LatestMessage, LmdGhost, NodeDump, Result as SuperResult};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::fmt;
 use std::marker::PhantomData;
 use std::sync::Arc;
 use store::{iter::BlockRootsIterator, Error as StoreError, Store};
-use types::{BeaconBlock, BeaconState, EthSpec, Hash256, Slot};
+use types::{BeaconBlock, BeaconState, Epoch, EthSpec, Hash256, Slot};
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -67,7 +67,13 @@ where
     }
 
     /// Process a block that was seen on the network.
-    fn process_block(&self, block: &BeaconBlock<E>, block_hash: Hash256) -> SuperResult<()> {
+    fn process_block(
+        &self,
+        block: &BeaconBlock<E>,
+        block_hash: Hash256,
+        _justified_epoch: Epoch,
+        _finalized_epoch: Epoch,
+    ) -> SuperResult<()> {
         self.core
             .write()
             .maybe_add_weightless_node(block.slot, block_hash)
@@ -107,6 +113,13 @@ where
     fn verify_integrity(&self) -> std::result::Result<(), String> {
         self.core.read().verify_integrity()
     }
+
+    fn dump(&self) -> SuperResult<ForkChoiceDump> {
+        self.core
+            .read()
+            .dump()
+            .map_err(|e| format!("dump failed: {:?}", e))
+    }
 }
 
 struct ReducedTree<T, E> {
@@ -252,31 +265,78 @@ where
     }
 
     fn find_head_from<'a>(&'a self, start_node: &'a Node) -> Result<&'a Node> {
-        if start_node.does_not_have_children() {
-            Ok(start_node)
-        } else {
-            let children = start_node
-                .children
-                .iter()
-                .map(|hash| self.get_node(*hash))
-                .collect::<Result<Vec<&Node>>>()?;
-
-            // TODO: check if `max_by` is `O(n^2)`.
-            let best_child = children
-                .iter()
-                .max_by(|a, b| {
-                    if a.weight != b.weight {
-                        a.weight.cmp(&b.weight)
-                    } else {
-                        a.block_hash.cmp(&b.block_hash)
-                    }
+        match self.best_child(start_node)? {
+            Some(best_child) => self.find_head_from(best_child),
+            None => Ok(start_node),
+        }
+    }
+
+    /// Returns the child of `node` with the highest weight, breaking ties by the highest block
+    /// hash. Returns `None` if `node` has no children.
+    fn best_child<'a>(&'a self, node: &'a Node) -> Result<Option<&'a Node>> {
+        let children = node
+            .children
+            .iter()
+            .map(|hash| self.get_node(*hash))
+            .collect::<Result<Vec<&Node>>>()?;
+
+        // TODO: check if `max_by` is `O(n^2)`.
+        Ok(children.into_iter().max_by(|a, b| {
+            if a.weight != b.weight {
+                a.weight.cmp(&b.weight)
+            } else {
+                a.block_hash.cmp(&b.block_hash)
+            }
+        }))
+    }
+
+    /// Returns a snapshot of all nodes in the tree and all latest votes.
+    ///
+    /// Reads each block from the store to determine its slot.
+    pub fn dump(&self) -> Result<ForkChoiceDump> {
+        let mut nodes = self
+            .nodes
+            .values()
+            .map(|node| {
+                let slot = if node.block_hash == self.root.0 {
+                    self.root.1
+                } else {
+                    self.get_block(node.block_hash)?.slot
+                };
+
+                Ok(NodeDump {
+                    block_root: node.block_hash,
+                    slot,
+                    parent_root: node.parent_hash,
+                    children: node.children.clone(),
+                    weight: node.weight,
+                    best_child: self.best_child(node)?.map(|child| child.block_hash),
                 })
-                // There can only be no maximum if there are no children. This code path is guarded
-                // against that condition.
-                .expect("There must be a maximally weighted node.");
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-            self.find_head_from(best_child)
-        }
+        nodes.sort_by_key(|node| (node.slot, node.block_root));
+
+        let latest_messages = self
+            .latest_votes
+            .0
+            .iter()
+            .enumerate()
+            .filter_map(|(validator_index, vote)| {
+                vote.map(|vote| LatestMessage {
+                    validator_index,
+                    block_root: vote.hash,
+                    slot: vote.slot,
+                })
+            })
+            .collect();
+
+        Ok(ForkChoiceDump {
+            root: self.root.0,
+            root_slot: self.root.1,
+            nodes,
+            latest_messages,
+        })
     }
 
     fn update_weight<F>(&mut self, start_block_root: Hash256, weight_fn: F) -> Result<u64>
@@ -7,20 +7,24 @@ use beacon_chain::test_utils::{
     generate_deterministic_keypairs, AttestationStrategy,
     BeaconChainHarness as BaseBeaconChainHarness, BlockStrategy,
 };
-use lmd_ghost::{LmdGhost, ThreadSafeReducedTree as BaseThreadSafeReducedTree};
+use lmd_ghost::{
+    LmdGhost, ThreadSafeProtoArray as BaseThreadSafeProtoArray,
+    ThreadSafeReducedTree as BaseThreadSafeReducedTree,
+};
 use rand::{prelude::*, rngs::StdRng};
 use std::sync::Arc;
 use store::{
     iter::{AncestorIter, BlockRootsIterator},
     MemoryStore, Store,
 };
-use types::{BeaconBlock, EthSpec, Hash256, MinimalEthSpec, Slot};
+use types::{BeaconBlock, BeaconState, Epoch, EthSpec, Hash256, MinimalEthSpec, Slot};
 
 // Should ideally be divisible by 3.
 pub const VALIDATOR_COUNT: usize = 3 * 8;
 
 type TestEthSpec = MinimalEthSpec;
 type ThreadSafeReducedTree = BaseThreadSafeReducedTree<MemoryStore, TestEthSpec>;
+type ThreadSafeProtoArray = BaseThreadSafeProtoArray<MemoryStore, TestEthSpec>;
 type BeaconChainHarness = BaseBeaconChainHarness<ThreadSafeReducedTree, TestEthSpec>;
 type RootAndSlot = (Hash256, Slot);
 
@@ -121,14 +125,14 @@ impl ForkedHarness {
     }
 
     /// Return a brand-new, empty fork choice with a reference to `harness.store`.
-    pub fn new_fork_choice(&self) -> ThreadSafeReducedTree {
+    pub fn new_fork_choice<L: LmdGhost<MemoryStore, TestEthSpec>>(&self) -> L {
         // Take a full clone of the store built by the harness.
         //
         // Taking a clone here ensures that each fork choice gets it's own store so there is no
         // cross-contamination between tests.
         let store: MemoryStore = self.store_clone();
 
-        ThreadSafeReducedTree::new(
+        L::new(
             Arc::new(store),
             &self.genesis_block,
             self.genesis_block_root,
@@ -182,15 +186,14 @@ const RANDOM_ACTIONS_PER_ITERATION: usize = 100;
 
 /// Create a single LMD instance and have one validator vote in reverse (highest to lowest slot)
 /// down the chain.
-#[test]
-fn random_scenario() {
+fn random_scenario<L: LmdGhost<MemoryStore, TestEthSpec>>() {
     let harness = &FORKED_HARNESS;
     let block_roots = harness.all_block_roots();
     let validators: Vec<usize> = (0..VALIDATOR_COUNT).collect();
     let mut rng = StdRng::seed_from_u64(9375205782030385); // Keyboard mash.
 
     for _ in 0..RANDOM_ITERATIONS {
-        let lmd = harness.new_fork_choice();
+        let lmd = harness.new_fork_choice::<L>();
 
         for _ in 0..RANDOM_ACTIONS_PER_ITERATION {
             let (root, slot) = block_roots[rng.next_u64() as usize % block_roots.len()];
@@ -210,11 +213,10 @@ fn random_scenario() {
 
 /// Create a single LMD instance and have one validator vote in reverse (highest to lowest slot)
 /// down the chain.
-#[test]
-fn single_voter_persistent_instance_reverse_order() {
+fn single_voter_persistent_instance_reverse_order<L: LmdGhost<MemoryStore, TestEthSpec>>() {
     let harness = &FORKED_HARNESS;
 
-    let lmd = harness.new_fork_choice();
+    let lmd = harness.new_fork_choice::<L>();
 
     assert_eq!(
         lmd.verify_integrity(),
@@ -246,12 +248,13 @@ fn single_voter_persistent_instance_reverse_order() {
 
 /// A single validator applies a single vote to each block in the honest fork, using a new tree
 /// each time.
-#[test]
-fn single_voter_many_instance_honest_blocks_voting_forwards() {
+fn single_voter_many_instance_honest_blocks_voting_forwards<
+    L: LmdGhost<MemoryStore, TestEthSpec>,
+>() {
     let harness = &FORKED_HARNESS;
 
     for (root, slot) in &harness.honest_roots {
-        let lmd = harness.new_fork_choice();
+        let lmd = harness.new_fork_choice::<L>();
         lmd.process_attestation(0, *root, *slot)
             .expect("fork choice should accept attestations to honest roots");
 
@@ -264,13 +267,14 @@ fn single_voter_many_instance_honest_blocks_voting_forwards() {
 }
 
 /// Same as above, but in reverse order (votes on the highest honest block first).
-#[test]
-fn single_voter_many_instance_honest_blocks_voting_in_reverse() {
+fn single_voter_many_instance_honest_blocks_voting_in_reverse<
+    L: LmdGhost<MemoryStore, TestEthSpec>,
+>() {
     let harness = &FORKED_HARNESS;
 
     // Same as above, but in reverse order (votes on the highest honest block first).
     for (root, slot) in harness.honest_roots.iter().rev() {
-        let lmd = harness.new_fork_choice();
+        let lmd = harness.new_fork_choice::<L>();
         lmd.process_attestation(0, *root, *slot)
             .expect("fork choice should accept attestations to honest roots in reverse");
 
@@ -284,12 +288,13 @@ fn single_voter_many_instance_honest_blocks_voting_in_reverse() {
 
 /// A single validator applies a single vote to each block in the faulty fork, using a new tree
 /// each time.
-#[test]
-fn single_voter_many_instance_faulty_blocks_voting_forwards() {
+fn single_voter_many_instance_faulty_blocks_voting_forwards<
+    L: LmdGhost<MemoryStore, TestEthSpec>,
+>() {
     let harness = &FORKED_HARNESS;
 
     for (root, slot) in &harness.faulty_roots {
-        let lmd = harness.new_fork_choice();
+        let lmd = harness.new_fork_choice::<L>();
         lmd.process_attestation(0, *root, *slot)
             .expect("fork choice should accept attestations to faulty roots");
 
@@ -302,12 +307,13 @@ fn single_voter_many_instance_faulty_blocks_voting_forwards() {
 }
 
 /// Same as above, but in reverse order (votes on the highest faulty block first).
-#[test]
-fn single_voter_many_instance_faulty_blocks_voting_in_reverse() {
+fn single_voter_many_instance_faulty_blocks_voting_in_reverse<
+    L: LmdGhost<MemoryStore, TestEthSpec>,
+>() {
     let harness = &FORKED_HARNESS;
 
     for (root, slot) in harness.faulty_roots.iter().rev() {
-        let lmd = harness.new_fork_choice();
+        let lmd = harness.new_fork_choice::<L>();
         lmd.process_attestation(0, *root, *slot)
             .expect("fork choice should accept attestations to faulty roots in reverse");
 
@@ -320,10 +326,10 @@ fn single_voter_many_instance_faulty_blocks_voting_in_reverse() {
 }
 
 /// Ensures that the finalized root can be set to all values in `roots`.
-fn test_update_finalized_root(roots: &[(Hash256, Slot)]) {
+fn test_update_finalized_root<L: LmdGhost<MemoryStore, TestEthSpec>>(roots: &[(Hash256, Slot)]) {
     let harness = &FORKED_HARNESS;
 
-    let lmd = harness.new_fork_choice();
+    let lmd = harness.new_fork_choice::<L>();
 
     for (root, _slot) in roots.iter().rev() {
         let block = harness
@@ -343,17 +349,241 @@ fn test_update_finalized_root(roots: &[(Hash256, Slot)]) {
 }
 
 /// Iterates from low-to-high slot through the faulty roots, updating the finalized root.
-#[test]
-fn update_finalized_root_faulty() {
+fn update_finalized_root_faulty<L: LmdGhost<MemoryStore, TestEthSpec>>() {
     let harness = &FORKED_HARNESS;
 
-    test_update_finalized_root(&harness.faulty_roots)
+    test_update_finalized_root::<L>(&harness.faulty_roots)
 }
 
 /// Iterates from low-to-high slot through the honest roots, updating the finalized root.
+fn update_finalized_root_honest<L: LmdGhost<MemoryStore, TestEthSpec>>() {
+    let harness = &FORKED_HARNESS;
+
+    test_update_finalized_root::<L>(&harness.honest_roots)
+}
+
+/// Votes on both forks and checks that following `best_child` from the root of the dump leads to
+/// the head returned by `find_head`.
+fn dump_follows_best_child_to_head<L: LmdGhost<MemoryStore, TestEthSpec>>() {
+    let harness = &FORKED_HARNESS;
+
+    let lmd = harness.new_fork_choice::<L>();
+
+    let (honest_root, honest_slot) = *harness.honest_roots.first().unwrap();
+    let (faulty_root, faulty_slot) = *harness.faulty_roots.first().unwrap();
+
+    let two_thirds = (VALIDATOR_COUNT / 3) * 2;
+
+    for validator_index in 0..VALIDATOR_COUNT {
+        let (root, slot) = if validator_index < two_thirds {
+            (honest_root, honest_slot)
+        } else {
+            (faulty_root, faulty_slot)
+        };
+
+        lmd.process_attestation(validator_index, root, slot)
+            .expect("fork choice should accept attestations");
+    }
+
+    let head = lmd
+        .find_head(
+            harness.genesis_block.slot,
+            harness.genesis_block_root,
+            ForkedHarness::weight_function,
+        )
+        .expect("should find head");
+
+    assert_eq!(head, honest_root, "Honest head should be selected");
+
+    let dump = lmd.dump().expect("should dump fork choice");
+
+    assert_eq!(dump.root, harness.genesis_block_root);
+    assert_eq!(dump.latest_messages.len(), VALIDATOR_COUNT);
+    assert!(dump
+        .latest_messages
+        .iter()
+        .all(|message| message.block_root == honest_root || message.block_root == faulty_root));
+
+    let root_node = dump
+        .nodes
+        .iter()
+        .find(|node| node.block_root == dump.root)
+        .expect("root should be in dump");
+    assert_eq!(root_node.weight, VALIDATOR_COUNT as u64);
+
+    let mut node = root_node;
+    while let Some(best_child) = node.best_child {
+        node = dump
+            .nodes
+            .iter()
+            .find(|node| node.block_root == best_child)
+            .expect("best child should be in dump");
+    }
+
+    assert_eq!(
+        node.block_root, head,
+        "Best children should lead to the head"
+    );
+    assert_eq!(node.slot, honest_slot);
+}
+
+/// Votes randomly on both forks and checks that both implementations agree on the head after
+/// each batch of votes.
+#[test]
+fn implementations_agree_on_head() {
+    let harness = &FORKED_HARNESS;
+    let block_roots = harness.all_block_roots();
+    let mut rng = StdRng::seed_from_u64(2847561039485721); // Keyboard mash.
+
+    let reduced_tree = harness.new_fork_choice::<ThreadSafeReducedTree>();
+    let proto_array = harness.new_fork_choice::<ThreadSafeProtoArray>();
+
+    for _ in 0..RANDOM_ITERATIONS {
+        for _ in 0..VALIDATOR_COUNT {
+            let (root, slot) = block_roots[rng.next_u64() as usize % block_roots.len()];
+            let validator_index = rng.next_u64() as usize % VALIDATOR_COUNT;
+
+            reduced_tree
+                .process_attestation(validator_index, root, slot)
+                .expect("reduced tree should accept attestations");
+            proto_array
+                .process_attestation(validator_index, root, slot)
+                .expect("proto array should accept attestations");
+        }
+
+        let reduced_tree_head = reduced_tree
+            .find_head(
+                harness.genesis_block.slot,
+                harness.genesis_block_root,
+                ForkedHarness::weight_function,
+            )
+            .expect("reduced tree should find head");
+        let proto_array_head = proto_array
+            .find_head(
+                harness.genesis_block.slot,
+                harness.genesis_block_root,
+                ForkedHarness::weight_function,
+            )
+            .expect("proto array should find head");
+
+        assert_eq!(
+            reduced_tree_head, proto_array_head,
+            "Implementations should agree on the head"
+        );
+    }
+}
+
+/// Processes the honest blocks, oldest first, after removing their states from the store and
+/// checks that the proto array does not need the states to find the head.
 #[test]
-fn update_finalized_root_honest() {
+fn proto_array_processes_blocks_without_their_states() {
     let harness = &FORKED_HARNESS;
+    let store = harness.store_clone();
+
+    let mut honest_roots = harness.honest_roots.clone();
+    honest_roots.sort_by_key(|(_, slot)| *slot);
+    honest_roots.dedup_by_key(|(root, _)| *root);
+
+    let blocks: Vec<(Hash256, BeaconBlock<TestEthSpec>)> = honest_roots
+        .iter()
+        .map(|(root, _)| {
+            let block = store
+                .get::<BeaconBlock<TestEthSpec>>(root)
+                .expect("DB should not error")
+                .expect("block should exist");
+            (*root, block)
+        })
+        .collect();
+
+    for (root, block) in &blocks {
+        if *root != harness.genesis_block_root {
+            store
+                .delete::<BeaconState<TestEthSpec>>(&block.state_root)
+                .expect("should delete state");
+        }
+    }
+
+    let proto_array = ThreadSafeProtoArray::new(
+        Arc::new(store),
+        &harness.genesis_block,
+        harness.genesis_block_root,
+    );
+
+    for (root, block) in &blocks {
+        proto_array
+            .process_block(block, *root, Epoch::new(0), Epoch::new(0))
+            .expect("proto array should not need the state of the block");
+    }
+
+    let head = proto_array
+        .find_head(
+            harness.genesis_block.slot,
+            harness.genesis_block_root,
+            ForkedHarness::weight_function,
+        )
+        .expect("proto array should find head");
+    let (honest_head, _) = honest_roots.last().expect("chain cannot be empty");
 
-    test_update_finalized_root(&harness.honest_roots)
+    assert_eq!(
+        head, *honest_head,
+        "The latest honest block should be the head"
+    );
 }
+
+/// Instantiates each of the scenarios above as a `#[test]` against the given `LmdGhost`
+/// implementation.
+macro_rules! fork_choice_tests {
+    ($name: ident, $lmd_ghost: ty) => {
+        mod $name {
+            use super::*;
+
+            #[test]
+            fn random_scenario() {
+                super::random_scenario::<$lmd_ghost>();
+            }
+
+            #[test]
+            fn single_voter_persistent_instance_reverse_order() {
+                super::single_voter_persistent_instance_reverse_order::<$lmd_ghost>();
+            }
+
+            #[test]
+            fn single_voter_many_instance_honest_blocks_voting_forwards() {
+                super::single_voter_many_instance_honest_blocks_voting_forwards::<$lmd_ghost>();
+            }
+
+            #[test]
+            fn single_voter_many_instance_honest_blocks_voting_in_reverse() {
+                super::single_voter_many_instance_honest_blocks_voting_in_reverse::<$lmd_ghost>();
+            }
+
+            #[test]
+            fn single_voter_many_instance_faulty_blocks_voting_forwards() {
+                super::single_voter_many_instance_faulty_blocks_voting_forwards::<$lmd_ghost>();
+            }
+
+            #[test]
+            fn single_voter_many_instance_faulty_blocks_voting_in_reverse() {
+                super::single_voter_many_instance_faulty_blocks_voting_in_reverse::<$lmd_ghost>();
+            }
+
+            #[test]
+            fn update_finalized_root_faulty() {
+                super::update_finalized_root_faulty::<$lmd_ghost>();
+            }
+
+            #[test]
+            fn update_finalized_root_honest() {
+                super::update_finalized_root_honest::<$lmd_ghost>();
+            }
+
+            #[test]
+            fn dump_follows_best_child_to_head() {
+                super::dump_follows_best_child_to_head::<$lmd_ghost>();
+            }
+        }
+    };
+}
+
+fork_choice_tests!(reduced_tree, ThreadSafeReducedTree);
+fork_choice_tests!(proto_array, ThreadSafeProtoArray);
@@ -227,6 +227,13 @@ impl<T: EthSpec> OperationPool<T> {
         Ok(())
     }
 
+    /// All proposer slashings in the pool, ordered by proposer index.
+    pub fn get_all_proposer_slashings(&self) -> Vec<ProposerSlashing> {
+        let mut slashings: Vec<_> = self.proposer_slashings.read().values().cloned().collect();
+        slashings.sort_by_key(|slashing| slashing.proposer_index);
+        slashings
+    }
+
     /// Compute the tuple ID that is used to identify an attester slashing.
     ///
     /// Depends on the fork field of the state, but not on the state's epoch.
@@ -254,6 +261,11 @@ impl<T: EthSpec> OperationPool<T> {
         Ok(())
     }
 
+    /// All attester slashings in the pool, in no particular order.
+    pub fn get_all_attester_slashings(&self) -> Vec<AttesterSlashing<T>> {
+        self.attester_slashings.read().values().cloned().collect()
+    }
+
     /// Get proposer and attester slashings for inclusion in a block.
     ///
     /// This function computes both types of slashings together, because
@@ -366,6 +378,13 @@ impl<T: EthSpec> OperationPool<T> {
         )
     }
 
+    /// All voluntary exits in the pool, ordered by validator index.
+    pub fn get_all_voluntary_exits(&self) -> Vec<VoluntaryExit> {
+        let mut exits: Vec<_> = self.voluntary_exits.read().values().cloned().collect();
+        exits.sort_by_key(|exit| exit.validator_index);
+        exits
+    }
+
     /// Prune if validator has already exited at the last finalized state.
     pub fn prune_voluntary_exits(&self, finalized_state: &BeaconState<T>) {
         prune_validator_hash_map(
@@ -14,8 +14,7 @@ pub fn initialize_beacon_state_from_eth1<T: EthSpec>(
     deposits: Vec<Deposit>,
     spec: &ChainSpec,
 ) -> Result<BeaconState<T>, BlockProcessingError> {
-    let genesis_time =
-        eth1_timestamp - eth1_timestamp % spec.seconds_per_day + 2 * spec.seconds_per_day;
+    let genesis_time = eth2_genesis_time(eth1_timestamp, spec);
     let eth1_data = Eth1Data {
         // Temporary deposit root
         deposit_root: Hash256::zero(),
@@ -63,6 +62,13 @@ pub fn initialize_beacon_state_from_eth1<T: EthSpec>(
     Ok(state)
 }
 
+/// Returns the genesis time of a state initialized from an eth1 block with the given timestamp.
+///
+/// Spec v0.8.1
+pub fn eth2_genesis_time(eth1_timestamp: u64, spec: &ChainSpec) -> u64 {
+    eth1_timestamp - eth1_timestamp % spec.seconds_per_day + 2 * spec.seconds_per_day
+}
+
 /// Determine whether a candidate genesis state is suitable for starting the chain.
 ///
 /// Spec v0.8.1
@@ -14,7 +14,7 @@ pub mod tests;
 pub mod validator_statuses;
 pub mod winning_root;
 
-pub use apply_rewards::process_rewards_and_penalties;
+pub use apply_rewards::{process_rewards_and_penalties, Delta, RewardBreakdown};
 pub use process_slashings::process_slashings;
 pub use registry_updates::process_registry_updates;
 
@@ -68,6 +68,32 @@ pub fn per_epoch_processing<T: EthSpec>(
     Ok(())
 }
 
+/// Returns the rewards and penalties that `per_epoch_processing` applies to each validator of
+/// `state`, without modifying `state`.
+///
+/// `state` should be at the last slot of an epoch, such that the next call to
+/// `per_slot_processing` performs the epoch transition.
+pub fn get_epoch_reward_breakdowns<T: EthSpec>(
+    state: &BeaconState<T>,
+    spec: &ChainSpec,
+) -> Result<Vec<RewardBreakdown>, Error> {
+    let mut state = state.clone();
+
+    state.build_committee_cache(RelativeEpoch::Previous, spec)?;
+    state.build_committee_cache(RelativeEpoch::Current, spec)?;
+    state.build_committee_cache(RelativeEpoch::Next, spec)?;
+
+    let mut validator_statuses = ValidatorStatuses::new(&state, spec)?;
+    validator_statuses.process_attestations(&state, spec)?;
+
+    // Rewards depend upon the finality and crosslinks that are updated earlier in the epoch
+    // transition.
+    process_justification_and_finalization(&mut state, &validator_statuses.total_balances)?;
+    process_crosslinks(&mut state, spec)?;
+
+    apply_rewards::get_reward_breakdowns(&state, &mut validator_statuses, spec)
+}
+
 /// Update the following fields on the `BeaconState`:
 ///
 /// - `justification_bitfield`.
@@ -4,10 +4,10 @@ use integer_sqrt::IntegerSquareRoot;
 use types::*;
 
 /// Use to track the changes to a validators balance.
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Debug, PartialEq)]
 pub struct Delta {
-    rewards: u64,
-    penalties: u64,
+    pub rewards: u64,
+    pub penalties: u64,
 }
 
 impl Delta {
@@ -30,6 +30,44 @@ impl std::ops::AddAssign for Delta {
     }
 }
 
+/// The changes to a validators balance during an epoch transition, by the duty that caused them.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct RewardBreakdown {
+    /// Attesting to the expected FFG source.
+    pub source: Delta,
+    /// The speed with which the validators attestation was included.
+    pub inclusion_delay: Delta,
+    /// Attesting to the expected FFG target.
+    pub target: Delta,
+    /// Attesting to the expected head.
+    pub head: Delta,
+    /// Penalties applied whilst the chain is not finalizing.
+    pub inactivity: Delta,
+    /// Attesting to the winning crosslink.
+    pub crosslink: Delta,
+    /// Including attestations in proposed blocks.
+    pub proposer: Delta,
+}
+
+impl RewardBreakdown {
+    /// Returns the sum of all the deltas.
+    pub fn total(&self) -> Delta {
+        let mut total = Delta::default();
+        for delta in &[
+            &self.source,
+            &self.inclusion_delay,
+            &self.target,
+            &self.head,
+            &self.inactivity,
+            &self.crosslink,
+            &self.proposer,
+        ] {
+            total += (*delta).clone();
+        }
+        total
+    }
+}
+
 /// Apply attester and proposer rewards.
 ///
 /// Spec v0.8.0
@@ -42,6 +80,33 @@ pub fn process_rewards_and_penalties<T: EthSpec>(
         return Ok(());
     }
 
+    let breakdowns = get_reward_breakdowns(state, validator_statuses, spec)?;
+
+    // Apply the deltas, over-flowing but not under-flowing (saturating at 0 instead).
+    for (i, breakdown) in breakdowns.iter().enumerate() {
+        let delta = breakdown.total();
+        state.balances[i] += delta.rewards;
+        state.balances[i] = state.balances[i].saturating_sub(delta.penalties);
+    }
+
+    Ok(())
+}
+
+/// Returns the rewards and penalties of each validator, as applied by
+/// `process_rewards_and_penalties`, without modifying the balances of `state`.
+///
+/// Spec v0.8.0
+pub fn get_reward_breakdowns<T: EthSpec>(
+    state: &BeaconState<T>,
+    validator_statuses: &mut ValidatorStatuses,
+    spec: &ChainSpec,
+) -> Result<Vec<RewardBreakdown>, Error> {
+    let mut breakdowns = vec![RewardBreakdown::default(); state.balances.len()];
+
+    if state.current_epoch() == T::genesis_epoch() {
+        return Ok(breakdowns);
+    }
+
     // Guard against an out-of-bounds during the validator balance update.
     if validator_statuses.statuses.len() != state.balances.len()
         || validator_statuses.statuses.len() != state.validators.len()
@@ -49,31 +114,23 @@ pub fn process_rewards_and_penalties<T: EthSpec>(
         return Err(Error::ValidatorStatusesInconsistent);
     }
 
-    let mut deltas = vec![Delta::default(); state.balances.len()];
-
-    get_attestation_deltas(&mut deltas, state, &validator_statuses, spec)?;
+    get_attestation_deltas(&mut breakdowns, state, &validator_statuses, spec)?;
 
     // Update statuses with the information from winning roots.
     validator_statuses.process_winning_roots(state, spec)?;
 
-    get_crosslink_deltas(&mut deltas, state, &validator_statuses, spec)?;
+    get_crosslink_deltas(&mut breakdowns, state, &validator_statuses, spec)?;
 
-    get_proposer_deltas(&mut deltas, state, validator_statuses, spec)?;
+    get_proposer_deltas(&mut breakdowns, state, validator_statuses, spec)?;
 
-    // Apply the deltas, over-flowing but not under-flowing (saturating at 0 instead).
-    for (i, delta) in deltas.iter().enumerate() {
-        state.balances[i] += delta.rewards;
-        state.balances[i] = state.balances[i].saturating_sub(delta.penalties);
-    }
-
-    Ok(())
+    Ok(breakdowns)
 }
 
 /// For each attesting validator, reward the proposer who was first to include their attestation.
 ///
 /// Spec v0.8.0
 fn get_proposer_deltas<T: EthSpec>(
-    deltas: &mut Vec<Delta>,
+    breakdowns: &mut Vec<RewardBreakdown>,
     state: &BeaconState<T>,
     validator_statuses: &mut ValidatorStatuses,
     spec: &ChainSpec,
@@ -91,11 +148,13 @@ fn get_proposer_deltas<T: EthSpec>(
                 spec,
             )?;
 
-            if inclusion.proposer_index >= deltas.len() {
+            if inclusion.proposer_index >= breakdowns.len() {
                 return Err(Error::ValidatorStatusesInconsistent);
             }
 
-            deltas[inclusion.proposer_index].reward(base_reward / spec.proposer_reward_quotient);
+            breakdowns[inclusion.proposer_index]
+                .proposer
+                .reward(base_reward / spec.proposer_reward_quotient);
         }
     }
 
@@ -106,7 +165,7 @@ fn get_proposer_deltas<T: EthSpec>(
 ///
 /// Spec v0.8.0
 fn get_attestation_deltas<T: EthSpec>(
-    deltas: &mut Vec<Delta>,
+    breakdowns: &mut Vec<RewardBreakdown>,
     state: &BeaconState<T>,
     validator_statuses: &ValidatorStatuses,
     spec: &ChainSpec,
@@ -121,39 +180,37 @@ fn get_attestation_deltas<T: EthSpec>(
             spec,
         )?;
 
-        let delta = get_attestation_delta::<T>(
+        get_attestation_delta::<T>(
+            &mut breakdowns[index],
             &validator,
             &validator_statuses.total_balances,
             base_reward,
             finality_delay,
             spec,
         );
-
-        deltas[index] += delta;
     }
 
     Ok(())
 }
 
-/// Determine the delta for a single validator, sans proposer rewards.
+/// Determine the deltas for a single validator, sans crosslink and proposer rewards.
 ///
 /// Spec v0.8.0
 fn get_attestation_delta<T: EthSpec>(
+    breakdown: &mut RewardBreakdown,
     validator: &ValidatorStatus,
     total_balances: &TotalBalances,
     base_reward: u64,
     finality_delay: u64,
     spec: &ChainSpec,
-) -> Delta {
-    let mut delta = Delta::default();
-
+) {
     // Is this validator eligible to be rewarded or penalized?
     // Spec: validator index in `eligible_validator_indices`
     let is_eligible = validator.is_active_in_previous_epoch
         || (validator.is_slashed && !validator.is_withdrawable_in_current_epoch);
 
     if !is_eligible {
-        return delta;
+        return;
     }
 
     let total_balance = total_balances.current_epoch;
@@ -165,49 +222,57 @@ fn get_attestation_delta<T: EthSpec>(
     // Spec:
     // - validator index in `get_unslashed_attesting_indices(state, matching_source_attestations)`
     if validator.is_previous_epoch_attester && !validator.is_slashed {
-        delta.reward(base_reward * total_attesting_balance / total_balance);
+        breakdown
+            .source
+            .reward(base_reward * total_attesting_balance / total_balance);
         // Inclusion speed bonus
         let proposer_reward = base_reward / spec.proposer_reward_quotient;
         let max_attester_reward = base_reward - proposer_reward;
         let inclusion = validator
             .inclusion_info
             .expect("It is a logic error for an attester not to have an inclusion distance.");
-        delta.reward(
+        breakdown.inclusion_delay.reward(
             max_attester_reward
                 * (T::SlotsPerEpoch::to_u64() + spec.min_attestation_inclusion_delay
                     - inclusion.distance)
                 / T::SlotsPerEpoch::to_u64(),
         );
     } else {
-        delta.penalize(base_reward);
+        breakdown.source.penalize(base_reward);
     }
 
     // Expected FFG target.
     // Spec:
     // - validator index in `get_unslashed_attesting_indices(state, matching_target_attestations)`
     if validator.is_previous_epoch_target_attester && !validator.is_slashed {
-        delta.reward(base_reward * matching_target_balance / total_balance);
+        breakdown
+            .target
+            .reward(base_reward * matching_target_balance / total_balance);
     } else {
-        delta.penalize(base_reward);
+        breakdown.target.penalize(base_reward);
     }
 
     // Expected head.
     // Spec:
     // - validator index in `get_unslashed_attesting_indices(state, matching_head_attestations)`
     if validator.is_previous_epoch_head_attester && !validator.is_slashed {
-        delta.reward(base_reward * matching_head_balance / total_balance);
+        breakdown
+            .head
+            .reward(base_reward * matching_head_balance / total_balance);
     } else {
-        delta.penalize(base_reward);
+        breakdown.head.penalize(base_reward);
     }
 
     // Inactivity penalty
     if finality_delay > spec.min_epochs_to_inactivity_penalty {
         // All eligible validators are penalized
-        delta.penalize(spec.base_rewards_per_epoch * base_reward);
+        breakdown
+            .inactivity
+            .penalize(spec.base_rewards_per_epoch * base_reward);
 
         // Additionally, all validators whose FFG target didn't match are penalized extra
         if !validator.is_previous_epoch_target_attester {
-            delta.penalize(
+            breakdown.inactivity.penalize(
                 validator.current_epoch_effective_balance * finality_delay
                     / spec.inactivity_penalty_quotient,
             );
@@ -216,23 +281,21 @@ fn get_attestation_delta<T: EthSpec>(
 
     // Proposer bonus is handled in `get_proposer_deltas`.
     //
-    // This function only computes the delta for a single validator, so it cannot also return a
-    // delta for a validator.
-
-    delta
+    // This function only computes the deltas for a single validator, so it cannot also compute
+    // the deltas of the proposers which included its attestation.
 }
 
 /// Calculate the deltas based upon the winning roots for attestations during the previous epoch.
 ///
 /// Spec v0.8.0
 fn get_crosslink_deltas<T: EthSpec>(
-    deltas: &mut Vec<Delta>,
+    breakdowns: &mut Vec<RewardBreakdown>,
     state: &BeaconState<T>,
     validator_statuses: &ValidatorStatuses,
     spec: &ChainSpec,
 ) -> Result<(), Error> {
     for (index, validator) in validator_statuses.statuses.iter().enumerate() {
-        let mut delta = Delta::default();
+        let delta = &mut breakdowns[index].crosslink;
 
         let base_reward = get_base_reward(
             state,
@@ -249,8 +312,6 @@ fn get_crosslink_deltas<T: EthSpec>(
         } else {
             delta.penalize(base_reward);
         }
-
-        deltas[index] += delta;
     }
 
     Ok(())
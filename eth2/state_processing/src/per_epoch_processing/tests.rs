@@ -1,5 +1,5 @@
 #![cfg(test)]
-use crate::per_epoch_processing::per_epoch_processing;
+use crate::per_epoch_processing::{get_epoch_reward_breakdowns, per_epoch_processing};
 use env_logger::{Builder, Env};
 use types::test_utils::TestingBeaconStateBuilder;
 use types::*;
@@ -21,3 +21,36 @@ fn runs_without_error() {
 
     per_epoch_processing(&mut state, &spec).unwrap();
 }
+
+#[test]
+fn reward_breakdowns_match_balance_changes() {
+    let spec = MinimalEthSpec::default_spec();
+
+    let mut builder: TestingBeaconStateBuilder<MinimalEthSpec> =
+        TestingBeaconStateBuilder::from_deterministic_keypairs(8, &spec);
+
+    let target_slot =
+        (MinimalEthSpec::genesis_epoch() + 4).end_slot(MinimalEthSpec::slots_per_epoch());
+    builder.teleport_to_slot(target_slot);
+
+    let (mut state, _keypairs) = builder.build();
+
+    let breakdowns = get_epoch_reward_breakdowns(&state, &spec).unwrap();
+    let previous_balances = state.balances.clone();
+
+    per_epoch_processing(&mut state, &spec).unwrap();
+
+    assert_eq!(breakdowns.len(), previous_balances.len());
+    for (i, breakdown) in breakdowns.iter().enumerate() {
+        // Without any attestations, every active validator misses all of its duties.
+        assert_eq!(breakdown.total().rewards, 0);
+        assert!(breakdown.source.penalties > 0);
+        assert!(breakdown.crosslink.penalties > 0);
+
+        let total = breakdown.total();
+        assert_eq!(
+            state.balances[i],
+            (previous_balances[i] + total.rewards).saturating_sub(total.penalties)
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use super::{Attestation, ChainSpec, Domain, EthSpec, Fork, PublicKey, SecretKey, Signature, Slot};
+use crate::test_utils::TestRandom;
+use eth2_hashing::hash;
+use serde_derive::{Deserialize, Serialize};
+use ssz_derive::{Decode, Encode};
+use std::convert::TryInto;
+use test_random_derive::TestRandom;
+use tree_hash::TreeHash;
+use tree_hash_derive::TreeHash;
+
+/// An aggregate of the attestations of a crosslink committee, published by a member of the
+/// committee who has been selected to aggregate.
+///
+/// The `selection_proof` is the aggregator's signature over the slot of the aggregate, with
+/// `Domain::SelectionProof`. Only the aggregator can produce it and signatures are deterministic, so
+/// the selection of aggregators can be verified by any node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode, TreeHash, TestRandom)]
+#[serde(bound = "T: EthSpec")]
+pub struct AggregateAndProof<T: EthSpec> {
+    pub aggregator_index: u64,
+    pub aggregate: Attestation<T>,
+    pub selection_proof: Signature,
+}
+
+impl<T: EthSpec> AggregateAndProof<T> {
+    /// Returns the message which is signed to produce the `selection_proof` for `slot`.
+    pub fn selection_proof_message(slot: Slot) -> Vec<u8> {
+        slot.tree_hash_root()
+    }
+
+    /// Returns the domain with which the `selection_proof` for `slot` is signed.
+    pub fn selection_proof_domain(slot: Slot, fork: &Fork, spec: &ChainSpec) -> u64 {
+        spec.get_domain(
+            slot.epoch(T::slots_per_epoch()),
+            Domain::SelectionProof,
+            fork,
+        )
+    }
+
+    /// Returns the `selection_proof` of the holder of `secret_key` for `slot`.
+    pub fn produce_selection_proof(
+        slot: Slot,
+        secret_key: &SecretKey,
+        fork: &Fork,
+        spec: &ChainSpec,
+    ) -> Signature {
+        Signature::new(
+            &Self::selection_proof_message(slot),
+            Self::selection_proof_domain(slot, fork, spec),
+            secret_key,
+        )
+    }
+
+    /// Returns `true` if `selection_proof` is the signature of `pubkey` over `slot`.
+    pub fn is_valid_selection_proof(
+        selection_proof: &Signature,
+        slot: Slot,
+        pubkey: &PublicKey,
+        fork: &Fork,
+        spec: &ChainSpec,
+    ) -> bool {
+        selection_proof.verify(
+            &Self::selection_proof_message(slot),
+            Self::selection_proof_domain(slot, fork, spec),
+            pubkey,
+        )
+    }
+
+    /// Returns `true` if the `selection_proof` selects its signer to aggregate the attestations of
+    /// a committee of `committee_len` members.
+    ///
+    /// On average, `spec.target_aggregators_per_committee` members of each committee are selected.
+    pub fn is_aggregator(
+        committee_len: usize,
+        selection_proof: &Signature,
+        spec: &ChainSpec,
+    ) -> bool {
+        let modulo = std::cmp::max(
+            1,
+            committee_len as u64 / spec.target_aggregators_per_committee,
+        );
+        let selection_hash = hash(&selection_proof.as_bytes());
+        let selection_int = u64::from_le_bytes(
+            selection_hash[0..8]
+                .try_into()
+                .expect("hash is at least 8 bytes"),
+        );
+
+        selection_int % modulo == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    ssz_tests!(AggregateAndProof<MainnetEthSpec>);
+
+    #[test]
+    fn selection_proof_is_bound_to_signer_and_slot() {
+        type T = AggregateAndProof<MainnetEthSpec>;
+
+        let spec = MainnetEthSpec::default_spec();
+        let fork = Fork::default();
+        let keypair = Keypair::random();
+        let other = Keypair::random();
+        let slot = Slot::new(42);
+
+        let proof = T::produce_selection_proof(slot, &keypair.sk, &fork, &spec);
+
+        assert!(T::is_valid_selection_proof(
+            &proof,
+            slot,
+            &keypair.pk,
+            &fork,
+            &spec
+        ));
+        assert!(!T::is_valid_selection_proof(
+            &proof,
+            slot + 1,
+            &keypair.pk,
+            &fork,
+            &spec
+        ));
+        assert!(!T::is_valid_selection_proof(
+            &proof, slot, &other.pk, &fork, &spec
+        ));
+    }
+
+    #[test]
+    fn small_committees_are_all_aggregators() {
+        let spec = MainnetEthSpec::default_spec();
+        let selection_proof = Signature::empty_signature();
+
+        for committee_len in 0..(spec.target_aggregators_per_committee as usize * 2) {
+            assert!(AggregateAndProof::<MainnetEthSpec>::is_aggregator(
+                committee_len,
+                &selection_proof,
+                &spec
+            ));
+        }
+    }
+
+    #[test]
+    fn large_committees_select_some_aggregators() {
+        let spec = MainnetEthSpec::default_spec();
+        let committee_len = spec.target_aggregators_per_committee as usize * 64;
+        let keypair = Keypair::random();
+
+        let aggregators = (0..committee_len)
+            .map(|i| Signature::new(&int_to_bytes::int_to_bytes8(i as u64), 0, &keypair.sk))
+            .filter(|proof| {
+                AggregateAndProof::<MainnetEthSpec>::is_aggregator(committee_len, proof, &spec)
+            })
+            .count();
+
+        assert!(aggregators > 0, "Some members should be selected");
+        assert!(
+            aggregators < committee_len / 4,
+            "Most members should not be selected"
+        );
+    }
+}
@@ -926,7 +926,8 @@ impl<T: EthSpec> BeaconState<T> {
         caches[current] = CommitteeCache::default();
     }
 
-    fn cache_index(relative_epoch: RelativeEpoch) -> usize {
+    /// Returns the index of the cache for some `RelativeEpoch` in `self.committee_caches`.
+    pub fn cache_index(relative_epoch: RelativeEpoch) -> usize {
         match relative_epoch {
             RelativeEpoch::Previous => 0,
             RelativeEpoch::Current => 1,
@@ -120,6 +120,11 @@ impl CommitteeCache {
         Some(epoch) == self.initialized_epoch
     }
 
+    /// Returns the epoch at which the cache was initialized, if any.
+    pub fn initialized_epoch(&self) -> Option<Epoch> {
+        self.initialized_epoch
+    }
+
     /// Returns the **shuffled** list of active validator indices for the initialized epoch.
     ///
     /// These indices are not in ascending order.
@@ -28,6 +28,16 @@ impl ExitCache {
         self.0.keys().max().cloned()
     }
 
+    /// Returns the number of distinct exit epochs in the cache.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if no exits have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Get the number of validators exiting/exited at a given epoch, or zero if not known.
     pub fn get_churn_at(&self, epoch: Epoch) -> u64 {
         self.0.get(&epoch).cloned().unwrap_or(0)
@@ -13,6 +13,7 @@ pub enum Domain {
     Deposit,
     VoluntaryExit,
     Transfer,
+    SelectionProof,
 }
 
 /// Holds all the "constants" for a BeaconChain.
@@ -34,11 +35,13 @@ pub struct ChainSpec {
      * Misc
      */
     pub target_committee_size: usize,
+    pub target_aggregators_per_committee: u64,
     pub min_per_epoch_churn_limit: u64,
     pub churn_limit_quotient: u64,
     pub shuffle_round_count: u8,
     pub min_genesis_active_validator_count: u64,
     pub min_genesis_time: u64,
+    pub eth1_follow_distance: u64,
 
     /*
      *  Gwei values
@@ -59,6 +62,7 @@ pub struct ChainSpec {
      * Time parameters
      */
     pub milliseconds_per_slot: u64,
+    pub seconds_per_eth1_block: u64,
     pub min_attestation_inclusion_delay: u64,
     pub min_seed_lookahead: Epoch,
     pub activation_exit_delay: u64,
@@ -90,6 +94,7 @@ pub struct ChainSpec {
     domain_deposit: u32,
     domain_voluntary_exit: u32,
     domain_transfer: u32,
+    domain_selection_proof: u32,
 
     pub boot_nodes: Vec<String>,
     pub network_id: u8,
@@ -107,6 +112,7 @@ impl ChainSpec {
             Domain::Deposit => self.domain_deposit,
             Domain::VoluntaryExit => self.domain_voluntary_exit,
             Domain::Transfer => self.domain_transfer,
+            Domain::SelectionProof => self.domain_selection_proof,
         };
 
         let mut bytes: Vec<u8> = int_to_bytes4(domain_constant);
@@ -135,11 +141,13 @@ impl ChainSpec {
              * Misc
              */
             target_committee_size: 128,
+            target_aggregators_per_committee: 16,
             min_per_epoch_churn_limit: 4,
             churn_limit_quotient: 65_536,
             shuffle_round_count: 90,
             min_genesis_active_validator_count: 65_536,
             min_genesis_time: 1_578_009_600, // Jan 3, 2020
+            eth1_follow_distance: 1_024,
 
             /*
              *  Gwei values
@@ -159,6 +167,7 @@ impl ChainSpec {
              * Time parameters
              */
             milliseconds_per_slot: 6_000,
+            seconds_per_eth1_block: 14,
             min_attestation_inclusion_delay: 1,
             min_seed_lookahead: Epoch::new(1),
             activation_exit_delay: 4,
@@ -185,6 +194,7 @@ impl ChainSpec {
             domain_deposit: 3,
             domain_voluntary_exit: 4,
             domain_transfer: 5,
+            domain_selection_proof: 6,
 
             /*
              * Network specific
@@ -207,6 +217,7 @@ impl ChainSpec {
             target_committee_size: 4,
             shuffle_round_count: 10,
             min_genesis_active_validator_count: 64,
+            eth1_follow_distance: 16,
             max_epochs_per_crosslink: 4,
             network_id: 2, // lighthouse testnet network id
             boot_nodes,
@@ -269,5 +280,6 @@ mod tests {
         test_domain(Domain::Deposit, spec.domain_deposit, &spec);
         test_domain(Domain::VoluntaryExit, spec.domain_voluntary_exit, &spec);
         test_domain(Domain::Transfer, spec.domain_transfer, &spec);
+        test_domain(Domain::SelectionProof, spec.domain_selection_proof, &spec);
     }
 }
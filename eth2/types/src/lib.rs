@@ -6,6 +6,7 @@
 #[macro_use]
 pub mod test_utils;
 
+pub mod aggregate_and_proof;
 pub mod attestation;
 pub mod attestation_data;
 pub mod attestation_data_and_custody_bit;
@@ -44,6 +45,7 @@ pub mod validator;
 use ethereum_types::{H160, H256, U256};
 use std::collections::HashMap;
 
+pub use crate::aggregate_and_proof::AggregateAndProof;
 pub use crate::attestation::Attestation;
 pub use crate::attestation_data::AttestationData;
 pub use crate::attestation_data_and_custody_bit::AttestationDataAndCustodyBit;
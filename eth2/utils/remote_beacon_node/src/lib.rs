@@ -0,0 +1,731 @@
+//! Provides a `RemoteBeaconNode` which interacts with a HTTP API on another Lighthouse (or
+//! compatible) instance.
+//!
+//! Presently, this is only used for testing but it _could_ become a user-facing library.
+
+use eth2_config::Eth2Config;
+use eth2_libp2p::{Enr, Multiaddr};
+use reqwest::{header, Client, Response, StatusCode, Url};
+use serde::{de::DeserializeOwned, Serialize};
+use ssz::{Decode, Encode};
+use std::marker::PhantomData;
+use std::time::Duration;
+use types::{
+    Attestation, AttesterSlashing, BeaconBlock, BeaconState, ChainSpec, Checkpoint, Epoch, EthSpec,
+    Fork, Hash256, ProposerSlashing, PublicKey, Signature, Slot, VoluntaryExit,
+};
+
+pub use rest_types::{
+    BlockHeaderProof, BlockId, BlockResponse, ChainDumpEntry, Committee, CommitteeCacheSize,
+    ForkChoiceNode, ForkChoiceResponse, HeadResponse, LatestMessage, MerkleProof, Proposer,
+    RewardDelta, StateCacheResponse, StateFieldProof, StateId, StateResponse, TrustedPeer,
+    ValidatorBalance, ValidatorDuty, ValidatorProof, ValidatorRewards, ValidatorStatus,
+};
+
+/// The default timeout for requests to the beacon node.
+pub const REQUEST_TIMEOUT_SECONDS: u64 = 5;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Unable to parse a URL. Check the server URL.
+    UrlParseError(reqwest::UrlError),
+    /// The server URL cannot have a path appended to it.
+    InvalidUrl,
+    /// The bearer token contains characters which are not permitted in a HTTP header.
+    InvalidToken,
+    /// The `reqwest` library returned an error.
+    ReqwestError(reqwest::Error),
+    /// There was an error when decoding an SSZ response.
+    SszDecodeError(ssz::DecodeError),
+    /// The server responded with an unsuccessful status code.
+    DidNotSucceed { status: StatusCode, body: String },
+}
+
+impl From<reqwest::UrlError> for Error {
+    fn from(e: reqwest::UrlError) -> Error {
+        Error::UrlParseError(e)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Error {
+        Error::ReqwestError(e)
+    }
+}
+
+impl From<ssz::DecodeError> for Error {
+    fn from(e: ssz::DecodeError) -> Error {
+        Error::SszDecodeError(e)
+    }
+}
+
+/// The encoding requested from the server for responses that support SSZ.
+///
+/// Endpoints which cannot be SSZ encoded always use JSON.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    JSON,
+    SSZ,
+}
+
+impl Encoding {
+    fn content_type(self) -> &'static str {
+        match self {
+            Encoding::JSON => "application/json",
+            Encoding::SSZ => "application/ssz",
+        }
+    }
+}
+
+/// The result of publishing an object (e.g., a block) to the beacon node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PublishStatus {
+    /// The object was valid and has been imported and published to the network.
+    Valid,
+    /// The object could not be imported, but has still been published to the network.
+    Invalid(String),
+}
+
+/// Connects to a remote Lighthouse (or compatible) node via HTTP.
+pub struct RemoteBeaconNode<E: EthSpec> {
+    pub http: HttpClient<E>,
+}
+
+impl<E: EthSpec> RemoteBeaconNode<E> {
+    /// Instantiates a client for the server at `http_endpoint` (e.g., `http://localhost:5052`).
+    pub fn new(http_endpoint: String) -> Result<Self, String> {
+        Ok(Self {
+            http: HttpClient::new(http_endpoint)
+                .map_err(|e| format!("Unable to create http client: {:?}", e))?,
+        })
+    }
+}
+
+/// A HTTP client for the beacon node REST API.
+///
+/// Endpoints are grouped in the same manner as the API routes, e.g., `http.beacon().get_head()`
+/// requests `/beacon/head`.
+#[derive(Clone)]
+pub struct HttpClient<E> {
+    client: Client,
+    url: Url,
+    encoding: Encoding,
+    _phantom: PhantomData<E>,
+}
+
+impl<E: EthSpec> HttpClient<E> {
+    /// Creates a client which requests JSON responses.
+    pub fn new(url: String) -> Result<Self, Error> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+            .build()?;
+
+        Ok(Self {
+            client,
+            url: Url::parse(&url)?,
+            encoding: Encoding::JSON,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Returns a client which authenticates every request with the bearer `token`.
+    pub fn with_bearer_token(mut self, token: &str) -> Result<Self, Error> {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            header::HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| Error::InvalidToken)?,
+        );
+
+        self.client = Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+            .default_headers(headers)
+            .build()?;
+
+        Ok(self)
+    }
+
+    /// Returns a client which requests `encoding` for all responses that support it.
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// The encoding requested for responses that support SSZ.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn beacon(&self) -> Beacon<E> {
+        Beacon(self.clone())
+    }
+
+    pub fn validator(&self) -> Validator<E> {
+        Validator(self.clone())
+    }
+
+    pub fn node(&self) -> Node<E> {
+        Node(self.clone())
+    }
+
+    pub fn network(&self) -> Network<E> {
+        Network(self.clone())
+    }
+
+    pub fn spec(&self) -> Spec<E> {
+        Spec(self.clone())
+    }
+
+    pub fn debug(&self) -> Debug<E> {
+        Debug(self.clone())
+    }
+
+    /// Returns the URL of the endpoint at `path`, relative to the server URL.
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .map_err(|_| Error::InvalidUrl)?
+            .pop_if_empty()
+            .extend(path.split('/'));
+        Ok(url)
+    }
+
+    /// Requests `url`, decoding the response as JSON.
+    fn json_get<T: DeserializeOwned>(
+        &self,
+        url: Url,
+        query: &[(&str, String)],
+    ) -> Result<T, Error> {
+        let mut response = self.get_response(url, query, Encoding::JSON)?;
+        response.json().map_err(Into::into)
+    }
+
+    /// Requests `url`, decoding the response as SSZ or JSON, depending on the `encoding` of the
+    /// client.
+    fn get<T: DeserializeOwned + Decode>(
+        &self,
+        url: Url,
+        query: &[(&str, String)],
+    ) -> Result<T, Error> {
+        let mut response = self.get_response(url, query, self.encoding)?;
+        match self.encoding {
+            Encoding::JSON => response.json().map_err(Into::into),
+            Encoding::SSZ => {
+                let mut bytes = vec![];
+                response.copy_to(&mut bytes)?;
+                T::from_ssz_bytes(&bytes).map_err(Into::into)
+            }
+        }
+    }
+
+    fn get_response(
+        &self,
+        url: Url,
+        query: &[(&str, String)],
+        encoding: Encoding,
+    ) -> Result<Response, Error> {
+        let response = self
+            .client
+            .get(url)
+            .query(query)
+            .header(header::CONTENT_TYPE, encoding.content_type())
+            .send()?;
+        error_for_status(response)
+    }
+
+    /// Sends `body` as JSON to `url`, returning the response.
+    fn json_post<T: Serialize>(&self, url: Url, body: &T) -> Result<Response, Error> {
+        self.client.post(url).json(body).send().map_err(Into::into)
+    }
+
+    /// Sends a `DELETE` request to `url` with the given query parameters.
+    fn delete(&self, url: Url, query: &[(&str, String)]) -> Result<(), Error> {
+        let response = self.client.delete(url).query(query).send()?;
+        error_for_status(response).map(|_| ())
+    }
+}
+
+/// Returns `Err` if the response does not have a success status code. The body of the response,
+/// which describes the error, is included in the error.
+fn error_for_status(mut response: Response) -> Result<Response, Error> {
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else {
+        Err(Error::DidNotSucceed {
+            status,
+            body: response.text().unwrap_or_default(),
+        })
+    }
+}
+
+/// Interprets the response to a request which publishes an object to the network.
+fn publish_status(response: Response) -> Result<PublishStatus, Error> {
+    let mut response = error_for_status(response)?;
+    match response.status() {
+        StatusCode::OK => Ok(PublishStatus::Valid),
+        StatusCode::ACCEPTED => Ok(PublishStatus::Invalid(response.text()?)),
+        status => Err(Error::DidNotSucceed {
+            status,
+            body: response.text()?,
+        }),
+    }
+}
+
+/// Returns the query parameters for an optional `epoch`.
+fn epoch_query(epoch: Option<Epoch>) -> Vec<(&'static str, String)> {
+    epoch
+        .into_iter()
+        .map(|epoch| ("epoch", epoch.as_u64().to_string()))
+        .collect()
+}
+
+/// Provides the functions on the `/beacon` endpoint of the node.
+#[derive(Clone)]
+pub struct Beacon<E>(HttpClient<E>);
+
+impl<E: EthSpec> Beacon<E> {
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        self.0.url(&format!("beacon/{}", path))
+    }
+
+    /// Returns a summary of the canonical head.
+    pub fn get_head(&self) -> Result<HeadResponse, Error> {
+        self.0.get(self.url("head")?, &[])
+    }
+
+    /// Returns the block identified by `block_id` and its root.
+    pub fn get_block(&self, block_id: BlockId) -> Result<BlockResponse<E>, Error> {
+        self.0
+            .get(self.url("block")?, &[("block_id", block_id.to_string())])
+    }
+
+    /// Returns the block and block root at the given slot.
+    pub fn get_block_by_slot(&self, slot: Slot) -> Result<BlockResponse<E>, Error> {
+        self.get_block(BlockId::Slot(slot))
+    }
+
+    /// Returns the block with the given root.
+    pub fn get_block_by_root(&self, root: Hash256) -> Result<BlockResponse<E>, Error> {
+        self.get_block(BlockId::Root(root))
+    }
+
+    /// Returns the root of the canonical block at the given slot.
+    pub fn get_block_root(&self, slot: Slot) -> Result<Hash256, Error> {
+        self.0.get(
+            self.url("block_root")?,
+            &[("slot", slot.as_u64().to_string())],
+        )
+    }
+
+    /// Returns the fork of the canonical head.
+    pub fn get_fork(&self) -> Result<Fork, Error> {
+        self.0.get(self.url("fork")?, &[])
+    }
+
+    /// Returns the validators that are active at `epoch`, or the current epoch if `epoch` is
+    /// `None`.
+    pub fn get_validators(&self, epoch: Option<Epoch>) -> Result<Vec<types::Validator>, Error> {
+        self.0.get(self.url("validators")?, &epoch_query(epoch))
+    }
+
+    /// Returns the balance and status of every validator in the head state.
+    pub fn get_validator_balances(&self) -> Result<Vec<ValidatorBalance>, Error> {
+        self.0.json_get(self.url("validators/balances")?, &[])
+    }
+
+    /// Returns the rewards and penalties applied to every validator at the start of `epoch`, or
+    /// of the current epoch if `epoch` is `None`.
+    pub fn get_validator_rewards(
+        &self,
+        epoch: Option<Epoch>,
+    ) -> Result<Vec<ValidatorRewards>, Error> {
+        self.0
+            .get(self.url("validators/rewards")?, &epoch_query(epoch))
+    }
+
+    /// Returns the crosslink committees of `epoch`, or of the current epoch if `epoch` is `None`.
+    pub fn get_committees(&self, epoch: Option<Epoch>) -> Result<Vec<Committee>, Error> {
+        self.0.get(self.url("committees")?, &epoch_query(epoch))
+    }
+
+    /// Returns the block proposers of `epoch`, or of the current epoch if `epoch` is `None`.
+    pub fn get_proposers(&self, epoch: Option<Epoch>) -> Result<Vec<Proposer>, Error> {
+        self.0.get(self.url("proposers")?, &epoch_query(epoch))
+    }
+
+    /// Returns the state identified by `state_id` and its root.
+    pub fn get_state(&self, state_id: StateId) -> Result<StateResponse<E>, Error> {
+        self.0
+            .get(self.url("state")?, &[("state_id", state_id.to_string())])
+    }
+
+    /// Returns the state and state root at the given slot.
+    pub fn get_state_by_slot(&self, slot: Slot) -> Result<StateResponse<E>, Error> {
+        self.get_state(StateId::Slot(slot))
+    }
+
+    /// Returns the state with the given root.
+    pub fn get_state_by_root(&self, root: Hash256) -> Result<StateResponse<E>, Error> {
+        self.get_state(StateId::Root(root))
+    }
+
+    /// Returns the root of the canonical state at the given slot.
+    pub fn get_state_root(&self, slot: Slot) -> Result<Hash256, Error> {
+        self.0.get(
+            self.url("state_root")?,
+            &[("slot", slot.as_u64().to_string())],
+        )
+    }
+
+    /// Returns the finalized checkpoint of the canonical head.
+    pub fn get_current_finalized_checkpoint(&self) -> Result<Checkpoint, Error> {
+        self.0
+            .get(self.url("state/current_finalized_checkpoint")?, &[])
+    }
+
+    /// Returns the genesis state.
+    pub fn get_genesis_state(&self) -> Result<BeaconState<E>, Error> {
+        self.0.get(self.url("state/genesis")?, &[])
+    }
+
+    /// Returns the `field` of the state identified by `state_id`, with a Merkle proof against its
+    /// state root.
+    pub fn get_state_field_proof(
+        &self,
+        state_id: StateId,
+        field: &str,
+    ) -> Result<StateFieldProof, Error> {
+        self.0.json_get(
+            self.url("state/proof")?,
+            &[
+                ("state_id", state_id.to_string()),
+                ("field", field.to_string()),
+            ],
+        )
+    }
+
+    /// Returns the validator with `validator_index` in the state identified by `state_id`, with a
+    /// Merkle proof against its state root.
+    pub fn get_validator_proof(
+        &self,
+        state_id: StateId,
+        validator_index: usize,
+    ) -> Result<ValidatorProof, Error> {
+        self.0.get(
+            self.url("validators/proof")?,
+            &[
+                ("state_id", state_id.to_string()),
+                ("validator_index", validator_index.to_string()),
+            ],
+        )
+    }
+
+    /// Returns the header of the block at `slot`, with a Merkle proof against the root of the
+    /// state identified by `state_id`.
+    pub fn get_block_header_proof(
+        &self,
+        state_id: StateId,
+        slot: Slot,
+    ) -> Result<BlockHeaderProof, Error> {
+        self.0.get(
+            self.url("block_header/proof")?,
+            &[
+                ("state_id", state_id.to_string()),
+                ("slot", slot.as_u64().to_string()),
+            ],
+        )
+    }
+
+    /// Returns the voluntary exits in the operation pool.
+    pub fn get_voluntary_exits(&self) -> Result<Vec<VoluntaryExit>, Error> {
+        self.0.get(self.url("pool/voluntary_exits")?, &[])
+    }
+
+    /// Submits a voluntary exit to the operation pool, to be published to the network.
+    pub fn submit_voluntary_exit(&self, exit: &VoluntaryExit) -> Result<(), Error> {
+        let response = self.0.json_post(self.url("pool/voluntary_exits")?, exit)?;
+        error_for_status(response).map(|_| ())
+    }
+
+    /// Returns the proposer slashings in the operation pool.
+    pub fn get_proposer_slashings(&self) -> Result<Vec<ProposerSlashing>, Error> {
+        self.0.get(self.url("pool/proposer_slashings")?, &[])
+    }
+
+    /// Submits a proposer slashing to the operation pool, to be published to the network.
+    pub fn submit_proposer_slashing(&self, slashing: &ProposerSlashing) -> Result<(), Error> {
+        let response = self
+            .0
+            .json_post(self.url("pool/proposer_slashings")?, slashing)?;
+        error_for_status(response).map(|_| ())
+    }
+
+    /// Returns the attester slashings in the operation pool.
+    pub fn get_attester_slashings(&self) -> Result<Vec<AttesterSlashing<E>>, Error> {
+        self.0.get(self.url("pool/attester_slashings")?, &[])
+    }
+
+    /// Submits an attester slashing to the operation pool, to be published to the network.
+    pub fn submit_attester_slashing(&self, slashing: &AttesterSlashing<E>) -> Result<(), Error> {
+        let response = self
+            .0
+            .json_post(self.url("pool/attester_slashings")?, slashing)?;
+        error_for_status(response).map(|_| ())
+    }
+}
+
+/// Provides the functions on the `/beacon/validator` endpoint of the node.
+#[derive(Clone)]
+pub struct Validator<E>(HttpClient<E>);
+
+impl<E: EthSpec> Validator<E> {
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        self.0.url(&format!("beacon/validator/{}", path))
+    }
+
+    /// Returns the duties of the given validators at `epoch`.
+    pub fn get_duties(
+        &self,
+        epoch: Epoch,
+        validator_pubkeys: &[PublicKey],
+    ) -> Result<Vec<ValidatorDuty>, Error> {
+        let mut query = vec![("epoch", epoch.as_u64().to_string())];
+        query.extend(
+            validator_pubkeys
+                .iter()
+                .map(|pubkey| ("validator_pubkeys", pubkey.as_hex_string())),
+        );
+        self.0.json_get(self.url("duties")?, &query)
+    }
+
+    /// Produces an unsigned block for `slot` with the given `randao_reveal`.
+    pub fn produce_block(
+        &self,
+        slot: Slot,
+        randao_reveal: &Signature,
+    ) -> Result<BeaconBlock<E>, Error> {
+        self.0.get(
+            self.url("block")?,
+            &[
+                ("slot", slot.as_u64().to_string()),
+                ("randao_reveal", hex::encode(randao_reveal.as_ssz_bytes())),
+            ],
+        )
+    }
+
+    /// Publishes a signed block to the beacon node, which imports it and publishes it to the
+    /// network.
+    pub fn publish_block(&self, block: &BeaconBlock<E>) -> Result<PublishStatus, Error> {
+        publish_status(self.0.json_post(self.url("block")?, block)?)
+    }
+
+    /// Produces an unsigned attestation for the given validator, who must be due to attest at
+    /// `slot` in `shard`.
+    pub fn produce_attestation(
+        &self,
+        validator_pubkey: &PublicKey,
+        poc_bit: bool,
+        slot: Slot,
+        shard: u64,
+    ) -> Result<Attestation<E>, Error> {
+        self.0.get(
+            self.url("attestation")?,
+            &[
+                ("validator_pubkey", validator_pubkey.as_hex_string()),
+                ("poc_bit", poc_bit.to_string()),
+                ("slot", slot.as_u64().to_string()),
+                ("shard", shard.to_string()),
+            ],
+        )
+    }
+
+    /// Publishes a signed attestation to the beacon node, which imports it and publishes it to
+    /// the network.
+    ///
+    /// If the `selection_proof` selects the validator to aggregate, the beacon node also publishes
+    /// the aggregate of its committee later in the slot.
+    pub fn publish_attestation(
+        &self,
+        attestation: &Attestation<E>,
+        selection_proof: Option<&Signature>,
+    ) -> Result<PublishStatus, Error> {
+        let mut url = self.url("attestation")?;
+        if let Some(selection_proof) = selection_proof {
+            url.query_pairs_mut().append_pair(
+                "selection_proof",
+                &format!("0x{}", hex::encode(selection_proof.as_ssz_bytes())),
+            );
+        }
+
+        publish_status(self.0.json_post(url, attestation)?)
+    }
+}
+
+/// Provides the functions on the `/node` endpoint of the node.
+#[derive(Clone)]
+pub struct Node<E>(HttpClient<E>);
+
+impl<E: EthSpec> Node<E> {
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        self.0.url(&format!("node/{}", path))
+    }
+
+    /// Returns the version string of the node.
+    pub fn get_version(&self) -> Result<String, Error> {
+        self.0.json_get(self.url("version")?, &[])
+    }
+
+    /// Returns the genesis time of the chain, in seconds since the UNIX epoch.
+    pub fn get_genesis_time(&self) -> Result<u64, Error> {
+        self.0.get(self.url("genesis_time")?, &[])
+    }
+}
+
+/// Provides the functions on the `/network` endpoint of the node.
+#[derive(Clone)]
+pub struct Network<E>(HttpClient<E>);
+
+impl<E: EthSpec> Network<E> {
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        self.0.url(&format!("network/{}", path))
+    }
+
+    /// Returns the ENR of the node.
+    pub fn get_enr(&self) -> Result<Enr, Error> {
+        self.0.json_get(self.url("enr")?, &[])
+    }
+
+    /// Returns the base58 encoded `PeerId` of the node.
+    pub fn get_peer_id(&self) -> Result<String, Error> {
+        self.0.json_get(self.url("peer_id")?, &[])
+    }
+
+    /// Returns the number of connected peers.
+    pub fn get_peer_count(&self) -> Result<usize, Error> {
+        self.0.get(self.url("peer_count")?, &[])
+    }
+
+    /// Returns the `PeerId`s of the connected peers.
+    pub fn get_peer_list(&self) -> Result<Vec<String>, Error> {
+        self.0.json_get(self.url("peers")?, &[])
+    }
+
+    /// Returns the TCP port the node listens on for libp2p connections.
+    pub fn get_listen_port(&self) -> Result<u16, Error> {
+        self.0.get(self.url("listen_port")?, &[])
+    }
+
+    /// Returns the libp2p addresses the node listens on.
+    pub fn get_listen_addresses(&self) -> Result<Vec<Multiaddr>, Error> {
+        self.0.json_get(self.url("listen_addresses")?, &[])
+    }
+
+    /// Returns the trusted peers of the node.
+    pub fn get_trusted_peers(&self) -> Result<Vec<TrustedPeer>, Error> {
+        self.0.json_get(self.url("trusted_peers")?, &[])
+    }
+
+    /// Adds a trusted peer, given as a peer id or a multiaddr with a `/p2p/` component. Returns
+    /// the peer id of the peer.
+    pub fn add_trusted_peer(&self, peer: &str) -> Result<String, Error> {
+        let response = self.0.json_post(self.url("trusted_peers")?, &peer)?;
+        error_for_status(response)?.json().map_err(Into::into)
+    }
+
+    /// Removes a trusted peer.
+    pub fn remove_trusted_peer(&self, peer_id: &str) -> Result<(), Error> {
+        self.0.delete(
+            self.url("trusted_peers")?,
+            &[("peer_id", peer_id.to_string())],
+        )
+    }
+
+    /// Returns the peer ids that the node refuses to connect to.
+    pub fn get_denied_peers(&self) -> Result<Vec<String>, Error> {
+        self.0.json_get(self.url("denied_peers")?, &[])
+    }
+
+    /// Denies a peer, disconnecting it if it is connected.
+    pub fn deny_peer(&self, peer_id: &str) -> Result<(), Error> {
+        let response = self.0.json_post(self.url("denied_peers")?, &peer_id)?;
+        error_for_status(response).map(|_| ())
+    }
+
+    /// Removes a peer from the deny list.
+    pub fn allow_peer(&self, peer_id: &str) -> Result<(), Error> {
+        self.0.delete(
+            self.url("denied_peers")?,
+            &[("peer_id", peer_id.to_string())],
+        )
+    }
+
+    /// Returns the IP ranges, in CIDR notation, that the node refuses connections from.
+    pub fn get_denied_ip_ranges(&self) -> Result<Vec<String>, Error> {
+        self.0.json_get(self.url("denied_ip_ranges")?, &[])
+    }
+
+    /// Refuses future connections from an IP range, given in CIDR notation.
+    pub fn deny_ip_range(&self, ip_range: &str) -> Result<(), Error> {
+        let response = self.0.json_post(self.url("denied_ip_ranges")?, &ip_range)?;
+        error_for_status(response).map(|_| ())
+    }
+
+    /// Removes an IP range from the deny list.
+    pub fn allow_ip_range(&self, ip_range: &str) -> Result<(), Error> {
+        self.0.delete(
+            self.url("denied_ip_ranges")?,
+            &[("ip_range", ip_range.to_string())],
+        )
+    }
+}
+
+/// Provides the functions on the `/spec` endpoint of the node.
+#[derive(Clone)]
+pub struct Spec<E>(HttpClient<E>);
+
+impl<E: EthSpec> Spec<E> {
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        self.0.url(&format!("spec/{}", path))
+    }
+
+    /// Returns the `ChainSpec` of the node.
+    pub fn get_spec(&self) -> Result<ChainSpec, Error> {
+        self.0.json_get(self.0.url("spec")?, &[])
+    }
+
+    /// Returns the number of slots per epoch of the node.
+    pub fn get_slots_per_epoch(&self) -> Result<u64, Error> {
+        self.0.get(self.url("slots_per_epoch")?, &[])
+    }
+
+    /// Returns the `Eth2Config` of the node.
+    pub fn get_eth2_config(&self) -> Result<Eth2Config, Error> {
+        self.0.json_get(self.url("eth2_config")?, &[])
+    }
+}
+
+/// Provides the functions on the `/debug` endpoint of the node.
+#[derive(Clone)]
+pub struct Debug<E>(HttpClient<E>);
+
+impl<E: EthSpec> Debug<E> {
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        self.0.url(&format!("debug/{}", path))
+    }
+
+    /// Returns the nodes, weights and latest messages of the fork choice.
+    pub fn get_fork_choice(&self) -> Result<ForkChoiceResponse, Error> {
+        self.0.json_get(self.url("fork_choice")?, &[])
+    }
+
+    /// Returns a summary of each block in the canonical chain, from genesis to the head.
+    pub fn get_chain_dump(&self) -> Result<Vec<ChainDumpEntry>, Error> {
+        self.0.json_get(self.url("chain_dump")?, &[])
+    }
+
+    /// Returns the sizes of the caches of the head state.
+    pub fn get_state_cache(&self) -> Result<StateCacheResponse, Error> {
+        self.0.json_get(self.url("state_cache")?, &[])
+    }
+}
@@ -0,0 +1,870 @@
+//! Round-trip tests of the `RemoteBeaconNode` against the REST API of a beacon node running in
+//! this process.
+
+use beacon_chain::test_utils::generate_deterministic_keypairs;
+use beacon_chain::BeaconChain;
+use client::{BeaconChainStartMethod, Client, ClientConfig, Eth2Config, RuntimeBeaconChainTypes};
+use remote_beacon_node::{
+    BlockId, Encoding, Error, HttpClient, PublishStatus, RemoteBeaconNode, RewardDelta, StateId,
+    ValidatorStatus,
+};
+use reqwest::StatusCode;
+use rest_api::ApiConfig;
+use slot_clock::TestingSlotClock;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use store::MemoryStore;
+use tokio::runtime::Runtime;
+use tree_hash::{SignedRoot, TreeHash};
+use types::{
+    test_utils::TestingProposerSlashingBuilder, BeaconBlock, Domain, Epoch, EthSpec, Keypair,
+    MinimalEthSpec, ProposerSlashing, Signature, Slot, VoluntaryExit,
+};
+use unused_port::{unused_tcp_port, unused_udp_port};
+
+type E = MinimalEthSpec;
+type TestChain = BeaconChain<RuntimeBeaconChainTypes<MemoryStore, E, TestingSlotClock>>;
+
+const VALIDATOR_COUNT: usize = 16;
+
+/// A beacon node with its REST API enabled.
+struct TestNode {
+    client: Client<MemoryStore, E, TestingSlotClock>,
+    remote_node: RemoteBeaconNode<E>,
+    keypairs: Vec<Keypair>,
+    /// The port on which the REST API listens.
+    api_port: u16,
+    /// The port on which the libp2p service listens.
+    libp2p_port: u16,
+    // Declared last so that the client is dropped before the runtime.
+    _runtime: Runtime,
+}
+
+impl TestNode {
+    /// Starts a node whose REST API and libp2p service listen on unused ports.
+    fn new() -> Self {
+        Self::with_api_config(|_| ())
+    }
+
+    /// As `new`, but applies `modify` to the REST API config before starting the node.
+    fn with_api_config<F: FnOnce(&mut ApiConfig)>(modify: F) -> Self {
+        let api_port = unused_tcp_port().expect("should find unused api port");
+        let libp2p_port = unused_tcp_port().expect("should find unused libp2p port");
+        let discovery_port = unused_udp_port().expect("should find unused discovery port");
+
+        let runtime = Runtime::new().expect("should start runtime");
+        let genesis_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("should read system time")
+            .as_secs();
+
+        let mut config = ClientConfig::default();
+        config.data_dir = std::env::temp_dir().join(format!(
+            "lighthouse_remote_beacon_node_{}_{}",
+            std::process::id(),
+            api_port
+        ));
+        config.beacon_chain_start_method = BeaconChainStartMethod::Generated {
+            validator_count: VALIDATOR_COUNT,
+            genesis_time,
+        };
+        config.rpc.enabled = false;
+        config.websocket_server.enabled = false;
+        config.rest_api.enabled = true;
+        config.rest_api.port = api_port;
+        config.network.network_dir = config.data_dir.join("network");
+        config.network.listen_address = "127.0.0.1".parse().expect("valid ip address");
+        config.network.discovery_address = config.network.listen_address;
+        config.network.libp2p_port = libp2p_port;
+        config.network.discovery_port = discovery_port;
+        modify(&mut config.rest_api);
+
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let client = Client::new(
+            config,
+            Eth2Config::minimal(),
+            MemoryStore::open(),
+            log,
+            &runtime.executor(),
+        )
+        .expect("should start client");
+
+        let remote_node = RemoteBeaconNode::new(format!("http://127.0.0.1:{}", api_port))
+            .expect("should create remote node");
+
+        Self {
+            client,
+            remote_node,
+            keypairs: generate_deterministic_keypairs(VALIDATOR_COUNT),
+            api_port,
+            libp2p_port,
+            _runtime: runtime,
+        }
+    }
+
+    /// Returns the URL of `path` on the REST API, using `scheme` and `host`.
+    fn url(&self, scheme: &str, host: &str, path: &str) -> String {
+        format!("{}://{}:{}{}", scheme, host, self.api_port, path)
+    }
+
+    fn chain(&self) -> Arc<TestChain> {
+        self.client.beacon_chain()
+    }
+
+    fn http(&self) -> &HttpClient<E> {
+        &self.remote_node.http
+    }
+
+    fn ssz_http(&self) -> HttpClient<E> {
+        self.remote_node.http.clone().with_encoding(Encoding::SSZ)
+    }
+
+    /// Returns the keypair of the proposer at `slot`.
+    fn proposer_keypair(&self, slot: Slot) -> &Keypair {
+        let proposer_index = self
+            .chain()
+            .block_proposer(slot)
+            .expect("should get proposer");
+        &self.keypairs[proposer_index]
+    }
+
+    /// Produces an unsigned block at `slot` via the REST API.
+    fn produce_block(&self, slot: Slot) -> BeaconBlock<E> {
+        let chain = self.chain();
+        let fork = chain.head().beacon_state.fork.clone();
+        let epoch = slot.epoch(E::slots_per_epoch());
+        let randao_reveal = Signature::new(
+            &epoch.tree_hash_root(),
+            chain.spec.get_domain(epoch, Domain::Randao, &fork),
+            &self.proposer_keypair(slot).sk,
+        );
+
+        self.ssz_http()
+            .validator()
+            .produce_block(slot, &randao_reveal)
+            .expect("should produce block")
+    }
+
+    /// Signs `block` with the key of its proposer.
+    fn sign_block(&self, block: &mut BeaconBlock<E>) {
+        let chain = self.chain();
+        let fork = chain.head().beacon_state.fork.clone();
+        let epoch = block.slot.epoch(E::slots_per_epoch());
+        block.signature = Signature::new(
+            &block.signed_root(),
+            chain.spec.get_domain(epoch, Domain::BeaconProposer, &fork),
+            &self.proposer_keypair(block.slot).sk,
+        );
+    }
+
+    /// Produces, signs and publishes a block at every slot after the head, up to and including
+    /// `slot`.
+    fn extend_chain_to(&self, slot: Slot) {
+        let head_slot = self.chain().head().beacon_block.slot;
+        for slot in (head_slot.as_u64() + 1)..=slot.as_u64() {
+            let slot = Slot::new(slot);
+            self.chain().slot_clock.set_slot(slot.as_u64());
+
+            let mut block = self.produce_block(slot);
+            self.sign_block(&mut block);
+            assert_eq!(
+                self.http()
+                    .validator()
+                    .publish_block(&block)
+                    .expect("should publish block"),
+                PublishStatus::Valid
+            );
+        }
+    }
+}
+
+#[test]
+fn beacon_endpoints() {
+    let node = TestNode::new();
+    let chain = node.chain();
+    let head = chain.head();
+
+    for http in &[node.http().clone(), node.ssz_http()] {
+        let beacon = http.beacon();
+
+        let head_response = beacon.get_head().expect("should get head");
+        assert_eq!(head_response.slot, head.beacon_state.slot);
+        assert_eq!(head_response.block_root, head.beacon_block_root);
+        assert_eq!(head_response.state_root, head.beacon_state_root);
+
+        let block = beacon
+            .get_block_by_slot(Slot::new(0))
+            .expect("should get genesis block");
+        assert_eq!(block.root, head.beacon_block_root);
+        assert_eq!(block.beacon_block, head.beacon_block);
+        assert_eq!(
+            beacon
+                .get_block_by_root(head.beacon_block_root)
+                .expect("should get block by root"),
+            block
+        );
+        assert_eq!(
+            beacon
+                .get_block_root(Slot::new(0))
+                .expect("should get block root"),
+            head.beacon_block_root
+        );
+
+        let state = beacon
+            .get_state_by_slot(Slot::new(0))
+            .expect("should get genesis state");
+        assert_eq!(state.root, head.beacon_state_root);
+        assert_eq!(
+            state.beacon_state.tree_hash_root(),
+            head.beacon_state.tree_hash_root()
+        );
+        assert_eq!(
+            beacon
+                .get_state_root(Slot::new(0))
+                .expect("should get state root"),
+            head.beacon_state_root
+        );
+        assert_eq!(
+            beacon
+                .get_genesis_state()
+                .expect("should get genesis state")
+                .tree_hash_root(),
+            head.beacon_state.tree_hash_root()
+        );
+
+        // At genesis, the head, genesis, finalized and justified blocks are all the same.
+        for block_id in &[
+            BlockId::Head,
+            BlockId::Genesis,
+            BlockId::Finalized,
+            BlockId::Justified,
+            BlockId::Root(head.beacon_block_root),
+        ] {
+            assert_eq!(
+                beacon.get_block(*block_id).expect("should get block by id"),
+                block
+            );
+        }
+        for state_id in &[
+            StateId::Head,
+            StateId::Genesis,
+            StateId::Finalized,
+            StateId::Justified,
+            StateId::Slot(Slot::new(0)),
+        ] {
+            assert_eq!(
+                beacon
+                    .get_state(*state_id)
+                    .expect("should get state by id")
+                    .root,
+                head.beacon_state_root
+            );
+        }
+
+        assert_eq!(
+            beacon.get_fork().expect("should get fork"),
+            head.beacon_state.fork
+        );
+        assert_eq!(
+            beacon
+                .get_current_finalized_checkpoint()
+                .expect("should get finalized checkpoint"),
+            head.beacon_state.finalized_checkpoint
+        );
+        assert_eq!(
+            beacon
+                .get_validators(Some(Epoch::new(0)))
+                .expect("should get validators")
+                .len(),
+            VALIDATOR_COUNT
+        );
+    }
+}
+
+#[test]
+fn committees_and_proposers() {
+    let node = TestNode::new();
+    let chain = node.chain();
+
+    for http in &[node.http().clone(), node.ssz_http()] {
+        let beacon = http.beacon();
+
+        // Each active validator is in exactly one committee per epoch.
+        for epoch in &[None, Some(Epoch::new(1))] {
+            let mut indices: Vec<usize> = beacon
+                .get_committees(*epoch)
+                .expect("should get committees")
+                .into_iter()
+                .flat_map(|committee| committee.committee)
+                .collect();
+            indices.sort();
+            assert_eq!(indices, (0..VALIDATOR_COUNT).collect::<Vec<_>>());
+        }
+
+        let proposers = beacon.get_proposers(None).expect("should get proposers");
+        assert_eq!(proposers.len(), E::slots_per_epoch() as usize);
+        for proposer in proposers {
+            assert_eq!(
+                proposer.validator_index,
+                chain
+                    .block_proposer(proposer.slot)
+                    .expect("should get proposer")
+            );
+        }
+        assert_eq!(
+            beacon
+                .get_proposers(Some(Epoch::new(1)))
+                .expect("should get proposers of the next epoch")
+                .len(),
+            E::slots_per_epoch() as usize
+        );
+
+        // Only the previous, current and next epochs have committees.
+        assert!(beacon.get_committees(Some(Epoch::new(2))).is_err());
+        assert!(beacon.get_proposers(Some(Epoch::new(2))).is_err());
+    }
+}
+
+#[test]
+fn validator_balances_and_rewards() {
+    let node = TestNode::new();
+    let head_state = node.chain().head().beacon_state;
+    let beacon = node.http().beacon();
+
+    let balances = beacon
+        .get_validator_balances()
+        .expect("should get validator balances");
+    assert_eq!(balances.len(), VALIDATOR_COUNT);
+    for (i, balance) in balances.iter().enumerate() {
+        assert_eq!(balance.validator_index, i);
+        assert_eq!(balance.pubkey, node.keypairs[i].pk);
+        assert_eq!(balance.balance, head_state.balances[i]);
+        assert_eq!(balance.status, ValidatorStatus::Active);
+    }
+
+    // No epoch transition has occurred at genesis.
+    assert!(beacon.get_validator_rewards(None).is_err());
+    assert!(beacon.get_validator_rewards(Some(Epoch::new(1))).is_err());
+
+    // The transition into epoch 1 applies no rewards, the transition into epoch 2 rewards the
+    // duties of epoch 0.
+    let epoch = Epoch::new(2);
+    let first_slot = epoch.start_slot(E::slots_per_epoch());
+    node.extend_chain_to(first_slot);
+
+    let rewards = beacon
+        .get_validator_rewards(None)
+        .expect("should get rewards of the current epoch");
+    assert_eq!(
+        beacon
+            .get_validator_rewards(Some(epoch))
+            .expect("should get rewards of epoch"),
+        rewards
+    );
+
+    let previous_balances = beacon
+        .get_state_by_slot(first_slot - 1)
+        .expect("should get state prior to the epoch transition")
+        .beacon_state
+        .balances;
+    let balances = beacon
+        .get_state_by_slot(first_slot)
+        .expect("should get state after the epoch transition")
+        .beacon_state
+        .balances;
+
+    assert_eq!(rewards.len(), VALIDATOR_COUNT);
+    for (i, reward) in rewards.iter().enumerate() {
+        assert_eq!(reward.validator_index, i);
+
+        let deltas: [&RewardDelta; 7] = [
+            &reward.source,
+            &reward.inclusion_delay,
+            &reward.target,
+            &reward.head,
+            &reward.inactivity,
+            &reward.crosslink,
+            &reward.proposer,
+        ];
+        let total_rewards: u64 = deltas.iter().map(|delta| delta.rewards).sum();
+        let total_penalties: u64 = deltas.iter().map(|delta| delta.penalties).sum();
+
+        // No validator attested, so every validator misses all of its duties.
+        assert_eq!(total_rewards, 0);
+        assert!(reward.source.penalties > 0);
+        assert_eq!(
+            balances[i],
+            (previous_balances[i] + total_rewards).saturating_sub(total_penalties)
+        );
+    }
+}
+
+#[test]
+fn node_network_and_spec_endpoints() {
+    let node = TestNode::new();
+    let chain = node.chain();
+    let http = node.http();
+
+    assert!(!http
+        .node()
+        .get_version()
+        .expect("should get version")
+        .is_empty());
+    assert_eq!(
+        http.node()
+            .get_genesis_time()
+            .expect("should get genesis time"),
+        chain.head().beacon_state.genesis_time
+    );
+
+    assert_eq!(
+        http.network()
+            .get_listen_port()
+            .expect("should get listen port"),
+        node.libp2p_port
+    );
+    assert_eq!(
+        http.network()
+            .get_peer_count()
+            .expect("should get peer count"),
+        0
+    );
+    assert!(http
+        .network()
+        .get_peer_list()
+        .expect("should get peer list")
+        .is_empty());
+    assert!(!http
+        .network()
+        .get_peer_id()
+        .expect("should get peer id")
+        .is_empty());
+
+    assert_eq!(
+        http.spec()
+            .get_slots_per_epoch()
+            .expect("should get slots per epoch"),
+        E::slots_per_epoch()
+    );
+    assert_eq!(
+        node.ssz_http()
+            .spec()
+            .get_slots_per_epoch()
+            .expect("should get slots per epoch as ssz"),
+        E::slots_per_epoch()
+    );
+    assert_eq!(http.spec().get_spec().expect("should get spec"), chain.spec);
+    assert_eq!(
+        http.spec()
+            .get_eth2_config()
+            .expect("should get eth2 config")
+            .spec_constants,
+        Eth2Config::minimal().spec_constants
+    );
+}
+
+#[test]
+fn produce_and_publish_block() {
+    let node = TestNode::new();
+    let slot = Slot::new(1);
+    node.chain().slot_clock.set_slot(slot.as_u64());
+
+    let mut block = node.produce_block(slot);
+    assert_eq!(block.slot, slot);
+
+    // An unsigned block cannot be imported, but is still published.
+    match node
+        .http()
+        .validator()
+        .publish_block(&block)
+        .expect("should publish unsigned block")
+    {
+        PublishStatus::Invalid(_) => {}
+        status => panic!("unsigned block should be invalid, got {:?}", status),
+    }
+
+    node.sign_block(&mut block);
+    assert_eq!(
+        node.http()
+            .validator()
+            .publish_block(&block)
+            .expect("should publish block"),
+        PublishStatus::Valid
+    );
+
+    let head = node.http().beacon().get_head().expect("should get head");
+    assert_eq!(head.slot, slot);
+    assert_eq!(head.block_root, block.canonical_root());
+}
+
+#[test]
+fn merkle_proofs() {
+    let node = TestNode::new();
+    let genesis_root = node.chain().head().beacon_block_root;
+
+    let slot = Slot::new(1);
+    node.chain().slot_clock.set_slot(slot.as_u64());
+    let mut block = node.produce_block(slot);
+    node.sign_block(&mut block);
+    assert_eq!(
+        node.http()
+            .validator()
+            .publish_block(&block)
+            .expect("should publish block"),
+        PublishStatus::Valid
+    );
+
+    let head = node.chain().head();
+    let beacon = node.http().beacon();
+
+    let field_proof = beacon
+        .get_state_field_proof(StateId::Head, "finalized_checkpoint")
+        .expect("should get state field proof");
+    assert!(field_proof.proof.verify());
+    assert_eq!(field_proof.proof.state_root, head.beacon_state_root);
+    assert_eq!(
+        field_proof.proof.leaf.as_bytes(),
+        &head.beacon_state.finalized_checkpoint.tree_hash_root()[..]
+    );
+    assert!(beacon
+        .get_state_field_proof(StateId::Head, "not_a_field")
+        .is_err());
+
+    for http in &[node.http().clone(), node.ssz_http()] {
+        let beacon = http.beacon();
+
+        let validator_proof = beacon
+            .get_validator_proof(StateId::Head, 3)
+            .expect("should get validator proof");
+        assert!(validator_proof.proof.verify());
+        assert_eq!(validator_proof.proof.state_root, head.beacon_state_root);
+        assert_eq!(validator_proof.validator, head.beacon_state.validators[3]);
+        assert_eq!(
+            validator_proof.proof.leaf.as_bytes(),
+            &validator_proof.validator.tree_hash_root()[..]
+        );
+
+        let header_proof = beacon
+            .get_block_header_proof(StateId::Head, Slot::new(0))
+            .expect("should get block header proof");
+        assert!(header_proof.proof.verify());
+        assert_eq!(header_proof.proof.state_root, head.beacon_state_root);
+        assert_eq!(header_proof.proof.leaf, genesis_root);
+        assert_eq!(header_proof.header.canonical_root(), genesis_root);
+    }
+
+    // Only blocks prior to the slot of the state can be proven.
+    assert!(beacon.get_block_header_proof(StateId::Head, slot).is_err());
+}
+
+#[test]
+fn debug_endpoints() {
+    let node = TestNode::new();
+    let genesis_root = node.chain().head().beacon_block_root;
+
+    let slot = Slot::new(1);
+    node.chain().slot_clock.set_slot(slot.as_u64());
+    let mut block = node.produce_block(slot);
+    node.sign_block(&mut block);
+    assert_eq!(
+        node.http()
+            .validator()
+            .publish_block(&block)
+            .expect("should publish block"),
+        PublishStatus::Valid
+    );
+
+    let head = node.chain().head();
+    let debug = node.http().debug();
+
+    let fork_choice = debug.get_fork_choice().expect("should get fork choice");
+    assert_eq!(fork_choice.head_block_root, head.beacon_block_root);
+    assert_eq!(fork_choice.root, genesis_root);
+    assert_eq!(fork_choice.root_slot, Slot::new(0));
+    assert!(fork_choice
+        .nodes
+        .iter()
+        .any(|node| node.block_root == genesis_root));
+
+    let chain_dump = debug.get_chain_dump().expect("should get chain dump");
+    assert_eq!(chain_dump.len(), 2);
+    assert_eq!(chain_dump[0].block_root, genesis_root);
+    assert_eq!(chain_dump[0].slot, Slot::new(0));
+    assert_eq!(chain_dump[1].block_root, head.beacon_block_root);
+    assert_eq!(chain_dump[1].parent_root, genesis_root);
+    assert_eq!(chain_dump[1].state_root, head.beacon_state_root);
+    assert_eq!(
+        chain_dump[1].finalized_checkpoint,
+        head.beacon_state.finalized_checkpoint
+    );
+
+    let state_cache = debug.get_state_cache().expect("should get state cache");
+    assert_eq!(state_cache.slot, slot);
+    assert_eq!(state_cache.state_root, head.beacon_state_root);
+    assert_eq!(state_cache.validator_count, VALIDATOR_COUNT);
+    assert_eq!(state_cache.pubkey_cache_len, VALIDATOR_COUNT);
+    assert_eq!(
+        state_cache.current_committee_cache.initialized_epoch,
+        Some(Epoch::new(0))
+    );
+    assert_eq!(
+        state_cache.current_committee_cache.active_validator_count,
+        VALIDATOR_COUNT
+    );
+}
+
+#[test]
+fn event_stream() {
+    let node = TestNode::new();
+    let url = node.url("http", "127.0.0.1", "/events");
+
+    let response = reqwest::get(&format!("{}?topics=head,not_a_topic", url))
+        .expect("should request events with an unknown topic");
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let response =
+        reqwest::get(&format!("{}?topics=block", url)).expect("should subscribe to events");
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        response.headers()[reqwest::header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+
+    let slot = Slot::new(1);
+    node.chain().slot_clock.set_slot(slot.as_u64());
+    let mut block = node.produce_block(slot);
+    node.sign_block(&mut block);
+    assert_eq!(
+        node.http()
+            .validator()
+            .publish_block(&block)
+            .expect("should publish block"),
+        PublishStatus::Valid
+    );
+
+    // Only the block event is streamed, the head event is filtered out.
+    let mut lines = BufReader::new(response).lines();
+    let mut next_line = || {
+        lines
+            .next()
+            .expect("stream should not end")
+            .expect("should read line")
+    };
+    assert_eq!(next_line(), "event: block");
+    let data = next_line();
+    assert!(data.starts_with("data: "));
+    assert!(data.contains(&format!("{:?}", block.canonical_root())));
+}
+
+#[test]
+fn operation_pool() {
+    let node = TestNode::new();
+    let beacon = node.http().beacon();
+
+    assert!(beacon
+        .get_voluntary_exits()
+        .expect("should get voluntary exits")
+        .is_empty());
+    assert!(beacon
+        .get_attester_slashings()
+        .expect("should get attester slashings")
+        .is_empty());
+
+    let chain = node.chain();
+    let fork = chain.head().beacon_state.fork.clone();
+    let proposer_index = 3;
+    let slashing = TestingProposerSlashingBuilder::double_vote::<E, _>(
+        proposer_index,
+        |index, message, epoch, domain| {
+            Signature::new(
+                message,
+                chain.spec.get_domain(epoch, domain, &fork),
+                &node.keypairs[index as usize].sk,
+            )
+        },
+    );
+
+    // A slashing with invalid signatures is rejected and not added to the pool.
+    let unsigned_slashing = ProposerSlashing {
+        proposer_index: 4,
+        ..slashing.clone()
+    };
+    assert!(beacon.submit_proposer_slashing(&unsigned_slashing).is_err());
+
+    beacon
+        .submit_proposer_slashing(&slashing)
+        .expect("should submit proposer slashing");
+    assert_eq!(
+        beacon
+            .get_proposer_slashings()
+            .expect("should get proposer slashings"),
+        vec![slashing]
+    );
+}
+
+/// Returns the status code of a failed request.
+fn error_status<T>(result: Result<T, Error>) -> StatusCode {
+    match result {
+        Err(Error::DidNotSucceed { status, .. }) => status,
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("request should not succeed"),
+    }
+}
+
+/// Writes `tokens` to a file in the temp dir, returning its path.
+fn write_tokens(name: &str, tokens: &[&str]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "lighthouse_remote_beacon_node_{}_{}",
+        std::process::id(),
+        name
+    ));
+    fs::write(&path, tokens.join("\n")).expect("should write tokens");
+    path
+}
+
+#[test]
+fn bearer_token_scopes() {
+    let read_tokens = write_tokens("read_tokens", &["# dashboards", "reader"]);
+    let write_tokens = write_tokens("write_tokens", &["writer"]);
+    let node = TestNode::with_api_config(|config| {
+        config.read_tokens_file = Some(read_tokens);
+        config.write_tokens_file = Some(write_tokens);
+    });
+    let http = |token: &str| {
+        node.http()
+            .clone()
+            .with_bearer_token(token)
+            .expect("should create client with token")
+    };
+
+    assert_eq!(
+        error_status(node.http().beacon().get_head()),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        error_status(http("not_a_token").beacon().get_head()),
+        StatusCode::UNAUTHORIZED
+    );
+    assert!(http("reader").beacon().get_head().is_ok());
+    assert!(http("writer").beacon().get_head().is_ok());
+
+    let exit = VoluntaryExit {
+        epoch: Epoch::new(0),
+        validator_index: 0,
+        signature: Signature::empty_signature(),
+    };
+    assert_eq!(
+        error_status(http("reader").beacon().submit_voluntary_exit(&exit)),
+        StatusCode::FORBIDDEN
+    );
+    // The write token reaches the handler, which rejects the unsigned exit.
+    assert_eq!(
+        error_status(http("writer").beacon().submit_voluntary_exit(&exit)),
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[test]
+fn writes_disabled_and_cors() {
+    let node = TestNode::with_api_config(|config| {
+        config.allow_writes = false;
+        config.cors_allowed_origins = vec!["https://dashboard.example.com".to_string()];
+    });
+
+    assert!(node.http().beacon().get_head().is_ok());
+    assert_eq!(
+        error_status(node.http().network().deny_peer("not_a_peer")),
+        StatusCode::FORBIDDEN
+    );
+
+    let client = reqwest::Client::new();
+    let url = node.url("http", "127.0.0.1", "/beacon/head");
+
+    let response = client
+        .get(&url)
+        .header("origin", "https://dashboard.example.com")
+        .send()
+        .expect("should get head");
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "https://dashboard.example.com"
+    );
+
+    let response = client
+        .get(&url)
+        .header("origin", "https://other.example.com")
+        .send()
+        .expect("should get head");
+    assert!(response
+        .headers()
+        .get("access-control-allow-origin")
+        .is_none());
+
+    let preflight = client
+        .request(reqwest::Method::OPTIONS, &url)
+        .header("origin", "https://dashboard.example.com")
+        .header("access-control-request-method", "GET")
+        .send()
+        .expect("should send preflight");
+    assert_eq!(preflight.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        preflight.headers()["access-control-allow-methods"],
+        "GET, OPTIONS"
+    );
+}
+
+#[test]
+fn tls() {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/tls");
+    let node = TestNode::with_api_config(|config| {
+        config.tls_cert = Some(fixtures.join("cert.pem"));
+        config.tls_key = Some(fixtures.join("key.pem"));
+    });
+
+    let cert = reqwest::Certificate::from_pem(
+        &fs::read(fixtures.join("cert.pem")).expect("should read certificate"),
+    )
+    .expect("should parse certificate");
+    let client = reqwest::Client::builder()
+        .add_root_certificate(cert)
+        .build()
+        .expect("should build client");
+
+    let version: String = client
+        .get(&node.url("https", "localhost", "/node/version"))
+        .send()
+        .expect("should connect with TLS")
+        .json()
+        .expect("should decode version");
+    assert!(version.starts_with("Lighthouse"));
+
+    // Plain HTTP is not served.
+    assert!(
+        reqwest::get(&node.url("http", "localhost", "/node/version"))
+            .and_then(|response| response.error_for_status())
+            .is_err()
+    );
+}
+
+#[test]
+fn unknown_routes_and_bad_queries_are_errors() {
+    let node = TestNode::new();
+
+    assert!(node
+        .http()
+        .beacon()
+        .get_block_by_slot(Slot::new(1_000))
+        .is_err());
+    assert!(node
+        .http()
+        .network()
+        .remove_trusted_peer("not a peer id")
+        .is_err());
+
+    let response = reqwest::get(&node.url("http", "127.0.0.1", "/beacon/state?state_id=latest"))
+        .expect("should request state with an invalid id");
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,512 @@
+//! Types used in the requests to, and responses from, the beacon node REST API.
+//!
+//! These types are shared between the HTTP server (`rest_api`) and its clients (e.g.,
+//! `remote_beacon_node`) so that both sides agree upon the encoding of each endpoint.
+
+use serde::{Deserialize, Serialize};
+use ssz_derive::{Decode, Encode};
+use std::fmt;
+use std::str::FromStr;
+use types::{
+    BeaconBlock, BeaconBlockHeader, BeaconState, ChainSpec, Checkpoint, Epoch, EthSpec, Hash256,
+    PublicKey, Shard, Slot, Validator,
+};
+
+/// The response to a `/beacon/head` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct HeadResponse {
+    pub slot: Slot,
+    pub block_root: Hash256,
+    pub state_root: Hash256,
+    pub finalized_slot: Slot,
+    pub finalized_block_root: Hash256,
+    pub justified_slot: Slot,
+    pub justified_block_root: Hash256,
+    pub previous_justified_slot: Slot,
+    pub previous_justified_block_root: Hash256,
+}
+
+/// The response to a `/beacon/block` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+#[serde(bound = "T: EthSpec")]
+pub struct BlockResponse<T: EthSpec> {
+    pub root: Hash256,
+    pub beacon_block: BeaconBlock<T>,
+}
+
+/// The response to a `/beacon/state` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+#[serde(bound = "T: EthSpec")]
+pub struct StateResponse<T: EthSpec> {
+    pub root: Hash256,
+    pub beacon_state: BeaconState<T>,
+}
+
+/// A crosslink committee, as returned by `/beacon/committees`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct Committee {
+    /// The slot at which the committee attests.
+    pub slot: Slot,
+    /// The shard the committee attests to.
+    pub shard: Shard,
+    /// The indices of the validators in the committee.
+    pub committee: Vec<usize>,
+}
+
+/// The proposer of a slot, as returned by `/beacon/proposers`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct Proposer {
+    pub slot: Slot,
+    pub validator_index: usize,
+}
+
+/// The status of a validator, derived from the epochs in its `Validator` record.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorStatus {
+    /// The validator has not yet been activated.
+    Pending,
+    /// The validator is active and has not initiated an exit.
+    Active,
+    /// The validator has initiated an exit, or has exited, and cannot yet withdraw.
+    Exiting,
+    /// The validator has been slashed and cannot yet withdraw.
+    Slashed,
+    /// The validator is able to withdraw.
+    Withdrawable,
+}
+
+impl ValidatorStatus {
+    /// Returns the status of `validator` at `epoch`.
+    pub fn from_validator(validator: &Validator, epoch: Epoch, spec: &ChainSpec) -> Self {
+        if validator.is_withdrawable_at(epoch) {
+            ValidatorStatus::Withdrawable
+        } else if validator.slashed {
+            ValidatorStatus::Slashed
+        } else if validator.exit_epoch != spec.far_future_epoch {
+            ValidatorStatus::Exiting
+        } else if validator.is_active_at(epoch) {
+            ValidatorStatus::Active
+        } else {
+            ValidatorStatus::Pending
+        }
+    }
+}
+
+/// The balance and status of a validator, as returned by `/beacon/validators/balances`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorBalance {
+    pub validator_index: usize,
+    pub pubkey: PublicKey,
+    pub balance: u64,
+    pub effective_balance: u64,
+    pub status: ValidatorStatus,
+}
+
+/// The rewards and penalties of one duty of a validator.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct RewardDelta {
+    pub rewards: u64,
+    pub penalties: u64,
+}
+
+/// The rewards and penalties applied to a validator at the start of an epoch, by duty, as
+/// returned by `/beacon/validators/rewards`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct ValidatorRewards {
+    pub validator_index: usize,
+    /// Attesting to the expected FFG source.
+    pub source: RewardDelta,
+    /// The speed with which the validator's attestation was included.
+    pub inclusion_delay: RewardDelta,
+    /// Attesting to the expected FFG target.
+    pub target: RewardDelta,
+    /// Attesting to the expected head.
+    pub head: RewardDelta,
+    /// Penalties applied whilst the chain is not finalizing.
+    pub inactivity: RewardDelta,
+    /// Attesting to the winning crosslink.
+    pub crosslink: RewardDelta,
+    /// Including attestations in proposed blocks.
+    pub proposer: RewardDelta,
+}
+
+/// A Merkle proof that `leaf` is in the tree of the `BeaconState` with `state_root`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct MerkleProof {
+    pub state_root: Hash256,
+    pub leaf: Hash256,
+    /// The sibling of each node on the path from `leaf` to `state_root`, ordered bottom-up.
+    pub branch: Vec<Hash256>,
+    /// The generalized index of `leaf` in the tree, i.e., `2^depth + index`.
+    pub generalized_index: u64,
+}
+
+impl MerkleProof {
+    /// Returns `true` if `branch` proves that `leaf` is at `generalized_index` in the tree with
+    /// root `state_root`.
+    pub fn verify(&self) -> bool {
+        if self.generalized_index == 0 {
+            return false;
+        }
+
+        let depth = 63 - self.generalized_index.leading_zeros() as usize;
+        let index = self.generalized_index - (1 << depth);
+
+        merkle_proof::verify_merkle_proof(
+            self.leaf,
+            &self.branch,
+            depth,
+            index as usize,
+            self.state_root,
+        )
+    }
+}
+
+/// A field of a `BeaconState` and its proof, as returned by `/beacon/state/proof`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateFieldProof {
+    pub field: String,
+    /// The JSON value of the field, the `leaf` of the proof is its tree hash root.
+    pub value: serde_json::Value,
+    pub proof: MerkleProof,
+}
+
+/// A validator record and its proof, as returned by `/beacon/validators/proof`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct ValidatorProof {
+    pub validator_index: usize,
+    pub validator: Validator,
+    pub proof: MerkleProof,
+}
+
+/// The header of the canonical block at `slot` and the proof of its root in the `block_roots` or
+/// `historical_roots` of a state, as returned by `/beacon/block_header/proof`.
+///
+/// The header is of the latest block at or prior to `slot`, in the case of skip slots.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct BlockHeaderProof {
+    pub slot: Slot,
+    pub header: BeaconBlockHeader,
+    pub proof: MerkleProof,
+}
+
+/// The duties of a single validator, as returned by `/beacon/validator/duties`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorDuty {
+    /// The validator's BLS public key, uniquely identifying them. _48-bytes, hex encoded with 0x prefix, case insensitive._
+    pub validator_pubkey: String,
+    /// The slot at which the validator must attest.
+    pub attestation_slot: Option<Slot>,
+    /// The shard in which the validator must attest.
+    pub attestation_shard: Option<Shard>,
+    /// The slot in which a validator must propose a block, or `null` if block production is not required.
+    pub block_proposal_slot: Option<Slot>,
+}
+
+impl ValidatorDuty {
+    pub fn new() -> ValidatorDuty {
+        ValidatorDuty {
+            validator_pubkey: "".to_string(),
+            attestation_slot: None,
+            attestation_shard: None,
+            block_proposal_slot: None,
+        }
+    }
+}
+
+/// A trusted peer, as returned by `/network/trusted_peers`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustedPeer {
+    /// The base58 encoded `PeerId` of the peer.
+    pub peer_id: String,
+    /// The address the peer is dialed on, if one was given.
+    pub address: Option<String>,
+}
+
+/// The contents of the fork choice, as returned by `/debug/fork_choice`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForkChoiceResponse {
+    /// The root of the canonical head block.
+    pub head_block_root: Hash256,
+    /// The root of the fork choice tree, typically the finalized block.
+    pub root: Hash256,
+    pub root_slot: Slot,
+    /// All nodes in the tree, ordered by slot.
+    pub nodes: Vec<ForkChoiceNode>,
+    /// The latest message of each validator, ordered by validator index.
+    pub latest_messages: Vec<LatestMessage>,
+}
+
+/// A node in the fork choice tree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForkChoiceNode {
+    pub block_root: Hash256,
+    pub slot: Slot,
+    /// The root of the parent node in the tree, which is not necessarily the parent block.
+    pub parent_root: Option<Hash256>,
+    pub children: Vec<Hash256>,
+    /// The weight of the node as of the last head update, in Gwei.
+    pub weight: u64,
+    /// The child with the highest weight as of the last head update.
+    pub best_child: Option<Hash256>,
+}
+
+/// The latest block a validator has attested to, according to the fork choice.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatestMessage {
+    pub validator_index: usize,
+    pub block_root: Hash256,
+    pub slot: Slot,
+}
+
+/// A block of the canonical chain, as returned by `/debug/chain_dump`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainDumpEntry {
+    pub slot: Slot,
+    pub block_root: Hash256,
+    pub parent_root: Hash256,
+    pub state_root: Hash256,
+    /// The current justified checkpoint of the post-state of the block.
+    pub current_justified_checkpoint: Checkpoint,
+    /// The finalized checkpoint of the post-state of the block.
+    pub finalized_checkpoint: Checkpoint,
+}
+
+/// The sizes of the caches of the head state, as returned by `/debug/state_cache`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateCacheResponse {
+    pub slot: Slot,
+    pub state_root: Hash256,
+    pub previous_committee_cache: CommitteeCacheSize,
+    pub current_committee_cache: CommitteeCacheSize,
+    pub next_committee_cache: CommitteeCacheSize,
+    /// The number of validators in the pubkey cache.
+    pub pubkey_cache_len: usize,
+    /// The number of validators in the state, which the pubkey cache should match.
+    pub validator_count: usize,
+    /// The number of distinct exit epochs in the exit cache.
+    pub exit_cache_len: usize,
+}
+
+/// The state of a single committee cache.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitteeCacheSize {
+    /// The epoch the cache was built for, or `null` if it is uninitialized.
+    pub initialized_epoch: Option<Epoch>,
+    /// The number of active validators in the shuffling.
+    pub active_validator_count: usize,
+}
+
+/// Identifies a `BeaconState`, as given in the `state_id` query parameter of endpoints which read
+/// a state.
+///
+/// Parsed from (and displayed as) `head`, `genesis`, `finalized`, `justified`, a slot (e.g.,
+/// `42`) or a `0x` prefixed state root.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StateId {
+    /// The state of the canonical head.
+    Head,
+    /// The genesis state.
+    Genesis,
+    /// The state of the finalized block of the canonical head.
+    Finalized,
+    /// The state of the current justified block of the canonical head.
+    Justified,
+    /// The canonical state at the given slot.
+    Slot(Slot),
+    /// The state with the given root.
+    Root(Hash256),
+}
+
+impl FromStr for StateId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_id(s)? {
+            Id::Head => Ok(StateId::Head),
+            Id::Genesis => Ok(StateId::Genesis),
+            Id::Finalized => Ok(StateId::Finalized),
+            Id::Justified => Ok(StateId::Justified),
+            Id::Slot(slot) => Ok(StateId::Slot(slot)),
+            Id::Root(root) => Ok(StateId::Root(root)),
+        }
+    }
+}
+
+impl fmt::Display for StateId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateId::Head => write!(f, "head"),
+            StateId::Genesis => write!(f, "genesis"),
+            StateId::Finalized => write!(f, "finalized"),
+            StateId::Justified => write!(f, "justified"),
+            StateId::Slot(slot) => write!(f, "{}", slot),
+            StateId::Root(root) => write!(f, "{:?}", root),
+        }
+    }
+}
+
+/// Identifies a `BeaconBlock`, as given in the `block_id` query parameter of endpoints which read
+/// a block.
+///
+/// Parsed from (and displayed as) `head`, `genesis`, `finalized`, `justified`, a slot (e.g.,
+/// `42`) or a `0x` prefixed block root.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockId {
+    /// The canonical head block.
+    Head,
+    /// The genesis block.
+    Genesis,
+    /// The finalized block of the canonical head.
+    Finalized,
+    /// The current justified block of the canonical head.
+    Justified,
+    /// The canonical block at the given slot.
+    Slot(Slot),
+    /// The block with the given root.
+    Root(Hash256),
+}
+
+impl FromStr for BlockId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_id(s)? {
+            Id::Head => Ok(BlockId::Head),
+            Id::Genesis => Ok(BlockId::Genesis),
+            Id::Finalized => Ok(BlockId::Finalized),
+            Id::Justified => Ok(BlockId::Justified),
+            Id::Slot(slot) => Ok(BlockId::Slot(slot)),
+            Id::Root(root) => Ok(BlockId::Root(root)),
+        }
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockId::Head => write!(f, "head"),
+            BlockId::Genesis => write!(f, "genesis"),
+            BlockId::Finalized => write!(f, "finalized"),
+            BlockId::Justified => write!(f, "justified"),
+            BlockId::Slot(slot) => write!(f, "{}", slot),
+            BlockId::Root(root) => write!(f, "{:?}", root),
+        }
+    }
+}
+
+/// The syntax shared by `StateId` and `BlockId`.
+enum Id {
+    Head,
+    Genesis,
+    Finalized,
+    Justified,
+    Slot(Slot),
+    Root(Hash256),
+}
+
+fn parse_id(s: &str) -> Result<Id, String> {
+    match s {
+        "head" => Ok(Id::Head),
+        "genesis" => Ok(Id::Genesis),
+        "finalized" => Ok(Id::Finalized),
+        "justified" => Ok(Id::Justified),
+        _ if s.starts_with("0x") => s[2..]
+            .parse()
+            .map(Id::Root)
+            .map_err(|e| format!("Unable to parse root: {:?}", e)),
+        _ => s
+            .parse::<u64>()
+            .map(|slot| Id::Slot(Slot::new(slot)))
+            .map_err(|_| {
+                format!(
+                "Invalid identifier {:?}, must be head, genesis, finalized, justified, a slot or \
+                 a 0x prefixed root",
+                s
+            )
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_id_round_trip() {
+        let root = Hash256::from_low_u64_be(42);
+        for id in &[
+            StateId::Head,
+            StateId::Genesis,
+            StateId::Finalized,
+            StateId::Justified,
+            StateId::Slot(Slot::new(7)),
+            StateId::Root(root),
+        ] {
+            assert_eq!(id.to_string().parse::<StateId>(), Ok(*id));
+        }
+
+        assert_eq!("12".parse::<BlockId>(), Ok(BlockId::Slot(Slot::new(12))));
+        assert_eq!(
+            format!("{:?}", root).parse::<BlockId>(),
+            Ok(BlockId::Root(root))
+        );
+    }
+
+    #[test]
+    fn validator_status() {
+        let spec = types::MinimalEthSpec::default_spec();
+        let epoch = Epoch::new(10);
+        let active = Validator {
+            activation_epoch: Epoch::new(0),
+            exit_epoch: spec.far_future_epoch,
+            withdrawable_epoch: spec.far_future_epoch,
+            ..Validator::default()
+        };
+        let status =
+            |validator: &Validator| ValidatorStatus::from_validator(validator, epoch, &spec);
+
+        assert_eq!(status(&active), ValidatorStatus::Active);
+        assert_eq!(
+            status(&Validator {
+                activation_epoch: Epoch::new(11),
+                ..active.clone()
+            }),
+            ValidatorStatus::Pending
+        );
+        assert_eq!(
+            status(&Validator {
+                exit_epoch: Epoch::new(12),
+                withdrawable_epoch: Epoch::new(20),
+                ..active.clone()
+            }),
+            ValidatorStatus::Exiting
+        );
+        assert_eq!(
+            status(&Validator {
+                slashed: true,
+                exit_epoch: Epoch::new(12),
+                withdrawable_epoch: Epoch::new(20),
+                ..active.clone()
+            }),
+            ValidatorStatus::Slashed
+        );
+        assert_eq!(
+            status(&Validator {
+                exit_epoch: Epoch::new(5),
+                withdrawable_epoch: Epoch::new(10),
+                ..active.clone()
+            }),
+            ValidatorStatus::Withdrawable
+        );
+    }
+
+    #[test]
+    fn invalid_ids() {
+        assert!("".parse::<StateId>().is_err());
+        assert!("latest".parse::<StateId>().is_err());
+        assert!("-1".parse::<StateId>().is_err());
+        assert!("0x1234".parse::<BlockId>().is_err());
+    }
+}
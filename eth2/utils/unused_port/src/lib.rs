@@ -0,0 +1,27 @@
+//! Provides network ports which are unused at the time of calling, allowing tests which run
+//! network services to execute in parallel without colliding on fixed ports.
+//!
+//! A port is found by binding to port `0` and reading the port allocated by the operating system.
+//! The port is released before it is returned, so it may be taken by another process before it is
+//! used. In practice, operating systems do not immediately re-allocate released ports.
+use std::net::{TcpListener, UdpSocket};
+
+/// Returns a TCP port on the loopback interface which is unused at the time of calling.
+pub fn unused_tcp_port() -> Result<u16, String> {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .map_err(|e| format!("Unable to bind TCP listener: {:?}", e))?;
+    listener
+        .local_addr()
+        .map(|address| address.port())
+        .map_err(|e| format!("Unable to read TCP listener address: {:?}", e))
+}
+
+/// Returns a UDP port on the loopback interface which is unused at the time of calling.
+pub fn unused_udp_port() -> Result<u16, String> {
+    let socket = UdpSocket::bind("127.0.0.1:0")
+        .map_err(|e| format!("Unable to bind UDP socket: {:?}", e))?;
+    socket
+        .local_addr()
+        .map(|address| address.port())
+        .map_err(|e| format!("Unable to read UDP socket address: {:?}", e))
+}
@@ -0,0 +1,82 @@
+//! Assertions on the liveness of a `LocalNetwork`.
+
+use crate::LocalNetwork;
+use types::{Epoch, EthSpec};
+
+/// Verifies that every node has the same head block.
+pub fn verify_heads_agree<E: EthSpec>(network: &LocalNetwork<E>) -> Result<(), String> {
+    let heads: Vec<_> = network
+        .beacon_chains()
+        .iter()
+        .map(|chain| {
+            let head = chain.head();
+            (head.beacon_block_root, head.beacon_block.slot)
+        })
+        .collect();
+
+    match heads.first() {
+        Some(first) if heads.iter().any(|head| head != first) => Err(format!(
+            "Nodes disagree on the head (root, slot): {:?}",
+            heads
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Verifies that the head of every node is at least `min_slot`.
+pub fn verify_head_slot_at_least<E: EthSpec>(
+    network: &LocalNetwork<E>,
+    min_slot: u64,
+) -> Result<(), String> {
+    for (index, chain) in network.beacon_chains().iter().enumerate() {
+        let head_slot = chain.head().beacon_block.slot;
+        if head_slot < min_slot {
+            return Err(format!(
+                "Node {} has head slot {}, expected at least {}",
+                index, head_slot, min_slot
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Verifies that the finalized epoch of every node is at least `min_epoch`.
+pub fn verify_finalized_epoch_at_least<E: EthSpec>(
+    network: &LocalNetwork<E>,
+    min_epoch: u64,
+) -> Result<(), String> {
+    for (index, epoch) in finalized_epochs(network).into_iter().enumerate() {
+        if epoch < min_epoch {
+            return Err(format!(
+                "Node {} has finalized epoch {}, expected at least {}",
+                index, epoch, min_epoch
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Verifies that no node has finalized beyond `max_epoch`.
+pub fn verify_finalized_epoch_at_most<E: EthSpec>(
+    network: &LocalNetwork<E>,
+    max_epoch: u64,
+) -> Result<(), String> {
+    for (index, epoch) in finalized_epochs(network).into_iter().enumerate() {
+        if epoch > max_epoch {
+            return Err(format!(
+                "Node {} has finalized epoch {}, expected at most {}",
+                index, epoch, max_epoch
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the finalized epoch of each node.
+pub fn finalized_epochs<E: EthSpec>(network: &LocalNetwork<E>) -> Vec<Epoch> {
+    network
+        .beacon_chains()
+        .iter()
+        .map(|chain| chain.head().beacon_state.finalized_checkpoint.epoch)
+        .collect()
+}
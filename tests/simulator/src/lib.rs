@@ -0,0 +1,16 @@
+//! An in-process simulator of a network of Lighthouse beacon nodes.
+//!
+//! A `LocalNetwork` launches several beacon node `Client`s within a single process. The nodes
+//! connect to each other over loopback TCP and follow the wall clock, with a short slot duration.
+//! Each genesis node is paired with a `validator_client` service, running in its own thread, which
+//! performs the duties of a range of the genesis validators via the gRPC API of its node.
+//!
+//! The network may be disrupted by stopping validator clients or by partitioning the nodes. The
+//! `checks` module provides assertions on the liveness of the network, such as all nodes agreeing
+//! upon a head and finality advancing.
+
+pub mod checks;
+mod local_network;
+mod validator_client;
+
+pub use local_network::{LocalNetwork, SimulatorChain, SimulatorClient, SimulatorConfig};
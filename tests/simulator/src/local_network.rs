@@ -0,0 +1,349 @@
+use crate::validator_client::LocalValidatorClient;
+use beacon_chain::BeaconChain;
+use client::{BeaconChainStartMethod, Client, ClientConfig, Eth2Config, RuntimeBeaconChainTypes};
+use eth2_libp2p::{Multiaddr, PeerId};
+use slog::{debug, info};
+use slot_clock::SlotClock;
+use std::ops::Range;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use store::MemoryStore;
+use tempfile::TempDir;
+use tokio::runtime::Runtime;
+use types::{EthSpec, Slot};
+use unused_port::{unused_tcp_port, unused_udp_port};
+
+/// A beacon node client as run by the simulator.
+pub type SimulatorClient<E> = Client<MemoryStore, E>;
+/// The beacon chain of a simulated beacon node.
+pub type SimulatorChain<E> = BeaconChain<RuntimeBeaconChainTypes<MemoryStore, E>>;
+
+/// Configuration of a `LocalNetwork`.
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    /// The number of beacon nodes launched at genesis.
+    pub node_count: usize,
+    /// The number of genesis validators. Validators are split into contiguous ranges, one for the
+    /// validator client of each genesis node.
+    pub validator_count: usize,
+    /// The wall-clock duration of each slot.
+    pub slot_duration: Duration,
+    /// The time between launching the network and genesis, allowing all nodes to start before
+    /// the first slot.
+    pub genesis_delay: Duration,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            node_count: 4,
+            validator_count: 64,
+            slot_duration: Duration::from_millis(2_000),
+            genesis_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// A single simulated beacon node and the validator client connected to it.
+struct LocalNode<E: EthSpec> {
+    /// Declared before the `client` so that the validator client stops before its beacon node.
+    validator_client: Option<LocalValidatorClient>,
+    client: SimulatorClient<E>,
+    validators: Range<usize>,
+    grpc_port: u16,
+    libp2p_address: Multiaddr,
+}
+
+impl<E: EthSpec> LocalNode<E> {
+    fn peer_id(&self) -> PeerId {
+        self.client.network.local_peer_id()
+    }
+
+    /// Returns the address of the node, including its peer id.
+    fn trusted_peer_address(&self) -> String {
+        format!("{}/p2p/{}", self.libp2p_address, self.peer_id().to_base58())
+    }
+}
+
+/// A network of beacon nodes and validator clients running within this process.
+pub struct LocalNetwork<E: EthSpec> {
+    /// The running nodes. Declared before the `runtime` so the nodes are dropped first.
+    nodes: Vec<LocalNode<E>>,
+    config: SimulatorConfig,
+    eth2_config: Eth2Config,
+    genesis_time: u64,
+    /// Removed when the network is dropped, after the nodes.
+    data_dir: TempDir,
+    log: slog::Logger,
+    runtime: Runtime,
+}
+
+impl<E: EthSpec> LocalNetwork<E> {
+    /// Launches `config.node_count` nodes from a common genesis state, each connected to all the
+    /// nodes launched before it, and a validator client for each node.
+    pub fn new(config: SimulatorConfig, log: slog::Logger) -> Result<Self, String> {
+        let runtime = Runtime::new().map_err(|e| format!("Unable to start runtime: {:?}", e))?;
+        let genesis_time = (SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| format!("Unable to read system time: {:?}", e))?
+            + config.genesis_delay)
+            .as_secs();
+        let data_dir = tempfile::Builder::new()
+            .prefix("lighthouse_simulator")
+            .tempdir()
+            .map_err(|e| format!("Unable to create data dir: {:?}", e))?;
+
+        let mut eth2_config = Eth2Config::minimal();
+        eth2_config.spec.milliseconds_per_slot = config.slot_duration.as_millis() as u64;
+
+        let mut network = LocalNetwork {
+            nodes: vec![],
+            eth2_config,
+            genesis_time,
+            data_dir,
+            log,
+            runtime,
+            config,
+        };
+
+        let node_count = network.config.node_count;
+        let validators_per_node = network.config.validator_count / node_count;
+        for index in 0..node_count {
+            let first = index * validators_per_node;
+            let last = if index + 1 == node_count {
+                network.config.validator_count
+            } else {
+                first + validators_per_node
+            };
+
+            network.add_node_with_validators(first..last)?;
+            network.start_validator_client(index)?;
+        }
+
+        info!(
+            network.log,
+            "Local network started";
+            "nodes" => node_count,
+            "validators" => network.config.validator_count,
+            "genesis_time" => genesis_time,
+        );
+
+        Ok(network)
+    }
+
+    /// Launches a new node, without any validators, that connects to all existing nodes.
+    ///
+    /// Returns the index of the new node.
+    pub fn add_node(&mut self) -> Result<usize, String> {
+        self.add_node_with_validators(0..0)
+    }
+
+    fn add_node_with_validators(&mut self, validators: Range<usize>) -> Result<usize, String> {
+        let index = self.nodes.len();
+        let grpc_port = unused_tcp_port()?;
+        let libp2p_port = unused_tcp_port()?;
+
+        let mut client_config = ClientConfig::default();
+        client_config.data_dir = self.data_dir.path().join(format!("node_{}", index));
+        client_config.beacon_chain_start_method = BeaconChainStartMethod::Generated {
+            validator_count: self.config.validator_count,
+            genesis_time: self.genesis_time,
+        };
+        client_config.rpc.enabled = true;
+        client_config.rpc.listen_address = "127.0.0.1".parse().expect("valid ip address");
+        client_config.rpc.port = grpc_port;
+        client_config.rest_api.enabled = false;
+        client_config.websocket_server.enabled = false;
+
+        let network_config = &mut client_config.network;
+        network_config.network_dir = client_config.data_dir.join("network");
+        network_config.listen_address = "127.0.0.1".parse().expect("valid ip address");
+        network_config.discovery_address = network_config.listen_address;
+        network_config.libp2p_port = libp2p_port;
+        network_config.discovery_port = unused_udp_port()?;
+        network_config.subscribe_all_subnets = true;
+        network_config.libp2p_nodes = self
+            .nodes
+            .iter()
+            .map(|node| node.libp2p_address.clone())
+            .collect();
+
+        let libp2p_address = format!("/ip4/127.0.0.1/tcp/{}", libp2p_port)
+            .parse()
+            .map_err(|e| format!("Invalid multiaddr: {:?}", e))?;
+
+        let client = SimulatorClient::<E>::new(
+            client_config,
+            self.eth2_config.clone(),
+            MemoryStore::open(),
+            self.log.new(slog::o!("node" => index)),
+            &self.runtime.executor(),
+        )
+        .map_err(|e| format!("Unable to start node {}: {:?}", index, e))?;
+
+        debug!(
+            self.log,
+            "Node started";
+            "node" => index,
+            "libp2p_port" => libp2p_port,
+            "grpc_port" => grpc_port,
+        );
+
+        self.nodes.push(LocalNode {
+            validator_client: None,
+            client,
+            validators,
+            grpc_port,
+            libp2p_address,
+        });
+
+        Ok(index)
+    }
+
+    /// Starts the validator client of the node at `index`, if it is not already running.
+    pub fn start_validator_client(&mut self, index: usize) -> Result<(), String> {
+        let node = &mut self.nodes[index];
+        if node.validator_client.is_some() || node.validators.start == node.validators.end {
+            return Ok(());
+        }
+
+        node.validator_client = Some(LocalValidatorClient::start::<E>(
+            node.grpc_port,
+            node.validators.clone(),
+            self.eth2_config.clone(),
+            self.log.new(slog::o!("validator_client" => index)),
+        )?);
+
+        debug!(
+            self.log,
+            "Validator client started";
+            "node" => index,
+            "validators" => format!("{:?}", node.validators),
+        );
+
+        Ok(())
+    }
+
+    /// Stops the validator client of the node at `index`, taking its validators offline.
+    pub fn stop_validator_client(&mut self, index: usize) {
+        if self.nodes[index].validator_client.take().is_some() {
+            debug!(self.log, "Validator client stopped"; "node" => index);
+        }
+    }
+
+    /// Disconnects every node in `left` from every node in `right` and prevents them from
+    /// reconnecting until `Self::heal` is called.
+    pub fn partition(&mut self, left: &[usize], right: &[usize]) {
+        self.for_each_pair(left, right, |node, other| {
+            node.client
+                .network
+                .libp2p_service()
+                .lock()
+                .deny_peer(other.peer_id());
+        });
+
+        info!(
+            self.log,
+            "Network partitioned";
+            "left" => format!("{:?}", left),
+            "right" => format!("{:?}", right),
+        );
+    }
+
+    /// Reverses `Self::partition`, reconnecting every node in `left` to every node in `right`.
+    pub fn heal(&mut self, left: &[usize], right: &[usize]) -> Result<(), String> {
+        self.for_each_pair(left, right, |node, other| {
+            node.client
+                .network
+                .libp2p_service()
+                .lock()
+                .allow_peer(&other.peer_id());
+        });
+
+        for &index in left {
+            for &other in right {
+                let address = self.nodes[other].trusted_peer_address();
+                self.nodes[index]
+                    .client
+                    .network
+                    .libp2p_service()
+                    .lock()
+                    .add_trusted_peer(&address)?;
+            }
+        }
+
+        info!(
+            self.log,
+            "Network healed";
+            "left" => format!("{:?}", left),
+            "right" => format!("{:?}", right),
+        );
+
+        Ok(())
+    }
+
+    /// Calls `f` for each node in `left` with each node in `right`, and vice versa.
+    fn for_each_pair<F>(&self, left: &[usize], right: &[usize], f: F)
+    where
+        F: Fn(&LocalNode<E>, &LocalNode<E>),
+    {
+        for &a in left {
+            for &b in right {
+                f(&self.nodes[a], &self.nodes[b]);
+                f(&self.nodes[b], &self.nodes[a]);
+            }
+        }
+    }
+
+    /// The number of running nodes.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the beacon chain of the node at `index`.
+    pub fn beacon_chain(&self, index: usize) -> Arc<SimulatorChain<E>> {
+        self.nodes[index].client.beacon_chain()
+    }
+
+    /// Returns the beacon chains of all nodes.
+    pub fn beacon_chains(&self) -> Vec<Arc<SimulatorChain<E>>> {
+        self.nodes
+            .iter()
+            .map(|node| node.client.beacon_chain())
+            .collect()
+    }
+
+    /// The slot the network is currently at, according to the wall clock. Prior to genesis, this
+    /// is the genesis slot.
+    pub fn current_slot(&self) -> Slot {
+        self.beacon_chain(0)
+            .slot_clock
+            .now()
+            .unwrap_or_else(|| Slot::new(0))
+    }
+
+    /// Runs the network for a number of epochs.
+    pub fn run_epochs(&mut self, epochs: u64) -> Result<(), String> {
+        self.run_slots(epochs * E::slots_per_epoch())
+    }
+
+    /// Runs the network until `slots` slots after the current slot have elapsed.
+    ///
+    /// The nodes and validator clients run on their own threads and follow the wall clock; this
+    /// function simply waits for them.
+    pub fn run_slots(&mut self, slots: u64) -> Result<(), String> {
+        let target = self.current_slot() + slots;
+        let chain = self.beacon_chain(0);
+
+        while chain.slot_clock.now().map_or(true, |slot| slot <= target) {
+            let duration_to_next_slot = chain
+                .slot_clock
+                .duration_to_next_slot()
+                .ok_or_else(|| "Unable to read slot clock".to_string())?;
+            thread::sleep(duration_to_next_slot);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! Validator clients run within the simulator process.
+//!
+//! Each `LocalValidatorClient` runs the `validator_client` service in its own thread. The service
+//! performs the duties of a range of the well-known interop keypairs via the gRPC API of a local
+//! beacon node, exactly as a standalone validator client would.
+
+use client::Eth2Config;
+use exit_future::Signal;
+use protos::services_grpc::ValidatorServiceClient;
+use slog::crit;
+use std::ops::Range;
+use std::thread::{self, JoinHandle};
+use types::{EthSpec, Keypair};
+use validator_client::config::{Config as ValidatorConfig, KeySource};
+use validator_client::Service as ValidatorService;
+
+/// A validator client running in its own thread, connected to a local beacon node.
+pub struct LocalValidatorClient {
+    exit_signal: Option<Signal>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LocalValidatorClient {
+    /// Starts a validator client for the `validators` of the interop keypairs, connected to the
+    /// beacon node serving gRPC on `grpc_port`.
+    pub fn start<E: EthSpec>(
+        grpc_port: u16,
+        validators: Range<usize>,
+        eth2_config: Eth2Config,
+        log: slog::Logger,
+    ) -> Result<Self, String> {
+        let mut config = ValidatorConfig::default();
+        config.server = "127.0.0.1".into();
+        config.server_grpc_port = grpc_port;
+        config.key_source = KeySource::TestingKeypairRange(validators);
+        config.slots_per_epoch = E::slots_per_epoch();
+
+        let (exit_signal, exit) = exit_future::signal();
+
+        let thread = thread::Builder::new()
+            .name(format!("validator-client-{}", grpc_port))
+            .spawn(move || {
+                if let Err(e) = ValidatorService::<ValidatorServiceClient, Keypair, E>::start(
+                    config,
+                    eth2_config,
+                    exit,
+                    log.clone(),
+                ) {
+                    crit!(log, "Validator client failed"; "error" => e.to_string());
+                }
+            })
+            .map_err(|e| format!("Unable to spawn validator client: {:?}", e))?;
+
+        Ok(Self {
+            exit_signal: Some(exit_signal),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for LocalValidatorClient {
+    /// Stops the validator client and waits for its thread to finish.
+    fn drop(&mut self) {
+        if let Some(exit_signal) = self.exit_signal.take() {
+            exit_signal.fire();
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
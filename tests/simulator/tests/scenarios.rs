@@ -0,0 +1,105 @@
+//! Each scenario runs a network in real time for several minutes, so they are ignored by default.
+//! Run them with `make test-simulator`.
+
+use simulator::checks::{
+    verify_finalized_epoch_at_least, verify_finalized_epoch_at_most, verify_head_slot_at_least,
+    verify_heads_agree,
+};
+use simulator::{LocalNetwork, SimulatorConfig};
+use slog::{o, Discard, Logger};
+use types::MinimalEthSpec;
+
+fn log() -> Logger {
+    Logger::root(Discard, o!())
+}
+
+fn network(node_count: usize) -> LocalNetwork<MinimalEthSpec> {
+    let config = SimulatorConfig {
+        node_count,
+        ..SimulatorConfig::default()
+    };
+    LocalNetwork::new(config, log()).expect("should start local network")
+}
+
+#[test]
+#[ignore]
+fn nodes_agree_and_finalize() {
+    let mut network = network(4);
+
+    network.run_epochs(6).expect("should run network");
+
+    verify_heads_agree(&network).expect("heads should agree");
+    verify_head_slot_at_least(&network, 6 * 8).expect("blocks should be produced");
+    verify_finalized_epoch_at_least(&network, 2).expect("finality should advance");
+}
+
+#[test]
+#[ignore]
+fn late_joiner_syncs() {
+    let mut network = network(3);
+
+    network.run_epochs(4).expect("should run network");
+    network.add_node().expect("should add node");
+    network.run_epochs(2).expect("should run network");
+
+    verify_heads_agree(&network).expect("late joiner should sync to the head");
+    verify_finalized_epoch_at_least(&network, 2).expect("late joiner should sync finality");
+}
+
+#[test]
+#[ignore]
+fn finalizes_with_a_quarter_of_validators_offline() {
+    let mut network = network(4);
+    network.stop_validator_client(0);
+
+    network.run_epochs(6).expect("should run network");
+
+    verify_heads_agree(&network).expect("heads should agree");
+    verify_finalized_epoch_at_least(&network, 2).expect("finality should advance");
+}
+
+#[test]
+#[ignore]
+fn does_not_finalize_with_half_of_validators_offline() {
+    let mut network = network(4);
+    network.stop_validator_client(0);
+    network.stop_validator_client(1);
+
+    network.run_epochs(4).expect("should run network");
+
+    verify_heads_agree(&network).expect("heads should agree");
+    verify_finalized_epoch_at_most(&network, 0).expect("finality should not advance");
+
+    network
+        .start_validator_client(0)
+        .expect("should restart validator client");
+    network
+        .start_validator_client(1)
+        .expect("should restart validator client");
+    network.run_epochs(4).expect("should run network");
+
+    verify_heads_agree(&network).expect("heads should agree");
+    verify_finalized_epoch_at_least(&network, 2).expect("finality should resume");
+}
+
+#[test]
+#[ignore]
+fn recovers_from_a_partition() {
+    let mut network = network(4);
+    let (left, right) = ([0, 1], [2, 3]);
+
+    network.run_epochs(1).expect("should run network");
+    network.partition(&left, &right);
+    network
+        .run_epochs(2)
+        .expect("should run partitioned network");
+
+    verify_finalized_epoch_at_most(&network, 0)
+        .expect("neither half of the network should finalize");
+
+    network.heal(&left, &right).expect("should heal network");
+    network.run_epochs(4).expect("should run healed network");
+
+    verify_heads_agree(&network).expect("heads should agree after healing");
+    verify_finalized_epoch_at_least(&network, 2).expect("finality should resume");
+}
@@ -1,6 +1,6 @@
 //TODO: generalise these enums to the crate
 use crate::block_producer::{BeaconNodeError, PublishOutcome};
-use types::{Attestation, AttestationData, EthSpec, Slot};
+use types::{Attestation, AttestationData, EthSpec, Signature, Slot};
 
 /// Defines the methods required to produce and publish attestations on a Beacon Node. Abstracts the
 /// actual beacon node.
@@ -15,9 +15,13 @@ pub trait BeaconNodeAttestation: Send + Sync {
 
     /// Request that the node publishes a attestation.
     ///
+    /// The `selection_proof` is supplied if the validator is selected to aggregate the
+    /// attestations of its committee, in which case the node publishes the aggregate.
+    ///
     /// Returns `true` if the publish was successful.
     fn publish_attestation<T: EthSpec>(
         &self,
         attestation: Attestation<T>,
+        selection_proof: Option<Signature>,
     ) -> Result<PublishOutcome, BeaconNodeError>;
 }
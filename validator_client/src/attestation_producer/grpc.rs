@@ -6,7 +6,7 @@ use ssz::{Decode, Encode};
 use protos::services::{
     Attestation as GrpcAttestation, ProduceAttestationDataRequest, PublishAttestationRequest,
 };
-use types::{Attestation, AttestationData, EthSpec, Slot};
+use types::{Attestation, AttestationData, EthSpec, Signature, Slot};
 
 impl BeaconNodeAttestation for AttestationServiceClient {
     fn produce_attestation_data(
@@ -31,6 +31,7 @@ impl BeaconNodeAttestation for AttestationServiceClient {
     fn publish_attestation<T: EthSpec>(
         &self,
         attestation: Attestation<T>,
+        selection_proof: Option<Signature>,
     ) -> Result<PublishOutcome, BeaconNodeError> {
         let mut req = PublishAttestationRequest::new();
 
@@ -41,6 +42,10 @@ impl BeaconNodeAttestation for AttestationServiceClient {
 
         req.set_attestation(grpc_attestation);
 
+        if let Some(selection_proof) = selection_proof {
+            req.set_selection_proof(selection_proof.as_ssz_bytes());
+        }
+
         let reply = self
             .publish_attestation(&req)
             .map_err(|err| BeaconNodeError::RemoteFailure(format!("{:?}", err)))?;
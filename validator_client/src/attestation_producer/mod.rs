@@ -11,8 +11,8 @@ use core::marker::PhantomData;
 use slog::{error, info, warn};
 use tree_hash::TreeHash;
 use types::{
-    AggregateSignature, Attestation, AttestationData, AttestationDataAndCustodyBit,
-    AttestationDuty, BitList,
+    AggregateAndProof, AggregateSignature, Attestation, AttestationData,
+    AttestationDataAndCustodyBit, AttestationDuty, BitList, Signature,
 };
 
 //TODO: Group these errors at a crate level
@@ -84,7 +84,11 @@ impl<'a, B: BeaconNodeAttestation, S: Signer, E: EthSpec> AttestationProducer<'a
         if self.safe_to_produce(&attestation) {
             let domain = self.spec.get_domain(epoch, Domain::Attestation, &self.fork);
             if let Some(attestation) = self.sign_attestation(attestation, self.duty, domain) {
-                match self.beacon_node.publish_attestation(attestation) {
+                let selection_proof = self.selection_proof();
+                match self
+                    .beacon_node
+                    .publish_attestation(attestation, selection_proof)
+                {
                     Ok(PublishOutcome::InvalidAttestation(_string)) => {
                         Ok(ValidatorEvent::InvalidAttestation)
                     }
@@ -142,6 +146,26 @@ impl<'a, B: BeaconNodeAttestation, S: Signer, E: EthSpec> AttestationProducer<'a
         })
     }
 
+    /// Returns the selection proof of the validator for the slot of the duty, if it selects the
+    /// validator to aggregate the attestations of its committee.
+    fn selection_proof(&self) -> Option<Signature> {
+        let slot = self.duty.slot;
+        let selection_proof = self.signer.sign_message(
+            &AggregateAndProof::<E>::selection_proof_message(slot),
+            AggregateAndProof::<E>::selection_proof_domain(slot, &self.fork, &self.spec),
+        )?;
+
+        if AggregateAndProof::<E>::is_aggregator(
+            self.duty.committee_len,
+            &selection_proof,
+            &self.spec,
+        ) {
+            Some(selection_proof)
+        } else {
+            None
+        }
+    }
+
     /// Returns `true` if signing an attestation is safe (non-slashable).
     ///
     /// !!! UNSAFE !!!
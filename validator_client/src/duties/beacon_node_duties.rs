@@ -1,5 +1,5 @@
 use super::EpochDuties;
-use types::{Epoch, PublicKey};
+use types::{AttestationDuty, Epoch, PublicKey};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum BeaconNodeDutiesError {
@@ -17,4 +17,9 @@ pub trait BeaconNodeDuties: Send + Sync {
         epoch: Epoch,
         pub_keys: &[PublicKey],
     ) -> Result<EpochDuties, BeaconNodeDutiesError>;
+
+    /// Requests the Beacon Node subscribes to the attestation subnets required to perform the
+    /// given attestation duties.
+    fn subscribe_to_subnets(&self, duties: &[AttestationDuty])
+        -> Result<(), BeaconNodeDutiesError>;
 }
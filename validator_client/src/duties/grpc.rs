@@ -2,7 +2,9 @@ use super::beacon_node_duties::{BeaconNodeDuties, BeaconNodeDutiesError};
 use super::epoch_duties::{EpochDuties, EpochDuty};
 // to use if we manually specify a timeout
 //use grpcio::CallOption;
-use protos::services::{GetDutiesRequest, Validators};
+use protos::services::{
+    AttestationSubnetDuty, GetDutiesRequest, SubscribeToSubnetsRequest, Validators,
+};
 use protos::services_grpc::ValidatorServiceClient;
 use ssz::ssz_encode;
 use std::collections::HashMap;
@@ -64,4 +66,34 @@ impl BeaconNodeDuties for ValidatorServiceClient {
         }
         Ok(epoch_duties)
     }
+
+    /// Requests the Beacon Node subscribes to the attestation subnets of the given duties.
+    fn subscribe_to_subnets(
+        &self,
+        duties: &[AttestationDuty],
+    ) -> Result<(), BeaconNodeDutiesError> {
+        let mut req = SubscribeToSubnetsRequest::new();
+        req.set_duties(
+            duties
+                .iter()
+                .map(|duty| {
+                    let mut subnet_duty = AttestationSubnetDuty::new();
+                    subnet_duty.set_attestation_slot(duty.slot.as_u64());
+                    subnet_duty.set_attestation_shard(duty.shard);
+                    subnet_duty
+                })
+                .collect(),
+        );
+
+        let reply = ValidatorServiceClient::subscribe_to_subnets(self, &req)
+            .map_err(|err| BeaconNodeDutiesError::RemoteFailure(format!("{:?}", err)))?;
+
+        if reply.get_success() {
+            Ok(())
+        } else {
+            Err(BeaconNodeDutiesError::RemoteFailure(
+                String::from_utf8_lossy(reply.get_msg()).into_owned(),
+            ))
+        }
+    }
 }
@@ -10,11 +10,11 @@ use self::epoch_duties::{EpochDuties, EpochDutiesMapError};
 pub use self::epoch_duties::{EpochDutiesMap, WorkInfo};
 use super::signer::Signer;
 use futures::Async;
-use slog::{debug, error, info};
+use slog::{debug, error, info, warn};
 use std::fmt::Display;
 use std::sync::Arc;
 use std::sync::RwLock;
-use types::{Epoch, PublicKey, Slot};
+use types::{AttestationDuty, Epoch, PublicKey, Slot};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum UpdateOutcome {
@@ -78,15 +78,35 @@ impl<U: BeaconNodeDuties, S: Signer + Display> DutiesManager<U, S> {
         match self.update(epoch) {
             Err(error) => error!(log, "Epoch duties poll error"; "error" => format!("{:?}", error)),
             Ok(UpdateOutcome::NoChange(epoch)) => debug!(log, "No change in duties"; "epoch" => epoch),
-            Ok(UpdateOutcome::DutiesChanged(epoch, duties)) => info!(log, "Duties changed (potential re-org)"; "epoch" => epoch, "duties" => format!("{:?}", duties)),
+            Ok(UpdateOutcome::DutiesChanged(epoch, duties)) => {
+                info!(log, "Duties changed (potential re-org)"; "epoch" => epoch, "duties" => format!("{:?}", duties));
+                self.subscribe_to_subnets(&duties, &log);
+            }
             Ok(UpdateOutcome::NewDuties(epoch, duties)) => {
                 info!(log, "New duties obtained"; "epoch" => epoch);
+                self.subscribe_to_subnets(&duties, &log);
                 print_duties(&log, duties);
             }
         };
         Ok(Async::Ready(()))
     }
 
+    /// Requests the beacon node subscribes to the attestation subnets required by `duties`.
+    fn subscribe_to_subnets(&self, duties: &EpochDuties, log: &slog::Logger) {
+        let attestation_duties: Vec<AttestationDuty> = duties
+            .values()
+            .filter_map(|duty| duty.map(|duty| duty.attestation_duty))
+            .collect();
+
+        if attestation_duties.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self.beacon_node.subscribe_to_subnets(&attestation_duties) {
+            warn!(log, "Unable to subscribe to attestation subnets"; "error" => format!("{:?}", e));
+        }
+    }
+
     /// Returns a list of (index, WorkInfo) indicating all the validators that have work to perform
     /// this slot.
     pub fn get_current_work(&self, slot: Slot) -> Option<Vec<(usize, WorkInfo)>> {
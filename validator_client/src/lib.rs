@@ -1,4 +1,11 @@
 extern crate libc;
+mod attestation_producer;
+mod block_producer;
 pub mod config;
+mod duties;
+pub mod error;
+mod service;
+mod signer;
 
 pub use crate::config::Config;
+pub use crate::service::Service;
@@ -1,16 +1,3 @@
-mod attestation_producer;
-mod block_producer;
-mod config;
-mod duties;
-pub mod error;
-mod service;
-mod signer;
-
-use crate::config::{
-    Config as ClientConfig, KeySource, DEFAULT_SERVER, DEFAULT_SERVER_GRPC_PORT,
-    DEFAULT_SERVER_HTTP_PORT,
-};
-use crate::service::Service as ValidatorService;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use eth2_config::Eth2Config;
 use lighthouse_bootstrap::Bootstrapper;
@@ -18,6 +5,11 @@ use protos::services_grpc::ValidatorServiceClient;
 use slog::{crit, error, info, o, Drain, Level, Logger};
 use std::path::PathBuf;
 use types::{InteropEthSpec, Keypair, MainnetEthSpec, MinimalEthSpec};
+use validator_client::config::{
+    Config as ClientConfig, KeySource, DEFAULT_SERVER, DEFAULT_SERVER_GRPC_PORT,
+    DEFAULT_SERVER_HTTP_PORT,
+};
+use validator_client::Service as ValidatorService;
 
 pub const DEFAULT_SPEC: &str = "minimal";
 pub const DEFAULT_DATA_DIR: &str = ".lighthouse-validator";
@@ -191,20 +183,26 @@ fn main() {
         "datadir" => client_config.full_data_dir().expect("Unable to find datadir").to_str(),
     );
 
+    // The service runs until the process is terminated, so the exit signal is never fired.
+    let (_exit_signal, exit) = exit_future::signal();
+
     let result = match eth2_config.spec_constants.as_str() {
         "mainnet" => ValidatorService::<ValidatorServiceClient, Keypair, MainnetEthSpec>::start(
             client_config,
             eth2_config,
+            exit,
             log.clone(),
         ),
         "minimal" => ValidatorService::<ValidatorServiceClient, Keypair, MinimalEthSpec>::start(
             client_config,
             eth2_config,
+            exit,
             log.clone(),
         ),
         "interop" => ValidatorService::<ValidatorServiceClient, Keypair, InteropEthSpec>::start(
             client_config,
             eth2_config,
+            exit,
             log.clone(),
         ),
         other => {
@@ -16,6 +16,7 @@ use crate::error as error_chain;
 use crate::signer::Signer;
 use bls::Keypair;
 use eth2_config::Eth2Config;
+use exit_future::Exit;
 use grpcio::{ChannelBuilder, EnvBuilder};
 use protos::services::Empty;
 use protos::services_grpc::{
@@ -206,11 +207,12 @@ impl<B: BeaconNodeDuties + 'static, S: Signer + 'static, E: EthSpec> Service<B,
         })
     }
 
-    /// Initialise the service then run the core thread.
+    /// Initialise the service then run the core thread until `exit` fires.
     // TODO: Improve handling of generic BeaconNode types, to stub grpcClient
     pub fn start(
         client_config: ValidatorConfig,
         eth2_config: Eth2Config,
+        exit: Exit,
         log: slog::Logger,
     ) -> error_chain::Result<()> {
         // connect to the node and retrieve its properties and initialize the gRPC clients
@@ -259,16 +261,19 @@ impl<B: BeaconNodeDuties + 'static, S: Signer + 'static, E: EthSpec> Service<B,
 
         /* kick off the core service */
         runtime.block_on(
-            interval
-                .for_each(move |_| {
-                    // wait for node to process
-                    std::thread::sleep(TIME_DELAY_FROM_SLOT);
-                    // if a non-fatal error occurs, proceed to the next slot.
-                    let _ignore_error = service.per_slot_execution();
-                    // completed a slot process
-                    Ok(())
-                })
-                .map_err(|e| format!("Service thread failed: {:?}", e)),
+            exit.until(
+                interval
+                    .for_each(move |_| {
+                        // wait for node to process
+                        std::thread::sleep(TIME_DELAY_FROM_SLOT);
+                        // if a non-fatal error occurs, proceed to the next slot.
+                        let _ignore_error = service.per_slot_execution();
+                        // completed a slot process
+                        Ok(())
+                    })
+                    .map_err(|e| format!("Service thread failed: {:?}", e)),
+            )
+            .map(|_| ()),
         )?;
         // validator client exited
         Ok(())